@@ -0,0 +1,64 @@
+//! Benchmark decoding of archived files.
+//!
+//! Run with `cargo bench --all-features`.
+use std::{fs, io, time::{Duration, Instant}};
+use delharc::LhaDecodeReader;
+
+const ARCHIVES: &[&str] = &[
+    "tests/lha213/lh5.lzh",
+    "tests/lha213/lh5_long.lzh",
+    "tests/lha_unix114i/lh6_long.lzh",
+    "tests/lha_unix114i/lh7_long.lzh",
+    #[cfg(feature = "lhx")]
+    "tests/unlha32/lhx_long.lzh",
+];
+
+const MIN_DURATION: Duration = Duration::from_secs(2);
+const MIN_ITERATIONS: u32 = 5;
+
+fn decode_all<R: io::Read>(archive: R) -> io::Result<u64> {
+    let mut total = 0;
+    let mut lha_reader = LhaDecodeReader::new(archive)?;
+    loop {
+        if lha_reader.is_decoder_supported() {
+            total += io::copy(&mut lha_reader, &mut io::sink())?;
+            lha_reader.crc_check()?;
+        }
+        if !lha_reader.next_file()? {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+fn main() -> io::Result<()> {
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with('-'));
+    for name in ARCHIVES {
+        if matches!(&filter, Some(filter) if !name.contains(filter.as_str())) {
+            continue;
+        }
+        let archive = fs::read(name)?;
+        bench(&format!("{} (memory)", name), || decode_all(&archive[..]))?;
+        bench(&format!("{} (file)", name), || decode_all(fs::File::open(name)?))?;
+    }
+    Ok(())
+}
+
+fn bench<F: FnMut() -> io::Result<u64>>(name: &str, mut decode: F) -> io::Result<()> {
+    let mut iterations = 0u32;
+    let mut total = 0u64;
+    let start = Instant::now();
+    let mut best = Duration::MAX;
+    while iterations < MIN_ITERATIONS || start.elapsed() < MIN_DURATION {
+        let now = Instant::now();
+        total += decode()?;
+        best = best.min(now.elapsed());
+        iterations += 1;
+    }
+    let elapsed = start.elapsed();
+    let size = total / iterations as u64;
+    println!("{:<45} {:>9} bytes {:>5} iterations, mean: {:>9.3?} best: {:>9.3?} {:>7.2} MiB/s",
+        name, size, iterations, elapsed / iterations, best,
+        size as f64 / best.as_secs_f64() / (1024.0 * 1024.0));
+    Ok(())
+}
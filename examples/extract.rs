@@ -50,7 +50,7 @@ fn main() -> io::Result<()> {
     let lha_reader = delharc::parse_file(ARCHIVE_NAME)?;
     extract_to_stdout(lha_reader, FILE_MATCH)?;
 
-    eprintln!("");
+    eprintln!();
     eprintln!("================================================================");
     eprintln!("  Extracting from &[u8]...");
     eprintln!("================================================================");
@@ -58,7 +58,7 @@ fn main() -> io::Result<()> {
     let lha_reader = delharc::LhaDecodeReader::new(SLICE)?;
     extract_to_stdout(lha_reader, FILE_MATCH)?;
 
-    eprintln!("");
+    eprintln!();
     eprintln!("================================================================");
     eprintln!("  Extracting from Cursor<Vec<u8>>...");
     eprintln!("================================================================");
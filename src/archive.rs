@@ -0,0 +1,569 @@
+//! # Archive listing and random access.
+//!
+//! [LhaArchive] provides operations on the whole archive, such as listing the archived files
+//! without decompressing their content, summarizing the archive, testing the integrity of
+//! the archived files or extracting single files from seekable sources.
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::decode::{CrcPolicy, LhaDecodeReader, OwnedEntryReader};
+use crate::error::LhaError;
+use crate::header::{CompressionMethod, LhaHeader, TimestampResult};
+
+/// A summary of an archived file, produced by [LhaArchive::list].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntrySummary {
+    /// The path name of the file, see [LhaHeader::parse_pathname].
+    pub path: PathBuf,
+    /// The compression method of the file.
+    pub compression: CompressionMethod,
+    /// The size of the compressed content in bytes.
+    pub compressed_size: u64,
+    /// The size of the decompressed content in bytes.
+    pub original_size: u64,
+    /// The CRC-16 checksum of the decompressed content.
+    pub file_crc: u16,
+    /// The last modified timestamp, see [LhaHeader::parse_last_modified].
+    pub last_modified: TimestampResult,
+    /// `true` if the entry is a directory or a symbolic link, see [LhaHeader::is_directory].
+    pub is_directory: bool,
+    /// The offset of the header from the beginning of the archive stream.
+    pub header_offset: u64,
+    /// The offset of the compressed content from the beginning of the archive stream.
+    pub content_offset: u64
+}
+
+impl EntrySummary {
+    /// Creates a summary of the file described by the `header`, found in the archive stream
+    /// at `header_offset`.
+    pub fn new(header: &LhaHeader, header_offset: u64) -> Self {
+        EntrySummary {
+            path: header.parse_pathname(),
+            compression: CompressionMethod::from(header.compression),
+            compressed_size: header.compressed_size,
+            original_size: header.original_size,
+            file_crc: header.file_crc,
+            last_modified: header.parse_last_modified(),
+            is_directory: header.is_directory(),
+            header_offset,
+            content_offset: header_offset + header.header_size
+        }
+    }
+}
+
+/// The totals of an archive, produced by [LhaArchive::stats] or collected with [ArchiveStats::add].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArchiveStats {
+    /// The number of entries, including directories.
+    pub entries: u64,
+    /// The number of directories and symbolic links, see [LhaHeader::is_directory].
+    pub directories: u64,
+    /// The total size of the compressed content in bytes.
+    pub compressed_size: u64,
+    /// The total size of the decompressed content in bytes.
+    pub original_size: u64,
+    /// The number of entries of each compression method, in the order of their first occurrence.
+    pub methods: Vec<(CompressionMethod, u64)>,
+    /// The number of headers of each level, indexed by the level.
+    pub header_levels: [u64;4]
+}
+
+impl ArchiveStats {
+    /// Adds the entry described by the `header` to the totals.
+    pub fn add(&mut self, header: &LhaHeader) {
+        self.entries += 1;
+        self.directories += header.is_directory() as u64;
+        self.compressed_size = self.compressed_size.saturating_add(header.compressed_size);
+        self.original_size = self.original_size.saturating_add(header.original_size);
+        let compression = CompressionMethod::from(header.compression);
+        match self.methods.iter_mut().find(|(method, _)| *method == compression) {
+            Some((_, count)) => *count += 1,
+            None => self.methods.push((compression, 1))
+        }
+        if let Some(count) = self.header_levels.get_mut(header.level as usize) {
+            *count += 1;
+        }
+    }
+    /// Returns the number of entries compressed with the given `method`.
+    pub fn method_count(&self, method: CompressionMethod) -> u64 {
+        self.methods.iter().find(|(m, _)| *m == method).map_or(0, |&(_, count)| count)
+    }
+    /// Returns the ratio of the compressed size to the original size, e.g. `0.25` if the archive
+    /// is four times smaller than its content, or `None` if the original size is `0`.
+    pub fn ratio(&self) -> Option<f64> {
+        (self.original_size != 0).then(|| self.compressed_size as f64 / self.original_size as f64)
+    }
+}
+
+/// The result of verifying the archived files, produced by [LhaArchive::verify].
+#[derive(Debug)]
+pub struct VerifyReport {
+    /// The results of verifying each of the archived files, in the order of the archive.
+    pub entries: Vec<VerifiedEntry>,
+    /// The error which stopped reading the archive, e.g. a malformed header, if any.
+    pub error: Option<io::Error>
+}
+
+/// The result of verifying an archived file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VerifiedEntry {
+    /// The summary of the file.
+    pub summary: EntrySummary,
+    /// The result of decompressing the file.
+    pub status: VerifyStatus
+}
+
+/// The status of a verified file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum VerifyStatus {
+    /// The file has been decompressed successfully and its checksum matches, or the entry is
+    /// a directory.
+    Ok,
+    /// The checksum of the decompressed content doesn't match the checksum from the header.
+    CrcMismatch {
+        /// The checksum found in the header.
+        expected: u16,
+        /// The checksum of the decompressed content.
+        got: u16
+    },
+    /// The compressed and the original size of a file stored without compression differ.
+    SizeMismatch {
+        /// The size of the stored content in bytes.
+        compressed_size: u64,
+        /// The size of the file in bytes.
+        original_size: u64
+    },
+    /// The compression method of the file is not supported.
+    Unsupported,
+    /// The archive ends before the end of the file's compressed content.
+    Truncated,
+    /// The compressed content could not be decompressed, described by the error message.
+    Corrupted(String)
+}
+
+impl VerifyReport {
+    /// Returns `true` if all files have been verified successfully.
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none() && self.entries.iter().all(|entry| entry.status == VerifyStatus::Ok)
+    }
+    /// Returns an iterator through the files which failed verification.
+    pub fn failed(&self) -> impl Iterator<Item=&VerifiedEntry> + '_ {
+        self.entries.iter().filter(|entry| entry.status != VerifyStatus::Ok)
+    }
+}
+
+/// An LHA archive read from the stream reader `R`.
+///
+/// With a seekable source the archived files can be accessed in any order, see
+/// [LhaArchive::by_name]. The index of the files is built lazily, on the first access, by reading
+/// all of the headers and seeking over the compressed content.
+#[derive(Debug)]
+pub struct LhaArchive<R> {
+    rd: R,
+    start: u64,
+    index: Option<Vec<EntrySummary>>
+}
+
+impl<R> LhaArchive<R> {
+    /// Creates an archive reading from `rd`, positioned at the beginning of the archive.
+    pub fn new(rd: R) -> Self {
+        LhaArchive { rd, start: 0, index: None }
+    }
+    /// Gets a reference to the underlying stream reader.
+    pub fn get_ref(&self) -> &R {
+        &self.rd
+    }
+    /// Unwraps the underlying stream reader and returns it.
+    pub fn into_inner(self) -> R {
+        self.rd
+    }
+}
+
+impl<R: Read> LhaArchive<R> {
+    /// Lists all files of the archive read from `rd`, without decompressing their content.
+    ///
+    /// The headers are being read one after another, the compressed content is being read and
+    /// discarded. The offsets are counted from the current position of `rd`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use delharc::archive::LhaArchive;
+    ///
+    /// let file = std::fs::File::open("archive.lzh")?;
+    /// for entry in LhaArchive::list(file)? {
+    ///     println!("{} {:>8} {}", entry.compression, entry.original_size, entry.path.display());
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if a header could not be read or parsed or the archive ends before
+    /// the end of the compressed content of a file.
+    pub fn list(mut rd: R) -> io::Result<Vec<EntrySummary>> {
+        read_index(&mut rd, skip_content)
+    }
+    /// Summarizes the archive read from `rd`, without decompressing the content of the files.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use delharc::archive::LhaArchive;
+    ///
+    /// let stats = LhaArchive::stats(std::fs::File::open("archive.lzh")?)?;
+    /// println!("{} files, {} bytes", stats.entries - stats.directories, stats.original_size);
+    /// for (method, count) in &stats.methods {
+    ///     println!("{}: {}", method, count);
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if a header could not be read or parsed or the archive ends before
+    /// the end of the compressed content of a file.
+    pub fn stats(mut rd: R) -> io::Result<ArchiveStats> {
+        let mut stats = ArchiveStats::default();
+        while let Some(header) = LhaHeader::read(rd.by_ref())? {
+            stats.add(&header);
+            skip_content(&mut rd, header.compressed_size)?;
+        }
+        Ok(stats)
+    }
+    /// Tests the integrity of the archive read from `rd`, like `lha t`, decompressing the content
+    /// of every archived file and verifying its checksum.
+    ///
+    /// The errors of the archived files are being reported in [VerifyReport::entries] and don't
+    /// prevent verifying the following files. An error which prevents reading the following headers
+    /// is being reported in [VerifyReport::error].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use delharc::archive::LhaArchive;
+    ///
+    /// let report = LhaArchive::verify(std::fs::File::open("archive.lzh")?)?;
+    /// for entry in report.failed() {
+    ///     println!("{}: {:?}", entry.summary.path.display(), entry.status);
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the first header could not be read or parsed.
+    pub fn verify(rd: R) -> io::Result<VerifyReport> {
+        let mut reader = LhaDecodeReader::default().with_crc_policy(CrcPolicy::Always);
+        let mut entries = Vec::new();
+        let mut error = None;
+        let mut more = reader.begin_new(rd)?;
+        while more {
+            let header = reader.header();
+            let summary = EntrySummary::new(header, header.header_offset.unwrap_or(0));
+            let status = verify_entry(&mut reader);
+            entries.push(VerifiedEntry { summary, status });
+            match reader.next_file() {
+                Ok(next) => more = next,
+                Err(e) => {
+                    error = Some(e.into());
+                    break
+                }
+            }
+        }
+        Ok(VerifyReport { entries, error })
+    }
+}
+
+impl<R: Read + Seek> LhaArchive<R> {
+    /// Opens the file with the given path name for reading.
+    ///
+    /// The `name` is compared with the [EntrySummary::path] of the archived files, so the directory
+    /// separators of the archive don't matter. If many files share the same path name, the first one
+    /// is opened.
+    ///
+    /// The checksum of the content is verified by the returned reader when the end of the file is
+    /// reached. Reading a file with an unsupported compression method results in an error.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::io::Read;
+    /// use delharc::archive::LhaArchive;
+    ///
+    /// let mut archive = LhaArchive::new(std::fs::File::open("archive.lzh")?);
+    /// let mut content = String::new();
+    /// archive.by_name("dir/file.txt")?.read_to_string(&mut content)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error of the kind [io::ErrorKind::NotFound] if there is no such file in
+    /// the archive. Other errors are returned if the index could not be built or the header of
+    /// the file could not be read again.
+    pub fn by_name<P: AsRef<Path>>(&mut self, name: P) -> io::Result<OwnedEntryReader<&mut R>> {
+        let name = name.as_ref();
+        let offset = self.index()?.iter()
+            .find(|entry| entry.path == name)
+            .map(|entry| entry.header_offset)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found in archive"))?;
+        self.open_at(offset)
+    }
+
+    /// Opens the `index`-th file of the archive for reading, counting from `0`.
+    ///
+    /// # Errors
+    /// Returns an error of the kind [io::ErrorKind::NotFound] if the `index` is not less than
+    /// the number of files, see [LhaArchive::len]. Other errors are returned if the index could not
+    /// be built or the header of the file could not be read again.
+    pub fn by_index(&mut self, index: usize) -> io::Result<OwnedEntryReader<&mut R>> {
+        let offset = self.index()?.get(index)
+            .map(|entry| entry.header_offset)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file index out of range"))?;
+        self.open_at(offset)
+    }
+    /// Returns the number of files in the archive.
+    ///
+    /// # Errors
+    /// Returns an error if the index could not be built.
+    pub fn len(&mut self) -> io::Result<usize> {
+        self.index().map(|index| index.len())
+    }
+    /// Returns `true` if there are no files in the archive.
+    ///
+    /// # Errors
+    /// Returns an error if the index could not be built.
+    pub fn is_empty(&mut self) -> io::Result<bool> {
+        self.len().map(|len| len == 0)
+    }
+    /// Returns the summaries of all files in the archive in the order of the files, indexed the same
+    /// as in [LhaArchive::by_index].
+    ///
+    /// # Errors
+    /// Returns an error if a header could not be read or parsed.
+    pub fn index(&mut self) -> io::Result<&[EntrySummary]> {
+        if self.index.is_none() {
+            self.start = self.rd.stream_position()?;
+            let index = read_index(&mut self.rd, |rd, size| {
+                let offset = i64::try_from(size).map_err(|_|
+                    io::Error::new(io::ErrorKind::InvalidData, "compressed size too large"))?;
+                rd.seek(SeekFrom::Current(offset)).map(drop)
+            })?;
+            self.index = Some(index);
+        }
+        Ok(self.index.as_deref().unwrap())
+    }
+
+    fn open_at(&mut self, header_offset: u64) -> io::Result<OwnedEntryReader<&mut R>> {
+        self.rd.seek(SeekFrom::Start(self.start + header_offset))?;
+        Ok(LhaDecodeReader::new(&mut self.rd)?.into_entry())
+    }
+}
+
+/// Decompresses the current file of the `reader` and verifies its content.
+fn verify_entry<R: Read>(reader: &mut LhaDecodeReader<R>) -> VerifyStatus {
+    let header = reader.header();
+    if header.is_directory() {
+        return VerifyStatus::Ok
+    }
+    if !reader.is_decoder_supported() {
+        return VerifyStatus::Unsupported
+    }
+    let stored = matches!(header.compression_method(),
+                          Ok(CompressionMethod::Lh0|CompressionMethod::Lz4|CompressionMethod::Pm0));
+    if stored && header.compressed_size != header.original_size {
+        let (compressed_size, original_size) = (header.compressed_size, header.original_size);
+        return VerifyStatus::SizeMismatch { compressed_size, original_size }
+    }
+    match io::copy(reader, &mut io::sink()).and_then(|_| reader.crc_check()).map_err(LhaError::from) {
+        Ok(..) => VerifyStatus::Ok,
+        Err(LhaError::BadChecksum { expected, got }) => VerifyStatus::CrcMismatch { expected, got },
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => VerifyStatus::Truncated,
+        Err(e) => VerifyStatus::Corrupted(e.to_string())
+    }
+}
+
+/// Reads and discards `size` bytes of the compressed content.
+fn skip_content<R: Read>(rd: &mut R, size: u64) -> io::Result<()> {
+    let skipped = io::copy(&mut rd.by_ref().take(size), &mut io::sink())?;
+    if skipped < size {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected end of archive"))
+    }
+    Ok(())
+}
+
+/// Reads all headers from `rd`, skipping over the compressed content with `skip`.
+fn read_index<R, F>(rd: &mut R, mut skip: F) -> io::Result<Vec<EntrySummary>>
+    where R: Read, F: FnMut(&mut R, u64) -> io::Result<()>
+{
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = LhaHeader::read(rd.by_ref())? {
+        let entry = EntrySummary::new(&header, offset);
+        skip(rd, header.compressed_size)?;
+        offset = entry.content_offset + header.compressed_size;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use crate::fixtures::stored_archive;
+    use crate::header::LhaHeaderBuilder;
+    use super::*;
+
+    #[test]
+    fn list_works() {
+        let mut archive = Vec::new();
+        let header = LhaHeaderBuilder::new().level(0).path("foo.txt").compression(CompressionMethod::Lh5)
+                                            .compressed_size(3).original_size(10).file_crc(0x1234)
+                                            .build().unwrap();
+        archive.extend_from_slice(&header.to_bytes().unwrap());
+        archive.extend_from_slice(b"abc");
+        let second = archive.len() as u64;
+        let header = LhaHeaderBuilder::new().level(2).path("dir").compression(CompressionMethod::Lhd)
+                                            .build().unwrap();
+        archive.extend_from_slice(&header.to_bytes().unwrap());
+        archive.push(0);
+        let entries = LhaArchive::list(&archive[..]).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, PathBuf::from("foo.txt"));
+        assert_eq!(entries[0].compression, CompressionMethod::Lh5);
+        assert_eq!((entries[0].compressed_size, entries[0].original_size), (3, 10));
+        assert_eq!(entries[0].file_crc, 0x1234);
+        assert!(!entries[0].is_directory);
+        assert_eq!(entries[0].header_offset, 0);
+        assert_eq!(entries[0].content_offset, second - 3);
+        assert_eq!(&archive[entries[0].content_offset as usize..][..3], b"abc");
+        assert_eq!(entries[1].path, PathBuf::from("dir"));
+        assert!(entries[1].is_directory);
+        assert_eq!(entries[1].header_offset, second);
+        assert_eq!(entries[1].content_offset, archive.len() as u64 - 1);
+
+        assert!(LhaArchive::list(&[0][..]).unwrap().is_empty());
+        let err = LhaArchive::list(&archive[..second as usize - 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn by_name_works() {
+        let files: &[(&str, &[u8])] = &[("foo", b"Hello"), ("dir/bar", b"World!"), ("foo", b"again")];
+        let mut data = b"prefix".to_vec();
+        data.extend_from_slice(&stored_archive(files));
+        let mut rd = Cursor::new(data);
+        rd.set_position(6);
+        let mut archive = LhaArchive::new(rd);
+        let mut content = Vec::new();
+        let mut entry = archive.by_name("dir/bar").unwrap();
+        assert_eq!(entry.header().original_size, 6);
+        assert!(entry.is_decoder_supported());
+        entry.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"World!");
+        drop(entry);
+        content.clear();
+        archive.by_name(Path::new("foo")).unwrap().read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"Hello");
+        assert_eq!(archive.by_name("bar").unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        let mut data = stored_archive(files);
+        let offset = data.iter().position(|&c| c == b'W').unwrap();
+        data[offset] = b'w';
+        let mut archive = LhaArchive::new(Cursor::new(data));
+        content.clear();
+        let err = archive.by_name("dir/bar").unwrap().read_to_end(&mut content).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(content, b"world!");
+    }
+
+    #[test]
+    fn by_index_works() {
+        let files: &[(&str, &[u8])] = &[("foo", b"Hello"), ("dir/bar", b"World!"), ("baz", b"")];
+        let mut archive = LhaArchive::new(Cursor::new(stored_archive(files)));
+        assert_eq!(archive.len().unwrap(), 3);
+        assert!(!archive.is_empty().unwrap());
+        for index in [2, 0, 1] {
+            let mut content = Vec::new();
+            let mut entry = archive.by_index(index).unwrap();
+            assert_eq!(entry.header().parse_pathname(), archive_path(files[index].0));
+            entry.read_to_end(&mut content).unwrap();
+            assert_eq!(content, files[index].1);
+        }
+        assert_eq!(archive.index().unwrap()[1].path, archive_path("dir/bar"));
+        assert_eq!(archive.by_index(3).unwrap_err().kind(), io::ErrorKind::NotFound);
+        let mut archive = LhaArchive::new(Cursor::new([0]));
+        assert_eq!(archive.len().unwrap(), 0);
+        assert!(archive.is_empty().unwrap());
+    }
+
+    #[test]
+    fn stats_works() {
+        let mut archive = stored_archive(&[("foo", b"Hello"), ("bar", b"World!")]);
+        archive.pop();
+        let header = LhaHeaderBuilder::new().level(0).path("baz").compression(CompressionMethod::Lh5)
+                     .compressed_size(3).original_size(9).build().unwrap();
+        archive.extend_from_slice(&header.to_bytes().unwrap());
+        archive.extend_from_slice(b"abc");
+        let header = LhaHeaderBuilder::new().path("dir").compression(CompressionMethod::Lhd).build().unwrap();
+        archive.extend_from_slice(&header.to_bytes().unwrap());
+        archive.push(0);
+        let stats = LhaArchive::stats(&archive[..]).unwrap();
+        assert_eq!(stats.entries, 4);
+        assert_eq!(stats.directories, 1);
+        assert_eq!(stats.compressed_size, 14);
+        assert_eq!(stats.original_size, 20);
+        assert_eq!(stats.ratio(), Some(0.7));
+        assert_eq!(stats.methods, [(CompressionMethod::Lh0, 2), (CompressionMethod::Lh5, 1), (CompressionMethod::Lhd, 1)]);
+        assert_eq!(stats.method_count(CompressionMethod::Lh0), 2);
+        assert_eq!(stats.method_count(CompressionMethod::Lh7), 0);
+        assert_eq!(stats.header_levels, [1, 0, 3, 0]);
+
+        let stats = LhaArchive::stats(&[0][..]).unwrap();
+        assert_eq!(stats, ArchiveStats::default());
+        assert_eq!(stats.ratio(), None);
+        archive.truncate(archive.len() - 10);
+        assert_eq!(LhaArchive::stats(&archive[..]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn verify_works() {
+        let files: &[(&str, &[u8])] = &[("foo", b"Hello"), ("bar", b"World!")];
+        let report = LhaArchive::verify(&stored_archive(files)[..]).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.entries.len(), 2);
+        assert_eq!(report.entries[1].summary.path, archive_path("bar"));
+
+        let mut archive = stored_archive(files);
+        archive.pop();
+        let index = archive.len() - 1;
+        archive[index] = b'?';
+        for (method, compressed_size) in [(*b"-lhz-", 3), (*b"-lh0-", 2)] {
+            let header = LhaHeaderBuilder::new().path("baz").raw_compression(method)
+                         .compressed_size(compressed_size).original_size(3).build().unwrap();
+            archive.extend_from_slice(&header.to_bytes().unwrap());
+            archive.extend_from_slice(&b"abc"[..compressed_size as usize]);
+        }
+        let header = LhaHeaderBuilder::new().path("dir").compression(CompressionMethod::Lhd).build().unwrap();
+        archive.extend_from_slice(&header.to_bytes().unwrap());
+        let header = LhaHeaderBuilder::new().path("qux").compressed_size(5).original_size(5).build().unwrap();
+        archive.extend_from_slice(&header.to_bytes().unwrap());
+        archive.extend_from_slice(b"abc");
+        let report = LhaArchive::verify(&archive[..]).unwrap();
+        assert!(!report.is_ok());
+        let statuses: Vec<_> = report.entries.iter().map(|entry| &entry.status).collect();
+        assert_eq!(statuses[0], &VerifyStatus::Ok);
+        assert!(matches!(statuses[1], VerifyStatus::CrcMismatch { .. }));
+        assert_eq!(statuses[2], &VerifyStatus::Unsupported);
+        assert_eq!(statuses[3], &VerifyStatus::SizeMismatch { compressed_size: 2, original_size: 3 });
+        assert_eq!(statuses[4], &VerifyStatus::Ok);
+        assert_eq!(statuses[5], &VerifyStatus::Truncated);
+        assert_eq!(report.failed().count(), 4);
+        assert_eq!(statuses.len(), 6);
+
+        assert!(LhaArchive::verify(&[0][..]).unwrap().entries.is_empty());
+        assert!(LhaArchive::verify(&b"junk"[..]).is_err());
+    }
+
+    fn archive_path(path: &str) -> PathBuf {
+        path.split('/').collect()
+    }
+}
@@ -1,10 +1,17 @@
 //! # Bit-stream tools.
-use core::mem;
+use core::{fmt, mem};
 use std::io::{self, Read};
 
-type BitBuf = usize;
+type BitBuf = u64;
 const BITBUF_BYTESIZE: usize = mem::size_of::<BitBuf>();
 const BITBUF_BITSIZE: u32 = (BITBUF_BYTESIZE * 8) as u32;
+/// The number of bits guaranteed to be available in the bit buffer after a refill,
+/// unless the stream ends.
+const BITBUF_REFILL_BITS: u32 = BITBUF_BITSIZE - 8;
+/// The default size of the read buffer.
+pub const READ_BUFFER_SIZE: usize = 4096;
+/// The minimum size of the read buffer, holding at least two refills of the bit buffer.
+const MIN_READ_BUFFER_SIZE: usize = 2 * BITBUF_BYTESIZE;
 
 /// The trait is implemented for all the types that can receive bits using [BitRead::read_bits].
 pub trait UBits: Copy {
@@ -25,21 +32,44 @@ pub trait BitRead {
     /// # Panics
     /// Panics if `n` exceed the bit capacity of `T`.
     fn read_bits<T: UBits>(&mut self, n: u32) -> io::Result<T>;
+    /// Returns the next `n` bits from the stream without consuming them.
+    ///
+    /// If the stream ends before `n` bits are available, the missing bits are returned as `0`.
+    /// Use [BitRead::skip_bits] to consume the bits afterwards.
+    ///
+    /// Returns `0` if `n` is `0`.
+    ///
+    /// # Panics
+    /// Panics if `n` exceeds [BitRead::MAX_PEEK_BITS].
+    fn peek_bits(&mut self, n: u32) -> io::Result<usize>;
+    /// Consumes the next `n` bits from the stream.
+    ///
+    /// # Panics
+    /// Panics if `n` exceeds [BitRead::MAX_PEEK_BITS].
+    fn skip_bits(&mut self, n: u32) -> io::Result<()>;
+    /// The maximum number of bits that can be peeked at once.
+    const MAX_PEEK_BITS: u32 = 24;
     /// Creates a "by reference" adaptor for this instance of `BitRead`.
     /// The returned adaptor also implements `BitRead` and will simply borrow this current reader.
+    #[allow(dead_code)]
     fn by_ref(&mut self) -> &mut Self {
         self
     }
 }
 
-/// A simple bit-stream reader, wrapped over a readable stream.
+/// A bit-stream reader, wrapped over a readable stream.
 ///
 /// Bits are being read from an each consecutive byte, starting from its highest bit.
-#[derive(Debug)]
+///
+/// The data is being read from the stream in bulk into the internal buffer, from which the 64-bit
+/// bit buffer is being refilled with as many bytes as it can hold at once.
 pub struct BitStream<R> {
     inner: R,
     // x..x10..0
     bits_buf: BitBuf,
+    buffer: Box<[u8]>,
+    pos: usize,
+    end: usize
 }
 
 macro_rules! impl_ubits {
@@ -56,63 +86,140 @@ macro_rules! impl_ubits {
 impl_ubits!(u8);
 impl_ubits!(u16);
 impl_ubits!(u32);
+impl_ubits!(u64);
 impl_ubits!(usize);
 
 impl<R: Read> BitStream<R> {
     /// Creates a new `BitStream<R>`.
     pub fn new(inner: R) -> BitStream<R> {
-        BitStream { inner, bits_buf: 1 << (BITBUF_BITSIZE - 1) }
+        BitStream {
+            inner,
+            bits_buf: 1 << (BITBUF_BITSIZE - 1),
+            buffer: vec![0u8;READ_BUFFER_SIZE].into_boxed_slice(),
+            pos: 0,
+            end: 0
+        }
     }
     /// Unwraps this `BitStream<R>`, returning the underlying reader.
     ///
-    /// Note that any leftover data in the internal bit buffer is lost. Therefore, a following read from
+    /// Note that any leftover data in the internal buffers is lost. Therefore, a following read from
     /// the underlying reader may lead to data loss.
     pub fn into_inner(self) -> R {
         self.inner
     }
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+    /// Resizes the internal read buffer to `size` bytes, but not less than the data already buffered
+    /// or the minimum size needed to refill the bit buffer. The buffered data is preserved.
+    pub fn set_buffer_size(&mut self, size: usize) {
+        let size = size.max(MIN_READ_BUFFER_SIZE).max(self.end - self.pos);
+        if size != self.buffer.len() {
+            let mut buffer = vec![0u8;size].into_boxed_slice();
+            buffer[..self.end - self.pos].copy_from_slice(&self.buffer[self.pos..self.end]);
+            self.end -= self.pos;
+            self.pos = 0;
+            self.buffer = buffer;
+        }
+    }
+    /// Replaces the underlying reader with `inner`, returning a new `BitStream<T>` together with
+    /// the previous reader. The internal buffers are being reused, but their content is discarded.
+    pub fn replace_inner<T: Read>(self, inner: T) -> (BitStream<T>, R) {
+        let bit_stream = BitStream {
+            inner,
+            bits_buf: 1 << (BITBUF_BITSIZE - 1),
+            buffer: self.buffer,
+            pos: 0,
+            end: 0
+        };
+        (bit_stream, self.inner)
+    }
+
+    #[inline]
+    fn have_bits(&self) -> u32 {
+        BITBUF_BITSIZE - self.bits_buf.trailing_zeros() - 1
+    }
 
     #[inline]
     fn next_bits(&mut self, n: u32) -> io::Result<BitBuf> {
         debug_assert!(n != 0 && n <= BITBUF_BITSIZE);
-        let have_bits = BITBUF_BITSIZE - self.bits_buf.trailing_zeros() - 1;
-        let res = self.bits_buf >> BITBUF_BITSIZE - n;
-
-        if n <= have_bits {
-            self.bits_buf <<= n;
-            return Ok(res)
+        let mut have_bits = self.have_bits();
+        if n > have_bits {
+            have_bits = self.refill()?;
+            if n > have_bits {
+                if n > BITBUF_REFILL_BITS {
+                    let high = self.next_bits(n - 32)?;
+                    let low = self.next_bits(32)?;
+                    return Ok(high << 32 | low)
+                }
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "some bits are missing from stream"))
+            }
         }
+        let res = self.bits_buf >> (BITBUF_BITSIZE - n);
+        self.bits_buf <<= n;
+        Ok(res)
+    }
 
-        let missing_bits = n - have_bits;
-        let mut buf = [0u8;BITBUF_BYTESIZE];
-        let bits_read = 8 * self.read_exact_or_to_end(&mut buf)? as u32;
-        if bits_read < missing_bits {
-            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "some bits are missing from stream"))
+    /// Refills the bit buffer with as many whole bytes as it can hold.
+    ///
+    /// Returns the number of bits available in the bit buffer.
+    #[inline]
+    fn refill(&mut self) -> io::Result<u32> {
+        let have_bits = self.have_bits();
+        if self.end - self.pos < BITBUF_BYTESIZE {
+            self.fill_read_buffer()?;
+        }
+        let free_bytes = (BITBUF_BITSIZE - 1 - have_bits) as usize / 8;
+        let nbytes = free_bytes.min(self.end - self.pos);
+        if nbytes == 0 {
+            return Ok(have_bits)
         }
-        let new_bits: BitBuf = BitBuf::from_be_bytes(buf);
-        // clear trailing bits and merge
-        let res = res & res - 1
-                | new_bits >> BITBUF_BITSIZE - missing_bits;
-        self.bits_buf = if missing_bits == BITBUF_BITSIZE {
-            0
+        let new_bits = if let Some(bytes) = self.buffer.get(self.pos..self.pos + BITBUF_BYTESIZE) {
+            BitBuf::from_be_bytes(bytes.try_into().unwrap())
         }
         else {
-            new_bits << missing_bits
-        } | 1 << BITBUF_BITSIZE - 1 - (bits_read - missing_bits);
-        Ok(res)
+            let mut bytes = [0u8;BITBUF_BYTESIZE];
+            bytes[..nbytes].copy_from_slice(&self.buffer[self.pos..self.pos + nbytes]);
+            BitBuf::from_be_bytes(bytes)
+        };
+        self.pos += nbytes;
+        let bits_read = 8 * nbytes as u32;
+        // nbytes is less than BITBUF_BYTESIZE so the shift can't overflow
+        let new_bits = new_bits & !(BitBuf::MAX >> bits_read);
+        // clear the marker bit, merge and put the marker after the new bits
+        self.bits_buf = (self.bits_buf & (self.bits_buf - 1))
+                      | (new_bits >> have_bits)
+                      | (1 << (BITBUF_BITSIZE - 1 - have_bits - bits_read));
+        Ok(have_bits + bits_read)
     }
 
-    #[inline]
-    fn read_exact_or_to_end(&mut self, mut buf: &mut[u8]) -> io::Result<usize> {
-        let orig_len = buf.len();
-        while !buf.is_empty() {
-            match self.inner.read(buf) {
+    /// Moves the unread data to the beginning of the read buffer and reads more data from the stream,
+    /// until enough bytes to fill the bit buffer are buffered or the stream ends.
+    #[cold]
+    fn fill_read_buffer(&mut self) -> io::Result<()> {
+        self.buffer.copy_within(self.pos..self.end, 0);
+        self.end -= self.pos;
+        self.pos = 0;
+        while self.end < BITBUF_BYTESIZE {
+            match self.inner.read(&mut self.buffer[self.end..]) {
                 Ok(0) => break,
-                Ok(n) => buf = &mut buf[n..],
+                Ok(n) => self.end += n,
                 Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
                 Err(e) => return Err(e),
             }
         }
-        Ok(orig_len - buf.len())
+        Ok(())
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for BitStream<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BitStream")
+         .field("inner", &self.inner)
+         .field("bits_buf", &self.bits_buf)
+         .field("buffered", &(self.end - self.pos))
+         .finish()
     }
 }
 
@@ -126,6 +233,16 @@ impl<R: BitRead> BitRead for &mut R {
     fn read_bits<T: UBits>(&mut self, n: u32) -> io::Result<T> {
         (*self).read_bits(n)
     }
+
+    #[inline]
+    fn peek_bits(&mut self, n: u32) -> io::Result<usize> {
+        (*self).peek_bits(n)
+    }
+
+    #[inline]
+    fn skip_bits(&mut self, n: u32) -> io::Result<()> {
+        (*self).skip_bits(n)
+    }
 }
 
 impl<R: Read> BitRead for BitStream<R> {
@@ -141,6 +258,32 @@ impl<R: Read> BitRead for BitStream<R> {
             _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "too many bits requested"))
         }.map(T::from_bits)
     }
+
+    #[inline]
+    fn peek_bits(&mut self, n: u32) -> io::Result<usize> {
+        assert!(n <= Self::MAX_PEEK_BITS);
+        if n == 0 {
+            return Ok(0)
+        }
+        if n > self.have_bits() {
+            self.refill()?;
+        }
+        // clear the marker bit
+        Ok(((self.bits_buf & (self.bits_buf - 1)) >> (BITBUF_BITSIZE - n)) as usize)
+    }
+
+    #[inline]
+    fn skip_bits(&mut self, n: u32) -> io::Result<()> {
+        assert!(n <= Self::MAX_PEEK_BITS);
+        if n == 0 {
+            return Ok(())
+        }
+        if n > self.have_bits() && n > self.refill()? {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "some bits are missing from stream"))
+        }
+        self.bits_buf <<= n;
+        Ok(())
+    }
 }
 
 #[inline(always)]
@@ -153,8 +296,8 @@ mod tests {
     use super::*;
     #[test]
     fn bit_stream_works() {
-        assert_eq!(BITBUF_BYTESIZE, mem::size_of::<usize>());
-        assert!(BITBUF_BITSIZE >= 32);
+        assert_eq!(BITBUF_BYTESIZE, mem::size_of::<u64>());
+        const _: () = assert!(BITBUF_BITSIZE >= 32);
         assert_eq!(BITBUF_BITSIZE, BITBUF_BYTESIZE as u32 * 8);
         let mut somebits: &[u8] = &[];
         let mut brdr = BitStream::new(&mut somebits);
@@ -163,12 +306,12 @@ mod tests {
         let mut somebits: &[u8] = &[0];
         let mut brdr = BitStream::new(&mut somebits);
         for _ in 0..8 {
-            assert_eq!(brdr.read_bit().unwrap(), false);
+            assert!(!brdr.read_bit().unwrap());
         }
         let mut somebits: &[u8] = &[!0];
         let mut brdr = BitStream::new(&mut somebits);
         for _ in 0..8 {
-            assert_eq!(brdr.read_bit().unwrap(), true);
+            assert!(brdr.read_bit().unwrap());
         }
         let mut somebits: &[u8] = &[0b01001100, 0b01110000, 0b11110000, 0b01111100, 0b00001111, 0b11000000, 0b01111111,
                                     0b00000000, 0b11111111, 0b00000000, 0b01111111, 0b11000000, 0b00001111, 0b11111100,
@@ -186,16 +329,89 @@ mod tests {
 
         let mut somebits: &[u8] = &[1,2,3,4,5,6,7,8];
         let mut brdr = BitStream::new(&mut somebits);
-        match BITBUF_BITSIZE {
-            #[cfg(target_pointer_width = "64")]
-            64 => {
-                assert_eq!(brdr.read_bits::<usize>(BITBUF_BITSIZE).unwrap(), 0x0102030405060708);
+        assert_eq!(brdr.read_bits::<u64>(BITBUF_BITSIZE).unwrap(), 0x0102030405060708);
+        assert_eq!(brdr.read_bit().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        let mut somebits: &[u8] = &[1,2,3,4,5,6,7,8,9];
+        let mut brdr = BitStream::new(&mut somebits);
+        assert!(!brdr.read_bit().unwrap());
+        assert_eq!(brdr.read_bits::<u64>(BITBUF_BITSIZE).unwrap(), 0x020406080a0c0e10);
+        assert_eq!(brdr.read_bits::<u8>(7).unwrap(), 9);
+
+        let mut brdr = BitStream::new(&[0xff,0xff][..]);
+        assert_eq!(brdr.read_bits::<u8>(3).unwrap(), 7);
+        let (mut brdr, rest) = brdr.replace_inner(&[0x81][..]);
+        assert!(rest.is_empty());
+        assert_eq!(brdr.read_bits::<u8>(8).unwrap(), 0x81);
+        assert_eq!(brdr.read_bit().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn set_buffer_size_works() {
+        let data: Vec<u8> = (0..100).collect();
+        let mut brdr = BitStream::new(&data[..]);
+        assert_eq!(brdr.read_bits::<u8>(8).unwrap(), 0);
+        brdr.set_buffer_size(1);
+        assert_eq!(brdr.buffer.len(), 93);
+        assert_eq!(brdr.read_bits::<u8>(8).unwrap(), 1);
+        let mut brdr = BitStream::new(&data[..]);
+        brdr.set_buffer_size(0);
+        assert_eq!(brdr.buffer.len(), MIN_READ_BUFFER_SIZE);
+        for n in 0..100 {
+            assert_eq!(brdr.read_bits::<u8>(8).unwrap(), n);
+            if n == 50 {
+                brdr.set_buffer_size(64);
+            }
+        }
+        assert_eq!(brdr.read_bit().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn peek_bits_works() {
+        let data: Vec<u8> = (0..120u32).map(|n| (n.wrapping_mul(0x9E3779B9) >> 13) as u8).collect();
+        let mut expected = BitStream::new(&data[..]);
+        let mut brdr = BitStream::new(&data[..]);
+        let mut total = 0;
+        for n in (0..).map(|i| (i * 7) % 25).take(80) {
+            if n % 3 == 0 {
+                assert_eq!(brdr.read_bits::<u32>(n).unwrap(), expected.read_bits::<u32>(n).unwrap());
             }
-            #[cfg(target_pointer_width = "32")]
-            32 => {
-                assert_eq!(brdr.read_bits::<usize>(BITBUF_BITSIZE).unwrap(), 0x01020304);
+            else {
+                let bits = brdr.peek_bits(n).unwrap();
+                assert_eq!(brdr.peek_bits(n).unwrap(), bits);
+                assert_eq!(bits, expected.read_bits::<usize>(n).unwrap());
+                brdr.skip_bits(n).unwrap();
+            }
+            total += n;
+        }
+        // peek past the end of stream
+        let rest = data.len() as u32 * 8 - total;
+        assert!(rest < 24);
+        assert_eq!(brdr.peek_bits(24).unwrap(), expected.read_bits::<usize>(rest).unwrap() << (24 - rest));
+        assert_eq!(brdr.skip_bits(rest + 1).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        brdr.skip_bits(rest).unwrap();
+        assert_eq!(brdr.peek_bits(1).unwrap(), 0);
+        assert_eq!(brdr.read_bit().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn short_reads_work() {
+        struct OneByteReader<'a>(&'a [u8]);
+        impl Read for OneByteReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = buf.len().min(1);
+                self.0.read(&mut buf[..len])
+            }
+        }
+        let data: Vec<u8> = (0..100).collect();
+        let mut expected = BitStream::new(&data[..]);
+        let mut brdr = BitStream::new(OneByteReader(&data));
+        assert_eq!(brdr.peek_bits(24).unwrap(), 0x000102);
+        for n in (1..=32).cycle().take(24) {
+            if n <= 24 {
+                assert_eq!(brdr.peek_bits(n).unwrap(), expected.peek_bits(n).unwrap());
             }
-            _ => unimplemented!()
+            assert_eq!(brdr.read_bits::<u32>(n).unwrap(), expected.read_bits::<u32>(n).unwrap());
         }
+        assert_eq!(brdr.read_bits::<u64>(64).unwrap(), expected.read_bits::<u64>(64).unwrap());
     }
 }
@@ -34,6 +34,24 @@ const CRC16_TABLE: [u16; 0x100] = [
   0x8201, 0x42c0, 0x4380, 0x8341, 0x4100, 0x81c1, 0x8081, 0x4040
 ];
 
+/// Tables for the slice-by-8 algorithm, the `n`-th table is for a byte followed by `n` zero bytes.
+const CRC16_SLICE8_TABLES: [[u16; 0x100]; 8] = slice8_tables();
+
+const fn slice8_tables() -> [[u16; 0x100]; 8] {
+    let mut tables = [CRC16_TABLE; 8];
+    let mut n = 1;
+    while n < 8 {
+        let mut i = 0;
+        while i < 0x100 {
+            let prev = tables[n - 1][i];
+            tables[n][i] = (prev >> 8) ^ CRC16_TABLE[(prev & 0xff) as usize];
+            i += 1;
+        }
+        n += 1;
+    }
+    tables
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Crc16 {
     sum: u16
@@ -46,10 +64,20 @@ impl Crc16 {
     }
 
     pub fn digest(&mut self, data: &[u8]) {
+        let [t0, t1, t2, t3, t4, t5, t6, t7] = &CRC16_SLICE8_TABLES;
         let mut sum = self.sum;
-        for n in data.iter() {
+        let mut chunks = data.chunks_exact(8);
+        for chunk in chunks.by_ref() {
+            let lo = sum as u8 ^ chunk[0];
+            let hi = (sum >> 8) as u8 ^ chunk[1];
+            sum = t7[lo as usize] ^ t6[hi as usize]
+                ^ t5[chunk[2] as usize] ^ t4[chunk[3] as usize]
+                ^ t3[chunk[4] as usize] ^ t2[chunk[5] as usize]
+                ^ t1[chunk[6] as usize] ^ t0[chunk[7] as usize];
+        }
+        for n in chunks.remainder().iter() {
             let index = sum as u8 ^ n;
-            sum = (sum >> 8) ^ CRC16_TABLE[index as usize];
+            sum = (sum >> 8) ^ t0[index as usize];
         }
         self.sum = sum;
     }
@@ -59,3 +87,49 @@ impl Crc16 {
         self.sum
     }
 }
+
+/// Digests the written data, e.g. copied with [std::io::copy].
+impl std::io::Write for Crc16 {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.digest(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest_bytewise(mut sum: u16, data: &[u8]) -> u16 {
+        for n in data.iter() {
+            let index = sum as u8 ^ n;
+            sum = (sum >> 8) ^ CRC16_TABLE[index as usize];
+        }
+        sum
+    }
+
+    #[test]
+    fn crc16_works() {
+        let mut crc = Crc16::default();
+        crc.digest(b"123456789");
+        assert_eq!(crc.sum16(), 0xbb3d);
+        let data: Vec<u8> = (0..1000u32).map(|n| (n.wrapping_mul(0x9E3779B9) >> 11) as u8).collect();
+        for start in 0..20 {
+            for end in (start..data.len()).step_by(37) {
+                let mut crc = Crc16::default();
+                crc.digest(&data[..start]);
+                let sum = crc.sum16();
+                crc.digest(&data[start..end]);
+                assert_eq!(crc.sum16(), digest_bytewise(sum, &data[start..end]));
+            }
+        }
+        crc.reset();
+        assert_eq!(crc.sum16(), 0);
+        assert_eq!(std::io::copy(&mut &b"123456789"[..], &mut crc).unwrap(), 9);
+        assert_eq!(crc.sum16(), 0xbb3d);
+    }
+}
@@ -2,17 +2,29 @@
 use core::fmt;
 use std::error::Error;
 use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::crc::Crc16;
-use crate::header::{CompressionMethod, LhaHeader};
+use crate::error::{with_context, ErrorContext, ErrorPhase, LhaError};
+use crate::header::{CompressionMethod, FilenameEncoding, LhaHeader, ParseMode, UnsupportedReason, DEFAULT_MAX_HEADER_SIZE};
+use crate::header::{find_signature, is_invalid_header, is_signature};
 
 #[cfg(feature = "lz")]
 mod lzs;
 #[cfg(feature = "lz")]
 mod lz5;
 #[cfg(feature = "lh1")]
-mod lhv1;
+pub(crate) mod lhv1;
+#[cfg(feature = "lh2")]
+mod lh2;
+#[cfg(feature = "lh3")]
+mod lh3;
 mod lhv2;
+mod entries;
+mod entry;
+mod options;
 
 #[cfg(feature = "lz")]
 pub use lzs::*;
@@ -20,12 +32,25 @@ pub use lzs::*;
 pub use lz5::*;
 #[cfg(feature = "lh1")]
 pub use lhv1::*;
+#[cfg(feature = "lh2")]
+pub use lh2::*;
+#[cfg(feature = "lh3")]
+pub use lh3::*;
 pub use lhv2::*;
+pub use entries::*;
+pub use entry::*;
+pub use options::*;
 
 /// The trait implemented by decoders.
 pub trait Decoder<R> {
     /// Unwraps and returns the inner reader.
     fn into_inner(self) -> R;
+    /// Returns a reference to the inner reader or `None` if the decoder doesn't provide it.
+    ///
+    /// The default implementation returns `None`.
+    fn get_ref(&self) -> Option<&R> {
+        None
+    }
     /// Fills the whole `buf` with decoded data.
     ///
     /// The caller should be aware of how large buffer can be provided to not exceed the size
@@ -33,6 +58,21 @@ pub trait Decoder<R> {
     fn fill_buffer(&mut self, buf: &mut[u8]) -> io::Result<()>;
 }
 
+/// An object-safe extension of the [Decoder] trait, allowing decoders to be used as trait objects.
+///
+/// This trait is implemented for every [Decoder]. A boxed decoder can be stored in [DecoderAny::BoxedDecoder], e.g. to provide a custom
+/// implementation of a compression method not supported by this library.
+pub trait DynDecoder<R>: Decoder<R> {
+    /// Unwraps the boxed decoder and returns the inner reader.
+    fn into_inner_boxed(self: Box<Self>) -> R;
+}
+
+/// A boxed decoder as stored in [DecoderAny::BoxedDecoder].
+pub type BoxedDecoder<R> = Box<dyn DynDecoder<R> + Send>;
+
+/// A detached decoder kept for reuse with the following files of the same compression method.
+pub(crate) type SpareDecoder = DecoderAny<io::Empty>;
+
 /// This type provides a convenient way to parse and decode LHA/LZH files.
 ///
 /// To read the current archived file's content use the [io::Read] trait methods on the instance of this type.
@@ -40,6 +80,9 @@ pub trait Decoder<R> {
 ///
 /// To parse and decode the next archive file, invoke [LhaDecodeReader::next_file].
 ///
+/// The content can be also read using [io::BufRead] methods, in which case the decompressed data is being
+/// provided in chunks from the internal output buffer, allocated on the first use.
+///
 /// After parsing the LHA header, a decompressed content of a file can be simply read from the `LhaDecodeReader<R>`,
 /// which decompresses it using a proper decoder, designated in the header, while reading data from the
 /// underlying stream.
@@ -47,14 +90,88 @@ pub trait Decoder<R> {
 /// If the compression method is not supported by the decoder, but otherwise the header has been parsed
 /// successfully, invoke [LhaDecodeReader::is_decoder_supported] to ensure you can actually read the file.
 /// Otherwise, trying to read from an unsupported decoder will result in an error.
+///
+/// The dictionary and other buffers of a decoder are being reused for the next file compressed with
+/// the same method, so processing archives with many small files doesn't require allocations per file.
 #[derive(Debug)]
 pub struct LhaDecodeReader<R> {
     header: LhaHeader,
     crc: Crc16,
     output_length: u64,
-    decoder: Option<DecoderAny<io::Take<R>>>
+    total_output_length: u64,
+    decoder: Option<DecoderAny<io::Take<R>>>,
+    decoder_factory: Option<DecoderFactory<R>>,
+    skipper: Option<Skipper<R>>,
+    scanner: Option<Scanner<R>>,
+    peeked: Option<Option<LhaHeader>>,
+    stream_offset: u64,
+    spare: Option<SpareDecoder>,
+    output: Vec<u8>,
+    output_pos: usize,
+    crc_policy: CrcPolicy,
+    crc_enabled: bool,
+    parse_mode: ParseMode,
+    max_header_size: usize,
+    max_total_header_size: Option<u64>,
+    total_header_size: u64,
+    filename_encoding: FilenameEncoding,
+    max_entry_size: Option<u64>,
+    max_compression_ratio: Option<u64>,
+    detect_sfx: bool,
+    read_buffer_size: usize,
+    output_buffer_size: usize,
+    header_count: u64,
+    progress: Option<ProgressCallback>,
+    cancel_flag: Option<Arc<AtomicBool>>
+}
+
+/// Determines when the CRC-16 checksum of the decompressed content is being computed by [LhaDecodeReader].
+///
+/// Computing the checksum has its cost, which can be avoided if the integrity of the content doesn't need
+/// to be verified, e.g. when only a part of the file's content is being read.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CrcPolicy {
+    /// The checksum is being computed for every file.
+    #[default]
+    Always,
+    /// The checksum is being computed only for files for which it has been requested with
+    /// [LhaDecodeReader::request_crc] before reading their content.
+    OnDemand,
+    /// The checksum is never computed.
+    Skip,
 }
 
+/// The default size of the output buffer used by the [io::BufRead] implementation of [LhaDecodeReader],
+/// see [LhaOptions::output_buffer_size].
+pub const DEFAULT_OUTPUT_BUFFER_SIZE: usize = 8192;
+
+/// The default size of the buffer of the compressed data read by the decoders from the stream reader,
+/// see [LhaOptions::read_buffer_size].
+pub const DEFAULT_READ_BUFFER_SIZE: usize = crate::bitstream::READ_BUFFER_SIZE;
+
+/// The maximum size of chunks decompressed by [LhaDecodeReader::decode_into] between the checks
+/// of the cancellation flag and the progress reports.
+const DECODE_CHUNK_SIZE: usize = 1 << 16;
+
+/// A function creating decoders for compression methods that are not supported by this library.
+///
+/// The function receives the compression method identifier from the header and the stream reader
+/// limited to the compressed size of the file. If the method is not handled by the function it should
+/// return the reader wrapped in [DecoderAny::UnsupportedDecoder].
+pub type DecoderFactory<R> = fn([u8;5], io::Take<R>) -> DecoderAny<io::Take<R>>;
+
+/// A function skipping over the given number of bytes of the stream reader.
+struct Skipper<R>(fn(&mut R, u64) -> io::Result<()>);
+
+/// A function moving the stream reader back by the given number of bytes and then forward to
+/// the next plausible header, returning the number of bytes skipped forward or `None` at the end
+/// of the stream.
+struct Scanner<R>(fn(&mut R, u64) -> io::Result<Option<u64>>);
+
+/// A function receiving the progress of decoding, registered with
+/// [LhaDecodeReader::with_progress_callback].
+struct ProgressCallback(Box<dyn FnMut(u64, u64, u64) + Send + Sync>);
+
 /// An empty decoder for storage only methods.
 #[derive(Debug)]
 pub struct PassthroughDecoder<R> {
@@ -90,12 +207,18 @@ pub enum DecoderAny<R> {
     Lz5Decoder(Lz5Decoder<R>),
     #[cfg(feature = "lh1")]
     Lh1Decoder(Lh1Decoder<R>),
+    #[cfg(feature = "lh2")]
+    Lh2Decoder(Lh2Decoder<R>),
+    #[cfg(feature = "lh3")]
+    Lh3Decoder(Lh3Decoder<R>),
     Lh4Decoder(Lh5Decoder<R>),
     Lh5Decoder(Lh5Decoder<R>),
     Lh6Decoder(Lh7Decoder<R>),
     Lh7Decoder(Lh7Decoder<R>),
     #[cfg(feature = "lhx")]
     LhxDecoder(LhxDecoder<R>),
+    /// A user provided decoder.
+    BoxedDecoder(BoxedDecoder<R>),
 }
 
 macro_rules! decoder_any_dispatch {
@@ -109,12 +232,17 @@ macro_rules! decoder_any_dispatch {
             DecoderAny::Lz5Decoder($($spec)*) => $expr,
             #[cfg(feature = "lh1")]
             DecoderAny::Lh1Decoder($($spec)*) => $expr,
+            #[cfg(feature = "lh2")]
+            DecoderAny::Lh2Decoder($($spec)*) => $expr,
+            #[cfg(feature = "lh3")]
+            DecoderAny::Lh3Decoder($($spec)*) => $expr,
             DecoderAny::Lh4Decoder($($spec)*)|
             DecoderAny::Lh5Decoder($($spec)*) => $expr,
             DecoderAny::Lh6Decoder($($spec)*)|
             DecoderAny::Lh7Decoder($($spec)*) => $expr,
             #[cfg(feature = "lhx")]
             DecoderAny::LhxDecoder($($spec)*) => $expr,
+            DecoderAny::BoxedDecoder($($spec)*) => $expr,
         }
     };
 }
@@ -127,7 +255,31 @@ impl<R: io::Read> Default for LhaDecodeReader<R> {
             header: Default::default(),
             crc: Crc16::default(),
             output_length: 0,
-            decoder: None
+            total_output_length: 0,
+            decoder: None,
+            decoder_factory: None,
+            skipper: None,
+            scanner: None,
+            peeked: None,
+            stream_offset: 0,
+            spare: None,
+            output: Vec::new(),
+            output_pos: 0,
+            crc_policy: CrcPolicy::Always,
+            crc_enabled: true,
+            parse_mode: ParseMode::Strict,
+            max_header_size: DEFAULT_MAX_HEADER_SIZE,
+            max_total_header_size: None,
+            total_header_size: 0,
+            filename_encoding: FilenameEncoding::Ascii,
+            max_entry_size: None,
+            max_compression_ratio: None,
+            detect_sfx: true,
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            output_buffer_size: DEFAULT_OUTPUT_BUFFER_SIZE,
+            header_count: 0,
+            progress: None,
+            cancel_flag: None
         }
     } 
 }
@@ -137,24 +289,267 @@ impl<R: io::Read> LhaDecodeReader<R> {
     ///
     /// Provide a stream reader.
     ///
+    /// If the stream begins with the `MZ` signature of an executable, e.g. a self-extracting archive,
+    /// the executable image is being skipped, see [LhaOptions::detect_sfx].
+    ///
     /// # Errors
     /// Returns an error if the header could not be read or parsed.
-    pub fn new(mut rd: R) -> Result<LhaDecodeReader<R>, LhaDecodeError<R>> {
-        let header = match LhaHeader::read(rd.by_ref()).and_then(|h|
-                        h.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "a header is missing"))
-                    )
-        {
-            Ok(h) => h,
-            Err(e) => return Err(wrap_err(rd, e))
-        };
-        let decoder = DecoderAny::new_from_header(&header, rd);
-        let crc = Crc16::default();
-        Ok(LhaDecodeReader {
-            header,
-            crc,
-            output_length: 0,
-            decoder: Some(decoder)
-        })
+    pub fn new(rd: R) -> Result<LhaDecodeReader<R>, LhaDecodeError<R>> {
+        LhaDecodeReader::new_with_options(rd, LhaOptions::new())
+    }
+    /// Creates a new instance of `LhaDecodeReader<R>` configured with `options` after reading and
+    /// parsing the first header from source.
+    ///
+    /// # Errors
+    /// Returns an error if the header could not be read or parsed.
+    pub fn new_with_options(rd: R, options: LhaOptions) -> Result<LhaDecodeReader<R>, LhaDecodeError<R>> {
+        let mut reader = LhaDecodeReader::default();
+        reader.set_options(options);
+        match reader.begin_new(rd) {
+            Ok(true) => Ok(reader),
+            Ok(false) => {
+                Err(wrap_err(reader.into_inner(), LhaError::MissingHeader.into()))
+            }
+            Err(e) => Err(e)
+        }
+    }
+    /// Applies `options` to the following files. The checksum policy is also being applied to
+    /// the current file if its content hasn't been read yet.
+    pub fn set_options(&mut self, options: LhaOptions) {
+        self.set_crc_policy(options.crc_policy);
+        self.parse_mode = options.parse_mode;
+        self.max_header_size = options.max_header_size;
+        self.max_total_header_size = options.max_total_header_size;
+        self.filename_encoding = options.filename_encoding;
+        self.max_entry_size = options.max_entry_size;
+        self.max_compression_ratio = options.max_compression_ratio;
+        self.detect_sfx = options.detect_sfx;
+        self.read_buffer_size = options.read_buffer_size;
+        self.output_buffer_size = options.output_buffer_size;
+    }
+    /// Returns the current configuration.
+    pub fn options(&self) -> LhaOptions {
+        LhaOptions {
+            parse_mode: self.parse_mode,
+            crc_policy: self.crc_policy,
+            max_header_size: self.max_header_size,
+            max_total_header_size: self.max_total_header_size,
+            filename_encoding: self.filename_encoding,
+            max_entry_size: self.max_entry_size,
+            max_compression_ratio: self.max_compression_ratio,
+            detect_sfx: self.detect_sfx,
+            read_buffer_size: self.read_buffer_size,
+            output_buffer_size: self.output_buffer_size
+        }
+    }
+    /// Returns the path name of the current file, decoded with the encoding configured with
+    /// [LhaOptions::filename_encoding], see [LhaHeader::parse_pathname_with].
+    pub fn pathname(&self) -> PathBuf {
+        self.header.parse_pathname_with(self.filename_encoding)
+    }
+    /// Creates a new instance of `LhaDecodeReader<R>` after reading and parsing the first header from
+    /// a seekable source, see [LhaDecodeReader::with_seeking].
+    ///
+    /// The executable stub of a self-extracting archive is being skipped, see
+    /// [LhaDecodeReader::new_seekable_with_options].
+    ///
+    /// # Errors
+    /// Returns an error if the header could not be read or parsed.
+    pub fn new_seekable(rd: R) -> Result<LhaDecodeReader<R>, LhaDecodeError<R>>
+        where R: io::Seek
+    {
+        LhaDecodeReader::new_seekable_with_options(rd, LhaOptions::new())
+    }
+    /// Creates a new instance of `LhaDecodeReader<R>` configured with `options` after reading and
+    /// parsing the first header from a seekable source, see [LhaDecodeReader::with_seeking].
+    ///
+    /// If the source begins with the `MZ` signature of an executable, e.g. a self-extracting archive,
+    /// the executable stub is being skipped up to the first valid header, unless disabled with
+    /// [LhaOptions::detect_sfx]. The [LhaHeader::header_offset] of the headers is being counted
+    /// from the first header.
+    ///
+    /// # Errors
+    /// Returns an error if the header could not be read or parsed.
+    pub fn new_seekable_with_options(mut rd: R, options: LhaOptions) -> Result<LhaDecodeReader<R>, LhaDecodeError<R>>
+        where R: io::Seek
+    {
+        if options.detect_sfx {
+            match skip_sfx_stub(&mut rd, options.parse_mode) {
+                Ok(true) => {}
+                // report the error of parsing the stub as a header
+                Ok(false) => return LhaDecodeReader::new_with_options(rd, options.detect_sfx(false)),
+                Err(e) => return Err(wrap_err(rd, e))
+            }
+        }
+        LhaDecodeReader::new_with_options(rd, options).map(LhaDecodeReader::with_seeking)
+    }
+    /// Makes [LhaDecodeReader::next_file] seek over the unread compressed content of the current file,
+    /// instead of reading and discarding it, and returns the modified instance of `LhaDecodeReader<R>`.
+    ///
+    /// This makes listing the files or extracting only some of them much faster with large archives.
+    pub fn with_seeking(mut self) -> Self
+        where R: io::Seek
+    {
+        self.skipper = Some(Skipper(seek_over::<R>));
+        self
+    }
+    /// Enables recovering from malformed headers and returns the modified instance of
+    /// `LhaDecodeReader<R>`.
+    ///
+    /// When a header can't be parsed by [LhaDecodeReader::next_file] or [LhaDecodeReader::begin_new],
+    /// the stream is being scanned forward for the next plausible
+    /// header, starting right after the beginning of the malformed one, instead of returning the error,
+    /// so the following files can still be extracted from a partially corrupted archive. The error is
+    /// returned only if no header could be found until the end of the stream.
+    ///
+    /// The content of a file which fails to decompress or whose checksum doesn't match is being
+    /// skipped by [LhaDecodeReader::next_file] according to its compressed size, so recovering
+    /// from such errors doesn't require scanning unless the header is corrupted as well.
+    ///
+    /// Errors of the underlying stream reader and exceeding the configured limits are never recovered from.
+    pub fn with_recovery(mut self) -> Self
+        where R: io::Seek
+    {
+        self.scanner = Some(Scanner(scan_header::<R>));
+        self
+    }
+    /// Registers a function creating decoders for compression methods not supported by this library
+    /// and returns the modified instance of `LhaDecodeReader<R>`.
+    ///
+    /// The `factory` is being invoked for every following file with a compression method that is either
+    /// not recognized or not supported by the built-in decoders. It is also being applied to the current
+    /// file if its content hasn't been read yet.
+    pub fn with_decoder_factory(mut self, factory: DecoderFactory<R>) -> Self {
+        self.decoder_factory = Some(factory);
+        if self.output_length == 0 && matches!(self.decoder, Some(DecoderAny::UnsupportedDecoder(..))) {
+            let limited_rd = self.decoder.take().unwrap().into_inner();
+            self.decoder = Some(factory(self.header.compression, limited_rd));
+        }
+        self
+    }
+    /// Registers a function receiving the progress of decoding and returns the modified instance
+    /// of `LhaDecodeReader<R>`.
+    ///
+    /// The `callback` is being invoked after every decompressed chunk of a file's content with
+    /// the index of the file in the archive, counted from 0, the number of bytes of the file's
+    /// compressed content read from the stream so far and the number of bytes decompressed so far.
+    /// The number of compressed bytes is reported as 0 if the decoder doesn't provide access to its
+    /// stream reader, see [Decoder::get_ref].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::io;
+    ///
+    /// let mut lha_reader = delharc::parse_file("archive.lzh")?
+    ///     .with_progress_callback(|index, bytes_in, bytes_out| {
+    ///         eprint!("\r#{}: {} -> {} bytes", index, bytes_in, bytes_out);
+    ///     });
+    /// loop {
+    ///     io::copy(&mut lha_reader, &mut io::sink())?;
+    ///     if !lha_reader.next_file()? {
+    ///         break
+    ///     }
+    /// }
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn with_progress_callback<F>(mut self, callback: F) -> Self
+        where F: FnMut(u64, u64, u64) + Send + Sync + 'static
+    {
+        self.set_progress_callback(callback);
+        self
+    }
+    /// Registers a function receiving the progress of decoding, see
+    /// [LhaDecodeReader::with_progress_callback].
+    pub fn set_progress_callback<F>(&mut self, callback: F)
+        where F: FnMut(u64, u64, u64) + Send + Sync + 'static
+    {
+        self.progress = Some(ProgressCallback(Box::new(callback)));
+    }
+    /// Removes the function registered with [LhaDecodeReader::with_progress_callback].
+    pub fn clear_progress_callback(&mut self) {
+        self.progress = None;
+    }
+    /// Registers a flag cancelling the decoding when set to `true`, e.g. from another thread, and
+    /// returns the modified instance of `LhaDecodeReader<R>`.
+    ///
+    /// The flag is being checked before every decompressed chunk of a file's content and before
+    /// skipping to the next file. Once the flag is set, these operations fail with [Cancelled]
+    /// wrapped in an [io::Error] of the kind [io::ErrorKind::Other], which can be retrieved with
+    /// [io::Error::get_ref]. Clear the flag to resume decoding.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::{io, sync::{Arc, atomic::{AtomicBool, Ordering}}};
+    ///
+    /// let cancel = Arc::new(AtomicBool::new(false));
+    /// let mut lha_reader = delharc::parse_file("archive.lzh")?.with_cancel_flag(Arc::clone(&cancel));
+    /// let handle = std::thread::spawn(move || io::copy(&mut lha_reader, &mut io::sink()));
+    /// cancel.store(true, Ordering::Relaxed);
+    /// if let Err(e) = handle.join().unwrap() {
+    ///     assert!(delharc::decode::Cancelled::is_cancelled(&e));
+    /// }
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn with_cancel_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.set_cancel_flag(flag);
+        self
+    }
+    /// Registers a flag cancelling the decoding, see [LhaDecodeReader::with_cancel_flag].
+    pub fn set_cancel_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancel_flag = Some(flag);
+    }
+    /// Removes the flag registered with [LhaDecodeReader::with_cancel_flag].
+    pub fn clear_cancel_flag(&mut self) {
+        self.cancel_flag = None;
+    }
+    /// Sets the policy of computing checksums of the decompressed content and returns the modified instance
+    /// of `LhaDecodeReader<R>`.
+    ///
+    /// The policy is also being applied to the current file if its content hasn't been read yet.
+    pub fn with_crc_policy(mut self, crc_policy: CrcPolicy) -> Self {
+        self.set_crc_policy(crc_policy);
+        self
+    }
+    /// Sets the policy of computing checksums of the decompressed content.
+    ///
+    /// The policy is also being applied to the current file if its content hasn't been read yet.
+    pub fn set_crc_policy(&mut self, crc_policy: CrcPolicy) {
+        self.crc_policy = crc_policy;
+        if self.output_length == 0 {
+            self.crc_enabled = crc_policy == CrcPolicy::Always;
+        }
+    }
+    /// Returns the current policy of computing checksums.
+    pub fn crc_policy(&self) -> CrcPolicy {
+        self.crc_policy
+    }
+    /// Sets the mode of parsing the headers of the following files and returns the modified instance
+    /// of `LhaDecodeReader<R>`.
+    ///
+    /// The mode is applied to the headers read by [LhaDecodeReader::next_file] and
+    /// [LhaDecodeReader::begin_new]. To parse the first header in the [ParseMode::Lenient] mode,
+    /// create the instance with [LhaDecodeReader::default] and invoke [LhaDecodeReader::begin_new].
+    pub fn with_parse_mode(mut self, parse_mode: ParseMode) -> Self {
+        self.parse_mode = parse_mode;
+        self
+    }
+    /// Sets the mode of parsing the headers of the following files.
+    pub fn set_parse_mode(&mut self, parse_mode: ParseMode) {
+        self.parse_mode = parse_mode;
+    }
+    /// Returns the current mode of parsing headers.
+    pub fn parse_mode(&self) -> ParseMode {
+        self.parse_mode
+    }
+    /// Requests computing the checksum of the current file's content with [CrcPolicy::OnDemand] policy.
+    ///
+    /// Returns `true` if the checksum is being computed. Returns `false` if the policy is [CrcPolicy::Skip]
+    /// or if the checksum is not being computed and some of the file's content has already been read.
+    pub fn request_crc(&mut self) -> bool {
+        if !self.crc_enabled && self.output_length == 0 && self.crc_policy == CrcPolicy::OnDemand {
+            self.crc_enabled = true;
+        }
+        self.crc_enabled
     }
     /// Attempts to read the first file header from a new source stream and initializes a decoder returning
     /// `Ok(true)` on success. Returns `Ok(false)` if there are no more headers in the stream.
@@ -166,28 +561,123 @@ impl<R: io::Read> LhaDecodeReader<R> {
     ///
     /// When `Ok(false)` has been returned, trying to read from the decoder will result in an error.
     ///
+    /// The [LhaHeader::header_offset] of the headers is being counted from the current position of `rd`.
+    ///
     /// # Errors
     /// Returns an error if the header could not be read or parsed. In this instance the inner stream
     /// reader is not being replaced by a new one and the provided source stream can be retrieved from
     /// the returned error.
-    pub fn begin_new(&mut self, mut rd: R) -> Result<bool, LhaDecodeError<R>> {
-        let res = match LhaHeader::read(rd.by_ref()) {
-            Ok(Some(header)) => {
-                let decoder = DecoderAny::new_from_header(&header, rd);
+    pub fn begin_new(&mut self, rd: R) -> Result<bool, LhaDecodeError<R>> {
+        self.begin_at(rd, 0)
+    }
+    /// Attempts to read the next file header from `rd` found at the archive `offset`.
+    fn begin_at(&mut self, mut rd: R, offset: u64) -> Result<bool, LhaDecodeError<R>> {
+        let header = match offset {
+            // the stream is being scanned by the scanner, if present
+            0 if self.detect_sfx && self.scanner.is_none() => self.read_first_header(&mut rd),
+            _ => self.read_header_at(&mut rd, offset)
+        };
+        match header {
+            Ok(header) => Ok(self.begin_with(rd, header)),
+            Err(e) => match self.scanner {
+                Some(Scanner(scanner)) if is_recoverable(&e) => self.resync_at(rd, offset, scanner, e),
+                _ => Err(wrap_err(rd, e))
+            }
+        }
+    }
+    /// Scans `rd` for the next header after the malformed header found at the archive `offset`.
+    fn resync_at(&mut self, mut rd: R, mut offset: u64,
+                 scanner: fn(&mut R, u64) -> io::Result<Option<u64>>, mut err: io::Error)
+        -> Result<bool, LhaDecodeError<R>>
+    {
+        loop {
+            // the stream is positioned where parsing the malformed header stopped
+            let back = (self.stream_offset - offset).saturating_sub(1);
+            match scanner(&mut rd, back) {
+                Ok(Some(skipped)) => {
+                    trace!(warn!(offset, next_offset = offset + 1 + skipped, "resynchronizing after invalid header"));
+                    offset += 1 + skipped;
+                }
+                Ok(None) => {
+                    trace!(warn!(offset, "no header found after invalid header"));
+                    return Err(wrap_err(rd, err))
+                }
+                Err(e) => return Err(wrap_err(rd, e))
+            }
+            match self.read_header_at(&mut rd, offset) {
+                Ok(header) => return Ok(self.begin_with(rd, header)),
+                Err(e) if is_recoverable(&e) => err = e,
+                Err(e) => return Err(wrap_err(rd, e))
+            }
+        }
+    }
+    /// Initializes a decoder of the file described by `header` with the content read from `rd`.
+    fn begin_with(&mut self, rd: R, header: Option<LhaHeader>) -> bool {
+        self.peeked = None;
+        let res = match header {
+            Some(header) => {
+                self.take_limited_inner();
+                let mut decoder = DecoderAny::new_from_header_reusing(&header, rd, &mut self.spare);
+                decoder.set_read_buffer_size(self.read_buffer_size);
+                let decoder = match (decoder, self.decoder_factory) {
+                    (DecoderAny::UnsupportedDecoder(decoder), Some(factory)) => {
+                        factory(header.compression, decoder.into_inner())
+                    }
+                    (decoder, _) => decoder
+                };
                 self.decoder = Some(decoder);
                 self.header = header;
                 true
             }
-            Ok(None) => {
+            None => {
                 let decoder = UnsupportedDecoder::new(rd.take(0));
                 self.decoder = Some(DecoderAny::UnsupportedDecoder(decoder));
+                // there is nothing to peek past the end of the archive
+                self.peeked = Some(None);
                 false
             }
-            Err(e) => return Err(wrap_err(rd, e))
         };
-        self.crc.reset();
-        self.output_length = 0;
-        Ok(res)
+        self.reset_output();
+        res
+    }
+    /// Reads the first header from `rd`. If the stream begins with the `MZ` signature of an executable,
+    /// the executable image is skipped and the header is read from its end.
+    fn read_first_header(&mut self, rd: &mut R) -> io::Result<Option<LhaHeader>> {
+        // a header may begin with "MZ" too, but is followed by the compression method
+        let mut magic = Vec::with_capacity(7);
+        io::Read::read_to_end(&mut io::Read::take(&mut *rd, 1), &mut magic)?;
+        if magic == b"M" {
+            io::Read::read_to_end(&mut io::Read::take(&mut *rd, 6), &mut magic)?;
+        }
+        if !magic.starts_with(b"MZ") || is_signature(&magic) {
+            // the header is at least as long as the bytes already read
+            return self.read_header_at(&mut io::Read::chain(magic.as_slice(), rd), 0)
+        }
+        skip_exe_image(rd, magic)?;
+        self.read_header_at(rd, 0)
+    }
+    /// Reads and parses the header found at the archive `offset`.
+    fn read_header_at<T: io::Read>(&mut self, rd: &mut T, offset: u64) -> io::Result<Option<LhaHeader>> {
+        let mut counting = CountingReader { inner: rd, count: 0 };
+        let header = LhaHeader::read_with_limit(&mut counting, self.parse_mode, Some(self.max_header_size));
+        self.stream_offset = offset + counting.count;
+        let header = header.map_err(|e| {
+            trace!(warn!(offset, error = %e, "invalid header"));
+            with_context(e, || ErrorContext { phase: ErrorPhase::Header, path: None, offset })
+        })?;
+        #[cfg(feature = "tracing")]
+        trace_header(offset, header.as_ref());
+        let header_size = header.as_ref().map_or(0, |header| header.header_size);
+        let header_count = header.is_some() as u64;
+        (self.total_header_size, self.header_count, self.total_output_length) = match offset {
+            0 => (header_size, header_count, 0),
+            _ => (self.total_header_size + header_size, self.header_count + header_count, self.total_output_length)
+        };
+        if let Some(limit) = self.max_total_header_size.filter(|&limit| self.total_header_size > limit) {
+            let e = LimitError::TotalHeaderSize { size: self.total_header_size, limit };
+            return Err(io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+        Ok(header.map(|header| LhaHeader { header_offset: Some(offset), ..header }))
     }
     /// Assigns externally parsed header and decoder to this instance of `LhaDecodeReader<R>`.
     ///
@@ -198,10 +688,11 @@ impl<R: io::Read> LhaDecodeReader<R> {
     ///
     /// This method assumes the file will be read and decoded from its beginning.
     pub fn begin_with_header_and_decoder(&mut self, header: LhaHeader, decoder: DecoderAny<io::Take<R>>) {
+        self.peeked = None;
+        self.stream_offset = header.header_offset.unwrap_or(0) + header.header_size;
         self.decoder = Some(decoder);
         self.header = header;
-        self.crc.reset();
-        self.output_length = 0;
+        self.reset_output();
     }
     /// Attempts to parse the next file's header.
     ///
@@ -218,13 +709,259 @@ impl<R: io::Read> LhaDecodeReader<R> {
     /// # Panics
     /// Panics if called when the underlying stream reader has been already taken.
     pub fn next_file(&mut self) -> Result<bool, LhaDecodeError<R>> {
-        let mut limited_rd = self.decoder.take().expect("decoder not empty").into_inner();
-        if limited_rd.limit() != 0 {
-            if let Err(e) = io::copy(&mut limited_rd, &mut io::sink()) {
+        let rd = self.skip_remaining()?;
+        match self.peeked.take() {
+            Some(header) => Ok(self.begin_with(rd, header)),
+            None => {
+                let offset = next_header_offset(&self.header);
+                self.begin_at(rd, offset)
+            }
+        }
+    }
+    /// Attempts to parse the next file's header like [LhaDecodeReader::next_file], skipping the files
+    /// compressed with the methods which are not supported, see [LhaDecodeReader::skip_unsupported].
+    ///
+    /// # Example
+    /// ```no_run
+    /// let mut lha_reader = delharc::parse_file("archive.lzh")?;
+    /// let mut skipped = Vec::new();
+    /// if lha_reader.skip_unsupported(&mut skipped)? {
+    ///     loop {
+    ///         println!("{}", lha_reader.header().parse_pathname().display());
+    ///         if !lha_reader.next_supported_file(&mut skipped)? {
+    ///             break
+    ///         }
+    ///     }
+    /// }
+    /// for header in skipped {
+    ///     eprintln!("skipped: {}", header.parse_pathname().display());
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if a header could not be read or parsed.
+    /// In this instance the underlying stream source will be taken and returned with the error.
+    ///
+    /// # Panics
+    /// Panics if called when the underlying stream reader has been already taken.
+    pub fn next_supported_file(&mut self, skipped: &mut Vec<LhaHeader>) -> Result<bool, LhaDecodeError<R>> {
+        Ok(self.next_file()? && self.skip_unsupported(skipped)?)
+    }
+    /// Skips the current file and the following files if their compression method is not supported,
+    /// appending their headers to `skipped`. The directory entries are not being skipped.
+    ///
+    /// Returns `Ok(true)` if the current file is supported or `Ok(false)` if there are no more headers.
+    ///
+    /// # Errors
+    /// Returns an error if a header could not be read or parsed.
+    /// In this instance the underlying stream source will be taken and returned with the error.
+    ///
+    /// # Panics
+    /// Panics if called when the underlying stream reader has been already taken.
+    pub fn skip_unsupported(&mut self, skipped: &mut Vec<LhaHeader>) -> Result<bool, LhaDecodeError<R>> {
+        while !self.is_decoder_supported() && !self.header.is_directory() {
+            trace!(debug!(path = ?self.header.parse_pathname(), method = %self.header.compression.escape_ascii(),
+                          "unsupported file skipped"));
+            skipped.push(self.header.clone());
+            if !self.next_file()? {
+                return Ok(false)
+            }
+        }
+        Ok(true)
+    }
+    /// Reads and returns the header of the next file without initializing its decoder, so the file
+    /// can be skipped cheaply with [LhaDecodeReader::skip_peeked]. Returns `Ok(None)` if there are
+    /// no more headers.
+    ///
+    /// The remaining content of the current file is being skipped, reading it after this call will
+    /// result in an error. The peeked file becomes the current one on the [LhaDecodeReader::next_file]
+    /// call. Calling this method again returns the same header.
+    ///
+    /// # Errors
+    /// Returns an error if the header could not be read or parsed.
+    /// In this instance the underlying stream source will be taken and returned with the error.
+    ///
+    /// # Panics
+    /// Panics if called when the underlying stream reader has been already taken.
+    pub fn peek_header(&mut self) -> Result<Option<&LhaHeader>, LhaDecodeError<R>> {
+        if self.peeked.is_none() {
+            let rd = self.skip_remaining()?;
+            let offset = next_header_offset(&self.header);
+            self.peek_at(rd, offset)?;
+        }
+        Ok(self.peeked.as_ref().and_then(Option::as_ref))
+    }
+    /// Skips the content of the next file, without initializing its decoder, and returns the header of
+    /// the following file, see [LhaDecodeReader::peek_header]. Returns `Ok(None)` if there are no more
+    /// headers.
+    ///
+    /// # Errors
+    /// Returns an error if the header could not be read or parsed.
+    /// In this instance the underlying stream source will be taken and returned with the error.
+    ///
+    /// # Panics
+    /// Panics if called when the underlying stream reader has been already taken.
+    pub fn skip_peeked(&mut self) -> Result<Option<&LhaHeader>, LhaDecodeError<R>> {
+        self.peek_header()?;
+        if let Some(Some(header)) = self.peeked.take() {
+            let rd = self.take_limited_inner().expect("decoder not empty").into_inner();
+            let rd = self.skip_limited(rd.take(header.compressed_size))?;
+            self.peek_at(rd, next_header_offset(&header))?;
+        }
+        else {
+            self.peeked = Some(None);
+        }
+        Ok(self.peeked.as_ref().and_then(Option::as_ref))
+    }
+
+    /// Seeks back to the beginning of the archive and reads the first file header again,
+    /// e.g. to extract the files after listing them.
+    ///
+    /// The beginning of the archive is the position of the stream reader when the reading of
+    /// the archive began, see [LhaHeader::header_offset]. On success returns `Ok(true)` if the header
+    /// has been read and parsed successfully or `Ok(false)` if the archive is empty.
+    ///
+    /// # Errors
+    /// Returns an error if seeking fails or the header could not be read or parsed.
+    /// In this instance the underlying stream source will be taken and returned with the error.
+    ///
+    /// # Panics
+    /// Panics if called when the underlying stream reader has been already taken.
+    pub fn rewind(&mut self) -> Result<bool, LhaDecodeError<R>>
+        where R: io::Seek
+    {
+        let limited_rd = self.take_limited_inner().expect("decoder not empty");
+        // the number of bytes of the current file's content read from the stream
+        let consumed = match self.peeked {
+            Some(..) => 0,
+            None => self.header.compressed_size - limited_rd.limit()
+        };
+        let mut rd = limited_rd.into_inner();
+        let offset = i64::try_from(self.stream_offset + consumed).map_err(|_|
+            io::Error::new(io::ErrorKind::InvalidData, "archive too large"));
+        if let Err(e) = offset.and_then(|offset| rd.seek(io::SeekFrom::Current(-offset))) {
+            return Err(wrap_err(rd, e))
+        }
+        self.begin_at(rd, 0)
+    }
+
+    fn peek_at(&mut self, mut rd: R, offset: u64) -> Result<(), LhaDecodeError<R>> {
+        match self.read_header_at(&mut rd, offset) {
+            Ok(header) => {
+                self.peeked = Some(header);
+                self.decoder = Some(DecoderAny::UnsupportedDecoder(UnsupportedDecoder::new(rd.take(0))));
+                Ok(())
+            }
+            Err(e) => Err(wrap_err(rd, e))
+        }
+    }
+    /// Skips the remaining content of the current file and returns the underlying stream reader.
+    fn skip_remaining(&mut self) -> Result<R, LhaDecodeError<R>> {
+        let limited_rd = self.take_limited_inner().expect("decoder not empty");
+        self.skip_limited(limited_rd)
+    }
+
+    fn skip_limited(&self, mut limited_rd: io::Take<R>) -> Result<R, LhaDecodeError<R>> {
+        if let Err(e) = self.check_cancelled() {
+            return Err(wrap_err(limited_rd.into_inner(), e))
+        }
+        let limit = limited_rd.limit();
+        if limit != 0 {
+            let res = match self.skipper {
+                Some(Skipper(skip)) => skip(limited_rd.get_mut(), limit),
+                None => io::copy(&mut limited_rd, &mut io::sink()).map(drop)
+            };
+            if let Err(e) = res {
                 return Err(wrap_err(limited_rd.into_inner(), e))
             }
         }
-        self.begin_new(limited_rd.into_inner())
+        Ok(limited_rd.into_inner())
+    }
+    /// Returns a reader of the raw, compressed content of the current file.
+    ///
+    /// The returned reader yields at most [LhaHeader::compressed_size] bytes, exactly as they are
+    /// stored in the archive. The remaining compressed content is being skipped on the
+    /// [LhaDecodeReader::next_file] call.
+    ///
+    /// After this call the decoder is being discarded and trying to read decompressed data from this
+    /// instance will result in an error.
+    ///
+    /// # Errors
+    /// Returns an error if some of the current file's content has already been decompressed.
+    ///
+    /// # Panics
+    /// Panics if called when the underlying stream reader has been already taken.
+    pub fn raw_entry_reader(&mut self) -> io::Result<&mut io::Take<R>> {
+        if self.output_length != 0 {
+            return Err(io::Error::new(io::ErrorKind::Other, "the file content has already been decompressed"))
+        }
+        let limited_rd = self.take_limited_inner().expect("decoder not empty");
+        let decoder = self.decoder.insert(DecoderAny::UnsupportedDecoder(UnsupportedDecoder::new(limited_rd)));
+        match decoder {
+            DecoderAny::UnsupportedDecoder(decoder) => Ok(&mut decoder.inner),
+            _ => unreachable!()
+        }
+    }
+    /// Decompresses the remaining content of the current file into the given `buf` with a single call.
+    ///
+    /// On success returns the number of bytes written to the beginning of `buf`, which is equal to
+    /// the value returned from [LhaDecodeReader::len] before the call.
+    ///
+    /// The file's checksum should be verified afterwards using [LhaDecodeReader::crc_check].
+    ///
+    /// # Errors
+    /// Returns an error if `buf` is too small to hold the remaining content of the file or if the
+    /// content could not be decompressed.
+    ///
+    /// # Panics
+    /// Panics if called when the underlying stream reader has been already taken.
+    pub fn decode_into(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = match usize::try_from(self.len()) {
+            Ok(len) if len <= buf.len() => len,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "buffer too small"))
+        };
+        let (head, target) = buf[0..len].split_at_mut(self.output.len() - self.output_pos);
+        head.copy_from_slice(&self.output[self.output_pos..]);
+        self.output_pos = self.output.len();
+        for chunk in target.chunks_mut(DECODE_CHUNK_SIZE) {
+            self.check_decode()
+                .and_then(|_| self.decoder.as_mut().expect("decoder not empty").fill_buffer(chunk))
+                .map_err(|e| self.with_context(e, ErrorPhase::Decode))?;
+            self.output_length += chunk.len() as u64;
+            self.total_output_length += chunk.len() as u64;
+            if self.crc_enabled {
+                self.crc.digest(chunk);
+            }
+            self.report_progress(chunk.len());
+        }
+        Ok(len)
+    }
+    /// Decompresses the remaining content of the current file and appends it to the given `vec`.
+    ///
+    /// The space for the content is being reserved up front, according to the original file size
+    /// found in the header.
+    ///
+    /// On success returns the number of bytes appended to `vec`.
+    ///
+    /// The file's checksum should be verified afterwards using [LhaDecodeReader::crc_check].
+    ///
+    /// # Errors
+    /// Returns an error if the memory for the content could not be allocated or if the content
+    /// could not be decompressed. In this instance the length of `vec` is not being changed.
+    ///
+    /// # Panics
+    /// Panics if called when the underlying stream reader has been already taken.
+    pub fn decode_to_vec(&mut self, vec: &mut Vec<u8>) -> io::Result<usize> {
+        let len = usize::try_from(self.len()).ok()
+                  .filter(|&len| vec.try_reserve_exact(len).is_ok())
+                  .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "file too large"))?;
+        let start = vec.len();
+        vec.resize(start + len, 0);
+        self.decode_into(&mut vec[start..]).map_err(|e| {
+            vec.truncate(start);
+            e
+        })
     }
     /// Returns a reference to the last parsed file's [LhaHeader].
     pub fn header(&self) -> &LhaHeader {
@@ -242,17 +979,43 @@ impl<R: io::Read> LhaDecodeReader<R> {
     /// After this call, reading from this instance will result in a panic.
     pub fn take_inner(&mut self) -> Option<R> {
         self.header.original_size = 0;
-        self.output_length = 0;
-        self.crc.reset();
-        self.decoder.take().map(|decoder| decoder.into_inner().into_inner())
+        self.reset_output();
+        self.take_limited_inner().map(|limited_rd| limited_rd.into_inner())
     }
     /// Returns the number of remaining bytes of the currently decompressed file to be read.
     pub fn len(&self) -> u64 {
-        self.header.original_size - self.output_length
+        self.header.original_size - self.output_length + (self.output.len() - self.output_pos) as u64
     }
     /// Returns `true` if the current file has been finished reading or if the file was empty.
     pub fn is_empty(&self) -> bool {
-        self.header.original_size == self.output_length
+        self.len() == 0
+    }
+    /// Returns the number of bytes of the current file's compressed content read from the stream
+    /// so far. The decoders may read ahead of the decompressed content.
+    ///
+    /// Returns `0` if the decoder doesn't provide access to its stream reader, see [Decoder::get_ref].
+    pub fn entry_bytes_in(&self) -> u64 {
+        match self.peeked {
+            Some(..) => self.header.compressed_size,
+            None => self.decoder.as_ref().and_then(Decoder::get_ref)
+                                .map_or(0, |rd| self.header.compressed_size - rd.limit())
+        }
+    }
+    /// Returns the number of bytes of the current file decompressed so far.
+    pub fn entry_bytes_out(&self) -> u64 {
+        self.output_length
+    }
+    /// Returns the number of bytes of the archive read from the stream so far, counted from
+    /// the beginning of the archive, see [LhaDecodeReader::entry_bytes_in].
+    pub fn total_bytes_in(&self) -> u64 {
+        match self.peeked {
+            Some(..) => self.stream_offset,
+            None => self.stream_offset + self.entry_bytes_in()
+        }
+    }
+    /// Returns the number of bytes of all files of the archive decompressed so far.
+    pub fn total_bytes_out(&self) -> u64 {
+        self.total_output_length
     }
     /// Returns `true` if an underlying stream reader is present in the decoder.
     pub fn is_present(&self) -> bool {
@@ -267,19 +1030,29 @@ impl<R: io::Read> LhaDecodeReader<R> {
     /// Returns `true` if the computed CRC-16 matches the checksum in the header.
     ///
     /// This should be called after the whole file has been read.
+    ///
+    /// Returns `false` if the checksum is not being computed, see [CrcPolicy].
     pub fn crc_is_ok(&self) -> bool {
-        self.crc.sum16() == self.header.file_crc
+        self.crc_enabled && self.crc.sum16() == self.header.file_crc
     }
     /// Returns CRC-16 checksum if the computed checksum matches the one in the header.
     /// Otherwise returns an error.
     ///
     /// This should be called after the whole file has been read.
+    ///
+    /// An error is also returned if the checksum is not being computed, see [CrcPolicy].
     pub fn crc_check(&self) -> io::Result<u16> {
-        if self.crc_is_ok() {
+        if !self.crc_enabled {
+            Err(io::Error::new(io::ErrorKind::Other, "crc16 not computed"))
+        }
+        else if self.crc_is_ok() {
+            trace!(debug!(crc = self.header.file_crc, "checksum verified"));
             Ok(self.header.file_crc)
         }
         else {
-            Err(io::Error::new(io::ErrorKind::InvalidData, "crc16 mismatch"))
+            trace!(warn!(expected = self.header.file_crc, got = self.crc.sum16(), "checksum mismatch"));
+            let err = LhaError::BadChecksum { expected: self.header.file_crc, got: self.crc.sum16() };
+            Err(self.with_context(err.into(), ErrorPhase::Checksum))
         }
     }
     /// Returns `true` if the current file's compression method is supported.
@@ -293,19 +1066,142 @@ impl<R: io::Read> LhaDecodeReader<R> {
     pub fn is_decoder_supported(&self) -> bool {
         self.decoder.as_ref().map(|d| d.is_supported()).unwrap_or(false)
     }
+    /// Returns `Ok(())` if the current file's compression method is supported, otherwise returns
+    /// the reason why it is not supported, e.g. the cargo feature enabling its decoder, see
+    /// [CompressionMethod::check_supported].
+    ///
+    /// # Example
+    /// ```no_run
+    /// let lha_reader = delharc::parse_file("archive.lzh")?;
+    /// if let Err(reason) = lha_reader.decoder_support() {
+    ///     eprintln!("{}: {}", lha_reader.header().parse_pathname().display(), reason);
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn decoder_support(&self) -> Result<(), UnsupportedReason> {
+        match self.is_decoder_supported() {
+            true => Ok(()),
+            false => CompressionMethod::from(self.header.compression).check_supported()
+        }
+    }
+
+    fn reset_output(&mut self) {
+        self.crc.reset();
+        self.crc_enabled = self.crc_policy == CrcPolicy::Always;
+        self.output_length = 0;
+        self.output.clear();
+        self.output_pos = 0;
+    }
+
+    /// Returns an error if the decoding has been cancelled or if the current file exceeds the limits
+    /// of decompressed files.
+    fn check_decode(&self) -> io::Result<()> {
+        self.check_cancelled()?;
+        // the unsupported decoder also replaces the decoder of the skipped or raw content
+        if self.peeked.is_none() && matches!(self.decoder, Some(DecoderAny::UnsupportedDecoder(..))) &&
+           !self.header.compression_method().map_or(false, |method| method.is_supported())
+        {
+            return Err(LhaError::UnsupportedMethod(self.header.compression).into())
+        }
+        let header = &self.header;
+        match LimitError::check(header.original_size, header.compressed_size,
+                                self.max_entry_size, self.max_compression_ratio)
+        {
+            Some(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+            None => Ok(())
+        }
+    }
+    /// Returns an error if the decoding has been cancelled with the registered flag.
+    fn check_cancelled(&self) -> io::Result<()> {
+        match &self.cancel_flag {
+            Some(flag) if flag.load(Ordering::Relaxed) => Err(Cancelled.into()),
+            _ => Ok(())
+        }
+    }
+    /// Passes the progress of decoding the current file to the registered callback after decoding
+    /// a chunk of `len` bytes.
+    fn report_progress(&mut self, len: usize) {
+        if len == 0 {
+            return
+        }
+        let index = self.header_count.saturating_sub(1);
+        if self.output_length == len as u64 {
+            trace!(debug!(index, path = ?self.header.parse_pathname(), "decoding started"));
+        }
+        if self.output_length == self.header.original_size {
+            trace!(debug!(index, bytes_in = self.entry_bytes_in(), bytes_out = self.output_length,
+                          "decoding finished"));
+        }
+        let bytes_in = self.entry_bytes_in();
+        if let Some(ProgressCallback(callback)) = self.progress.as_mut() {
+            callback(index, bytes_in, self.output_length);
+        }
+    }
+
+    /// Attaches the context of the current file to the error occurred in the given `phase`.
+    fn with_context(&self, err: io::Error, phase: ErrorPhase) -> io::Error {
+        with_context(err, || {
+            let offset = self.header.header_offset.unwrap_or(0);
+            ErrorContext { phase, path: Some(self.pathname()), offset }
+        })
+    }
+
+    fn take_limited_inner(&mut self) -> Option<io::Take<R>> {
+        self.decoder.take().map(|decoder| decoder.into_inner_reusing(&mut self.spare))
+    }
 }
 
 impl<R: io::Read> io::Read for LhaDecodeReader<R> {
     fn read(&mut self, buf: &mut[u8]) -> io::Result<usize> {
+        if self.output_pos != self.output.len() {
+            let len = io::Read::read(&mut &self.output[self.output_pos..], buf)?;
+            self.output_pos += len;
+            return Ok(len)
+        }
         let len = buf.len().min((self.header.original_size - self.output_length) as usize);
         let target = &mut buf[0..len];
-        self.decoder.as_mut().unwrap().fill_buffer(target)?;
+        self.check_decode()
+            .and_then(|_| self.decoder.as_mut().unwrap().fill_buffer(target))
+            .map_err(|e| self.with_context(e, ErrorPhase::Decode))?;
         self.output_length += len as u64;
-        self.crc.digest(target);
+        self.total_output_length += len as u64;
+        if self.crc_enabled {
+            self.crc.digest(target);
+        }
+        self.report_progress(len);
         Ok(len)
     }
 }
 
+impl<R: io::Read> io::BufRead for LhaDecodeReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.output_pos == self.output.len() {
+            let len = self.output_buffer_size.min((self.header.original_size - self.output_length) as usize);
+            self.output.clear();
+            self.output.resize(len, 0);
+            self.output_pos = 0;
+            let res = self.check_decode().and_then(|_|
+                self.decoder.as_mut().unwrap().fill_buffer(&mut self.output)
+            );
+            if let Err(e) = res {
+                self.output.clear();
+                return Err(self.with_context(e, ErrorPhase::Decode))
+            }
+            self.output_length += len as u64;
+            self.total_output_length += len as u64;
+            if self.crc_enabled {
+                self.crc.digest(&self.output);
+            }
+            self.report_progress(len);
+        }
+        Ok(&self.output[self.output_pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.output_pos = (self.output_pos + amt).min(self.output.len());
+    }
+}
+
 impl<R: io::Read> DecoderAny<R> {
     /// Creates an instance of `DecoderAny<Take<R>>` from the given `LhaHeader` reference and a stream reader.
     pub fn new_from_header(header: &LhaHeader, rd: R) -> DecoderAny<io::Take<R>> {
@@ -331,6 +1227,10 @@ impl<R: io::Read> DecoderAny<R> {
             CompressionMethod::Lz5 => DecoderAny::Lz5Decoder(Lz5Decoder::new(rd)),
             #[cfg(feature = "lh1")]
             CompressionMethod::Lh1 => DecoderAny::Lh1Decoder(Lh1Decoder::new(rd)),
+            #[cfg(feature = "lh2")]
+            CompressionMethod::Lh2 => DecoderAny::Lh2Decoder(Lh2Decoder::new(rd)),
+            #[cfg(feature = "lh3")]
+            CompressionMethod::Lh3 => DecoderAny::Lh3Decoder(Lh3Decoder::new(rd)),
             CompressionMethod::Lh4 => DecoderAny::Lh4Decoder(Lh5Decoder::new(rd)),
             CompressionMethod::Lh5 => DecoderAny::Lh5Decoder(Lh5Decoder::new(rd)),
             CompressionMethod::Lh6 => DecoderAny::Lh6Decoder(Lh7Decoder::new(rd)),
@@ -340,26 +1240,201 @@ impl<R: io::Read> DecoderAny<R> {
             _ => DecoderAny::UnsupportedDecoder(UnsupportedDecoder::new(rd))
         }
     }
+    /// Creates an instance of `DecoderAny<R>` from a user provided decoder.
+    pub fn new_boxed<D: DynDecoder<R> + Send + 'static>(decoder: D) -> Self {
+        DecoderAny::BoxedDecoder(Box::new(decoder))
+    }
     /// Returns `true` if the decoder is able to decode the file's content.
     pub fn is_supported(&self) -> bool {
+        !matches!(self, DecoderAny::UnsupportedDecoder(..))
+    }
+    /// Sets the size of the buffer of the data read from the stream reader, if the decoder has one.
+    pub(crate) fn set_read_buffer_size(&mut self, size: usize) {
+        match self {
+            #[cfg(feature = "lz")]
+            DecoderAny::LzsDecoder(decoder) => decoder.set_read_buffer_size(size),
+            #[cfg(feature = "lh1")]
+            DecoderAny::Lh1Decoder(decoder) => decoder.set_read_buffer_size(size),
+            #[cfg(feature = "lh2")]
+            DecoderAny::Lh2Decoder(decoder) => decoder.set_read_buffer_size(size),
+            #[cfg(feature = "lh3")]
+            DecoderAny::Lh3Decoder(decoder) => decoder.set_read_buffer_size(size),
+            DecoderAny::Lh4Decoder(decoder)|
+            DecoderAny::Lh5Decoder(decoder) => decoder.set_read_buffer_size(size),
+            DecoderAny::Lh6Decoder(decoder)|
+            DecoderAny::Lh7Decoder(decoder) => decoder.set_read_buffer_size(size),
+            #[cfg(feature = "lhx")]
+            DecoderAny::LhxDecoder(decoder) => decoder.set_read_buffer_size(size),
+            _ => {}
+        }
+    }
+    /// Creates an instance of `DecoderAny<Take<R>>` the same way as [DecoderAny::new_from_header] does,
+    /// but takes the buffers from the `spare` decoder if it is of the matching kind.
+    pub(crate) fn new_from_header_reusing(
+            header: &LhaHeader,
+            rd: R,
+            spare: &mut Option<SpareDecoder>
+        ) -> DecoderAny<io::Take<R>>
+    {
+        let limited_rd = rd.take(header.compressed_size);
+        let compression = match header.compression_method() {
+            Ok(compression) => compression,
+            Err(..) => return DecoderAny::UnsupportedDecoder(UnsupportedDecoder::new(limited_rd))
+        };
+        reuse_spare(spare, compression, limited_rd).unwrap_or_else(|limited_rd|
+            DecoderAny::new_from_compression(compression, limited_rd)
+        )
+    }
+    /// Unwraps and returns the inner reader.
+    ///
+    /// The decoder's buffers are being kept in `spare` for reuse, unless the decoder has none.
+    pub(crate) fn into_inner_reusing(self, spare: &mut Option<SpareDecoder>) -> R {
+        macro_rules! keep_spare {
+            ($variant:ident($decoder:expr)) => {{
+                let (mut decoder, rd) = $decoder.replace_reader(io::empty());
+                decoder.reset();
+                *spare = Some(DecoderAny::$variant(decoder));
+                rd
+            }};
+        }
         match self {
-            DecoderAny::UnsupportedDecoder(..) => false,
-            _ => true
+            #[cfg(feature = "lz")]
+            DecoderAny::LzsDecoder(decoder) => keep_spare!(LzsDecoder(decoder)),
+            #[cfg(feature = "lz")]
+            DecoderAny::Lz5Decoder(decoder) => keep_spare!(Lz5Decoder(decoder)),
+            #[cfg(feature = "lh1")]
+            DecoderAny::Lh1Decoder(decoder) => keep_spare!(Lh1Decoder(decoder)),
+            #[cfg(feature = "lh2")]
+            DecoderAny::Lh2Decoder(decoder) => keep_spare!(Lh2Decoder(decoder)),
+            #[cfg(feature = "lh3")]
+            DecoderAny::Lh3Decoder(decoder) => keep_spare!(Lh3Decoder(decoder)),
+            DecoderAny::Lh4Decoder(decoder) => keep_spare!(Lh4Decoder(decoder)),
+            DecoderAny::Lh5Decoder(decoder) => keep_spare!(Lh5Decoder(decoder)),
+            DecoderAny::Lh6Decoder(decoder) => keep_spare!(Lh6Decoder(decoder)),
+            DecoderAny::Lh7Decoder(decoder) => keep_spare!(Lh7Decoder(decoder)),
+            #[cfg(feature = "lhx")]
+            DecoderAny::LhxDecoder(decoder) => keep_spare!(LhxDecoder(decoder)),
+            decoder => decoder.into_inner()
         }
     }
 }
 
+/// Takes the `spare` decoder and attaches it to the stream reader `rd` if the decoder is suitable
+/// for the given compression method. Otherwise `spare` is left intact and `rd` is returned back.
+fn reuse_spare<T: io::Read>(
+        spare: &mut Option<SpareDecoder>,
+        compression: CompressionMethod,
+        rd: T
+    ) -> Result<DecoderAny<T>, T>
+{
+    let decoder = match spare.take() {
+        Some(decoder) => decoder,
+        None => return Err(rd)
+    };
+    Ok(match (decoder, compression) {
+        #[cfg(feature = "lz")]
+        (DecoderAny::LzsDecoder(decoder), CompressionMethod::Lzs) => {
+            DecoderAny::LzsDecoder(decoder.replace_reader(rd).0)
+        }
+        #[cfg(feature = "lz")]
+        (DecoderAny::Lz5Decoder(decoder), CompressionMethod::Lz5) => {
+            DecoderAny::Lz5Decoder(decoder.replace_reader(rd).0)
+        }
+        #[cfg(feature = "lh1")]
+        (DecoderAny::Lh1Decoder(decoder), CompressionMethod::Lh1) => {
+            DecoderAny::Lh1Decoder(decoder.replace_reader(rd).0)
+        }
+        #[cfg(feature = "lh2")]
+        (DecoderAny::Lh2Decoder(decoder), CompressionMethod::Lh2) => {
+            DecoderAny::Lh2Decoder(decoder.replace_reader(rd).0)
+        }
+        #[cfg(feature = "lh3")]
+        (DecoderAny::Lh3Decoder(decoder), CompressionMethod::Lh3) => {
+            DecoderAny::Lh3Decoder(decoder.replace_reader(rd).0)
+        }
+        (DecoderAny::Lh4Decoder(decoder)|DecoderAny::Lh5Decoder(decoder), CompressionMethod::Lh4) => {
+            DecoderAny::Lh4Decoder(decoder.replace_reader(rd).0)
+        }
+        (DecoderAny::Lh4Decoder(decoder)|DecoderAny::Lh5Decoder(decoder), CompressionMethod::Lh5) => {
+            DecoderAny::Lh5Decoder(decoder.replace_reader(rd).0)
+        }
+        (DecoderAny::Lh6Decoder(decoder)|DecoderAny::Lh7Decoder(decoder), CompressionMethod::Lh6) => {
+            DecoderAny::Lh6Decoder(decoder.replace_reader(rd).0)
+        }
+        (DecoderAny::Lh6Decoder(decoder)|DecoderAny::Lh7Decoder(decoder), CompressionMethod::Lh7) => {
+            DecoderAny::Lh7Decoder(decoder.replace_reader(rd).0)
+        }
+        #[cfg(feature = "lhx")]
+        (DecoderAny::LhxDecoder(decoder), CompressionMethod::Lhx) => {
+            DecoderAny::LhxDecoder(decoder.replace_reader(rd).0)
+        }
+        (decoder, _) => {
+            *spare = Some(decoder);
+            return Err(rd)
+        }
+    })
+}
+
 impl<R: io::Read> Decoder<R> for DecoderAny<R> {
     fn into_inner(self) -> R {
         decoder_any_dispatch!((self)(decoder) => decoder.into_inner())
     }
 
+    fn get_ref(&self) -> Option<&R> {
+        decoder_any_dispatch!((self)(decoder) => decoder.get_ref())
+    }
+
     #[inline]
     fn fill_buffer(&mut self, buf: &mut[u8]) -> io::Result<()> {
         decoder_any_dispatch!((self)(decoder) => decoder.fill_buffer(buf))
     }
 }
 
+impl<R, D: Decoder<R>> DynDecoder<R> for D {
+    fn into_inner_boxed(self: Box<Self>) -> R {
+        (*self).into_inner()
+    }
+}
+
+impl<R> fmt::Debug for Skipper<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Skipper")
+    }
+}
+
+impl<R> fmt::Debug for Scanner<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Scanner")
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+impl<R> fmt::Debug for dyn DynDecoder<R> + Send {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DynDecoder")
+    }
+}
+
+impl<R> Decoder<R> for BoxedDecoder<R> {
+    fn into_inner(self) -> R {
+        DynDecoder::into_inner_boxed(self)
+    }
+
+    fn get_ref(&self) -> Option<&R> {
+        (**self).get_ref()
+    }
+
+    #[inline]
+    fn fill_buffer(&mut self, buf: &mut[u8]) -> io::Result<()> {
+        (**self).fill_buffer(buf)
+    }
+}
+
 impl<R: io::Read> PassthroughDecoder<R> {
     pub fn new(inner: R) -> Self {
         PassthroughDecoder { inner }
@@ -371,6 +1446,10 @@ impl<R: io::Read> Decoder<R> for PassthroughDecoder<R> {
         self.inner
     }
 
+    fn get_ref(&self) -> Option<&R> {
+        Some(&self.inner)
+    }
+
     #[inline]
     fn fill_buffer(&mut self, buf: &mut[u8]) -> io::Result<()> {
         self.inner.read_exact(buf)
@@ -388,6 +1467,10 @@ impl<R: io::Read> Decoder<R> for UnsupportedDecoder<R> {
         self.inner
     }
 
+    fn get_ref(&self) -> Option<&R> {
+        Some(&self.inner)
+    }
+
     #[inline]
     fn fill_buffer(&mut self, _buf: &mut[u8]) -> io::Result<()> {
         Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported compression method"))
@@ -435,6 +1518,134 @@ impl<R> From<LhaDecodeError<R>> for io::Error {
     }
 }
 
+fn seek_over<R: io::Seek>(rd: &mut R, len: u64) -> io::Result<()> {
+    let offset = i64::try_from(len).map_err(|_|
+        io::Error::new(io::ErrorKind::InvalidData, "compressed size too large"))?;
+    rd.seek(io::SeekFrom::Current(offset)).map(drop)
+}
+
+/// Returns the number of bytes skipped before the next plausible header, after moving the stream
+/// `back` by the given number of bytes, and positions the stream at the header.
+fn scan_header<R: io::Read + io::Seek>(rd: &mut R, back: u64) -> io::Result<Option<u64>> {
+    let back = i64::try_from(back).map_err(|_|
+        io::Error::new(io::ErrorKind::InvalidData, "header too large"))?;
+    rd.seek(io::SeekFrom::Current(-back))?;
+    find_signature(rd)
+}
+
+/// Positions the stream at the first header if it begins with the executable stub of
+/// a self-extracting archive. Returns `false`, with the stream at its initial position, if there
+/// is a stub without any valid header.
+fn skip_sfx_stub<R: io::Read + io::Seek>(rd: &mut R, mode: ParseMode) -> io::Result<bool> {
+    let start = rd.stream_position()?;
+    let mut magic = Vec::with_capacity(7);
+    io::Read::read_to_end(&mut io::Read::take(&mut *rd, 7), &mut magic)?;
+    if !magic.starts_with(b"MZ") || is_signature(&magic) {
+        return rd.seek(io::SeekFrom::Start(start)).map(|_| true)
+    }
+    rd.seek(io::SeekFrom::Start(start + 2))?;
+    match LhaHeader::scan_with_mode(&mut *rd, mode)?.and_then(|header| header.header_offset) {
+        Some(offset) => {
+            trace!(debug!(size = offset + 2, "self-extracting stub skipped"));
+            rd.seek(io::SeekFrom::Start(start + 2 + offset)).map(|_| true)
+        }
+        None => rd.seek(io::SeekFrom::Start(start)).map(|_| false)
+    }
+}
+
+/// The largest offset of the PE header in the executable stub of a self-extracting archive.
+const MAX_PE_HEADER_OFFSET: usize = 0x10000;
+
+/// Skips the executable image of a self-extracting archive, beginning with the `magic` bytes
+/// already read from `rd`.
+///
+/// The size is found in the MS-DOS header and the section table of a PE executable, so the
+/// stream is read no further than the end of the image. This allows skipping the stub without
+/// seeking, provided the archive immediately follows the image.
+fn skip_exe_image<R: io::Read>(rd: &mut R, magic: Vec<u8>) -> io::Result<()> {
+    fn read_up_to<R: io::Read>(rd: &mut R, head: &mut Vec<u8>, len: usize) -> io::Result<()> {
+        let missing = len.saturating_sub(head.len());
+        if io::Read::read_to_end(&mut io::Read::take(&mut *rd, missing as u64), head)? != missing {
+            return Err(io::ErrorKind::UnexpectedEof.into())
+        }
+        Ok(())
+    }
+    let u16_at = |head: &[u8], pos: usize| u16::from_le_bytes([head[pos], head[pos + 1]]);
+    let u32_at = |head: &[u8], pos: usize| u32::from_le_bytes([head[pos], head[pos + 1], head[pos + 2], head[pos + 3]]);
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid executable stub");
+
+    let mut head = magic;
+    read_up_to(rd, &mut head, 0x1c)?;
+    // the number of bytes in the last page and the number of 512-byte pages
+    let (last, pages) = (u16_at(&head, 2), u16_at(&head, 4));
+    if pages == 0 || last >= 512 {
+        return Err(invalid())
+    }
+    let mut size = u64::from(pages) * 512 - match last { 0 => 0, last => 512 - u64::from(last) };
+    // the relocation table offset of 0x40 marks the executables with a new header
+    if u16_at(&head, 0x18) == 0x40 && size >= 0x40 {
+        read_up_to(rd, &mut head, 0x40)?;
+        let pe = usize::try_from(u32_at(&head, 0x3c)).map_err(|_| invalid())?;
+        if (0x40..MAX_PE_HEADER_OFFSET).contains(&pe) {
+            read_up_to(rd, &mut head, pe + 24)?;
+            if head[pe..pe + 4] == *b"PE\0\0" {
+                let sections = usize::from(u16_at(&head, pe + 6));
+                let table = pe + 24 + usize::from(u16_at(&head, pe + 20));
+                read_up_to(rd, &mut head, table + sections * 40)?;
+                for section in head[table..].chunks_exact(40) {
+                    // the offset and the size of the section content in the file
+                    let end = u64::from(u32_at(section, 20)) + u64::from(u32_at(section, 16));
+                    size = size.max(end);
+                }
+            }
+        }
+    }
+    let remaining = size.checked_sub(head.len() as u64).ok_or_else(invalid)?;
+    if io::copy(&mut io::Read::take(&mut *rd, remaining), &mut io::sink())? != remaining {
+        return Err(io::ErrorKind::UnexpectedEof.into())
+    }
+    trace!(debug!(size, "self-extracting stub skipped"));
+    Ok(())
+}
+
+/// Emits the event of parsing the header found at the archive `offset`.
+#[cfg(feature = "tracing")]
+fn trace_header(offset: u64, header: Option<&LhaHeader>) {
+    match header {
+        Some(header) => tracing::debug!(
+            offset, level = header.level, size = header.header_size,
+            method = %header.compression.escape_ascii(), path = ?header.parse_pathname(),
+            compressed_size = header.compressed_size, original_size = header.original_size,
+            "header parsed"),
+        None => tracing::debug!(offset, "end of archive")
+    }
+}
+
+/// Returns `true` if the error of reading a header can be recovered from by scanning for
+/// the next header.
+fn is_recoverable(err: &io::Error) -> bool {
+    is_invalid_header(err) && !err.get_ref().map_or(false, |e| e.is::<LimitError>())
+}
+
+/// Returns the offset of the header following the file described by `header`.
+fn next_header_offset(header: &LhaHeader) -> u64 {
+    header.header_offset.unwrap_or(0) + header.header_size + header.compressed_size
+}
+
+/// Counts the bytes read from the wrapped reader.
+struct CountingReader<'a, R> {
+    inner: &'a mut R,
+    count: u64
+}
+
+impl<R: io::Read> io::Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.count += len as u64;
+        Ok(len)
+    }
+}
+
 fn wrap_err<R>(read: R, source: io::Error) -> LhaDecodeError<R> {
     LhaDecodeError { read, source }
 }
@@ -443,6 +1654,7 @@ fn wrap_err<R>(read: R, source: io::Error) -> LhaDecodeError<R> {
 mod tests {
     use std::io;
     use super::*;
+    use crate::fixtures::{method_entry, packed_entry};
 
     #[test]
     fn decode_error_works() {
@@ -455,4 +1667,746 @@ mod tests {
         assert_eq!(rd.position(), 1);
         assert_eq!(rd.into_inner(), vec![0u8;3]);
     }
+
+    #[test]
+    fn decoder_factory_works() {
+        fn factory<R: io::Read>(method: [u8;5], rd: io::Take<R>) -> DecoderAny<io::Take<R>> {
+            match &method {
+                b"-zz0-" => DecoderAny::PassthroughDecoder(PassthroughDecoder::new(rd)),
+                _ => DecoderAny::UnsupportedDecoder(UnsupportedDecoder::new(rd))
+            }
+        }
+        let mut archive = method_entry(b"-zz0-", b"Hello");
+        archive.extend(method_entry(b"-zz1-", b"World!"));
+        archive.extend(method_entry(b"-zz0-", b"Bye"));
+        archive.push(0);
+        let lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        assert!(!lha_reader.is_decoder_supported());
+        let mut lha_reader = lha_reader.with_decoder_factory(factory);
+        assert!(lha_reader.is_decoder_supported());
+        let mut content = String::new();
+        io::Read::read_to_string(&mut lha_reader, &mut content).unwrap();
+        assert_eq!(content, "Hello");
+        assert!(lha_reader.crc_is_ok());
+        assert!(lha_reader.next_file().unwrap());
+        assert!(!lha_reader.is_decoder_supported());
+        assert!(lha_reader.next_file().unwrap());
+        assert!(lha_reader.is_decoder_supported());
+        let mut content = String::new();
+        io::Read::read_to_string(&mut lha_reader, &mut content).unwrap();
+        assert_eq!(content, "Bye");
+        assert!(lha_reader.crc_is_ok());
+        assert!(!lha_reader.next_file().unwrap());
+    }
+
+    #[derive(Debug)]
+    struct XorDecoder<R>(R);
+
+    impl<R: io::Read> Decoder<R> for XorDecoder<R> {
+        fn into_inner(self) -> R {
+            self.0
+        }
+
+        fn fill_buffer(&mut self, buf: &mut[u8]) -> io::Result<()> {
+            self.0.read_exact(buf)?;
+            buf.iter_mut().for_each(|b| *b ^= 0x55);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn boxed_decoder_works() {
+        fn factory<R: io::Read + Send + 'static>(method: [u8;5], rd: io::Take<R>) -> DecoderAny<io::Take<R>> {
+            match &method {
+                b"-xr0-" => DecoderAny::new_boxed(XorDecoder(rd)),
+                _ => DecoderAny::UnsupportedDecoder(UnsupportedDecoder::new(rd))
+            }
+        }
+        let packed: Vec<u8> = b"Hello World!".iter().map(|b| b ^ 0x55).collect();
+        let mut archive = packed_entry(b"-xr0-", &packed, b"Hello World!");
+        archive.push(0);
+        let lha_reader = LhaDecodeReader::new(io::Cursor::new(archive)).unwrap();
+        let mut lha_reader = lha_reader.with_decoder_factory(factory);
+        assert!(lha_reader.is_decoder_supported());
+        let mut content = String::new();
+        io::Read::read_to_string(&mut lha_reader, &mut content).unwrap();
+        assert_eq!(content, "Hello World!");
+        assert!(lha_reader.crc_is_ok());
+        assert!(!lha_reader.next_file().unwrap());
+
+        let mut decoder: BoxedDecoder<io::Cursor<Vec<u8>>> = Box::new(XorDecoder(io::Cursor::new(packed)));
+        let mut buf = [0u8;5];
+        decoder.fill_buffer(&mut buf).unwrap();
+        assert_eq!(&buf, b"Hello");
+        assert_eq!(decoder.into_inner().position(), 5);
+    }
+
+    #[test]
+    fn raw_entry_reader_works() {
+        let packed: Vec<u8> = b"Hello World!".iter().map(|b| b ^ 0x55).collect();
+        let mut archive = packed_entry(b"-xr0-", &packed, b"Hello World!");
+        archive.extend(method_entry(b"-lh0-", b"Bye"));
+        archive.extend(method_entry(b"-lh0-", b"Stored"));
+        archive.push(0);
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        assert!(!lha_reader.is_decoder_supported());
+        let mut raw = Vec::new();
+        io::Read::read_to_end(lha_reader.raw_entry_reader().unwrap(), &mut raw).unwrap();
+        assert_eq!(raw, packed);
+        assert!(lha_reader.next_file().unwrap());
+        let mut buf = [0u8;2];
+        io::Read::read_exact(lha_reader.raw_entry_reader().unwrap(), &mut buf).unwrap();
+        assert_eq!(&buf, b"By");
+        assert!(!lha_reader.is_decoder_supported());
+        assert!(io::Read::read(&mut lha_reader, &mut buf).is_err());
+        assert!(lha_reader.next_file().unwrap());
+        io::Read::read_exact(&mut lha_reader, &mut buf).unwrap();
+        assert_eq!(&buf, b"St");
+        assert!(lha_reader.raw_entry_reader().is_err());
+        assert!(!lha_reader.next_file().unwrap());
+    }
+
+    #[test]
+    fn decode_into_works() {
+        let mut archive = method_entry(b"-lh0-", b"Hello World!");
+        archive.extend(method_entry(b"-lh0-", b"Bye"));
+        archive.push(0);
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        let mut buf = [0u8;16];
+        assert_eq!(lha_reader.decode_into(&mut buf).unwrap(), 12);
+        assert_eq!(&buf[..12], b"Hello World!");
+        assert!(lha_reader.crc_is_ok());
+        assert!(lha_reader.is_empty());
+        assert_eq!(lha_reader.decode_into(&mut buf).unwrap(), 0);
+        assert!(lha_reader.next_file().unwrap());
+        assert!(lha_reader.decode_into(&mut buf[..2]).is_err());
+        let mut vec = b"Good".to_vec();
+        assert_eq!(lha_reader.decode_to_vec(&mut vec).unwrap(), 3);
+        assert_eq!(vec, b"GoodBye");
+        lha_reader.crc_check().unwrap();
+        assert!(!lha_reader.next_file().unwrap());
+    }
+
+    #[test]
+    fn buf_read_works() {
+        use io::{BufRead, Read};
+        let text: String = (0..2000).map(|n| format!("line {}\n", n)).collect();
+        let mut archive = method_entry(b"-lh0-", text.as_bytes());
+        archive.extend(method_entry(b"-lh0-", b"Hello\nWorld!"));
+        archive.extend(method_entry(b"-lh0-", b"Bye"));
+        archive.push(0);
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        let mut line = String::new();
+        lha_reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "line 0\n");
+        assert_eq!(lha_reader.len(), text.len() as u64 - 7);
+        let mut buf = [0u8;7];
+        io::Read::read_exact(&mut lha_reader, &mut buf).unwrap();
+        assert_eq!(&buf, b"line 1\n");
+        assert_eq!(lha_reader.fill_buf().unwrap()[..7], *b"line 2\n");
+        lha_reader.consume(7);
+        let lines: Vec<String> = lha_reader.by_ref().lines().map(Result::unwrap).collect();
+        assert_eq!(lines.len(), 1997);
+        assert_eq!(lines[1996], "line 1999");
+        assert!(lha_reader.is_empty());
+        lha_reader.crc_check().unwrap();
+        assert!(lha_reader.next_file().unwrap());
+        assert_eq!(lha_reader.fill_buf().unwrap(), b"Hello\nWorld!");
+        lha_reader.consume(6);
+        let mut vec = Vec::new();
+        assert_eq!(lha_reader.decode_to_vec(&mut vec).unwrap(), 6);
+        assert_eq!(vec, b"World!");
+        lha_reader.crc_check().unwrap();
+        assert!(lha_reader.next_file().unwrap());
+        let mut content = Vec::new();
+        lha_reader.read_until(0, &mut content).unwrap();
+        assert_eq!(content, b"Bye");
+        assert!(lha_reader.fill_buf().unwrap().is_empty());
+        lha_reader.crc_check().unwrap();
+        assert!(!lha_reader.next_file().unwrap());
+    }
+
+    #[test]
+    fn crc_policy_works() {
+        let mut archive = method_entry(b"-lh0-", b"Hello");
+        archive.extend(method_entry(b"-lh0-", b"World!"));
+        archive.extend(method_entry(b"-lh0-", b"Bye"));
+        archive.push(0);
+        let mut buf = [0u8;16];
+        let lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        assert_eq!(lha_reader.crc_policy(), CrcPolicy::Always);
+        let mut lha_reader = lha_reader.with_crc_policy(CrcPolicy::OnDemand);
+        assert_eq!(lha_reader.decode_into(&mut buf).unwrap(), 5);
+        assert!(!lha_reader.crc_is_ok());
+        assert_eq!(lha_reader.crc_check().unwrap_err().kind(), io::ErrorKind::Other);
+        assert!(!lha_reader.request_crc());
+        assert!(lha_reader.next_file().unwrap());
+        assert!(lha_reader.request_crc());
+        assert_eq!(lha_reader.decode_into(&mut buf).unwrap(), 6);
+        assert!(lha_reader.crc_is_ok());
+        assert!(lha_reader.next_file().unwrap());
+        lha_reader.set_crc_policy(CrcPolicy::Skip);
+        assert!(!lha_reader.request_crc());
+        assert_eq!(lha_reader.decode_into(&mut buf).unwrap(), 3);
+        assert!(lha_reader.crc_check().is_err());
+        lha_reader.set_crc_policy(CrcPolicy::Always);
+        assert!(lha_reader.crc_check().is_err());
+        assert!(!lha_reader.next_file().unwrap());
+    }
+
+    #[test]
+    fn parse_mode_works() {
+        let mut archive = method_entry(b"-lh0-", b"Hello");
+        archive[1] ^= 1;
+        let second = archive.len();
+        archive.extend(method_entry(b"-lh0-", b"World!"));
+        archive[second + 1] ^= 1;
+        archive.push(0);
+        let mut buf = [0u8;16];
+        let err = io::Error::from(LhaDecodeReader::new(&archive[..]).unwrap_err());
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let mut lha_reader = LhaDecodeReader::default().with_parse_mode(ParseMode::Lenient);
+        assert_eq!(lha_reader.parse_mode(), ParseMode::Lenient);
+        assert!(lha_reader.begin_new(&archive[..]).unwrap());
+        assert!(matches!(lha_reader.header().warnings[..], [crate::header::HeaderWarning::Checksum {..}]));
+        assert_eq!(lha_reader.decode_into(&mut buf).unwrap(), 5);
+        assert!(lha_reader.crc_is_ok());
+        lha_reader.set_parse_mode(ParseMode::Strict);
+        let err = io::Error::from(lha_reader.next_file().unwrap_err());
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn header_offset_works() {
+        let mut archive = method_entry(b"-lh0-", b"Hello");
+        let second = archive.len() as u64;
+        archive.extend(method_entry(b"-lh0-", b"World!"));
+        let third = archive.len() as u64;
+        archive.extend(method_entry(b"-lh0-", b"Bye"));
+        archive.push(0);
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        assert_eq!(lha_reader.header().header_offset, Some(0));
+        assert_eq!(lha_reader.header().header_size, second - 5);
+        let mut buf = [0u8;2];
+        io::Read::read_exact(&mut lha_reader, &mut buf).unwrap();
+        assert!(lha_reader.next_file().unwrap());
+        assert_eq!(lha_reader.header().header_offset, Some(second));
+        assert_eq!(lha_reader.header().header_size, third - second - 6);
+        assert!(lha_reader.next_file().unwrap());
+        assert_eq!(lha_reader.header().header_offset, Some(third));
+        assert!(!lha_reader.next_file().unwrap());
+        assert!(lha_reader.begin_new(&archive[second as usize..]).unwrap());
+        assert_eq!(lha_reader.header().header_offset, Some(0));
+    }
+
+    #[test]
+    fn decoder_reuse_works() {
+        let content = include_bytes!("../tests/decode/lh0.bin");
+        let lh5 = include_bytes!("../tests/decode/lh5.bin");
+        let lh7 = include_bytes!("../tests/decode/lh7.bin");
+        let (lh5, lh7) = (&lh5[..lh5.len() - 1], &lh7[..lh7.len() - 1]);
+        let mut archive = Vec::new();
+        for (method, packed) in [(b"-lh5-", lh5), (b"-lh5-", lh5), (b"-lh0-", &content[..]),
+                                 (b"-lh4-", lh5), (b"-lh7-", lh7), (b"-lh5-", lh5)]
+        {
+            archive.extend(packed_entry(method, packed, content));
+        }
+        archive.push(0);
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        let mut buf = [0u8;1000];
+        io::Read::read_exact(&mut lha_reader, &mut buf).unwrap();
+        assert!(lha_reader.spare.is_none());
+        let mut count = 1;
+        while lha_reader.next_file().unwrap() {
+            let mut output = Vec::new();
+            assert_eq!(lha_reader.decode_to_vec(&mut output).unwrap(), content.len());
+            assert_eq!(output, content);
+            lha_reader.crc_check().unwrap();
+            count += 1;
+        }
+        assert_eq!(count, 6);
+        assert!(matches!(lha_reader.spare, Some(DecoderAny::Lh5Decoder(..))));
+    }
+
+    #[test]
+    fn seeking_works() {
+        /// Counts the bytes being read.
+        struct Counting(io::Cursor<Vec<u8>>, u64);
+
+        impl io::Read for Counting {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = self.0.read(buf)?;
+                self.1 += len as u64;
+                Ok(len)
+            }
+        }
+
+        impl io::Seek for Counting {
+            fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+                self.0.seek(pos)
+            }
+        }
+
+        let content = vec![b'x'; 10000];
+        let mut archive = method_entry(b"-lh0-", &content);
+        archive.extend(method_entry(b"-lh0-", b"Hello"));
+        archive.push(0);
+        let counting = Counting(io::Cursor::new(archive.clone()), 0);
+        let mut lha_reader = LhaDecodeReader::new_seekable(counting).unwrap();
+        let mut buf = [0u8;100];
+        io::Read::read_exact(&mut lha_reader, &mut buf).unwrap();
+        assert!(lha_reader.next_file().unwrap());
+        let mut output = Vec::new();
+        lha_reader.decode_to_vec(&mut output).unwrap();
+        assert_eq!(output, b"Hello");
+        lha_reader.crc_check().unwrap();
+        assert!(!lha_reader.next_file().unwrap());
+        let counting = lha_reader.into_inner();
+        assert_eq!(counting.0.position(), archive.len() as u64);
+        assert!(counting.1 < 1000);
+
+        let counting = Counting(io::Cursor::new(archive.clone()), 0);
+        let mut lha_reader = LhaDecodeReader::new(counting).unwrap();
+        assert!(lha_reader.next_file().unwrap());
+        assert!(!lha_reader.next_file().unwrap());
+        assert_eq!(lha_reader.into_inner().1, archive.len() as u64);
+    }
+
+    #[test]
+    fn peek_header_works() {
+        let mut archive = Vec::new();
+        for content in [&b"Hello"[..], b"World!", b"Bye", b"Done"] {
+            archive.extend(method_entry(b"-lh0-", content));
+        }
+        archive.push(0);
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        assert_eq!(lha_reader.peek_header().unwrap().unwrap().original_size, 6);
+        assert_eq!(lha_reader.peek_header().unwrap().unwrap().original_size, 6);
+        assert_eq!(lha_reader.header().original_size, 5);
+        assert!(lha_reader.next_file().unwrap());
+        let mut output = Vec::new();
+        lha_reader.decode_to_vec(&mut output).unwrap();
+        assert_eq!(output, b"World!");
+        lha_reader.crc_check().unwrap();
+        assert_eq!(lha_reader.skip_peeked().unwrap().unwrap().original_size, 4);
+        assert_eq!(lha_reader.header().original_size, 6);
+        assert!(lha_reader.next_file().unwrap());
+        let header = lha_reader.header();
+        assert_eq!(header.original_size, 4);
+        assert_eq!(header.header_offset, Some(archive.len() as u64 - 1 - header.header_size - 4));
+        let mut output = Vec::new();
+        lha_reader.decode_to_vec(&mut output).unwrap();
+        assert_eq!(output, b"Done");
+        assert!(lha_reader.peek_header().unwrap().is_none());
+        assert!(lha_reader.skip_peeked().unwrap().is_none());
+        assert!(!lha_reader.next_file().unwrap());
+        assert_eq!(lha_reader.into_inner().len(), 0);
+    }
+
+    #[test]
+    fn rewind_works() {
+        let mut data = b"SFX".to_vec();
+        for content in [&b"Hello"[..], b"World!", b"Bye"] {
+            data.extend(method_entry(b"-lh0-", content));
+        }
+        for end_marker in [true, false] {
+            let mut data = data.clone();
+            if end_marker {
+                data.push(0);
+            }
+            let mut rd = io::Cursor::new(data);
+            rd.set_position(3);
+            let mut lha_reader = LhaDecodeReader::new(rd).unwrap();
+            let mut buf = [0u8;2];
+            io::Read::read_exact(&mut lha_reader, &mut buf).unwrap();
+            assert!(lha_reader.rewind().unwrap());
+            let mut output = Vec::new();
+            lha_reader.decode_to_vec(&mut output).unwrap();
+            assert_eq!(output, b"Hello");
+            lha_reader.crc_check().unwrap();
+            assert!(lha_reader.next_file().unwrap());
+            io::Read::read_exact(&mut lha_reader, &mut buf).unwrap();
+            assert!(lha_reader.peek_header().unwrap().is_some());
+            assert!(lha_reader.rewind().unwrap());
+            assert_eq!(lha_reader.header().original_size, 5);
+            assert!(lha_reader.next_file().unwrap());
+            assert!(lha_reader.next_file().unwrap());
+            assert!(!lha_reader.next_file().unwrap());
+            assert!(lha_reader.rewind().unwrap());
+            assert_eq!(lha_reader.header().header_offset, Some(0));
+            io::Read::read_exact(lha_reader.raw_entry_reader().unwrap(), &mut buf).unwrap();
+            assert!(lha_reader.rewind().unwrap());
+            let mut output = Vec::new();
+            lha_reader.decode_to_vec(&mut output).unwrap();
+            assert_eq!(output, b"Hello");
+            assert_eq!(lha_reader.into_inner().position(), 3 + method_entry(b"-lh0-", b"Hello").len() as u64);
+        }
+    }
+
+    #[test]
+    fn options_works() {
+        let mut archive = method_entry(b"-lh0-", b"Hello");
+        archive.push(0);
+        let options = LhaOptions::new().strict(false).crc_policy(CrcPolicy::OnDemand)
+                                       .max_header_size(archive.len() - 6)
+                                       .filename_encoding(FilenameEncoding::Latin1);
+        let lha_reader = LhaDecodeReader::new_with_options(&archive[..], options).unwrap();
+        assert_eq!(lha_reader.options(), options);
+        assert_eq!(lha_reader.parse_mode(), ParseMode::Lenient);
+        assert_eq!(lha_reader.crc_policy(), CrcPolicy::OnDemand);
+        assert_eq!(lha_reader.pathname().to_str(), Some("a"));
+        let options = options.max_header_size(archive.len() - 7);
+        let err = LhaDecodeReader::new_with_options(&archive[..], options).unwrap_err();
+        assert_eq!(err.to_string(), "LHA decode error: header at offset 0: header too large");
+        let err = LhaDecodeReader::new_with_options(&[0][..], options).unwrap_err();
+        assert_eq!(err.to_string(), "LHA decode error: a header is missing");
+        assert_eq!(err.into_inner().len(), 0);
+        drop(lha_reader);
+
+        archive[1] ^= 1;
+        assert!(LhaDecodeReader::new_with_options(&archive[..], LhaOptions::new()).is_err());
+        let lha_reader = LhaDecodeReader::new_with_options(&archive[..], LhaOptions::new().strict(false)).unwrap();
+        assert_eq!(lha_reader.header().warnings.len(), 1);
+    }
+
+    #[test]
+    fn limits_work() {
+        let content = include_bytes!("../tests/decode/lh0.bin");
+        let lh5 = include_bytes!("../tests/decode/lh5.bin");
+        let mut archive = method_entry(b"-lh0-", b"Hello");
+        archive.extend(packed_entry(b"-lh5-", &lh5[..lh5.len() - 1], content));
+        archive.push(0);
+        let limit_error = |e: io::Error| {
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+            *e.get_ref().unwrap().downcast_ref::<LimitError>().unwrap()
+        };
+        let options = LhaOptions::new().max_entry_size(4);
+        let mut lha_reader = LhaDecodeReader::new_with_options(&archive[..], options).unwrap();
+        let mut output = Vec::new();
+        let err = lha_reader.decode_to_vec(&mut output).unwrap_err();
+        assert_eq!(limit_error(err), LimitError::EntrySize { size: 5, limit: 4 });
+        assert!(output.is_empty());
+        assert!(lha_reader.next_file().unwrap());
+        let err = io::Read::read(&mut lha_reader, &mut [0u8;10]).unwrap_err();
+        assert!(matches!(limit_error(err), LimitError::EntrySize { limit: 4, .. }));
+        assert!(!lha_reader.next_file().unwrap());
+
+        let options = LhaOptions::new().max_compression_ratio(1);
+        let mut lha_reader = LhaDecodeReader::new_with_options(&archive[..], options).unwrap();
+        assert_eq!(lha_reader.decode_to_vec(&mut output).unwrap(), 5);
+        assert!(lha_reader.next_file().unwrap());
+        let err = io::BufRead::fill_buf(&mut lha_reader).unwrap_err();
+        assert!(matches!(limit_error(err), LimitError::CompressionRatio { limit: 1, .. }));
+        lha_reader.set_options(options.max_compression_ratio(100));
+        output.clear();
+        lha_reader.decode_to_vec(&mut output).unwrap();
+        assert_eq!(output, content);
+        lha_reader.crc_check().unwrap();
+
+        let header_size = lha_reader.header().header_size;
+        let options = LhaOptions::new().max_total_header_size(header_size * 2 - 1);
+        let mut lha_reader = LhaDecodeReader::new_with_options(&archive[..], options).unwrap();
+        let err = lha_reader.next_file().map_err(io::Error::from).unwrap_err();
+        let size = header_size * 2;
+        assert_eq!(limit_error(err), LimitError::TotalHeaderSize { size, limit: size - 1 });
+        let options = options.max_total_header_size(header_size * 2);
+        let mut lha_reader = LhaDecodeReader::new_with_options(io::Cursor::new(&archive[..]), options).unwrap();
+        assert!(lha_reader.next_file().unwrap());
+        assert!(!lha_reader.next_file().unwrap());
+        assert!(lha_reader.rewind().unwrap());
+        assert!(lha_reader.next_file().unwrap());
+    }
+
+    #[test]
+    fn progress_callback_works() {
+        use std::sync::{Arc, Mutex};
+        let content = include_bytes!("../tests/decode/lh0.bin");
+        let lh5 = include_bytes!("../tests/decode/lh5.bin");
+        let packed = &lh5[..lh5.len() - 1];
+        let mut archive = method_entry(b"-lh0-", b"Hello");
+        archive.extend(packed_entry(b"-lh5-", packed, content));
+        archive.push(0);
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&progress);
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap()
+            .with_progress_callback(move |index, bytes_in, bytes_out| {
+                sink.lock().unwrap().push((index, bytes_in, bytes_out));
+            });
+        let mut output = Vec::new();
+        lha_reader.decode_to_vec(&mut output).unwrap();
+        assert_eq!(*progress.lock().unwrap(), [(0, 5, 5)]);
+        assert!(lha_reader.next_file().unwrap());
+        io::copy(&mut lha_reader, &mut io::sink()).unwrap();
+        lha_reader.crc_check().unwrap();
+        let progress = core::mem::take(&mut *progress.lock().unwrap());
+        assert!(progress.len() > 1);
+        assert_eq!(progress[1..].iter().filter(|&&(index, ..)| index != 1).count(), 0);
+        assert!(progress.windows(2).all(|w| w[0].1 <= w[1].1 && w[0].2 < w[1].2));
+        assert_eq!(progress.last().unwrap().1, packed.len() as u64);
+        assert_eq!(progress.last().unwrap().2, content.len() as u64);
+        assert!(!lha_reader.next_file().unwrap());
+    }
+
+    #[test]
+    fn buffer_sizes_work() {
+        use io::{BufRead, Read};
+        let content = include_bytes!("../tests/decode/lh0.bin");
+        let lh5 = include_bytes!("../tests/decode/lh5.bin");
+        let mut archive = packed_entry(b"-lh5-", &lh5[..lh5.len() - 1], content);
+        archive.push(0);
+        let options = LhaOptions::new().read_buffer_size(0).output_buffer_size(100);
+        let mut lha_reader = LhaDecodeReader::new_with_options(&archive[..], options).unwrap();
+        assert_eq!(lha_reader.options(), options);
+        assert_eq!(lha_reader.fill_buf().unwrap().len(), 100);
+        let mut output = Vec::new();
+        lha_reader.read_to_end(&mut output).unwrap();
+        assert_eq!(output, content);
+        lha_reader.crc_check().unwrap();
+        let options = options.read_buffer_size(1 << 20).output_buffer_size(0);
+        let mut lha_reader = LhaDecodeReader::new_with_options(&archive[..], options).unwrap();
+        assert_eq!(lha_reader.fill_buf().unwrap().len(), 1);
+        output.clear();
+        lha_reader.read_to_end(&mut output).unwrap();
+        assert_eq!(output, content);
+        lha_reader.crc_check().unwrap();
+    }
+
+    #[test]
+    fn position_counters_work() {
+        let content = include_bytes!("../tests/decode/lh0.bin");
+        let lh5 = include_bytes!("../tests/decode/lh5.bin");
+        let packed = &lh5[..lh5.len() - 1];
+        let mut archive = method_entry(b"-lh0-", b"Hello");
+        archive.extend(packed_entry(b"-lh5-", packed, content));
+        archive.push(0);
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        assert_eq!((lha_reader.entry_bytes_in(), lha_reader.entry_bytes_out()), (0, 0));
+        assert_eq!((lha_reader.total_bytes_in(), lha_reader.total_bytes_out()), (25, 0));
+        let mut buf = [0u8; 2];
+        io::Read::read_exact(&mut lha_reader, &mut buf).unwrap();
+        assert_eq!((lha_reader.entry_bytes_in(), lha_reader.entry_bytes_out()), (2, 2));
+        assert_eq!((lha_reader.total_bytes_in(), lha_reader.total_bytes_out()), (27, 2));
+        assert!(lha_reader.next_file().unwrap());
+        assert_eq!((lha_reader.entry_bytes_in(), lha_reader.entry_bytes_out()), (0, 0));
+        assert_eq!((lha_reader.total_bytes_in(), lha_reader.total_bytes_out()), (55, 2));
+        io::copy(&mut lha_reader, &mut io::sink()).unwrap();
+        assert_eq!(lha_reader.entry_bytes_in(), packed.len() as u64);
+        assert_eq!(lha_reader.entry_bytes_out(), content.len() as u64);
+        assert_eq!(lha_reader.total_bytes_in(), 55 + packed.len() as u64);
+        assert_eq!(lha_reader.total_bytes_out(), 2 + content.len() as u64);
+        assert!(!lha_reader.next_file().unwrap());
+        assert_eq!(lha_reader.total_bytes_in(), archive.len() as u64);
+    }
+
+    #[test]
+    fn cancel_flag_works() {
+        let content = include_bytes!("../tests/decode/lh0.bin");
+        let lh5 = include_bytes!("../tests/decode/lh5.bin");
+        let mut archive = packed_entry(b"-lh5-", &lh5[..lh5.len() - 1], content);
+        archive.extend(method_entry(b"-lh0-", b"Hello"));
+        archive.push(0);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancel);
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap()
+            .with_cancel_flag(Arc::clone(&cancel))
+            .with_progress_callback(move |_, _, bytes_out| {
+                if bytes_out >= 100 {
+                    flag.store(true, Ordering::Relaxed);
+                }
+            });
+        let mut buf = [0u8;100];
+        io::Read::read_exact(&mut lha_reader, &mut buf).unwrap();
+        assert_eq!(buf, content[..100]);
+        let err = io::Read::read(&mut lha_reader, &mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert!(Cancelled::is_cancelled(&err));
+        assert!(Cancelled::is_cancelled(&io::BufRead::fill_buf(&mut lha_reader).unwrap_err()));
+        let mut output = Vec::new();
+        assert!(Cancelled::is_cancelled(&lha_reader.decode_to_vec(&mut output).unwrap_err()));
+        assert!(output.is_empty());
+        cancel.store(false, Ordering::Relaxed);
+        lha_reader.clear_progress_callback();
+        lha_reader.decode_to_vec(&mut output).unwrap();
+        assert_eq!(output, content[100..]);
+        lha_reader.crc_check().unwrap();
+
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap().with_cancel_flag(Arc::clone(&cancel));
+        cancel.store(true, Ordering::Relaxed);
+        let err = io::Error::from(lha_reader.next_file().unwrap_err());
+        assert!(Cancelled::is_cancelled(&err));
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap().with_cancel_flag(cancel);
+        lha_reader.clear_cancel_flag();
+        assert!(lha_reader.next_file().unwrap());
+        assert!(!Cancelled::is_cancelled(&io::Error::new(io::ErrorKind::Other, "decoding cancelled")));
+    }
+
+    #[test]
+    fn lha_error_works() {
+        let mut archive = method_entry(b"-lh0-", b"Hello");
+        archive.extend(method_entry(b"-pm2-", b"World"));
+        archive.push(0);
+        let offset = archive.len() / 2 - 1;
+        archive[offset] ^= 1;
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        assert_eq!(lha_reader.decoder_support(), Ok(()));
+        io::copy(&mut lha_reader, &mut io::sink()).unwrap();
+        let err = LhaError::from(lha_reader.crc_check().unwrap_err());
+        assert!(matches!(err, LhaError::BadChecksum { expected, got } if expected != got));
+        assert!(lha_reader.next_file().unwrap());
+        let err = io::Read::read(&mut lha_reader, &mut [0u8;5]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(LhaError::from(err), LhaError::UnsupportedMethod(method) if &method == b"-pm2-"));
+        assert_eq!(lha_reader.decoder_support(), Err(UnsupportedReason::NotImplemented));
+        lha_reader.raw_entry_reader().unwrap();
+        let err = LhaError::from(io::BufRead::fill_buf(&mut lha_reader).unwrap_err());
+        assert!(matches!(err, LhaError::UnsupportedMethod(..)));
+        drop(lha_reader);
+
+        let err = LhaDecodeReader::new(&[0][..]).map_err(io::Error::from).unwrap_err();
+        assert!(matches!(LhaError::from(err), LhaError::MissingHeader));
+        let err = LhaDecodeReader::new(&archive[..5]).map_err(io::Error::from).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(ErrorContext::of(&err).map(|context| context.phase), Some(ErrorPhase::Header));
+        assert!(matches!(LhaError::from(err), LhaError::TruncatedHeader));
+        archive[1] ^= 1;
+        let err = LhaDecodeReader::new(&archive[..]).map_err(io::Error::from).unwrap_err();
+        assert!(matches!(LhaError::from(err), LhaError::BadHeader(crate::header::HeaderWarning::Checksum { .. })));
+    }
+
+    #[test]
+    fn next_supported_file_works() {
+        let mut archive = method_entry(b"-pm2-", b"a");
+        archive.extend(method_entry(b"-lh0-", b"b"));
+        archive.extend(method_entry(b"-zzz-", b"c"));
+        archive.extend(method_entry(b"-lhd-", b""));
+        archive.extend(method_entry(b"-pm1-", b"d"));
+        archive.push(0);
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        let mut skipped = Vec::new();
+        assert!(lha_reader.skip_unsupported(&mut skipped).unwrap());
+        assert_eq!(lha_reader.header().original_size, 1);
+        assert!(lha_reader.skip_unsupported(&mut skipped).unwrap());
+        assert_eq!(skipped.len(), 1);
+        assert!(lha_reader.next_supported_file(&mut skipped).unwrap());
+        assert!(lha_reader.header().is_directory());
+        assert!(!lha_reader.next_supported_file(&mut skipped).unwrap());
+        let methods: Vec<_> = skipped.iter().map(|header| header.compression).collect();
+        assert_eq!(methods, [*b"-pm2-", *b"-zzz-", *b"-pm1-"]);
+    }
+
+    #[test]
+    fn error_context_works() {
+        let mut archive = method_entry(b"-lh0-", b"Hello");
+        archive.extend(method_entry(b"-pm2-", b"World"));
+        archive.push(0);
+        let offset = archive.len() / 2 - 1;
+        archive[offset] ^= 1;
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        io::copy(&mut lha_reader, &mut io::sink()).unwrap();
+        let err = lha_reader.crc_check().unwrap_err();
+        let context = ErrorContext::of(&err).unwrap();
+        assert_eq!((context.phase, context.path.as_deref(), context.offset), (ErrorPhase::Checksum, Some("a".as_ref()), 0));
+        assert!(lha_reader.next_file().unwrap());
+        let err = lha_reader.decode_to_vec(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.to_string(), "decoding \"a\" at offset 30: unsupported compression method: -pm2-");
+        assert_eq!(ErrorContext::of(&err).unwrap().phase, ErrorPhase::Decode);
+        drop(lha_reader);
+        archive[31] ^= 1;
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        let err = io::Error::from(lha_reader.next_file().unwrap_err());
+        let context = ErrorContext::of(&err).unwrap();
+        assert_eq!((context.phase, context.path.as_ref(), context.offset), (ErrorPhase::Header, None, 30));
+    }
+
+    #[test]
+    fn recovery_works() {
+        let mut archive = b"garbage".to_vec();
+        archive.extend(method_entry(b"-lh0-", b"Hello"));
+        let offset = archive.len();
+        archive.extend(method_entry(b"-lh0-", b"-lh0-"));
+        archive.extend(method_entry(b"-lh5-", b"World"));
+        archive.extend(method_entry(b"-lh0-", b"!"));
+        let last_offset = archive.len();
+        archive.extend(method_entry(b"-lh0-", b"Bye"));
+        archive.push(0);
+        // corrupt the header checksum of the 2nd file
+        archive[offset + 1] ^= 1;
+        let mut lha_reader = LhaDecodeReader::default();
+        assert!(lha_reader.begin_new(io::Cursor::new(&archive[..])).is_err());
+        let mut lha_reader = LhaDecodeReader::default().with_recovery();
+        assert!(lha_reader.begin_new(io::Cursor::new(&archive[..])).unwrap());
+        assert_eq!(lha_reader.header().header_offset, Some(7));
+        let mut contents = Vec::new();
+        loop {
+            let mut content = Vec::new();
+            if lha_reader.decode_to_vec(&mut content).is_ok() && lha_reader.crc_check().is_ok() {
+                contents.push(content);
+            }
+            if !lha_reader.next_file().unwrap() {
+                break
+            }
+        }
+        assert_eq!(contents, [&b"Hello"[..], b"!", b"Bye"]);
+
+        let truncated = &archive[offset..last_offset + 5];
+        let mut lha_reader = LhaDecodeReader::default().with_recovery();
+        assert!(lha_reader.begin_new(io::Cursor::new(truncated)).unwrap());
+        assert_eq!(lha_reader.header().compression, *b"-lh5-");
+        assert!(lha_reader.next_file().unwrap());
+        assert_eq!(lha_reader.header().header_offset, Some((last_offset - offset - 26) as u64));
+        let err = lha_reader.next_file().map_err(io::Error::from).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn sfx_detection_works() {
+        // an MS-DOS executable of 300 bytes: 300 bytes in the last of 1 page
+        let mut stub = b"MZ\x2c\x01\x01\0".to_vec();
+        stub.resize(0x1c, 0);
+        stub.resize(200, 0x90);
+        stub.extend_from_slice(b" stub -lh5- -lz5- stub");
+        stub.resize(300, 0x90);
+        let mut archive = stub.clone();
+        archive.extend(method_entry(b"-lh0-", b"Hello"));
+        archive.extend(method_entry(b"-lh0-", b"World"));
+        archive.push(0);
+        let mut lha_reader = LhaDecodeReader::new_seekable(io::Cursor::new(&archive[..])).unwrap();
+        assert_eq!(lha_reader.header().header_offset, Some(0));
+        let mut output = Vec::new();
+        lha_reader.decode_to_vec(&mut output).unwrap();
+        assert_eq!(output, b"Hello");
+        assert!(lha_reader.next_file().unwrap());
+        assert!(lha_reader.rewind().unwrap());
+        assert_eq!(lha_reader.header().header_offset, Some(0));
+        assert_eq!(lha_reader.into_inner().position(), (stub.len() + 25) as u64);
+        let options = LhaOptions::new().detect_sfx(false);
+        assert!(LhaDecodeReader::new_seekable_with_options(io::Cursor::new(&archive[..]), options).is_err());
+        assert!(LhaDecodeReader::new_seekable(io::Cursor::new(&archive[..stub.len() + 10])).is_err());
+
+        let mut rd = io::Cursor::new(&archive[..]);
+        rd.set_position(stub.len() as u64);
+        let lha_reader = LhaDecodeReader::new_seekable(rd).unwrap();
+        assert_eq!(lha_reader.into_inner().position(), (stub.len() + 25) as u64);
+
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        assert_eq!(lha_reader.header().header_offset, Some(0));
+        assert!(lha_reader.next_file().unwrap());
+        assert_eq!(lha_reader.header().header_offset, Some(30));
+        let mut output = Vec::new();
+        lha_reader.decode_to_vec(&mut output).unwrap();
+        assert_eq!(output, b"World");
+        assert!(!lha_reader.next_file().unwrap());
+        let options = LhaOptions::new().detect_sfx(false);
+        assert!(LhaDecodeReader::new_with_options(&archive[..], options).is_err());
+        assert!(LhaDecodeReader::new(&archive[..stub.len() + 10]).is_err());
+        drop(lha_reader);
+        // the image ends past the archive's first header
+        archive[4] = 2;
+        assert!(LhaDecodeReader::new(&archive[..]).is_err());
+        // only the executables are skipped
+        let mut archive = b"junk".to_vec();
+        archive.extend(method_entry(b"-lh0-", b"Hello"));
+        assert!(LhaDecodeReader::new(&archive[..]).is_err());
+        assert!(LhaDecodeReader::new_seekable(io::Cursor::new(&archive[..])).is_err());
+    }
 }
@@ -0,0 +1,307 @@
+use core::fmt;
+use std::io;
+use std::path::Path;
+use crate::header::LhaHeader;
+use super::LhaDecodeReader;
+
+/// An archived file yielded by [Entries].
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// The header of the file.
+    pub header: LhaHeader,
+    /// The decompressed content of the file or `None` if the file's compression method is not
+    /// supported, e.g. for directory entries, see [LhaHeader::is_directory].
+    pub content: Option<Vec<u8>>
+}
+
+/// An iterator through the archived files, returned from [LhaDecodeReader::entries].
+///
+/// The iterator yields an error if the next header could not be read or parsed, or the content of
+/// the file could not be decompressed or its checksum doesn't match. After a header error no more
+/// entries are yielded.
+#[derive(Debug)]
+pub struct Entries<'a, R> {
+    reader: &'a mut LhaDecodeReader<R>,
+    started: bool,
+    done: bool
+}
+
+/// An iterator through the archived files accepted by a predicate, returned from
+/// [LhaDecodeReader::entries_where] and [LhaDecodeReader::entries_matching].
+///
+/// The content of the files which are not accepted is being skipped without decompressing it.
+pub struct FilteredEntries<'a, R, F> {
+    entries: Entries<'a, R>,
+    predicate: F
+}
+
+impl<R: io::Read> LhaDecodeReader<R> {
+    /// Returns an iterator through the archived files, starting with the current one, yielding
+    /// the headers with the decompressed content of the files.
+    ///
+    /// The whole content of each file is decompressed into memory, to stream the content of large
+    /// files use [LhaDecodeReader::next_file] instead. The checksums of the files are verified
+    /// according to the [CrcPolicy][super::CrcPolicy].
+    ///
+    /// The current file should not have been read before this call, otherwise only its remaining
+    /// content is yielded.
+    ///
+    /// # Example
+    /// ```no_run
+    /// for entry in delharc::parse_file("archive.lzh")?.entries() {
+    ///     let entry = entry?;
+    ///     if let Some(content) = entry.content {
+    ///         println!("{}: {} bytes", entry.header.parse_pathname().display(), content.len());
+    ///     }
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn entries(&mut self) -> Entries<'_, R> {
+        let done = self.is_absent();
+        Entries { reader: self, started: false, done }
+    }
+    /// Returns an iterator through the archived files accepted by the `predicate`, starting with
+    /// the current one, like [LhaDecodeReader::entries].
+    ///
+    /// The content of the other files is being skipped without decompressing it, see
+    /// [LhaDecodeReader::next_file].
+    ///
+    /// # Example
+    /// ```no_run
+    /// let mut lha_reader = delharc::parse_file("archive.lzh")?;
+    /// for entry in lha_reader.entries_where(|header| header.original_size < 1024) {
+    ///     println!("{}", entry?.header.parse_pathname().display());
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn entries_where<F>(&mut self, predicate: F) -> FilteredEntries<'_, R, F>
+        where F: FnMut(&LhaHeader) -> bool
+    {
+        FilteredEntries { entries: self.entries(), predicate }
+    }
+    /// Returns an iterator through the archived files with path names matching the glob `pattern`,
+    /// see [LhaDecodeReader::entries_where].
+    ///
+    /// The path names are being compared with `/` as the directory separator. In the `pattern`, `?`
+    /// matches any character except `/`, `*` matches any sequence of characters except `/`, `**`
+    /// matches any sequence of characters and `**/` matches any sequence of directories, including
+    /// none. Other characters match themselves.
+    ///
+    /// # Example
+    /// ```no_run
+    /// let mut lha_reader = delharc::parse_file("archive.lzh")?;
+    /// for entry in lha_reader.entries_matching("src/**/*.c") {
+    ///     println!("{}", entry?.header.parse_pathname().display());
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn entries_matching(&mut self, pattern: &str)
+        -> FilteredEntries<'_, R, impl FnMut(&LhaHeader) -> bool>
+    {
+        let pattern: Vec<char> = pattern.chars().collect();
+        self.entries_where(move |header| {
+            let path: Vec<char> = glob_path(&header.parse_pathname()).chars().collect();
+            glob_matches(&pattern, &path)
+        })
+    }
+}
+
+impl<'a, R: io::Read> Entries<'a, R> {
+    fn next_entry<F: FnMut(&LhaHeader) -> bool>(&mut self, mut predicate: F) -> io::Result<Option<Entry>> {
+        let reader = &mut *self.reader;
+        loop {
+            if self.started && !reader.next_file()? {
+                return Ok(None)
+            }
+            self.started = true;
+            if predicate(reader.header()) {
+                break
+            }
+        }
+        let header = reader.header().clone();
+        if !reader.is_decoder_supported() {
+            return Ok(Some(Entry { header, content: None }))
+        }
+        let verify = reader.request_crc();
+        let mut content = Vec::new();
+        reader.decode_to_vec(&mut content)?;
+        if verify {
+            reader.crc_check()?;
+        }
+        Ok(Some(Entry { header, content: Some(content) }))
+    }
+
+    fn next_where<F: FnMut(&LhaHeader) -> bool>(&mut self, predicate: F) -> Option<io::Result<Entry>> {
+        if self.done {
+            return None
+        }
+        match self.next_entry(predicate) {
+            Ok(Some(entry)) => Some(Ok(entry)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                // the content errors don't prevent reading the following headers
+                self.done = self.reader.is_absent();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<'a, R: io::Read> Iterator for Entries<'a, R> {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_where(|_| true)
+    }
+}
+
+impl<'a, R: io::Read> core::iter::FusedIterator for Entries<'a, R> {}
+
+impl<'a, R: io::Read, F: FnMut(&LhaHeader) -> bool> Iterator for FilteredEntries<'a, R, F> {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next_where(&mut self.predicate)
+    }
+}
+
+impl<'a, R: io::Read, F: FnMut(&LhaHeader) -> bool> core::iter::FusedIterator for FilteredEntries<'a, R, F> {}
+
+impl<'a, R: fmt::Debug, F> fmt::Debug for FilteredEntries<'a, R, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilteredEntries")
+         .field("entries", &self.entries)
+         .finish()
+    }
+}
+
+/// Joins the components of the `path` with `/`.
+fn glob_path(path: &Path) -> String {
+    let components: Vec<_> = path.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+    components.join("/")
+}
+
+/// Returns `true` if the `path` matches the glob `pattern`, see [LhaDecodeReader::entries_matching].
+fn glob_matches(pattern: &[char], path: &[char]) -> bool {
+    let stride = path.len() + 1;
+    let mut memo = vec![None; (pattern.len() + 1) * stride];
+    glob_matches_memo(pattern, path, stride, &mut memo)
+}
+
+/// Matches the remaining `pattern` and `path`, remembering the results by their lengths in `memo`,
+/// so the wildcards don't backtrack exponentially.
+fn glob_matches_memo(pattern: &[char], path: &[char], stride: usize, memo: &mut [Option<bool>]) -> bool {
+    let key = pattern.len() * stride + path.len();
+    if let Some(matched) = memo[key] {
+        return matched
+    }
+    let mut matches = |pattern, path| glob_matches_memo(pattern, path, stride, memo);
+    let matched = match pattern {
+        [] => path.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            matches(rest, path) ||
+            path.iter().enumerate().any(|(i, &c)| c == '/' && matches(rest, &path[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|i| matches(rest, &path[i..])),
+        ['*', rest @ ..] => {
+            let end = path.iter().position(|&c| c == '/').unwrap_or(path.len());
+            (0..=end).any(|i| matches(rest, &path[i..]))
+        }
+        ['?', rest @ ..] => matches!(path, [c, tail @ ..] if *c != '/' && matches(rest, tail)),
+        [p, rest @ ..] => matches!(path, [c, tail @ ..] if c == p && matches(rest, tail))
+    };
+    memo[key] = Some(matched);
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::stored_archive;
+    use crate::header::LhaHeaderBuilder;
+    use crate::decode::CrcPolicy;
+    use super::*;
+
+    #[test]
+    fn entries_works() {
+        let archive = stored_archive(&[("foo", b"Hello"), ("bar", b""), ("baz", b"World!")]);
+        let mut reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        let entries: Vec<_> = reader.entries().map(|entry| {
+            let entry = entry.unwrap();
+            (entry.header.parse_pathname().to_str().unwrap().to_string(), entry.content.unwrap())
+        }).collect();
+        assert_eq!(entries, [("foo".to_string(), b"Hello".to_vec()),
+                             ("bar".to_string(), Vec::new()),
+                             ("baz".to_string(), b"World!".to_vec())]);
+        assert_eq!(reader.into_inner().len(), 0);
+
+        let mut archive = stored_archive(&[("foo", b"Hello"), ("bar", b"World!")]);
+        let offset = archive.len() - 2;
+        archive[offset] ^= 1;
+        let mut reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        let mut entries = reader.entries();
+        assert_eq!(entries.next().unwrap().unwrap().content.unwrap(), b"Hello");
+        assert_eq!(entries.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(entries.next().is_none());
+        let mut reader = LhaDecodeReader::new(&archive[..]).unwrap().with_crc_policy(CrcPolicy::Skip);
+        assert_eq!(reader.entries().nth(1).unwrap().unwrap().content.unwrap(), b"World\x20");
+
+        let header = LhaHeaderBuilder::new().path("foo").raw_compression(*b"-zzz-")
+                                            .compressed_size(5).build().unwrap();
+        let mut archive = header.to_bytes().unwrap();
+        archive.extend_from_slice(b"Hello\x01");
+        let mut reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        let mut entries = reader.entries();
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.content, None);
+        assert_eq!(&entry.header.compression, b"-zzz-");
+        assert_eq!(entries.next().unwrap().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert!(entries.next().is_none());
+    }
+
+    #[test]
+    fn filtered_entries_works() {
+        let files: &[(&str, &[u8])] = &[("src/main.c", b"main"), ("src/lib/util.c", b"util"),
+                                        ("src/main.h", b"header"), ("main.c", b"top")];
+        let archive = stored_archive(files);
+        let mut reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        let contents: Vec<_> = reader.entries_matching("src/**/*.c")
+                                     .map(|entry| entry.unwrap().content.unwrap()).collect();
+        assert_eq!(contents, [&b"main"[..], b"util"]);
+        assert_eq!(reader.into_inner().len(), 0);
+
+        let mut reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        let mut entries = reader.entries_where(|header| header.original_size > 4);
+        assert_eq!(entries.next().unwrap().unwrap().content.unwrap(), b"header");
+        assert!(entries.next().is_none());
+        assert!(entries.next().is_none());
+
+        let mut reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        assert_eq!(reader.entries_matching("*.c").count(), 1);
+    }
+
+    #[test]
+    fn glob_matches_works() {
+        let matches = |pattern: &str, path: &str| {
+            glob_matches(&pattern.chars().collect::<Vec<_>>(), &path.chars().collect::<Vec<_>>())
+        };
+        assert!(matches("src/**/*.c", "src/a.c"));
+        assert!(matches("src/**/*.c", "src/a/b/c.c"));
+        assert!(!matches("src/**/*.c", "src/a.h"));
+        assert!(!matches("src/**/*.c", "lib/src/a.c"));
+        assert!(matches("*.c", "a.c"));
+        assert!(!matches("*.c", "dir/a.c"));
+        assert!(matches("**.c", "dir/a.c"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "a/c"));
+        assert!(matches("**", ""));
+        assert!(!matches("", "a"));
+        let name = "a".repeat(60);
+        assert!(!matches("**a**a**a**a**a**a**b", &name));
+        assert!(!matches("*a*a*a*a*a*a*b", &name));
+        assert!(matches("**a**a**a**a**a**a**", &name));
+        assert_eq!(glob_path(Path::new("dir/a.c")), "dir/a.c");
+    }
+}
@@ -0,0 +1,262 @@
+use std::io;
+use crate::header::LhaHeader;
+use super::LhaDecodeReader;
+
+/// A reader of the current file's decompressed content, borrowing [LhaDecodeReader], returned from
+/// [LhaDecodeReader::entry].
+///
+/// The reader ends at the end of the current file, so it can be passed to functions reading until
+/// the end of the stream. The checksum of the content is verified when the end of the file is
+/// reached, a mismatch is reported as an error of the kind [io::ErrorKind::InvalidData] instead of
+/// the end of file.
+#[derive(Debug)]
+pub struct EntryReader<'a, R> {
+    reader: &'a mut LhaDecodeReader<R>,
+    verify: bool
+}
+
+/// A reader of the current file's decompressed content, owning [LhaDecodeReader], returned from
+/// [LhaDecodeReader::into_entry].
+///
+/// The reader behaves like [EntryReader], but it can be moved to another thread or stored in
+/// a `'static` context. The [LhaDecodeReader] can be retrieved back with
+/// [OwnedEntryReader::finish] to continue with the following files.
+#[derive(Debug)]
+pub struct OwnedEntryReader<R> {
+    reader: LhaDecodeReader<R>,
+    verify: bool
+}
+
+impl<R: io::Read> LhaDecodeReader<R> {
+    /// Returns a reader of the current file's remaining decompressed content.
+    ///
+    /// The checksum is being verified at the end of the file if it's being computed, see
+    /// [LhaDecodeReader::request_crc].
+    ///
+    /// # Example
+    /// ```no_run
+    /// let mut lha_reader = delharc::parse_file("archive.lzh")?;
+    /// let value: serde_json::Value = serde_json::from_reader(lha_reader.entry())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn entry(&mut self) -> EntryReader<'_, R> {
+        let verify = self.request_crc();
+        EntryReader { reader: self, verify }
+    }
+    /// Opens the current file's decompressed content as an archive, e.g. an `.lzh` file archived in
+    /// another archive, reading the first header of the nested archive.
+    ///
+    /// The nested archive is being decompressed while it's being read, without buffering it.
+    /// The checksum of the current file is verified when the end of the file is reached, see
+    /// [LhaDecodeReader::entry].
+    ///
+    /// # Example
+    /// ```no_run
+    /// let mut lha_reader = delharc::parse_file("archive.lzh")?;
+    /// if lha_reader.header().parse_pathname().extension().map_or(false, |ext| ext == "lzh") {
+    ///     let mut nested = lha_reader.open_nested()?;
+    ///     loop {
+    ///         println!("{}", nested.header().parse_pathname().display());
+    ///         if !nested.next_file()? {
+    ///             break
+    ///         }
+    ///     }
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the header of the nested archive could not be read or parsed.
+    pub fn open_nested(&mut self) -> io::Result<LhaDecodeReader<EntryReader<'_, R>>> {
+        let parse_mode = self.parse_mode;
+        let reader = LhaDecodeReader::new(self.entry())?;
+        Ok(reader.with_parse_mode(parse_mode))
+    }
+    /// Converts the `LhaDecodeReader<R>` into a reader of the current file's remaining decompressed
+    /// content, see [LhaDecodeReader::entry].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::{io, thread};
+    ///
+    /// let lha_reader = delharc::parse_file("archive.lzh")?;
+    /// let handle = thread::spawn(move || {
+    ///     let mut entry = lha_reader.into_entry();
+    ///     io::copy(&mut entry, &mut io::sink())?;
+    ///     Ok::<_, io::Error>(entry.finish())
+    /// });
+    /// let mut lha_reader = handle.join().unwrap()?;
+    /// lha_reader.next_file()?;
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    pub fn into_entry(mut self) -> OwnedEntryReader<R> {
+        let verify = self.request_crc();
+        OwnedEntryReader { reader: self, verify }
+    }
+}
+
+impl<'a, R: io::Read> EntryReader<'a, R> {
+    /// Returns a reference to the current file's [LhaHeader].
+    pub fn header(&self) -> &LhaHeader {
+        self.reader.header()
+    }
+    /// Returns the number of remaining bytes of the file to be read.
+    pub fn len(&self) -> u64 {
+        self.reader.len()
+    }
+    /// Returns `true` if the file has been finished reading or if the file was empty.
+    pub fn is_empty(&self) -> bool {
+        self.reader.is_empty()
+    }
+    /// Returns `true` if the file's compression method is supported.
+    pub fn is_decoder_supported(&self) -> bool {
+        self.reader.is_decoder_supported()
+    }
+}
+
+impl<R: io::Read> OwnedEntryReader<R> {
+    /// Returns a reference to the current file's [LhaHeader].
+    pub fn header(&self) -> &LhaHeader {
+        self.reader.header()
+    }
+    /// Returns the number of remaining bytes of the file to be read.
+    pub fn len(&self) -> u64 {
+        self.reader.len()
+    }
+    /// Returns `true` if the file has been finished reading or if the file was empty.
+    pub fn is_empty(&self) -> bool {
+        self.reader.is_empty()
+    }
+    /// Returns `true` if the file's compression method is supported.
+    pub fn is_decoder_supported(&self) -> bool {
+        self.reader.is_decoder_supported()
+    }
+    /// Returns the [LhaDecodeReader] back, e.g. to proceed to the next file with
+    /// [LhaDecodeReader::next_file].
+    pub fn finish(self) -> LhaDecodeReader<R> {
+        self.reader
+    }
+}
+
+macro_rules! impl_entry_read {
+    ($reader:ident, $($lt:lifetime)?) => {
+        impl<$($lt,)? R: io::Read> io::Read for $reader<$($lt,)? R> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = io::Read::read(&mut self.reader, buf)?;
+                if len == 0 && !buf.is_empty() {
+                    check_end(&self.reader, self.verify)?;
+                }
+                Ok(len)
+            }
+        }
+
+        impl<$($lt,)? R: io::Read> io::BufRead for $reader<$($lt,)? R> {
+            fn fill_buf(&mut self) -> io::Result<&[u8]> {
+                check_end(&self.reader, self.verify)?;
+                io::BufRead::fill_buf(&mut self.reader)
+            }
+
+            fn consume(&mut self, amt: usize) {
+                io::BufRead::consume(&mut self.reader, amt)
+            }
+        }
+    };
+}
+
+impl_entry_read!(EntryReader, 'a);
+impl_entry_read!(OwnedEntryReader,);
+
+/// Verifies the checksum if the end of the file has been reached.
+fn check_end<R: io::Read>(reader: &LhaDecodeReader<R>, verify: bool) -> io::Result<()> {
+    if verify && reader.is_empty() {
+        reader.crc_check()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, Read};
+    use crate::fixtures::stored_entry;
+    use super::*;
+
+    #[test]
+    fn entry_works() {
+        let mut crc = crate::crc::Crc16::default();
+        crc.digest(b"Hello\nWorld!");
+        let mut archive = stored_entry("foo", b"Hello\nWorld!", crc.sum16());
+        archive.extend(stored_entry("bar", b"Bye", 0));
+        archive.push(0);
+        let mut reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        let mut entry = reader.entry();
+        assert_eq!(entry.header().original_size, 12);
+        let mut line = String::new();
+        entry.read_line(&mut line).unwrap();
+        assert_eq!(line, "Hello\n");
+        assert_eq!(entry.len(), 6);
+        let mut rest = String::new();
+        entry.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "World!");
+        assert!(entry.is_empty());
+        assert_eq!(entry.read(&mut [0]).unwrap(), 0);
+        assert!(reader.next_file().unwrap());
+        let mut rest = Vec::new();
+        let err = reader.entry().read_to_end(&mut rest).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(rest, b"Bye");
+        assert_eq!(reader.entry().fill_buf().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(!reader.next_file().unwrap());
+    }
+
+    #[test]
+    fn into_entry_works() {
+        let mut crc = crate::crc::Crc16::default();
+        crc.digest(b"Hello");
+        let mut archive = stored_entry("foo", b"Hello", crc.sum16());
+        archive.extend(stored_entry("bar", b"Bye", 0));
+        archive.push(0);
+        let reader = LhaDecodeReader::new(io::Cursor::new(archive)).map_err(io::Error::from).unwrap();
+        let entry = std::thread::spawn(move || {
+            let mut entry = reader.into_entry();
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content).unwrap();
+            assert_eq!(content, b"Hello");
+            entry
+        }).join().unwrap();
+        assert!(entry.is_empty());
+        assert_eq!(entry.header().original_size, 5);
+        let mut reader = entry.finish();
+        assert!(reader.next_file().unwrap());
+        let mut entry = reader.into_entry();
+        assert_eq!(entry.len(), 3);
+        assert_eq!(entry.fill_buf().unwrap(), b"Bye");
+        entry.consume(3);
+        assert_eq!(entry.read(&mut [0]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(!entry.finish().next_file().unwrap());
+    }
+
+    #[test]
+    fn open_nested_works() {
+        let mut inner = stored_entry("foo", b"", 0);
+        inner.extend(stored_entry("bar", b"", 0));
+        inner.push(0);
+        let mut crc = crate::crc::Crc16::default();
+        crc.digest(&inner);
+        let mut archive = stored_entry("inner.lzh", &inner, crc.sum16());
+        archive.extend(stored_entry("baz", b"", 0));
+        archive.push(0);
+        let mut reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        let mut nested = reader.open_nested().unwrap();
+        assert_eq!(nested.header().parse_pathname().to_str(), Some("foo"));
+        assert!(nested.next_file().unwrap());
+        assert_eq!(nested.header().parse_pathname().to_str(), Some("bar"));
+        assert!(!nested.next_file().unwrap());
+        let mut rest = Vec::new();
+        nested.into_inner().read_to_end(&mut rest).unwrap();
+        assert!(rest.is_empty());
+        assert!(reader.next_file().unwrap());
+        assert_eq!(reader.header().parse_pathname().to_str(), Some("baz"));
+        let err = reader.open_nested().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
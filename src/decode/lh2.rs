@@ -0,0 +1,135 @@
+use core::num::NonZeroU16;
+use std::io::{self, Read};
+use crate::decode::Decoder;
+use crate::ringbuf::*;
+use crate::bitstream::*;
+
+mod dyntree;
+use dyntree::*;
+
+const RING_BUFFER_SIZE: usize = 8192;
+
+/// A decoder for `-lh2-` compression method.
+#[derive(Debug)]
+pub struct Lh2Decoder<R> {
+    bit_reader: BitStream<R>,
+    trees: Box<DynHuffTrees>,
+    decoded_count: u64,
+    copy_progress: Option<(u16, NonZeroU16)>,
+    ringbuf: Box<RingArrayBuf<RING_BUFFER_SIZE>>,
+}
+
+impl<R: Read> Lh2Decoder<R> {
+    pub fn new(rd: R) -> Lh2Decoder<R> {
+        let bit_reader = BitStream::new(rd);
+        let ringbuf = Default::default();
+        let trees = Box::default();
+        Lh2Decoder {
+            bit_reader,
+            ringbuf,
+            trees,
+            decoded_count: 0,
+            copy_progress: None
+        }
+    }
+
+    /// Sets the size of the buffer of the data read from the stream reader.
+    pub(crate) fn set_read_buffer_size(&mut self, size: usize) {
+        self.bit_reader.set_buffer_size(size);
+    }
+
+    /// Replaces the stream reader with `rd`, moving the allocated buffers to the returned decoder
+    /// together with the previous reader.
+    pub(crate) fn replace_reader<T: Read>(self, rd: T) -> (Lh2Decoder<T>, R) {
+        let (bit_reader, rd) = self.bit_reader.replace_inner(rd);
+        let decoder = Lh2Decoder {
+            bit_reader,
+            ringbuf: self.ringbuf,
+            trees: self.trees,
+            decoded_count: self.decoded_count,
+            copy_progress: self.copy_progress
+        };
+        (decoder, rd)
+    }
+
+    /// Restores the initial state of the decoder, reusing the allocated buffers.
+    pub(crate) fn reset(&mut self) {
+        self.ringbuf.reset();
+        *self.trees = DynHuffTrees::new();
+        self.decoded_count = 0;
+        self.copy_progress = None;
+    }
+
+    #[inline]
+    fn read_command(&mut self) -> io::Result<u16> {
+        self.trees.read_command(&mut self.bit_reader)
+    }
+
+    #[inline]
+    fn read_offset(&mut self) -> io::Result<u16> {
+        let group = self.trees.read_position_group(&mut self.bit_reader, self.decoded_count)?;
+        let offset = self.bit_reader.read_bits::<u16>(POSITION_GROUP_BITS)?;
+        Ok(group << POSITION_GROUP_BITS | offset)
+    }
+
+    /// Copies the matched bytes to `target` and returns the number of bytes copied.
+    fn copy_from_history(&mut self, target: &mut [u8], offset: usize, count: usize) -> usize {
+        let len = target.len().min(count);
+        self.ringbuf.copy_from_offset(offset, &mut target[..len]);
+        self.copy_progress = NonZeroU16::new((count - len) as u16)
+                             .map(|count| (offset as u16, count));
+        len
+    }
+}
+
+impl<R: Read> Decoder<R> for Lh2Decoder<R> {
+    fn into_inner(self) -> R {
+        self.bit_reader.into_inner()
+    }
+
+    fn get_ref(&self) -> Option<&R> {
+        Some(self.bit_reader.get_ref())
+    }
+
+    fn fill_buffer(&mut self, buf: &mut[u8]) -> io::Result<()> {
+        let mut index = 0;
+        if let Some((offset, count)) = self.copy_progress {
+            index = self.copy_from_history(buf, offset as usize, count.get() as usize);
+        }
+
+        while let Some(dst) = buf.get_mut(index) {
+            match self.read_command()? {
+                code @ 0..=0xff => {
+                    let value = code as u8;
+                    *dst = value;
+                    self.ringbuf.push(value);
+                    index += 1;
+                    self.decoded_count += 1;
+                }
+                code => {
+                    // the position tree depends on the count of bytes decoded before the match
+                    let offset = self.read_offset()?;
+                    let count = code - 0x100 + 3;
+                    self.decoded_count += u64::from(count);
+                    index += self.copy_from_history(&mut buf[index..],
+                                                    offset as usize,
+                                                    count.into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn lh2_works() {
+        println!("Lh2Decoder<Empty> {}", core::mem::size_of::<Lh2Decoder<io::Empty>>());
+        println!("Lh2Decoder<File> {}", core::mem::size_of::<Lh2Decoder<fs::File>>());
+        println!("DynHuffTrees {}", core::mem::size_of::<DynHuffTrees>());
+    }
+}
@@ -0,0 +1,393 @@
+//! # Dynamic Huffman Coding of the `-lh2-` method.
+//!
+//! The command tree is an adaptive Huffman tree similar to the one used by `-lh1-`, but with
+//! a larger alphabet and an escape code for longer matches.
+//!
+//! The position tree starts with a single leaf and gets a new leaf for every next 64 bytes
+//! of decoded output until the whole history window is covered.
+//!
+//! Both trees share the same node arrays, just like in the original LHa implementation.
+use std::io;
+use crate::bitstream::BitRead;
+
+/// The number of command codes, the last one is an escape code followed by 8 more bits.
+pub const NUM_COMMANDS: usize = 256 + 32 - 3 + 1;
+/// The maximum number of position groups, each group covers 64 consecutive offsets.
+pub const NUM_POSITION_GROUPS: usize = 8192 / 64;
+/// The number of bits of an offset following the position group.
+pub const POSITION_GROUP_BITS: u32 = 6;
+
+const ESCAPE_CODE: usize = NUM_COMMANDS - 1;
+const ESCAPE_BITS: u32 = 8;
+// the number of codes the tree could encode for matches up to 256 bytes
+const NUM_CHAR: usize = 256 + 60 - 3 + 1;
+const TREE_SIZE_C: usize = NUM_CHAR * 2;
+const TREE_SIZE_P: usize = NUM_POSITION_GROUPS * 2;
+const TREE_SIZE: usize = TREE_SIZE_C + TREE_SIZE_P;
+const ROOT_C: usize = 0;
+const ROOT_P: usize = TREE_SIZE_C;
+const REORDER_LIMIT: u16 = 32 * 1024;
+const POSITION_ROOT_FREQ: u16 = u16::MAX;
+const GROUP_SIZE: u64 = 1 << POSITION_GROUP_BITS;
+
+/// Both adaptive trees of the `-lh2-` method.
+///
+/// Nodes are kept sorted by their frequency, from the root (highest) down. Nodes sharing
+/// the same frequency form blocks, the first node of each block being its leader.
+#[derive(Debug, Clone)]
+pub struct DynHuffTrees {
+    /// A branch node index (the other child is at `index - 1`) or a negated leaf value.
+    child: [i16; TREE_SIZE],
+    parent: [u16; TREE_SIZE],
+    /// node -> block id
+    block: [u16; TREE_SIZE],
+    /// block id -> leader node index
+    edge: [u16; TREE_SIZE],
+    /// block id allocation stack
+    stock: [u16; TREE_SIZE],
+    freq: [u16; TREE_SIZE],
+    /// leaf value -> node index
+    leaves: [u16; TREE_SIZE / 2],
+    avail: usize,
+    most_p: usize,
+    total_p: u16,
+    next_count: u64,
+}
+
+impl Default for DynHuffTrees {
+    fn default() -> Self {
+        DynHuffTrees::new()
+    }
+}
+
+impl DynHuffTrees {
+    pub fn new() -> Self {
+        let mut trees = DynHuffTrees {
+            child: [0; TREE_SIZE],
+            parent: [0; TREE_SIZE],
+            block: [0; TREE_SIZE],
+            edge: [0; TREE_SIZE],
+            stock: [0; TREE_SIZE],
+            freq: [0; TREE_SIZE],
+            leaves: [0; TREE_SIZE / 2],
+            avail: 0,
+            most_p: ROOT_P,
+            total_p: 0,
+            next_count: GROUP_SIZE,
+        };
+        trees.init_command_tree();
+        trees.init_position_tree();
+        trees
+    }
+
+    /// Reads the next command code from the bit stream.
+    ///
+    /// Returns a literal byte value for codes `0..=255`, otherwise the code represents a match
+    /// length of `code - 253`.
+    pub fn read_command<R: BitRead>(&mut self, rd: &mut R) -> io::Result<u16> {
+        let value = self.read_leaf(ROOT_C, rd)?;
+        self.update_command(value);
+        let mut code = value as u16;
+        if value == ESCAPE_CODE {
+            code += rd.read_bits::<u16>(ESCAPE_BITS)?;
+        }
+        Ok(code)
+    }
+
+    /// Reads the next position group from the bit stream.
+    ///
+    /// `count` is the number of bytes decoded so far, which determines the number of
+    /// position groups available.
+    pub fn read_position_group<R: BitRead>(&mut self, rd: &mut R, count: u64) -> io::Result<u16> {
+        while count > self.next_count {
+            self.grow_position_tree((self.next_count / GROUP_SIZE) as usize);
+            self.next_count += GROUP_SIZE;
+            if self.next_count >= NUM_POSITION_GROUPS as u64 * GROUP_SIZE {
+                self.next_count = u64::MAX;
+            }
+        }
+        let value = self.read_leaf(ROOT_P, rd)? - NUM_CHAR;
+        self.update_position(value);
+        Ok(value as u16)
+    }
+
+    #[inline]
+    fn read_leaf<R: BitRead>(&self, root: usize, rd: &mut R) -> io::Result<usize> {
+        let mut node = self.child[root];
+        while node > 0 {
+            let bit = rd.read_bit()?;
+            node = self.child[node as usize - bit as usize];
+        }
+        Ok(!node as usize)
+    }
+
+    fn init_command_tree(&mut self) {
+        for (i, stock) in self.stock[..TREE_SIZE_C].iter_mut().enumerate() {
+            *stock = i as u16;
+        }
+        self.block[..TREE_SIZE_C].fill(0);
+        // leaves
+        let last = NUM_COMMANDS * 2 - 2;
+        for value in 0..NUM_COMMANDS {
+            let node = last - value;
+            self.freq[node] = 1;
+            self.child[node] = !(value as i16);
+            self.leaves[value] = node as u16;
+            self.block[node] = 1;
+        }
+        self.avail = 2;
+        self.edge[1] = (NUM_COMMANDS - 1) as u16;
+        // branches
+        let mut child = last;
+        for node in (0..NUM_COMMANDS - 1).rev() {
+            let freq = self.freq[child] + self.freq[child - 1];
+            self.freq[node] = freq;
+            self.child[node] = child as i16;
+            self.parent[child] = node as u16;
+            self.parent[child - 1] = node as u16;
+            if freq == self.freq[node + 1] {
+                self.block[node] = self.block[node + 1];
+            }
+            else {
+                self.block[node] = self.alloc_block();
+            }
+            self.edge[self.block[node] as usize] = node as u16;
+            child -= 2;
+        }
+    }
+
+    fn init_position_tree(&mut self) {
+        self.freq[ROOT_P] = 1;
+        self.child[ROOT_P] = !(NUM_CHAR as i16);
+        self.leaves[NUM_CHAR] = ROOT_P as u16;
+        self.block[ROOT_P] = self.alloc_block();
+        self.edge[self.block[ROOT_P] as usize] = ROOT_P as u16;
+        self.most_p = ROOT_P;
+        self.total_p = 0;
+        self.next_count = GROUP_SIZE;
+    }
+
+    #[inline]
+    fn alloc_block(&mut self) -> u16 {
+        let block = self.stock[self.avail];
+        self.avail += 1;
+        block
+    }
+
+    #[inline]
+    fn free_block(&mut self, block: u16) {
+        self.avail -= 1;
+        self.stock[self.avail] = block;
+    }
+
+    #[inline]
+    fn set_as_parent(&mut self, child: i16, node: usize) {
+        if child >= 0 {
+            let child = child as usize;
+            self.parent[child] = node as u16;
+            self.parent[child - 1] = node as u16;
+        }
+        else {
+            self.leaves[!child as usize] = node as u16;
+        }
+    }
+
+    fn update_command(&mut self, value: usize) {
+        if self.freq[ROOT_C] == REORDER_LIMIT {
+            self.rebuild_tree(ROOT_C, NUM_COMMANDS * 2 - 1);
+        }
+        self.freq[ROOT_C] += 1;
+        let mut node = self.leaves[value] as usize;
+        loop {
+            node = self.increment(node);
+            if node == ROOT_C {
+                break
+            }
+        }
+    }
+
+    fn update_position(&mut self, value: usize) {
+        if self.total_p == REORDER_LIMIT {
+            self.rebuild_tree(ROOT_P, self.most_p + 1);
+            self.total_p = self.freq[ROOT_P];
+            self.freq[ROOT_P] = POSITION_ROOT_FREQ;
+        }
+        let mut node = self.leaves[value + NUM_CHAR] as usize;
+        while node != ROOT_P {
+            node = self.increment(node);
+        }
+        self.total_p += 1;
+    }
+
+    /// Splits the last leaf of the position tree, creating a new leaf for the `group`.
+    fn grow_position_tree(&mut self, group: usize) {
+        let most_p = self.most_p;
+        let moved = most_p + 1;
+        let fresh = moved + 1;
+        self.child[moved] = self.child[most_p];
+        self.leaves[!self.child[moved] as usize] = moved as u16;
+        self.child[fresh] = !((group + NUM_CHAR) as i16);
+        self.leaves[group + NUM_CHAR] = fresh as u16;
+        self.child[most_p] = fresh as i16;
+        self.freq[moved] = self.freq[most_p];
+        self.freq[fresh] = 0;
+        self.block[moved] = self.block[most_p];
+        if most_p == ROOT_P {
+            self.freq[ROOT_P] = POSITION_ROOT_FREQ;
+            self.edge[self.block[ROOT_P] as usize] += 1;
+        }
+        self.parent[moved] = most_p as u16;
+        self.parent[fresh] = most_p as u16;
+        self.block[fresh] = self.alloc_block();
+        self.edge[self.block[fresh] as usize] = fresh as u16;
+        self.most_p = fresh;
+        self.update_position(group);
+    }
+
+    /// Increments the frequency of the node, swapping it with the leader of its block first.
+    ///
+    /// Returns the index of the parent node.
+    fn increment(&mut self, mut node: usize) -> usize {
+        let block = self.block[node];
+        let leader = self.edge[block as usize] as usize;
+        if leader != node || block == self.block[node + 1] {
+            if leader != node {
+                let node_child = self.child[node];
+                let leader_child = self.child[leader];
+                self.child[node] = leader_child;
+                self.child[leader] = node_child;
+                self.set_as_parent(node_child, leader);
+                self.set_as_parent(leader_child, node);
+                node = leader;
+            }
+            // the node leaves its block
+            self.edge[block as usize] += 1;
+            self.freq[node] = self.freq[node].wrapping_add(1);
+            if self.freq[node] == self.freq[node - 1] {
+                self.block[node] = self.block[node - 1];
+            }
+            else {
+                self.block[node] = self.alloc_block();
+                self.edge[self.block[node] as usize] = node as u16;
+            }
+        }
+        else {
+            // the node is the only member of its block
+            self.freq[node] = self.freq[node].wrapping_add(1);
+            if self.freq[node] == self.freq[node - 1] {
+                self.free_block(block);
+                self.block[node] = self.block[node - 1];
+            }
+        }
+        self.parent[node] as usize
+    }
+
+    /// Rebuilds the tree between `start` and `end` node indices, halving leaf frequencies.
+    #[inline(never)]
+    fn rebuild_tree(&mut self, start: usize, end: usize) {
+        // move leaves to the front, maintaining their order, and free all blocks
+        let mut block = 0;
+        let mut j = start;
+        for i in start..end {
+            let child = self.child[i];
+            if child < 0 {
+                self.freq[j] = ((self.freq[i] as u32 + 1) / 2) as u16;
+                self.child[j] = child;
+                j += 1;
+            }
+            block = self.block[i];
+            if self.edge[block as usize] as usize == i {
+                self.free_block(block);
+            }
+        }
+        // rebuild branches from the bottom up, moving leaves back
+        let (start, mut i, mut j, mut l) = (start as isize, end as isize - 1, j as isize - 1, end as isize - 2);
+        while i >= start {
+            while i >= l {
+                self.freq[i as usize] = self.freq[j as usize];
+                self.child[i as usize] = self.child[j as usize];
+                i -= 1;
+                j -= 1;
+            }
+            let freq = self.freq[l as usize] as u32 + self.freq[l as usize + 1] as u32;
+            let mut k = start;
+            while freq < self.freq[k as usize] as u32 {
+                k += 1;
+            }
+            while j >= k {
+                self.freq[i as usize] = self.freq[j as usize];
+                self.child[i as usize] = self.child[j as usize];
+                i -= 1;
+                j -= 1;
+            }
+            self.freq[i as usize] = freq as u16;
+            self.child[i as usize] = (l + 1) as i16;
+            i -= 1;
+            l -= 2;
+        }
+        // restore links and blocks
+        let mut freq = 0;
+        for i in start as usize..end {
+            self.set_as_parent(self.child[i], i);
+            if self.freq[i] == freq {
+                self.block[i] = block;
+            }
+            else {
+                block = self.alloc_block();
+                self.block[i] = block;
+                self.edge[block as usize] = i as u16;
+                freq = self.freq[i];
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitstream::BitStream;
+
+    fn code_length(trees: &DynHuffTrees, value: usize) -> usize {
+        let mut node = trees.leaves[value] as usize;
+        let mut len = 0;
+        while node != ROOT_C {
+            node = trees.parent[node] as usize;
+            len += 1;
+        }
+        len
+    }
+
+    #[test]
+    fn dyntrees_works() {
+        let mut trees = DynHuffTrees::new();
+        // the initial command tree is almost balanced with 286 leaves:
+        // 226 leaves at depth 8 and 60 leaves at depth 9
+        let lengths: Vec<usize> = (0..NUM_COMMANDS).map(|value| code_length(&trees, value)).collect();
+        assert_eq!(lengths.iter().filter(|&&len| len == 8).count(), 226);
+        assert_eq!(lengths.iter().filter(|&&len| len == 9).count(), 60);
+        // the position tree has a single leaf, so no bits are being read
+        let bits: &[u8] = &[];
+        let mut stream = BitStream::new(bits);
+        assert_eq!(trees.read_position_group(&mut stream, 0).unwrap(), 0);
+        assert_eq!(trees.read_position_group(&mut stream, 64).unwrap(), 0);
+        assert_eq!(trees.most_p, ROOT_P);
+        assert_eq!(trees.next_count, 64);
+        // the position tree grows after more than 64 bytes are decoded,
+        // the new leaf is being swapped with the old one as soon as it becomes more frequent
+        let bits: &[u8] = &[0b0000_0000];
+        let mut stream = BitStream::new(bits);
+        assert_eq!(trees.read_position_group(&mut stream, 65).unwrap(), 1);
+        assert_eq!(trees.read_position_group(&mut stream, 65).unwrap(), 0);
+        assert_eq!(trees.most_p, ROOT_P + 2);
+        assert_eq!(trees.next_count, 128);
+        // the command tree adapts: a frequent code gets shorter
+        let value = 0x20;
+        for _ in 0..100 {
+            trees.update_command(value);
+        }
+        assert!(code_length(&trees, value) < 8);
+        for value in 0..NUM_COMMANDS {
+            assert!(code_length(&trees, value) > 0);
+        }
+    }
+}
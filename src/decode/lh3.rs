@@ -0,0 +1,257 @@
+use core::num::NonZeroU16;
+use std::io::{self, Read};
+
+use crate::bitstream::*;
+use crate::statictree::*;
+use crate::ringbuf::*;
+
+use super::Decoder;
+
+const RING_BUFFER_SIZE: usize = 8192;
+/// The number of command codes, the last one is an escape code followed by 8 more bits.
+const NUM_COMMANDS: usize = 256 + 32 - 3 + 1;
+const ESCAPE_CODE: u16 = NUM_COMMANDS as u16 - 1;
+const ESCAPE_BITS: u32 = 8;
+const COMMAND_BITS: u32 = 9;
+/// The number of position groups, each group covers 64 consecutive offsets.
+const NUM_POSITION_GROUPS: usize = RING_BUFFER_SIZE / 64;
+const POSITION_GROUP_BITS: u32 = 6;
+const GROUP_BITS: u32 = 7;
+const LENGTH_BITS: u32 = 4;
+
+/// Code lengths of the predefined offset tree.
+const READY_MADE_LENGTHS: [u8; NUM_POSITION_GROUPS] = ready_made_lengths();
+
+/// A decoder for `-lh3-` compression method.
+#[derive(Debug)]
+pub struct Lh3Decoder<R> {
+    bit_reader: BitStream<R>,
+    command_tree: HuffTree,
+    offset_tree: HuffTree,
+    remaining_commands: u16,
+    copy_progress: Option<(u16, NonZeroU16)>,
+    ringbuf: Box<RingArrayBuf<RING_BUFFER_SIZE>>,
+}
+
+impl<R: Read> Lh3Decoder<R> {
+    pub fn new(rd: R) -> Lh3Decoder<R> {
+        let bit_reader = BitStream::new(rd);
+        let ringbuf = Default::default();
+        let command_tree = HuffTree::with_capacity(NUM_COMMANDS * 2);
+        let offset_tree = HuffTree::with_capacity(NUM_POSITION_GROUPS * 2);
+        Lh3Decoder {
+            bit_reader,
+            ringbuf,
+            command_tree,
+            offset_tree,
+            remaining_commands: 0,
+            copy_progress: None
+        }
+    }
+
+    /// Sets the size of the buffer of the data read from the stream reader.
+    pub(crate) fn set_read_buffer_size(&mut self, size: usize) {
+        self.bit_reader.set_buffer_size(size);
+    }
+
+    /// Replaces the stream reader with `rd`, moving the allocated buffers to the returned decoder
+    /// together with the previous reader.
+    pub(crate) fn replace_reader<T: Read>(self, rd: T) -> (Lh3Decoder<T>, R) {
+        let (bit_reader, rd) = self.bit_reader.replace_inner(rd);
+        let decoder = Lh3Decoder {
+            bit_reader,
+            ringbuf: self.ringbuf,
+            command_tree: self.command_tree,
+            offset_tree: self.offset_tree,
+            remaining_commands: self.remaining_commands,
+            copy_progress: self.copy_progress
+        };
+        (decoder, rd)
+    }
+
+    /// Restores the initial state of the decoder, reusing the allocated buffers.
+    pub(crate) fn reset(&mut self) {
+        self.ringbuf.reset();
+        self.remaining_commands = 0;
+        self.copy_progress = None;
+    }
+
+    /// Returns `true` if the first three code lengths equal to 1, which marks a single code tree.
+    #[inline]
+    fn is_single_code(head_lengths: &[u8]) -> bool {
+        head_lengths.iter().all(|&len| len == 1)
+    }
+
+    fn read_command_length(&mut self) -> io::Result<u8> {
+        Ok(if self.bit_reader.read_bit()? {
+            self.bit_reader.read_bits::<u8>(LENGTH_BITS)? + 1
+        }
+        else {
+            0
+        })
+    }
+
+    fn read_command_tree(&mut self) -> io::Result<()> {
+        let mut code_lengths = [0u8; NUM_COMMANDS];
+        let (head, tail) = code_lengths.split_at_mut(3);
+
+        for p in head.iter_mut() {
+            *p = self.read_command_length()?;
+        }
+
+        // single code only
+        if Self::is_single_code(head) {
+            let code = self.bit_reader.read_bits(COMMAND_BITS)?;
+            self.command_tree.set_single(code);
+            return Ok(());
+        }
+
+        for p in tail.iter_mut() {
+            *p = self.read_command_length()?;
+        }
+
+        self.command_tree.build_tree(&code_lengths)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(())
+    }
+
+    fn read_offset_tree(&mut self) -> io::Result<()> {
+        let mut code_lengths = [0u8; NUM_POSITION_GROUPS];
+        let (head, tail) = code_lengths.split_at_mut(3);
+
+        for p in head.iter_mut() {
+            *p = self.bit_reader.read_bits(LENGTH_BITS)?;
+        }
+
+        // single code only
+        if Self::is_single_code(head) {
+            let code = self.bit_reader.read_bits(GROUP_BITS)?;
+            self.offset_tree.set_single(code);
+            return Ok(());
+        }
+
+        for p in tail.iter_mut() {
+            *p = self.bit_reader.read_bits(LENGTH_BITS)?;
+        }
+
+        self.offset_tree.build_tree(&code_lengths)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(())
+    }
+
+    fn begin_new_block(&mut self) -> io::Result<()> {
+        self.remaining_commands = self.bit_reader.read_bits(16)?;
+        self.read_command_tree()?;
+        if self.bit_reader.read_bit()? {
+            self.read_offset_tree()
+        }
+        else {
+            self.offset_tree.build_tree(&READY_MADE_LENGTHS)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+    }
+
+    #[inline]
+    fn read_command(&mut self) -> io::Result<u16> {
+        let code = self.command_tree.read_entry(&mut self.bit_reader)?;
+        if code == ESCAPE_CODE {
+            return Ok(code + self.bit_reader.read_bits::<u16>(ESCAPE_BITS)?)
+        }
+        Ok(code)
+    }
+
+    #[inline]
+    fn read_offset(&mut self) -> io::Result<u16> {
+        let group = self.offset_tree.read_entry(&mut self.bit_reader)?;
+        let offset = self.bit_reader.read_bits::<u16>(POSITION_GROUP_BITS)?;
+        Ok(group << POSITION_GROUP_BITS | offset)
+    }
+
+    /// Copies the matched bytes to `target` and returns the number of bytes copied.
+    fn copy_from_history(&mut self, target: &mut [u8], offset: usize, count: usize) -> usize {
+        let len = target.len().min(count);
+        self.ringbuf.copy_from_offset(offset, &mut target[..len]);
+        self.copy_progress = NonZeroU16::new((count - len) as u16)
+                             .map(|count| (offset as u16, count));
+        len
+    }
+}
+
+impl<R: Read> Decoder<R> for Lh3Decoder<R> {
+    fn into_inner(self) -> R {
+        self.bit_reader.into_inner()
+    }
+
+    fn get_ref(&self) -> Option<&R> {
+        Some(self.bit_reader.get_ref())
+    }
+
+    fn fill_buffer(&mut self, buf: &mut[u8]) -> io::Result<()> {
+        let mut index = 0;
+        if let Some((offset, count)) = self.copy_progress {
+            index = self.copy_from_history(buf, offset as usize, count.get() as usize);
+        }
+
+        while let Some(dst) = buf.get_mut(index) {
+            while self.remaining_commands == 0 {
+                self.begin_new_block()?;
+            }
+
+            self.remaining_commands -= 1;
+
+            match self.read_command()? {
+                code @ 0..=0xff => {
+                    let value = code as u8;
+                    *dst = value;
+                    self.ringbuf.push(value);
+                    index += 1;
+                }
+                code => {
+                    let offset = self.read_offset()?;
+                    index += self.copy_from_history(&mut buf[index..],
+                                                    offset as usize,
+                                                    (code - 0x100 + 3).into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The code lengths of offset groups increase at predefined group indices.
+const fn ready_made_lengths() -> [u8; NUM_POSITION_GROUPS] {
+    const STEPS: [usize; 7] = [1, 1, 3, 6, 13, 31, 78];
+    let mut lengths = [0u8; NUM_POSITION_GROUPS];
+    let mut len = 2;
+    let mut step = 0;
+    let mut group = 0;
+    while group < NUM_POSITION_GROUPS {
+        while step < STEPS.len() && STEPS[step] == group {
+            len += 1;
+            step += 1;
+        }
+        lengths[group] = len;
+        group += 1;
+    }
+    lengths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn lh3_works() {
+        println!("Lh3Decoder<Empty> {}", core::mem::size_of::<Lh3Decoder<io::Empty>>());
+        println!("Lh3Decoder<File> {}", core::mem::size_of::<Lh3Decoder<fs::File>>());
+        assert_eq!(&READY_MADE_LENGTHS[0..14], &[2, 4, 4, 5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 7]);
+        assert_eq!(READY_MADE_LENGTHS[30], 7);
+        assert_eq!(READY_MADE_LENGTHS[31], 8);
+        assert_eq!(READY_MADE_LENGTHS[77], 8);
+        assert_eq!(READY_MADE_LENGTHS[78], 9);
+        assert_eq!(READY_MADE_LENGTHS[127], 9);
+        let mut tree = HuffTree::with_capacity(NUM_POSITION_GROUPS * 2);
+        tree.build_tree(&READY_MADE_LENGTHS).unwrap();
+    }
+}
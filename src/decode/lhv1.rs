@@ -4,7 +4,7 @@ use crate::decode::Decoder;
 use crate::ringbuf::*;
 use crate::bitstream::*;
 
-mod dyntree;
+pub(crate) mod dyntree;
 use dyntree::*;
 
 const RING_BUFFER_SIZE: usize = 4096;
@@ -31,6 +31,31 @@ impl<R: Read> Lh1Decoder<R> {
         }
     }
 
+    /// Sets the size of the buffer of the data read from the stream reader.
+    pub(crate) fn set_read_buffer_size(&mut self, size: usize) {
+        self.bit_reader.set_buffer_size(size);
+    }
+
+    /// Replaces the stream reader with `rd`, moving the allocated buffers to the returned decoder
+    /// together with the previous reader.
+    pub(crate) fn replace_reader<T: Read>(self, rd: T) -> (Lh1Decoder<T>, R) {
+        let (bit_reader, rd) = self.bit_reader.replace_inner(rd);
+        let decoder = Lh1Decoder {
+            bit_reader,
+            ringbuf: self.ringbuf,
+            command_tree: self.command_tree,
+            copy_progress: self.copy_progress
+        };
+        (decoder, rd)
+    }
+
+    /// Restores the initial state of the decoder, reusing the allocated buffers.
+    pub(crate) fn reset(&mut self) {
+        self.ringbuf.reset();
+        *self.command_tree = DynHuffTree::new();
+        self.copy_progress = None;
+    }
+
     #[inline]
     fn read_command(&mut self) -> io::Result<u16> {
         self.command_tree.read_entry(&mut self.bit_reader)
@@ -44,21 +69,13 @@ impl<R: Read> Lh1Decoder<R> {
         Ok(offset)
     }
 
-    fn copy_from_history<'a, I: Iterator<Item=&'a mut u8> + ExactSizeIterator>(
-            &mut self,
-            target: I,
-            offset: usize,
-            count: usize
-        ) -> io::Result<()>
-    {
-        let history_iter = self.ringbuf.iter_from_offset(offset);
-        let count_after = count - target.len().min(count);
-        for (t, s) in target.zip(history_iter).take(count) {
-            *t = s;
-        }
-        self.copy_progress = NonZeroU16::new(count_after as u16)
+    /// Copies the matched bytes to `target` and returns the number of bytes copied.
+    fn copy_from_history(&mut self, target: &mut [u8], offset: usize, count: usize) -> usize {
+        let len = target.len().min(count);
+        self.ringbuf.copy_from_offset(offset, &mut target[..len]);
+        self.copy_progress = NonZeroU16::new((count - len) as u16)
                              .map(|count| (offset as u16, count));
-        Ok(())
+        len
     }
 }
 
@@ -67,29 +84,29 @@ impl<R: Read> Decoder<R> for Lh1Decoder<R> {
         self.bit_reader.into_inner()
     }
 
+    fn get_ref(&self) -> Option<&R> {
+        Some(self.bit_reader.get_ref())
+    }
+
     fn fill_buffer(&mut self, buf: &mut[u8]) -> io::Result<()> {
-        let buflen = buf.len();
-        let mut target = buf.iter_mut();
+        let mut index = 0;
         if let Some((offset, count)) = self.copy_progress {
-            self.copy_from_history(&mut target,
-                                   offset as usize,
-                                   count.get() as usize)?;
+            index = self.copy_from_history(buf, offset as usize, count.get() as usize);
         }
 
-        while let Some(dst) = target.next() {
+        while let Some(dst) = buf.get_mut(index) {
             match self.read_command()? {
                 code @ 0..=0xff => {
                     let value = code as u8;
                     *dst = value;
                     self.ringbuf.push(value);
+                    index += 1;
                 }
                 count => {
                     let offset = self.read_offset()?;
-                    let index = buflen - target.len() - 1;
-                    target = buf[index..].iter_mut();
-                    self.copy_from_history(&mut target,
-                                           offset as usize,
-                                           (count - 0x100 + 3).into())?;
+                    index += self.copy_from_history(&mut buf[index..],
+                                                    offset as usize,
+                                                    (count - 0x100 + 3).into());
                 }
             }
         }
@@ -352,17 +352,16 @@ impl DynHuffTree {
 
     #[inline]
     fn promote_to_leader(&mut self, node_index: usize) -> usize {
-        let (node, head) = self.nodes[..node_index as usize + 1].split_last_mut().unwrap();
+        let (node, head) = self.nodes[..node_index + 1].split_last_mut().unwrap();
         let leader_index = self.groups.get_leader_index(node.group);
 
         if leader_index == node_index {
             return node_index
         }
         // swap the new leader with the old one
-        let prev_leader = &mut head[leader_index as usize];
-        let entry = node.entry;
-        node.entry = prev_leader.entry;
-        prev_leader.entry = entry;
+        let prev_leader = &mut head[leader_index];
+        core::mem::swap(&mut node.entry, &mut prev_leader.entry);
+        let entry = prev_leader.entry;
         // update old leader
         match node.entry.as_type() {
             NodeType::Leaf(value) => {
@@ -420,7 +419,7 @@ impl DynHuffTree {
     }
 
     #[inline]
-    fn increment_for_value(&mut self, value: u16) {
+    pub fn increment_for_value(&mut self, value: u16) {
         // reorder tree when limit reached
         if self.nodes[0].freq >= REORDER_LIMIT {
             self.rebuild_tree();
@@ -436,6 +435,22 @@ impl DynHuffTree {
         }
     }
 
+    /// Returns the code of the `value` and its length in bits, without updating the tree.
+    pub fn code_of(&self, value: u16) -> (u32, u32) {
+        let (mut code, mut len) = (0u32, 0u32);
+        let mut node_index = self.leaves.get_leaf_node_index(value);
+        while node_index != 0 {
+            let parent_index = self.nodes[node_index].parent as usize;
+            if let NodeType::Branch(child_index) = self.nodes[parent_index].entry.as_type() {
+                // the bit 0 selects the child at child_index, the bit 1 the one before it
+                code |= ((child_index as usize - node_index) as u32) << len;
+            }
+            len += 1;
+            node_index = parent_index;
+        }
+        (code, len)
+    }
+
     pub fn read_entry<R: BitRead>(&mut self, mut path: R) -> io::Result<u16> {
         let nodes = &self.nodes;
         let mut node = &nodes[0];
@@ -492,8 +507,8 @@ mod tests {
         let mut leaves: HashMap<u16, usize> = HashMap::with_capacity(NUM_LEAVES);
         let mut children: HashSet<u16> = HashSet::with_capacity(NUM_NODES);
         let mut groups: HashSet<u16> = HashSet::with_capacity(NUM_NODES);
-        let mut freq = u16::max_value();
-        let mut group = u16::max_value();
+        let mut freq = u16::MAX;
+        let mut group = u16::MAX;
         // root is a branch
         assert!(!tree.nodes[0].is_leaf());
         assert_eq!(tree.nodes[0].parent, 0);
@@ -609,7 +624,7 @@ mod tests {
         println!("--------------\n{}", tree);
 
         // now with some random bit stream
-        let rnd_stream: &mut (dyn RngCore) = rng;
+        let rnd_stream: &mut dyn RngCore = rng;
         let mut rndstream = BitStream::new(rnd_stream);
         for _ in 0..1_000_000 {
             tree.read_entry(&mut rndstream).unwrap();
@@ -70,6 +70,33 @@ impl<C: LhaDecoderConfig, R: Read> LhaV2Decoder<C, R> {
         }
     }
 
+    /// Sets the size of the buffer of the data read from the stream reader.
+    pub(crate) fn set_read_buffer_size(&mut self, size: usize) {
+        self.bit_reader.set_buffer_size(size);
+    }
+
+    /// Replaces the stream reader with `rd`, moving the allocated buffers to the returned decoder
+    /// together with the previous reader.
+    pub(crate) fn replace_reader<T: Read>(self, rd: T) -> (LhaV2Decoder<C, T>, R) {
+        let (bit_reader, rd) = self.bit_reader.replace_inner(rd);
+        let decoder = LhaV2Decoder {
+            bit_reader,
+            ringbuf: self.ringbuf,
+            command_tree: self.command_tree,
+            offset_tree: self.offset_tree,
+            remaining_commands: self.remaining_commands,
+            copy_progress: self.copy_progress
+        };
+        (decoder, rd)
+    }
+
+    /// Restores the initial state of the decoder, reusing the allocated buffers.
+    pub(crate) fn reset(&mut self) {
+        self.ringbuf.reset();
+        self.remaining_commands = 0;
+        self.copy_progress = None;
+    }
+
     // reads code length value, usually 0..=7 but might be higher
     fn read_code_length(&mut self) -> io::Result<u8> {
         let mut len: u8 = self.bit_reader.read_bits(3)?;
@@ -111,17 +138,16 @@ impl<C: LhaDecoderConfig, R: Read> LhaV2Decoder<C, R> {
         }
 
         // read actual lengths
-        for p in code_lengths[0..num_codes.min(3)].iter_mut() {
-            *p = self.read_code_length()?;
-            // println!("length: {:?}", *p);
-        }
-        // 2-bit skip value follows
-        let skip: usize = self.bit_reader.read_bits(2)?;
-        // println!("skip: {:?}", skip);
-
-        for p in code_lengths[3 + skip..num_codes].iter_mut() {
-            *p = self.read_code_length()?;
-            // println!("length: {:?}", *p);
+        let mut index = 0;
+        while index < num_codes {
+            code_lengths[index] = self.read_code_length()?;
+            index += 1;
+            // 2-bit skip value follows the third length
+            if index == 3 {
+                let skip: usize = self.bit_reader.read_bits(2)?;
+                // println!("skip: {:?}", skip);
+                index += skip;
+            }
         }
 
         self.offset_tree.build_tree(&code_lengths[0..num_codes])
@@ -224,26 +250,18 @@ impl<C: LhaDecoderConfig, R: Read> LhaV2Decoder<C, R> {
         //   bits => 4 -> 1xxx
             bits => {
                 let res: u32 = self.bit_reader.read_bits(bits - 1)?;
-                Ok(res | (1 << bits - 1))
+                Ok(res | (1 << (bits - 1)))
             }
         }
     }
 
-    fn copy_from_history<'a, I: Iterator<Item=&'a mut u8> + ExactSizeIterator>(
-            &mut self,
-            target: I,
-            offset: usize,
-            count: usize
-        ) -> io::Result<()>
-    {
-        let history_iter = self.ringbuf.iter_from_offset(offset);
-        let count_after = count - target.len().min(count);
-        for (t, s) in target.zip(history_iter).take(count) {
-            *t = s;
-        }
-        self.copy_progress = NonZeroU32::new(count_after as u32)
+    /// Copies the matched bytes to `target` and returns the number of bytes copied.
+    fn copy_from_history(&mut self, target: &mut [u8], offset: usize, count: usize) -> usize {
+        let len = target.len().min(count);
+        self.ringbuf.copy_from_offset(offset, &mut target[..len]);
+        self.copy_progress = NonZeroU32::new((count - len) as u32)
                              .map(|count| (offset as u32, count));
-        Ok(())
+        len
     }
 
 }
@@ -253,16 +271,17 @@ impl<C: LhaDecoderConfig, R: Read> Decoder<R> for LhaV2Decoder<C, R> {
         self.bit_reader.into_inner()
     }
 
+    fn get_ref(&self) -> Option<&R> {
+        Some(self.bit_reader.get_ref())
+    }
+
     fn fill_buffer(&mut self, buf: &mut[u8]) -> io::Result<()> {
-        let buflen = buf.len();
-        let mut target = buf.iter_mut();
+        let mut index = 0;
         if let Some((offset, count)) = self.copy_progress {
-            self.copy_from_history(&mut target,
-                                   offset as usize,
-                                   count.get() as usize)?;
+            index = self.copy_from_history(buf, offset as usize, count.get() as usize);
         }
 
-        while let Some(dst) = target.next() {
+        while let Some(dst) = buf.get_mut(index) {
             while self.remaining_commands == 0 {
                 self.begin_new_block()?;
             }
@@ -274,14 +293,13 @@ impl<C: LhaDecoderConfig, R: Read> Decoder<R> for LhaV2Decoder<C, R> {
                     let value = code as u8;
                     *dst = value;
                     self.ringbuf.push(value);
+                    index += 1;
                 }
                 count => {
                     let offset = self.read_offset()?;
-                    let index = buflen - target.len() - 1;
-                    target = buf[index..].iter_mut();
-                    self.copy_from_history(&mut target,
-                                           offset as usize, 
-                                           (count - 0x100 + 3).into())?;
+                    index += self.copy_from_history(&mut buf[index..],
+                                                    offset as usize,
+                                                    (count - 0x100 + 3).into());
                 }
             }
         }
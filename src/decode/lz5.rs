@@ -19,34 +19,7 @@ pub struct Lz5Decoder<R> {
 impl<R: Read> Lz5Decoder<R> {
     pub fn new(reader: R) -> Lz5Decoder<R> {
         let mut ringbuf = Box::new(RingArrayBuf::default());
-
-        // fill 13 times with each byte value (3328)
-        for i in 0..=255 {
-            for _ in 0..13 {
-                ringbuf.push(i);
-            }
-        }
-        // 256 ascending values (3584)
-        for i in 0..=255 {
-            ringbuf.push(i);
-        }
-        // 256 descending values (3840)
-        for i in (0..=255).rev() {
-            ringbuf.push(i);
-        }
-        // 128 zeroes (3968)
-        for _ in 0..128 {
-            ringbuf.push(0);
-        }
-        // leave a gap of 110 default spaces (4078)
-        ringbuf.set_cursor(START_OFFSET);
-        // a margin of zeroes (4096)
-        while ringbuf.cursor() != 0 {
-            ringbuf.push(0);
-        }
-        // set the start offset
-        ringbuf.set_cursor(START_OFFSET);
-
+        init_ringbuf(&mut ringbuf);
         Lz5Decoder {
             reader,
             ringbuf,
@@ -55,22 +28,63 @@ impl<R: Read> Lz5Decoder<R> {
         }
     }
 
-    fn copy_from_history<'a, I: Iterator<Item=&'a mut u8> + ExactSizeIterator>(
-            &mut self,
-            target: I,
-            pos: usize,
-            count: usize
-        ) -> io::Result<()>
-    {
-        let history_iter = self.ringbuf.iter_from_pos(pos);
-        let real_count = target.len().min(count);
-        for (t, s) in target.zip(history_iter).take(real_count) {
-            *t = s;
+    /// Replaces the stream reader with `rd`, moving the allocated buffers to the returned decoder
+    /// together with the previous reader.
+    pub(crate) fn replace_reader<T: Read>(self, rd: T) -> (Lz5Decoder<T>, R) {
+        let decoder = Lz5Decoder {
+            reader: rd,
+            ringbuf: self.ringbuf,
+            bitmap: self.bitmap,
+            copy_progress: self.copy_progress
+        };
+        (decoder, self.reader)
+    }
+
+    /// Restores the initial state of the decoder, reusing the allocated buffers.
+    pub(crate) fn reset(&mut self) {
+        self.ringbuf.reset();
+        init_ringbuf(&mut self.ringbuf);
+        self.bitmap = 1;
+        self.copy_progress = None;
+    }
+
+    /// Copies the matched bytes to `target` and returns the number of bytes copied.
+    fn copy_from_history(&mut self, target: &mut [u8], pos: usize, count: usize) -> usize {
+        let len = target.len().min(count);
+        self.ringbuf.copy_from_pos(pos, &mut target[..len]);
+        self.copy_progress = NonZeroU16::new((count - len) as u16)
+                             .map(|count| ((pos + len) as u16, count));
+        len
+    }
+}
+
+fn init_ringbuf(ringbuf: &mut RingArrayBuf<RING_BUFFER_SIZE>) {
+    // fill 13 times with each byte value (3328)
+    for i in 0..=255 {
+        for _ in 0..13 {
+            ringbuf.push(i);
         }
-        self.copy_progress = NonZeroU16::new((count - real_count) as u16)
-                             .map(|count| ((pos + real_count) as u16, count));
-        Ok(())
     }
+    // 256 ascending values (3584)
+    for i in 0..=255 {
+        ringbuf.push(i);
+    }
+    // 256 descending values (3840)
+    for i in (0..=255).rev() {
+        ringbuf.push(i);
+    }
+    // 128 zeroes (3968)
+    for _ in 0..128 {
+        ringbuf.push(0);
+    }
+    // leave a gap of 110 default spaces (4078)
+    ringbuf.set_cursor(START_OFFSET);
+    // a margin of zeroes (4096)
+    while ringbuf.cursor() != 0 {
+        ringbuf.push(0);
+    }
+    // set the start offset
+    ringbuf.set_cursor(START_OFFSET);
 }
 
 impl<R: Read> Decoder<R> for Lz5Decoder<R> {
@@ -78,18 +92,19 @@ impl<R: Read> Decoder<R> for Lz5Decoder<R> {
         self.reader
     }
 
+    fn get_ref(&self) -> Option<&R> {
+        Some(&self.reader)
+    }
+
     fn fill_buffer(&mut self, buf: &mut[u8]) -> io::Result<()> {
-        let buflen = buf.len();
-        let mut target = buf.iter_mut();
+        let mut index = 0;
         if let Some((pos, count)) = self.copy_progress {
-            self.copy_from_history(&mut target,
-                                   pos as usize,
-                                   count.get() as usize)?;
+            index = self.copy_from_history(buf, pos as usize, count.get() as usize);
         }
 
         let mut bitmap = self.bitmap;
 
-        while let Some(dst) = target.next() {
+        while let Some(dst) = buf.get_mut(index) {
             if bitmap == 1 {
                 let mut byte = 0u8;
                 self.reader.read_exact(slice::from_mut(&mut byte))?;
@@ -101,6 +116,7 @@ impl<R: Read> Decoder<R> for Lz5Decoder<R> {
                 self.reader.read_exact(slice::from_mut(&mut value))?;
                 *dst = value;
                 self.ringbuf.push(value);
+                index += 1;
             }
             else {
                 let mut cmd = [0u8;2];
@@ -108,9 +124,7 @@ impl<R: Read> Decoder<R> for Lz5Decoder<R> {
                 let [lo, hi] = cmd;
                 let pos = (((hi & 0xf0) as usize) << 4) | lo as usize;
                 let count = (hi & 0x0f) as usize;
-                let index = buflen - target.len() - 1;
-                target = buf[index..].iter_mut();
-                self.copy_from_history(&mut target, pos, count + 3)?;
+                index += self.copy_from_history(&mut buf[index..], pos, count + 3);
             }
 
             bitmap >>= 1;
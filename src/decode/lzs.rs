@@ -27,21 +27,37 @@ impl<R: Read> LzsDecoder<R> {
         }
     }
 
-    fn copy_from_history<'a, I: Iterator<Item=&'a mut u8> + ExactSizeIterator>(
-            &mut self,
-            target: I,
-            pos: usize,
-            count: usize
-        ) -> io::Result<()>
-    {
-        let history_iter = self.ringbuf.iter_from_pos(pos);
-        let real_count = target.len().min(count);
-        for (t, s) in target.zip(history_iter).take(real_count) {
-            *t = s;
-        }
-        self.copy_progress = NonZeroU16::new((count - real_count) as u16)
-                             .map(|count| ((pos + real_count) as u16, count));
-        Ok(())
+    /// Sets the size of the buffer of the data read from the stream reader.
+    pub(crate) fn set_read_buffer_size(&mut self, size: usize) {
+        self.bit_reader.set_buffer_size(size);
+    }
+
+    /// Replaces the stream reader with `rd`, moving the allocated buffers to the returned decoder
+    /// together with the previous reader.
+    pub(crate) fn replace_reader<T: Read>(self, rd: T) -> (LzsDecoder<T>, R) {
+        let (bit_reader, rd) = self.bit_reader.replace_inner(rd);
+        let decoder = LzsDecoder {
+            bit_reader,
+            ringbuf: self.ringbuf,
+            copy_progress: self.copy_progress
+        };
+        (decoder, rd)
+    }
+
+    /// Restores the initial state of the decoder, reusing the allocated buffers.
+    pub(crate) fn reset(&mut self) {
+        self.ringbuf.reset();
+        self.ringbuf.set_cursor(START_OFFSET);
+        self.copy_progress = None;
+    }
+
+    /// Copies the matched bytes to `target` and returns the number of bytes copied.
+    fn copy_from_history(&mut self, target: &mut [u8], pos: usize, count: usize) -> usize {
+        let len = target.len().min(count);
+        self.ringbuf.copy_from_pos(pos, &mut target[..len]);
+        self.copy_progress = NonZeroU16::new((count - len) as u16)
+                             .map(|count| ((pos + len) as u16, count));
+        len
     }
 }
 
@@ -50,27 +66,27 @@ impl<R: Read> Decoder<R> for LzsDecoder<R> {
         self.bit_reader.into_inner()
     }
 
+    fn get_ref(&self) -> Option<&R> {
+        Some(self.bit_reader.get_ref())
+    }
+
     fn fill_buffer(&mut self, buf: &mut[u8]) -> io::Result<()> {
-        let buflen = buf.len();
-        let mut target = buf.iter_mut();
+        let mut index = 0;
         if let Some((pos, count)) = self.copy_progress {
-            self.copy_from_history(&mut target,
-                                   pos as usize,
-                                   count.get() as usize)?;
+            index = self.copy_from_history(buf, pos as usize, count.get() as usize);
         }
 
-        while let Some(dst) = target.next() {
+        while let Some(dst) = buf.get_mut(index) {
             if self.bit_reader.read_bit()? {
                 let value = self.bit_reader.read_bits(8)?;
                 *dst = value;
                 self.ringbuf.push(value);
+                index += 1;
             }
             else {
                 let pos = self.bit_reader.read_bits(11)?;
                 let count: usize = self.bit_reader.read_bits(4)?;
-                let index = buflen - target.len() - 1;
-                target = buf[index..].iter_mut();
-                self.copy_from_history(&mut target, pos, count + 2)?;
+                index += self.copy_from_history(&mut buf[index..], pos, count + 2);
             }
         }
         Ok(())
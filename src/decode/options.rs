@@ -0,0 +1,229 @@
+use core::fmt;
+use std::error::Error;
+use std::io;
+use crate::header::{FilenameEncoding, ParseMode, DEFAULT_MAX_HEADER_SIZE};
+use super::{CrcPolicy, DEFAULT_OUTPUT_BUFFER_SIZE, DEFAULT_READ_BUFFER_SIZE};
+
+/// The configuration of [LhaDecodeReader][super::LhaDecodeReader], applied with
+/// [LhaDecodeReader::new_with_options][super::LhaDecodeReader::new_with_options] or
+/// [parse_file_with_options][crate::parse_file_with_options].
+///
+/// ```
+/// use delharc::decode::{CrcPolicy, LhaOptions};
+///
+/// let options = LhaOptions::new().strict(false)
+///                                .max_header_size(64 * 1024)
+///                                .crc_policy(CrcPolicy::OnDemand);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LhaOptions {
+    pub(crate) parse_mode: ParseMode,
+    pub(super) crc_policy: CrcPolicy,
+    pub(crate) max_header_size: usize,
+    pub(super) max_total_header_size: Option<u64>,
+    pub(super) filename_encoding: FilenameEncoding,
+    pub(crate) max_entry_size: Option<u64>,
+    pub(crate) max_compression_ratio: Option<u64>,
+    pub(super) detect_sfx: bool,
+    pub(super) read_buffer_size: usize,
+    pub(super) output_buffer_size: usize
+}
+
+/// The error of reading an archive exceeding the limits set with [LhaOptions::max_entry_size],
+/// [LhaOptions::max_compression_ratio] or [LhaOptions::max_total_header_size].
+///
+/// The error is returned wrapped in an [io::Error][std::io::Error] of the kind
+/// [io::ErrorKind::InvalidData][std::io::ErrorKind::InvalidData], before any of the file's
+/// content is decompressed, and can be retrieved with [io::Error::get_ref][std::io::Error::get_ref].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LimitError {
+    /// The original size of the file exceeds the limit.
+    EntrySize {
+        /// The original size of the file.
+        size: u64,
+        /// The maximum size.
+        limit: u64
+    },
+    /// The ratio of the original size to the compressed size of the file exceeds the limit.
+    CompressionRatio {
+        /// The original size of the file.
+        size: u64,
+        /// The compressed size of the file.
+        compressed_size: u64,
+        /// The maximum ratio.
+        limit: u64
+    },
+    /// The total size of the headers read from the archive exceeds the limit.
+    TotalHeaderSize {
+        /// The total size of the headers.
+        size: u64,
+        /// The maximum total size.
+        limit: u64
+    }
+}
+
+/// The error of decoding cancelled with the flag registered with
+/// [LhaDecodeReader::with_cancel_flag][super::LhaDecodeReader::with_cancel_flag].
+///
+/// The error is returned wrapped in an [io::Error] of the kind [io::ErrorKind::Other].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cancelled;
+
+impl Default for LhaOptions {
+    fn default() -> Self {
+        LhaOptions::new()
+    }
+}
+
+impl LhaOptions {
+    /// Creates the default options: the headers are parsed in the [ParseMode::Strict] mode and
+    /// their size is limited to [DEFAULT_MAX_HEADER_SIZE] bytes, the checksums are always computed,
+    /// the names are decoded with [FilenameEncoding::Ascii], the decompressed files are not limited,
+    /// the self-extracting archives are being detected and the buffers have the sizes of
+    /// [DEFAULT_READ_BUFFER_SIZE] and [DEFAULT_OUTPUT_BUFFER_SIZE] bytes.
+    pub fn new() -> Self {
+        LhaOptions {
+            parse_mode: ParseMode::Strict,
+            crc_policy: CrcPolicy::Always,
+            max_header_size: DEFAULT_MAX_HEADER_SIZE,
+            max_total_header_size: None,
+            filename_encoding: FilenameEncoding::Ascii,
+            max_entry_size: None,
+            max_compression_ratio: None,
+            detect_sfx: true,
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            output_buffer_size: DEFAULT_OUTPUT_BUFFER_SIZE
+        }
+    }
+    /// Selects [ParseMode::Strict] if `strict` is `true`, otherwise [ParseMode::Lenient].
+    pub fn strict(self, strict: bool) -> Self {
+        self.parse_mode(if strict { ParseMode::Strict } else { ParseMode::Lenient })
+    }
+    /// Sets the mode of parsing the headers, see [LhaHeader::read_with_mode][crate::LhaHeader::read_with_mode].
+    pub fn parse_mode(mut self, parse_mode: ParseMode) -> Self {
+        self.parse_mode = parse_mode;
+        self
+    }
+    /// Sets the policy of computing checksums of the decompressed content.
+    pub fn crc_policy(mut self, crc_policy: CrcPolicy) -> Self {
+        self.crc_policy = crc_policy;
+        self
+    }
+    /// Sets the maximum size of a header in bytes, see
+    /// [LhaHeader::read_with_limit][crate::LhaHeader::read_with_limit].
+    pub fn max_header_size(mut self, max_header_size: usize) -> Self {
+        self.max_header_size = max_header_size;
+        self
+    }
+    /// Sets the maximum total size of all headers of the archive in bytes. Reading more headers
+    /// results in [LimitError::TotalHeaderSize].
+    pub fn max_total_header_size(mut self, max_total_header_size: u64) -> Self {
+        self.max_total_header_size = Some(max_total_header_size);
+        self
+    }
+    /// Sets the maximum original size of the decompressed files. Decompressing a larger file results
+    /// in [LimitError::EntrySize].
+    pub fn max_entry_size(mut self, max_entry_size: u64) -> Self {
+        self.max_entry_size = Some(max_entry_size);
+        self
+    }
+    /// Sets the maximum ratio of the original size to the compressed size of the decompressed files.
+    /// Decompressing a file compressed better results in [LimitError::CompressionRatio].
+    pub fn max_compression_ratio(mut self, max_compression_ratio: u64) -> Self {
+        self.max_compression_ratio = Some(max_compression_ratio);
+        self
+    }
+    /// Enables or disables skipping the executable stub of self-extracting archives, i.e. `.exe`
+    /// files beginning with the `MZ` signature followed by a normal archive. Enabled by default.
+    ///
+    /// The constructors of seekable sources:
+    /// [LhaDecodeReader::new_seekable_with_options][super::LhaDecodeReader::new_seekable_with_options]
+    /// and [parse_file_with_options][crate::parse_file_with_options], search for the first valid
+    /// header past the stub, see [LhaHeader::scan][crate::LhaHeader::scan]. Without seeking, the size
+    /// of the MS-DOS or PE executable image is read from its headers, so the archive must immediately
+    /// follow the image. The offsets of the headers are being counted from the first header.
+    pub fn detect_sfx(mut self, detect_sfx: bool) -> Self {
+        self.detect_sfx = detect_sfx;
+        self
+    }
+    /// Sets the size of the buffer of the compressed data read by the decoders from the stream
+    /// reader in bytes. Sizes below 16 bytes are rounded up.
+    ///
+    /// Smaller buffers save memory, larger ones reduce the number of reads from the stream reader.
+    /// The methods without compression and the `-lz5-` method read the stream reader directly.
+    pub fn read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.read_buffer_size = read_buffer_size;
+        self
+    }
+    /// Sets the size of the buffer of the decompressed data used by the [io::BufRead][std::io::BufRead]
+    /// implementation of [LhaDecodeReader][super::LhaDecodeReader] in bytes. Sizes below 1 byte are
+    /// rounded up.
+    pub fn output_buffer_size(mut self, output_buffer_size: usize) -> Self {
+        self.output_buffer_size = output_buffer_size.max(1);
+        self
+    }
+    /// Sets the encoding of file names used by
+    /// [LhaDecodeReader::pathname][super::LhaDecodeReader::pathname].
+    pub fn filename_encoding(mut self, filename_encoding: FilenameEncoding) -> Self {
+        self.filename_encoding = filename_encoding;
+        self
+    }
+}
+
+impl LimitError {
+    /// Returns the limit exceeded by the file with the given sizes, if any.
+    pub(crate) fn check(
+            size: u64,
+            compressed_size: u64,
+            max_entry_size: Option<u64>,
+            max_compression_ratio: Option<u64>
+        ) -> Option<LimitError>
+    {
+        if let Some(limit) = max_entry_size.filter(|&limit| size > limit) {
+            return Some(LimitError::EntrySize { size, limit })
+        }
+        max_compression_ratio
+            .filter(|&limit| size > compressed_size.saturating_mul(limit))
+            .map(|limit| LimitError::CompressionRatio { size, compressed_size, limit })
+    }
+}
+
+impl Error for LimitError {}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::EntrySize { size, limit } => {
+                write!(f, "file size exceeds the limit: {}, limit: {}", size, limit)
+            }
+            LimitError::CompressionRatio { size, compressed_size, limit } => {
+                write!(f, "compression ratio exceeds the limit: {}/{}, limit: {}", size, compressed_size, limit)
+            }
+            LimitError::TotalHeaderSize { size, limit } => {
+                write!(f, "total size of headers exceeds the limit: {}, limit: {}", size, limit)
+            }
+        }
+    }
+}
+
+impl Cancelled {
+    /// Returns `true` if the given `err` has been caused by cancelling the decoding.
+    pub fn is_cancelled(err: &io::Error) -> bool {
+        err.get_ref().map_or(false, |e| e.is::<Cancelled>())
+    }
+}
+
+impl Error for Cancelled {}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("decoding cancelled")
+    }
+}
+
+impl From<Cancelled> for io::Error {
+    fn from(e: Cancelled) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, e)
+    }
+}
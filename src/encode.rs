@@ -0,0 +1,809 @@
+//! # Creating archives.
+//!
+//! [LhaWriter] writes the archived files, preceded by their headers, to the stream writer
+//! and terminates the archive with the end-of-archive marker.
+//!
+//! The files can be compressed with the `-lh4-`, `-lh5-`, `-lh6-` and `-lh7-` methods,
+//! see [LhaV2Encoder], with the `-lh1-` method if the `lh1` feature is enabled, see `Lh1Encoder`,
+//! or stored without compression. The speed and the ratio of the compression are tuned
+//! with [CompressionOptions].
+use std::io::{self, Read, Seek, Write};
+use crate::crc::Crc16;
+use crate::decode::LhaDecodeReader;
+use crate::error::LhaError;
+use crate::header::{CompressionMethod, FilenameEncoding, LhaHeader, ParseMode};
+
+mod bitwriter;
+mod huffman;
+mod matcher;
+#[cfg(feature = "lh1")]
+mod lhv1;
+mod lhv2;
+
+#[cfg(feature = "lh1")]
+pub use lhv1::*;
+pub use lhv2::*;
+
+/// The parameters of the encoders, trading the speed of compression for the compression ratio.
+///
+/// The options don't change the format of the compressed content, which can be decompressed
+/// the same way regardless of them.
+///
+/// # Example
+/// ```
+/// use delharc::{CompressionMethod, encode::{CompressionOptions, LhaV2Encoder}};
+///
+/// let options = CompressionOptions::level(9).block_size(0x8000);
+/// let encoder = LhaV2Encoder::with_options(CompressionMethod::Lh7, options).unwrap();
+/// let data = b"Hello, World! Hello, World! Hello, World!";
+/// assert!(encoder.encode(data).len() < data.len());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CompressionOptions {
+    lazy_matching: bool,
+    search_depth: usize,
+    block_size: usize
+}
+
+impl CompressionOptions {
+    /// The maximum number of commands in a block of the `-lh4-` to `-lh7-` methods.
+    pub const MAX_BLOCK_SIZE: usize = 0xFFFF;
+    /// Returns the options of the given compression `level`, from `0`, the fastest, to `9`,
+    /// the best compression. The levels above `9` are treated as `9`.
+    ///
+    /// The levels from `4` up use the lazy matching. The default level is `6`.
+    pub fn level(level: u32) -> Self {
+        const DEPTHS: [usize; 10] = [1, 4, 8, 16, 32, 64, 128, 256, 1024, 4096];
+        let level = level.min(9) as usize;
+        CompressionOptions {
+            lazy_matching: level >= 4,
+            search_depth: DEPTHS[level],
+            block_size: 16384
+        }
+    }
+    /// Returns the options of the fastest compression, the same as level `0`.
+    pub fn fast() -> Self {
+        CompressionOptions::level(0)
+    }
+    /// Returns the options of the best compression, the same as level `9`.
+    pub fn best() -> Self {
+        CompressionOptions::level(9)
+    }
+    /// Enables or disables the lazy matching: deferring a match by one byte if a longer match
+    /// follows it. Improves the compression ratio at the cost of roughly twice as many searches.
+    pub fn lazy_matching(mut self, lazy_matching: bool) -> Self {
+        self.lazy_matching = lazy_matching;
+        self
+    }
+    /// Sets the maximum number of the earlier occurrences of the data examined while searching for
+    /// the longest match. The larger the depth, the better and slower the compression.
+    /// The depth of `0` is treated as `1`.
+    pub fn search_depth(mut self, search_depth: usize) -> Self {
+        self.search_depth = search_depth.max(1);
+        self
+    }
+    /// Sets the maximum number of commands, the literal bytes or the matches, encoded with the same
+    /// Huffman trees by the `-lh4-` to `-lh7-` methods. The smaller blocks adapt better to the varying
+    /// content, but each block carries its own trees. The size is clamped to `1..=MAX_BLOCK_SIZE`.
+    ///
+    /// The `-lh1-` method adapts its codes continuously and ignores the block size.
+    pub fn block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size.clamp(1, Self::MAX_BLOCK_SIZE);
+        self
+    }
+    /// Returns `true` if the lazy matching is enabled.
+    pub fn is_lazy_matching(&self) -> bool {
+        self.lazy_matching
+    }
+    /// Returns the maximum number of the examined match candidates.
+    pub fn get_search_depth(&self) -> usize {
+        self.search_depth
+    }
+    /// Returns the maximum number of commands in a block.
+    pub fn get_block_size(&self) -> usize {
+        self.block_size
+    }
+}
+
+impl Default for CompressionOptions {
+    fn default() -> Self {
+        CompressionOptions::level(6)
+    }
+}
+
+/// Writes files to an LHA archive.
+///
+/// The headers are written as level 2 headers, with the file and directory names and the Unix
+/// metadata in the extra headers and the header's CRC-16 in the ["Common"][crate::header::ext::EXT_HEADER_COMMON]
+/// extra header. The headers made with [LhaHeaderBuilder][crate::header::LhaHeaderBuilder] are level 2
+/// headers by default, the headers of other levels, e.g. read from other archives, are converted
+/// with [LhaHeader::to_level2]. The ["MS-DOS Size"][crate::header::ext::EXT_HEADER_MSDOS_SIZE] extra
+/// header is added for files larger than 4GB.
+///
+/// The files are compressed with the compression method of their headers and the writer's
+/// [CompressionOptions], see [compress_with_options]. The sizes and the checksum of each file are
+/// filled in by the writer. The content of unknown size can be written with [LhaWriter::start_file]
+/// or, if the archive is seekable, copied without buffering with [LhaWriter::add_stream].
+///
+/// The archive must be terminated with [LhaWriter::finish], otherwise the end-of-archive marker
+/// is not written.
+///
+/// # Example
+/// ```
+/// use delharc::{CompressionMethod, LhaWriter, header::LhaHeaderBuilder};
+///
+/// let mut lha_writer = LhaWriter::new(Vec::new());
+/// lha_writer.add_directory(LhaHeaderBuilder::new().path("dir").build()?)?;
+/// let header = LhaHeaderBuilder::new().path("dir/hello.txt").compression(CompressionMethod::Lh5);
+/// lha_writer.add_file(header.build()?, &b"Hello, World!"[..])?;
+/// let archive = lha_writer.finish()?;
+///
+/// let content = delharc::decompress_entry(&archive[..], "dir/hello.txt")?;
+/// assert_eq!(content, b"Hello, World!");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct LhaWriter<W> {
+    inner: W,
+    filename_encoding: FilenameEncoding,
+    options: CompressionOptions
+}
+
+/// Receives the content of a file added with [LhaWriter::start_file].
+///
+/// The content is being buffered in memory, until [EntryWriter::finish] compresses it and writes it
+/// to the archive. The file is discarded if the `EntryWriter` is dropped without finishing.
+#[derive(Debug)]
+pub struct EntryWriter<'a, W: Write> {
+    lha_writer: &'a mut LhaWriter<W>,
+    header: LhaHeader,
+    content: Vec<u8>
+}
+
+/// The fate of an entry of the archive copied with [LhaWriter::copy_archive].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum RewriteAction {
+    /// The entry is copied verbatim.
+    Keep,
+    /// The entry is dropped.
+    Delete,
+    /// The entry is replaced with a new one, added with [LhaWriter::add_file] or with
+    /// [LhaWriter::add_directory] if the `header` is of a directory.
+    Replace {
+        /// The header of the new entry.
+        header: LhaHeader,
+        /// The uncompressed content of the new entry.
+        content: Vec<u8>
+    }
+}
+
+impl<W: Write> LhaWriter<W> {
+    /// Creates a new instance of `LhaWriter<W>` writing the archive to `wr`.
+    pub fn new(wr: W) -> Self {
+        LhaWriter { inner: wr, filename_encoding: FilenameEncoding::Auto, options: CompressionOptions::default() }
+    }
+    /// Sets the options of the encoders compressing the added files, [CompressionOptions::default]
+    /// by default.
+    pub fn with_compression_options(mut self, options: CompressionOptions) -> Self {
+        self.options = options;
+        self
+    }
+    /// Sets the encoding of the names in the headers of level 0 and 1, used to find the directory
+    /// separators when the headers are converted to level 2, see [LhaHeader::to_level2].
+    /// [FilenameEncoding::Auto] by default.
+    pub fn with_filename_encoding(mut self, encoding: FilenameEncoding) -> Self {
+        self.filename_encoding = encoding;
+        self
+    }
+    /// Reads the content of the file from `rd`, compresses it with the compression method of
+    /// the given `header` and the writer's options and writes it to the archive preceded by the `header`.
+    ///
+    /// The content is being read until the end of `rd` before the header is written, because
+    /// the header precedes the content. If the compressed content is not smaller than the original,
+    /// the file is stored with the `-lh0-` method instead. Returns the header as written.
+    ///
+    /// # Errors
+    /// Returns an error from the attempts to read `rd` or write to the archive or an error of the kind
+    /// [io::ErrorKind::InvalidInput] if the compression method is not supported, see [compress],
+    /// or the header can't be serialized, see [LhaHeader::write].
+    pub fn add_file<R: Read>(&mut self, mut header: LhaHeader, mut rd: R) -> io::Result<LhaHeader> {
+        let method = header.compression_method()
+                           .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut content = Vec::new();
+        rd.read_to_end(&mut content)?;
+        let mut crc = Crc16::default();
+        crc.digest(&content);
+        let compressed = match method {
+            CompressionMethod::Lh0|CompressionMethod::Lz4|CompressionMethod::Pm0 => None,
+            method => Some(compress_with_options(method, &content, self.options)?).filter(|data| data.len() < content.len())
+        };
+        let packed = match compressed.as_deref() {
+            Some(packed) => packed,
+            None => {
+                if !matches!(method, CompressionMethod::Lz4|CompressionMethod::Pm0) {
+                    header.compression = *CompressionMethod::Lh0.as_identifier();
+                }
+                &content
+            }
+        };
+        header.compressed_size = packed.len() as u64;
+        header.original_size = content.len() as u64;
+        header.file_crc = crc.sum16();
+        self.write_entry(header, packed)
+    }
+    /// Begins adding a file of an unknown size with the given `header`. The content of the file
+    /// is written to the returned [EntryWriter] and the file is added with [EntryWriter::finish].
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Write;
+    /// use delharc::{CompressionMethod, LhaWriter, header::LhaHeaderBuilder};
+    ///
+    /// let mut lha_writer = LhaWriter::new(Vec::new());
+    /// let header = LhaHeaderBuilder::new().path("hello.txt").compression(CompressionMethod::Lh5);
+    /// let mut entry = lha_writer.start_file(header.build()?);
+    /// write!(entry, "Hello, {}!", "World")?;
+    /// entry.finish()?;
+    /// let archive = lha_writer.finish()?;
+    ///
+    /// assert_eq!(delharc::decompress_entry(&archive[..], "hello.txt")?, b"Hello, World!");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn start_file(&mut self, header: LhaHeader) -> EntryWriter<'_, W> {
+        EntryWriter { lha_writer: self, header, content: Vec::new() }
+    }
+    /// Copies the content of the file from the seekable `rd` to the archive without compression,
+    /// preceded by the given `header`, without buffering the content in memory.
+    ///
+    /// The content is being read twice, starting at the current position of `rd`: first to compute
+    /// its size and checksum, then, after seeking back, while it's being copied to the archive.
+    /// Returns the header as written.
+    ///
+    /// # Errors
+    /// Returns an error from the attempts to read or seek `rd` or write to the archive, an error of
+    /// the kind [io::ErrorKind::InvalidInput] if the header can't be serialized, see [LhaHeader::write],
+    /// or an error of the kind [io::ErrorKind::UnexpectedEof] if the content got shorter in the meantime.
+    pub fn add_stored<R: Read + Seek>(&mut self, mut header: LhaHeader, mut rd: R) -> io::Result<LhaHeader> {
+        let start = rd.stream_position()?;
+        let mut crc = Crc16::default();
+        let size = io::copy(&mut rd, &mut crc)?;
+        rd.seek(io::SeekFrom::Start(start))?;
+        header.compression = *CompressionMethod::Lh0.as_identifier();
+        header.compressed_size = size;
+        header.original_size = size;
+        header.file_crc = crc.sum16();
+        let header = self.write_entry(header, &[])?;
+        let copied = io::copy(&mut rd.take(size), &mut self.inner)?;
+        if copied != size {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "file content changed while being archived"))
+        }
+        Ok(header)
+    }
+    /// Writes a directory entry to the archive with the given `header`.
+    ///
+    /// The content of the directory is not being added, the files in it should be added with their
+    /// paths prefixed with the directory's path. Returns the header as written.
+    ///
+    /// # Errors
+    /// Returns an error from the attempts to write to the archive or an error of the kind
+    /// [io::ErrorKind::InvalidInput] if the header can't be serialized, see [LhaHeader::write].
+    pub fn add_directory(&mut self, mut header: LhaHeader) -> io::Result<LhaHeader> {
+        header.compression = *CompressionMethod::Lhd.as_identifier();
+        header.compressed_size = 0;
+        header.original_size = 0;
+        header.file_crc = 0;
+        self.write_entry(header, &[])
+    }
+    /// Copies an entry of another archive: the `header` followed by the compressed content read
+    /// from `rd`, without decompressing or compressing it.
+    ///
+    /// The header is written as it was read, if it was read with [LhaHeader::read_with_raw],
+    /// otherwise it's being serialized at its level, see [LhaHeader::write].
+    ///
+    /// # Errors
+    /// Returns an error from the attempts to read `rd` or write to the archive, an error of the kind
+    /// [io::ErrorKind::InvalidInput] if the header can't be serialized or an error of the kind
+    /// [io::ErrorKind::UnexpectedEof] if `rd` ends before [LhaHeader::compressed_size] bytes are copied.
+    pub fn copy_entry<R: Read>(&mut self, header: &LhaHeader, rd: R) -> io::Result<()> {
+        match header.raw_header.as_deref() {
+            Some(raw) => self.inner.write_all(raw)?,
+            None => self.inner.write_all(&header.to_bytes()?)?
+        }
+        let copied = io::copy(&mut rd.take(header.compressed_size), &mut self.inner)?;
+        if copied != header.compressed_size {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected end of archive"))
+        }
+        Ok(())
+    }
+    /// Copies the entries of the archive read from `rd` to this archive, e.g. to delete or replace
+    /// some of them. The `action` receives the index of each entry, counting from `0`, and its header,
+    /// and decides whether the entry is kept, deleted or replaced.
+    ///
+    /// The kept entries are copied with [LhaWriter::copy_entry], so their headers and compressed
+    /// content are preserved byte for byte. The reading stops at the end-of-archive marker or at
+    /// the end of `rd`. More files can be added afterwards.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::fs::File;
+    /// use delharc::{LhaWriter, encode::RewriteAction};
+    ///
+    /// let mut lha_writer = LhaWriter::new(File::create("new.lzh")?);
+    /// lha_writer.copy_archive(File::open("old.lzh")?, |_, header| {
+    ///     if header.parse_pathname().ends_with("obsolete.txt") {
+    ///         RewriteAction::Delete
+    ///     }
+    ///     else {
+    ///         RewriteAction::Keep
+    ///     }
+    /// })?;
+    /// lha_writer.finish()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error from the attempts to read `rd` or write to the archive, the errors of
+    /// parsing the headers or adding the new entries.
+    pub fn copy_archive<R, F>(&mut self, mut rd: R, mut action: F) -> io::Result<()>
+        where R: Read, F: FnMut(usize, &LhaHeader) -> RewriteAction
+    {
+        let mut index = 0;
+        while let Some(header) = LhaHeader::read_with_raw(&mut rd, ParseMode::Strict)? {
+            match action(index, &header) {
+                RewriteAction::Keep => self.copy_entry(&header, &mut rd)?,
+                action => {
+                    let size = header.compressed_size;
+                    if io::copy(&mut (&mut rd).take(size), &mut io::sink())? != size {
+                        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected end of archive"))
+                    }
+                    match action {
+                        RewriteAction::Replace { header, .. } if header.is_directory() => {
+                            self.add_directory(header)?;
+                        }
+                        RewriteAction::Replace { header, content } => {
+                            self.add_file(header, &content[..])?;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            index += 1;
+        }
+        Ok(())
+    }
+    /// Writes the end-of-archive marker, flushes the stream writer and returns it.
+    ///
+    /// # Errors
+    /// Returns an error from the attempts to write to the stream writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.write_all(&[0])?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+    /// Returns a reference to the stream writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+    /// Returns a mutable reference to the stream writer.
+    ///
+    /// Writing to the stream writer directly corrupts the archive.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    fn write_entry(&mut self, header: LhaHeader, content: &[u8]) -> io::Result<LhaHeader> {
+        let mut header = match header.level {
+            2 => header,
+            _ => header.to_level2(self.filename_encoding)?
+        };
+        header.sync_msdos_size()?;
+        let bytes = header.to_bytes()?;
+        self.inner.write_all(&bytes)?;
+        self.inner.write_all(content)?;
+        header.header_size = bytes.len() as u64;
+        header.raw_header = None;
+        header.header_offset = None;
+        Ok(header)
+    }
+}
+
+impl<W: Write + Seek> LhaWriter<W> {
+    /// Copies the content of the file from `rd`, e.g. a pipe, to the archive without compression and
+    /// without buffering it in memory, preceded by the given `header`. The size of the content
+    /// doesn't need to be known upfront.
+    ///
+    /// The header is written first, with the ["MS-DOS Size"][crate::header::ext::EXT_HEADER_MSDOS_SIZE]
+    /// extra header reserving the room for the sizes of any magnitude. When `rd` ends, the header
+    /// is rewritten in place with the actual sizes and the checksum of the content. Returns the header
+    /// as written.
+    ///
+    /// # Errors
+    /// Returns an error from the attempts to read `rd` or write to or seek the archive or an error of
+    /// the kind [io::ErrorKind::InvalidInput] if the header can't be serialized, see [LhaHeader::write].
+    /// The archive is left incomplete if the copying fails.
+    pub fn add_stream<R: Read>(&mut self, mut header: LhaHeader, mut rd: R) -> io::Result<LhaHeader> {
+        header.compression = *CompressionMethod::Lh0.as_identifier();
+        header.compressed_size = u64::MAX;
+        header.original_size = u64::MAX;
+        header.file_crc = 0;
+        let start = self.inner.stream_position()?;
+        let mut header = self.write_entry(header, &[])?;
+        let mut crc = Crc16::default();
+        let mut size = 0u64;
+        let mut buf = vec![0u8; 0x10000];
+        loop {
+            let len = match rd.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e)
+            };
+            crc.digest(&buf[..len]);
+            self.inner.write_all(&buf[..len])?;
+            size += len as u64;
+        }
+        header.compressed_size = size;
+        header.original_size = size;
+        header.file_crc = crc.sum16();
+        header.sync_msdos_size()?;
+        let bytes = header.to_bytes()?;
+        if bytes.len() as u64 != header.header_size {
+            return Err(io::Error::new(io::ErrorKind::Other, "header size changed while being rewritten"))
+        }
+        let end = self.inner.stream_position()?;
+        self.inner.seek(io::SeekFrom::Start(start))?;
+        self.inner.write_all(&bytes)?;
+        self.inner.seek(io::SeekFrom::Start(end))?;
+        Ok(header)
+    }
+}
+
+impl<W: Write> EntryWriter<'_, W> {
+    /// Returns the header of the file.
+    pub fn header(&self) -> &LhaHeader {
+        &self.header
+    }
+    /// Returns the number of bytes of the content written so far.
+    pub fn len(&self) -> u64 {
+        self.content.len() as u64
+    }
+    /// Returns `true` if no content was written so far.
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+    /// Compresses the written content and adds the file to the archive, see [LhaWriter::add_file].
+    /// Returns the header as written.
+    ///
+    /// # Errors
+    /// Returns the errors of [LhaWriter::add_file].
+    pub fn finish(self) -> io::Result<LhaHeader> {
+        self.lha_writer.add_file(self.header, &self.content[..])
+    }
+}
+
+impl<W: Write> Write for EntryWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.content.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Compresses `data` with the given compression `method` and the default [CompressionOptions]
+/// and returns the compressed content, see [compress_with_options].
+///
+/// # Errors
+/// Returns an error of the kind [io::ErrorKind::InvalidInput] if there is no encoder of the `method`.
+pub fn compress(method: CompressionMethod, data: &[u8]) -> io::Result<Vec<u8>> {
+    compress_with_options(method, data, CompressionOptions::default())
+}
+
+/// Compresses `data` with the given compression `method` and `options` and returns the compressed
+/// content.
+///
+/// The `-lh0-`, `-lz4-` and `-pm0-` methods store the data without compression, the `-lh4-`,
+/// `-lh5-`, `-lh6-` and `-lh7-` methods are encoded with [LhaV2Encoder] and the `-lh1-` method
+/// with `Lh1Encoder`, if the `lh1` feature is enabled.
+///
+/// # Errors
+/// Returns an error of the kind [io::ErrorKind::InvalidInput] if there is no encoder of the `method`.
+pub fn compress_with_options(method: CompressionMethod, data: &[u8], options: CompressionOptions)
+    -> io::Result<Vec<u8>>
+{
+    match method {
+        CompressionMethod::Lh0|CompressionMethod::Lz4|CompressionMethod::Pm0 => Ok(data.to_vec()),
+        #[cfg(feature = "lh1")]
+        CompressionMethod::Lh1 => Ok(Lh1Encoder::with_options(options).encode(data)),
+        method => match LhaV2Encoder::with_options(method, options) {
+            Some(encoder) => Ok(encoder.encode(data)),
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "unsupported compression method"))
+        }
+    }
+}
+
+/// Reads the archive from `rd` and writes it to `wr` with the content of the files decompressed and
+/// compressed again with the `method`, e.g. to convert old `-lh1-` archives to `-lh7-`. Returns `wr`.
+///
+/// The headers are preserved, except for the compression method, the compressed size and
+/// the header level, see [LhaWriter]. The checksums of the decompressed files are verified.
+/// The directories are copied and the files, which don't get smaller, are stored with the `-lh0-`
+/// method, see [LhaWriter::add_file].
+///
+/// # Example
+/// ```no_run
+/// use std::fs::File;
+/// use delharc::CompressionMethod;
+///
+/// let dst = File::create("new.lzh")?;
+/// delharc::encode::recompress(File::open("old.lzh")?, dst, CompressionMethod::Lh7)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// # Errors
+/// Returns an error of the kind [io::ErrorKind::InvalidInput] if there is no encoder of the `method`,
+/// [LhaError::UnsupportedMethod] if a file can't be decompressed,
+/// [LhaError::BadChecksum] if the checksum of a file doesn't match
+/// and other errors from the attempts to read or write the archives.
+pub fn recompress<R: Read, W: Write>(rd: R, wr: W, method: CompressionMethod) -> io::Result<W> {
+    if !method.is_encoder_supported() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "unsupported compression method"))
+    }
+    let mut lha_writer = LhaWriter::new(wr);
+    let mut lha_reader = LhaDecodeReader::default();
+    let mut more = lha_reader.begin_new(rd)?;
+    let mut content = Vec::new();
+    while more {
+        let mut header = lha_reader.header().clone();
+        if header.is_directory() {
+            lha_writer.add_directory(header)?;
+        }
+        else if !lha_reader.is_decoder_supported() {
+            return Err(LhaError::UnsupportedMethod(header.compression).into())
+        }
+        else {
+            content.clear();
+            lha_reader.read_to_end(&mut content)?;
+            lha_reader.crc_check()?;
+            header.compression = method.into();
+            lha_writer.add_file(header, &content[..])?;
+        }
+        more = lha_reader.next_file()?;
+    }
+    lha_writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::header::LhaHeaderBuilder;
+    use super::*;
+
+    #[test]
+    fn add_stored_works() {
+        let content = b"junk: Hello, World!";
+        let mut rd = io::Cursor::new(&content[..]);
+        rd.set_position(6);
+        let mut lha_writer = LhaWriter::new(Vec::new());
+        let header = lha_writer.add_stored(LhaHeaderBuilder::new().path("foo").build().unwrap(), rd).unwrap();
+        assert_eq!(header.compression_method().unwrap(), CompressionMethod::Lh0);
+        assert_eq!(header.original_size, 13);
+        let stored = lha_writer.add_file(LhaHeaderBuilder::new().path("bar").build().unwrap(), &content[6..]).unwrap();
+        assert_eq!(header.file_crc, stored.file_crc);
+        let archive = lha_writer.finish().unwrap();
+        assert_eq!(crate::decompress_entry(&archive[..], "foo").unwrap(), b"Hello, World!");
+        assert_eq!(crate::decompress_entry(&archive[..], "bar").unwrap(), b"Hello, World!");
+    }
+
+    #[test]
+    fn streaming_entries_work() {
+        let content = b"Hello, World! ".repeat(100);
+        let mut lha_writer = LhaWriter::new(io::Cursor::new(Vec::new()));
+        let header = LhaHeaderBuilder::new().path("foo").compression(CompressionMethod::Lh5);
+        let mut entry = lha_writer.start_file(header.build().unwrap());
+        for chunk in content.chunks(33) {
+            entry.write_all(chunk).unwrap();
+        }
+        assert_eq!(entry.len(), 1400);
+        let header = entry.finish().unwrap();
+        assert_eq!(header.compression_method().unwrap(), CompressionMethod::Lh5);
+        assert!(header.compressed_size < 100);
+        // dropped without finishing
+        lha_writer.start_file(LhaHeaderBuilder::new().path("baz").build().unwrap()).write_all(b"baz").unwrap();
+        let header = LhaHeaderBuilder::new().path("bar").unix_mode(0o100644).build().unwrap();
+        let header = lha_writer.add_stream(header, &content[..]).unwrap();
+        assert_eq!(header.compression_method().unwrap(), CompressionMethod::Lh0);
+        assert_eq!((header.compressed_size, header.original_size), (1400, 1400));
+        lha_writer.add_file(LhaHeaderBuilder::new().path("qux").build().unwrap(), &b"qux"[..]).unwrap();
+        let archive = lha_writer.finish().unwrap().into_inner();
+
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        for (name, content) in [("foo", &content[..]), ("bar", &content[..]), ("qux", b"qux")] {
+            assert_eq!(lha_reader.header().parse_pathname(), std::path::Path::new(name));
+            let mut output = Vec::new();
+            lha_reader.read_to_end(&mut output).unwrap();
+            assert_eq!(output, content);
+            lha_reader.crc_check().unwrap();
+            if name == "bar" {
+                assert_eq!(lha_reader.header().unix_mode(), Some(0o100644));
+                assert_eq!(lha_reader.header().original_size, 1400);
+            }
+            assert_eq!(lha_reader.next_file().unwrap(), name != "qux");
+        }
+    }
+
+    #[test]
+    fn level2_headers_work() {
+        let mut lha_writer = LhaWriter::new(Vec::new());
+        let header = LhaHeaderBuilder::new().level(0).path("dir/foo").build().unwrap();
+        let header = lha_writer.add_file(header, &b"foo"[..]).unwrap();
+        assert_eq!(header.level, 2);
+        assert!(header.filename.is_empty());
+        assert_eq!(header.get_extra(crate::header::ext::EXT_HEADER_PATH), Some(&b"dir\xFF"[..]));
+        let header = LhaHeaderBuilder::new().level(1).path("bar").unix_mode(0o100600).build().unwrap();
+        lha_writer.add_file(header, &b"bar"[..]).unwrap();
+        let archive = lha_writer.finish().unwrap();
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        assert_eq!(lha_reader.header().level, 2);
+        assert_eq!(lha_reader.header().parse_pathname(), std::path::Path::new("dir").join("foo"));
+        assert!(lha_reader.next_file().unwrap());
+        assert_eq!(lha_reader.header().level, 2);
+        assert_eq!(lha_reader.header().unix_mode(), Some(0o100600));
+    }
+
+    #[test]
+    fn copy_archive_works() {
+        let mut lha_writer = LhaWriter::new(Vec::new());
+        for (name, content) in [("foo", &b"foo foo foo foo"[..]), ("bar", b"bar"), ("baz", b"baz")] {
+            let header = LhaHeaderBuilder::new().path(name).compression(CompressionMethod::Lh5);
+            lha_writer.add_file(header.build().unwrap(), content).unwrap();
+        }
+        let archive = lha_writer.finish().unwrap();
+
+        let mut lha_writer = LhaWriter::new(Vec::new());
+        let mut names = Vec::new();
+        lha_writer.copy_archive(&archive[..], |index, header| {
+            names.push((index, header.parse_pathname()));
+            match index {
+                1 => RewriteAction::Delete,
+                2 => RewriteAction::Replace {
+                    header: LhaHeaderBuilder::new().path("qux").build().unwrap(),
+                    content: b"qux".to_vec()
+                },
+                _ => RewriteAction::Keep
+            }
+        }).unwrap();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[2].1.to_str(), Some("baz"));
+        let rewritten = lha_writer.finish().unwrap();
+        // the first entry is copied verbatim
+        let foo = LhaHeader::read(&archive[..]).unwrap().unwrap();
+        let foo_len = (foo.header_size + foo.compressed_size) as usize;
+        assert_eq!(archive[..foo_len], rewritten[..foo_len]);
+        let entries = crate::archive::LhaArchive::list(&rewritten[..]).unwrap();
+        let names: Vec<_> = entries.iter().map(|entry| entry.path.to_str().unwrap()).collect();
+        assert_eq!(names, ["foo", "qux"]);
+        assert_eq!(crate::decompress_entry(&rewritten[..], "foo").unwrap(), b"foo foo foo foo");
+
+        let mut lha_writer = LhaWriter::new(Vec::new());
+        let err = lha_writer.copy_archive(&archive[..archive.len() - 10], |_, _| RewriteAction::Keep).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn compression_options_work() {
+        assert_eq!(CompressionOptions::default(), CompressionOptions::level(6));
+        assert_eq!(CompressionOptions::level(100), CompressionOptions::best());
+        assert!(!CompressionOptions::fast().is_lazy_matching());
+        let options = CompressionOptions::fast().search_depth(0).block_size(1 << 20).lazy_matching(true);
+        assert_eq!(options.get_search_depth(), 1);
+        assert_eq!(options.get_block_size(), CompressionOptions::MAX_BLOCK_SIZE);
+        assert!(options.is_lazy_matching());
+        assert_eq!(options.block_size(0).get_block_size(), 1);
+
+        let content = std::fs::read("src/decode.rs").unwrap();
+        let mut sizes = Vec::new();
+        for options in [CompressionOptions::fast(), CompressionOptions::default(), CompressionOptions::best()] {
+            let mut lha_writer = LhaWriter::new(Vec::new()).with_compression_options(options);
+            let header = LhaHeaderBuilder::new().path("foo").compression(CompressionMethod::Lh5);
+            let header = lha_writer.add_file(header.build().unwrap(), &content[..]).unwrap();
+            sizes.push(header.compressed_size);
+            let archive = lha_writer.finish().unwrap();
+            assert_eq!(crate::decompress_entry(&archive[..], "foo").unwrap(), content);
+        }
+        assert!(sizes[0] > sizes[1] && sizes[1] >= sizes[2]);
+    }
+
+    #[test]
+    fn recompress_works() {
+        let content = b"Hello, World! ".repeat(100);
+        let mut lha_writer = LhaWriter::new(Vec::new());
+        lha_writer.add_directory(LhaHeaderBuilder::new().path("dir").build().unwrap()).unwrap();
+        let header = LhaHeaderBuilder::new().path("dir/foo").unix_mode(0o100600).comment(b"foo");
+        lha_writer.add_file(header.build().unwrap(), &content[..]).unwrap();
+        let header = LhaHeaderBuilder::new().path("bar").compression(CompressionMethod::Lh5);
+        lha_writer.add_file(header.build().unwrap(), &content[..]).unwrap();
+        let archive = lha_writer.finish().unwrap();
+
+        let methods = [
+            CompressionMethod::Lh7,
+            #[cfg(feature = "lh1")]
+            CompressionMethod::Lh1
+        ];
+        for method in methods {
+            let recompressed = recompress(&archive[..], Vec::new(), method).unwrap();
+            let mut lha_reader = LhaDecodeReader::new(&recompressed[..]).unwrap();
+            assert!(lha_reader.header().is_directory());
+            for name in ["dir/foo", "bar"] {
+                assert!(lha_reader.next_file().unwrap());
+                let header = lha_reader.header();
+                assert_eq!(header.compression_method().unwrap(), method);
+                assert!(header.compressed_size < 200);
+                assert_eq!(header.parse_pathname(), std::path::Path::new(name));
+                let mut output = Vec::new();
+                lha_reader.read_to_end(&mut output).unwrap();
+                assert_eq!(output, content);
+                lha_reader.crc_check().unwrap();
+            }
+            assert_eq!(lha_reader.header().unix_mode(), None);
+            assert!(!lha_reader.next_file().unwrap());
+            let recompressed = recompress(&recompressed[..], Vec::new(), CompressionMethod::Lh0).unwrap();
+            let mut archive = crate::archive::LhaArchive::new(io::Cursor::new(&recompressed[..]));
+            let foo = archive.by_name("dir/foo").unwrap();
+            assert_eq!(foo.header().unix_mode(), Some(0o100600));
+            assert_eq!(foo.header().get_extra(crate::header::ext::EXT_HEADER_COMMENT), Some(&b"foo"[..]));
+            assert_eq!(foo.header().compression_method().unwrap(), CompressionMethod::Lh0);
+        }
+        assert_eq!(recompress(&archive[..], Vec::new(), CompressionMethod::Lh2).unwrap_err().kind(),
+                   io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn lha_writer_works() {
+        let mut lha_writer = LhaWriter::new(Vec::new());
+        let dir = LhaHeaderBuilder::new().path("dir").build().unwrap();
+        let header = lha_writer.add_directory(dir).unwrap();
+        assert!(header.is_directory());
+        let file = LhaHeaderBuilder::new().path("dir/foo.txt").unix_mode(0o100644).build().unwrap();
+        let header = lha_writer.add_file(file, &b"Hello, World!"[..]).unwrap();
+        assert_eq!(header.original_size, 13);
+        assert_eq!(header.compressed_size, 13);
+        let empty = LhaHeaderBuilder::new().path("empty").level(1).compression(CompressionMethod::Lh5);
+        let header = lha_writer.add_file(empty.build().unwrap(), io::empty()).unwrap();
+        assert_eq!(header.compression_method().unwrap(), CompressionMethod::Lh0);
+        let content = b"Hello, World! ".repeat(100);
+        let file = LhaHeaderBuilder::new().path("hello.txt").compression(CompressionMethod::Lh7);
+        let header = lha_writer.add_file(file.build().unwrap(), &content[..]).unwrap();
+        assert_eq!(header.compression_method().unwrap(), CompressionMethod::Lh7);
+        assert!(header.compressed_size < 100);
+        let file = LhaHeaderBuilder::new().path("bad").compression(CompressionMethod::Lh2);
+        let err = lha_writer.add_file(file.build().unwrap(), &content[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let archive = lha_writer.finish().unwrap();
+        assert_eq!(archive.last(), Some(&0));
+
+        let mut lha_reader = LhaDecodeReader::new(&archive[..]).unwrap();
+        assert!(lha_reader.header().is_directory());
+        assert_eq!(lha_reader.header().level, 2);
+        assert_eq!(lha_reader.header().parse_pathname().to_str(), Some("dir"));
+        assert!(lha_reader.next_file().unwrap());
+        assert_eq!(lha_reader.header().parse_pathname(), std::path::Path::new("dir").join("foo.txt"));
+        assert_eq!(lha_reader.header().unix_mode(), Some(0o100644));
+        let mut content = Vec::new();
+        lha_reader.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"Hello, World!");
+        lha_reader.crc_check().unwrap();
+        assert!(lha_reader.next_file().unwrap());
+        assert_eq!(lha_reader.header().level, 2);
+        assert_eq!(lha_reader.header().parse_pathname().to_str(), Some("empty"));
+        assert_eq!(lha_reader.header().original_size, 0);
+        assert!(lha_reader.next_file().unwrap());
+        content.clear();
+        lha_reader.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"Hello, World! ".repeat(100));
+        lha_reader.crc_check().unwrap();
+        assert!(!lha_reader.next_file().unwrap());
+    }
+}
@@ -0,0 +1,48 @@
+/// Collects bits, the most significant bit first, into a vector of bytes.
+#[derive(Debug, Default)]
+pub(crate) struct BitWriter {
+    bytes: Vec<u8>,
+    bits_buf: u64,
+    bits: u32
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        BitWriter::default()
+    }
+
+    /// Appends the `n` least significant bits of `value`, `n` must not exceed 32.
+    #[inline]
+    pub fn write_bits(&mut self, n: u32, value: u32) {
+        debug_assert!(n <= 32 && (n == 32 || value >> n == 0));
+        self.bits_buf = self.bits_buf << n | value as u64;
+        self.bits += n;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.bits_buf >> self.bits) as u8);
+        }
+    }
+
+    /// Pads the last byte with zero bits and returns the written bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.bits != 0 {
+            self.write_bits(8 - self.bits, 0);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_writer_works() {
+        let mut wr = BitWriter::new();
+        wr.write_bits(3, 0b101);
+        wr.write_bits(0, 0);
+        wr.write_bits(16, 0xABCD);
+        wr.write_bits(32, 0x12345678);
+        assert_eq!(wr.finish(), [0b1011_0101, 0b0111_1001, 0b1010_0010, 0x46, 0x8A, 0xCF, 0b0000_0000]);
+    }
+}
@@ -0,0 +1,109 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// The maximum length of the codes accepted by the LHA decoders.
+pub(crate) const MAX_CODE_LENGTH: u8 = 16;
+
+/// Returns the lengths of the Huffman codes of values with the given frequencies, not longer than
+/// `max_len` bits.
+///
+/// The values which do not occur get the length of `0`. If less than two values occur, all lengths
+/// are `0`, such a tree is represented by a single value without reading any bits.
+pub(crate) fn code_lengths(freqs: &[u32], max_len: u8) -> Vec<u8> {
+    let mut freqs = freqs.to_vec();
+    loop {
+        let lengths = unlimited_code_lengths(&freqs);
+        if lengths.iter().all(|&len| len <= max_len) {
+            return lengths
+        }
+        // flatten the distribution until the tree is shallow enough
+        for freq in freqs.iter_mut().filter(|freq| **freq != 0) {
+            *freq = (*freq >> 1).max(1);
+        }
+    }
+}
+
+fn unlimited_code_lengths(freqs: &[u32]) -> Vec<u8> {
+    let mut lengths = vec![0u8; freqs.len()];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = freqs.iter().enumerate()
+                    .filter(|(_, &freq)| freq != 0)
+                    .map(|(value, &freq)| Reverse((freq as u64, value)))
+                    .collect();
+    if heap.len() < 2 {
+        return lengths
+    }
+    // the parents of the leaves followed by the parents of the internal nodes
+    let mut parents = vec![usize::MAX; freqs.len()];
+    while heap.len() > 1 {
+        let Reverse((freq1, node1)) = heap.pop().unwrap();
+        let Reverse((freq2, node2)) = heap.pop().unwrap();
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[node1] = node;
+        parents[node2] = node;
+        heap.push(Reverse((freq1 + freq2, node)));
+    }
+    // the parents are created after their children, so the depths are resolved from the root
+    let mut depths = vec![0u8; parents.len()];
+    for node in (0..parents.len() - 1).rev() {
+        if let Some(&depth) = depths.get(parents[node]) {
+            depths[node] = depth.saturating_add(1);
+        }
+    }
+    lengths.copy_from_slice(&depths[..freqs.len()]);
+    lengths
+}
+
+/// Returns the canonical Huffman codes of values with the given code lengths, as expected by
+/// the LHA decoders: shorter codes precede the longer ones and the codes of the same length are
+/// ordered by their values.
+pub(crate) fn canonical_codes(lengths: &[u8]) -> Vec<u16> {
+    let mut count = [0u32; MAX_CODE_LENGTH as usize + 2];
+    for &len in lengths {
+        count[len as usize] += 1;
+    }
+    count[0] = 0;
+    let mut next_code = [0u32; MAX_CODE_LENGTH as usize + 2];
+    for len in 1..next_code.len() {
+        next_code[len] = (next_code[len - 1] + count[len - 1]) << 1;
+    }
+    lengths.iter().map(|&len| {
+        if len == 0 {
+            return 0
+        }
+        let code = next_code[len as usize];
+        next_code[len as usize] += 1;
+        code as u16
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_lengths_works() {
+        assert_eq!(code_lengths(&[0, 0, 0], 16), [0, 0, 0]);
+        assert_eq!(code_lengths(&[0, 5, 0], 16), [0, 0, 0]);
+        assert_eq!(code_lengths(&[1, 0, 1], 16), [1, 0, 1]);
+        assert_eq!(code_lengths(&[10, 1, 1, 2], 16), [1, 3, 3, 2]);
+        let freqs: Vec<u32> = (0..30).map(|n| 1 << n).collect();
+        let lengths = code_lengths(&freqs, 16);
+        assert!(lengths.iter().all(|&len| (1..=16).contains(&len)));
+        let kraft: f64 = lengths.iter().map(|&len| 0.5f64.powi(len.into())).sum();
+        assert_eq!(kraft, 1.0);
+    }
+
+    #[test]
+    fn canonical_codes_works() {
+        assert_eq!(canonical_codes(&[1, 3, 3, 2]), [0b0, 0b110, 0b111, 0b10]);
+        assert_eq!(canonical_codes(&[2, 0, 2, 2, 2]), [0b00, 0, 0b01, 0b10, 0b11]);
+        let mut tree = crate::statictree::HuffTree::with_capacity(10);
+        tree.build_tree(&[1, 3, 3, 2]).unwrap();
+        let mut bits = crate::bitstream::BitStream::new(&[0b1101_0111, 0b0000_0000][..]);
+        assert_eq!(tree.read_entry(&mut bits).unwrap(), 1);
+        assert_eq!(tree.read_entry(&mut bits).unwrap(), 3);
+        assert_eq!(tree.read_entry(&mut bits).unwrap(), 2);
+        assert_eq!(tree.read_entry(&mut bits).unwrap(), 0);
+    }
+}
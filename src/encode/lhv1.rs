@@ -0,0 +1,101 @@
+use crate::decode::lhv1::dyntree::DynHuffTree;
+
+use super::CompressionOptions;
+use super::bitwriter::BitWriter;
+use super::matcher::*;
+
+const HISTORY_BITS: u32 = 12;
+const MAX_MATCH: usize = 60;
+
+/// An encoder for `-lh1-` compression method.
+///
+/// The data is being compressed with LZSS over the 4kB sliding window, the literal bytes and match
+/// lengths are encoded with the adaptive Huffman coding and the match offsets with the fixed
+/// codes of the upper 6 bits followed by the lower 6 bits, readable by LHarc version 1 and
+/// the later LHA unpackers. The speed and the ratio of the compression depend on
+/// the [CompressionOptions].
+///
+/// ```
+/// use delharc::encode::Lh1Encoder;
+///
+/// let data = b"Hello, World! Hello, World! Hello, World!";
+/// let compressed = Lh1Encoder::new().encode(data);
+/// assert!(compressed.len() < data.len());
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lh1Encoder {
+    options: CompressionOptions
+}
+
+impl Lh1Encoder {
+    /// Creates an encoder with the default options.
+    pub fn new() -> Self {
+        Lh1Encoder::default()
+    }
+
+    /// Creates an encoder with the given `options`.
+    pub fn with_options(options: CompressionOptions) -> Self {
+        Lh1Encoder { options }
+    }
+
+    /// Compresses `data` and returns the compressed content.
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let mut finder = MatchFinder::new(HISTORY_BITS, MAX_MATCH, self.options.search_depth);
+        let mut command_tree = Box::new(DynHuffTree::new());
+        let mut wr = BitWriter::new();
+        finder.parse(data, self.options.lazy_matching, |token| match token {
+            Token::Literal(byte) => write_command(&mut wr, &mut command_tree, byte.into()),
+            Token::Match { len, distance } => {
+                write_command(&mut wr, &mut command_tree, (0x100 + len - MIN_MATCH) as u16);
+                write_offset(&mut wr, (distance - 1) as u32);
+            }
+        });
+        wr.finish()
+    }
+}
+
+fn write_command(wr: &mut BitWriter, command_tree: &mut DynHuffTree, command: u16) {
+    let (code, len) = command_tree.code_of(command);
+    wr.write_bits(len, code);
+    command_tree.increment_for_value(command);
+}
+
+/// Writes the upper 6 bits of the offset with a 3 to 8 bits long code, followed by the lower 6 bits.
+fn write_offset(wr: &mut BitWriter, offset: u32) {
+    let high = offset >> 6;
+    let (code, bits) = match high {
+        0 => (0, 3),
+        1..=3 => (high + 1, 4),
+        4..=11 => (high + 6, 5),
+        12..=23 => (high + 24, 6),
+        24..=47 => (high + 72, 7),
+        _ => (high + 192, 8)
+    };
+    wr.write_bits(bits, code);
+    wr.write_bits(6, offset & 0x3F);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decode::{Decoder, Lh1Decoder};
+    use super::*;
+
+    #[test]
+    fn lh1_encoder_works() {
+        let text = std::fs::read("src/decode.rs").unwrap();
+        let noise: Vec<u8> = (0..50_000u32).map(|n| (n.wrapping_mul(2654435761) >> 13) as u8).collect();
+        let inputs = [Vec::new(), b"a".to_vec(), vec![0u8; 100_000], (0..=255).cycle().take(10_000).collect(),
+                      noise, text];
+        for data in inputs {
+            for options in [CompressionOptions::fast(), CompressionOptions::best()] {
+                let compressed = Lh1Encoder::with_options(options).encode(&data);
+                let mut decoder = Lh1Decoder::new(&compressed[..]);
+                let mut output = vec![0u8; data.len()];
+                decoder.fill_buffer(&mut output).unwrap();
+                assert!(output == data);
+            }
+        }
+        let data = b"Hello, World! ".repeat(1000);
+        assert!(Lh1Encoder::new().encode(&data).len() < data.len() / 20);
+    }
+}
@@ -0,0 +1,297 @@
+use crate::header::CompressionMethod;
+
+use super::CompressionOptions;
+use super::bitwriter::BitWriter;
+use super::huffman::*;
+use super::matcher::*;
+
+const NUM_COMMANDS: usize = 510;
+const NUM_TEMP_CODES: usize = 19;
+const MAX_MATCH: usize = 256;
+
+/// An encoder for LHArc version 2 compression methods: `-lh4-`, `-lh5-`, `-lh6-` and `-lh7-`.
+///
+/// The data is being compressed with LZSS over the sliding window of the method's size and
+/// the commands are encoded in blocks, each with its own static Huffman trees, readable by
+/// the decoders of this library and other LHA unpackers. The speed and the ratio of the compression
+/// depend on the [CompressionOptions].
+///
+/// ```
+/// use delharc::{CompressionMethod, encode::LhaV2Encoder};
+///
+/// let data = b"Hello, World! Hello, World! Hello, World!";
+/// let compressed = LhaV2Encoder::new(CompressionMethod::Lh5).unwrap().encode(data);
+/// assert!(compressed.len() < data.len());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct LhaV2Encoder {
+    dict_bits: u32,
+    offset_codes: usize,
+    offset_bits: u32,
+    options: CompressionOptions
+}
+
+impl LhaV2Encoder {
+    /// Creates an encoder of the given compression `method` with the default options. Returns `None`
+    /// if the method is not one of `-lh4-`, `-lh5-`, `-lh6-` or `-lh7-`.
+    pub fn new(method: CompressionMethod) -> Option<Self> {
+        Self::with_options(method, CompressionOptions::default())
+    }
+
+    /// Creates an encoder of the given compression `method` with the given `options`. Returns `None`
+    /// if the method is not one of `-lh4-`, `-lh5-`, `-lh6-` or `-lh7-`.
+    pub fn with_options(method: CompressionMethod, options: CompressionOptions) -> Option<Self> {
+        let (dict_bits, offset_codes, offset_bits) = match method {
+            CompressionMethod::Lh4 => (12, 14, 4),
+            CompressionMethod::Lh5 => (13, 14, 4),
+            CompressionMethod::Lh6 => (15, 16, 5),
+            CompressionMethod::Lh7 => (16, 17, 5),
+            _ => return None
+        };
+        Some(LhaV2Encoder { dict_bits, offset_codes, offset_bits, options })
+    }
+
+    /// Compresses `data` and returns the compressed content.
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let options = &self.options;
+        let mut finder = MatchFinder::new(self.dict_bits, MAX_MATCH, options.search_depth);
+        let mut wr = BitWriter::new();
+        let mut block = Vec::with_capacity(options.block_size);
+        finder.parse(data, options.lazy_matching, |token| {
+            block.push(match token {
+                Token::Literal(byte) => (byte as u16, 0),
+                Token::Match { len, distance } => ((0x100 + len - MIN_MATCH) as u16, (distance - 1) as u32)
+            });
+            if block.len() == options.block_size {
+                self.write_block(&mut wr, &block);
+                block.clear();
+            }
+        });
+        if !block.is_empty() {
+            self.write_block(&mut wr, &block);
+        }
+        wr.finish()
+    }
+
+    /// Writes the block of commands: literal bytes below `0x100` or match lengths with offsets.
+    fn write_block(&self, wr: &mut BitWriter, block: &[(u16, u32)]) {
+        let mut command_freqs = [0u32; NUM_COMMANDS];
+        let mut offset_freqs = vec![0u32; self.offset_codes];
+        for &(command, offset) in block {
+            command_freqs[command as usize] += 1;
+            if command >= 0x100 {
+                offset_freqs[offset_code(offset)] += 1;
+            }
+        }
+        let command_lengths = code_lengths(&command_freqs, MAX_CODE_LENGTH);
+        let offset_lengths = code_lengths(&offset_freqs, MAX_CODE_LENGTH);
+
+        wr.write_bits(16, block.len() as u32);
+        match single_value(&command_freqs, &command_lengths) {
+            Some(command) => {
+                // the temporary tree is not used, but it's expected anyway
+                wr.write_bits(5, 0);
+                wr.write_bits(5, 0);
+                wr.write_bits(9, 0);
+                wr.write_bits(9, command as u32);
+            }
+            None => write_command_tree(wr, &command_lengths)
+        }
+        match single_value(&offset_freqs, &offset_lengths) {
+            Some(code) => {
+                wr.write_bits(self.offset_bits, 0);
+                wr.write_bits(self.offset_bits, code as u32);
+            }
+            None => {
+                let num_codes = trimmed_len(&offset_lengths);
+                wr.write_bits(self.offset_bits, num_codes as u32);
+                for &len in &offset_lengths[..num_codes] {
+                    write_code_length(wr, len);
+                }
+            }
+        }
+
+        let command_codes = canonical_codes(&command_lengths);
+        let offset_codes = canonical_codes(&offset_lengths);
+        for &(command, offset) in block {
+            let command = command as usize;
+            wr.write_bits(command_lengths[command].into(), command_codes[command].into());
+            if command >= 0x100 {
+                let code = offset_code(offset);
+                wr.write_bits(offset_lengths[code].into(), offset_codes[code].into());
+                if code >= 2 {
+                    let bits = code as u32 - 1;
+                    wr.write_bits(bits, offset & ((1 << bits) - 1));
+                }
+            }
+        }
+    }
+}
+
+/// Returns the number of significant bits of the `offset`, encoded with the offset tree.
+#[inline]
+fn offset_code(offset: u32) -> usize {
+    (u32::BITS - offset.leading_zeros()) as usize
+}
+
+/// Returns the only value of the tree if the tree has less than two values.
+fn single_value(freqs: &[u32], lengths: &[u8]) -> Option<usize> {
+    if lengths.iter().any(|&len| len != 0) {
+        return None
+    }
+    Some(freqs.iter().position(|&freq| freq != 0).unwrap_or(0))
+}
+
+/// Returns the number of code lengths without the trailing zeros.
+fn trimmed_len(lengths: &[u8]) -> usize {
+    lengths.iter().rposition(|&len| len != 0).map_or(0, |index| index + 1)
+}
+
+/// Writes a code length as 3 bits, the lengths from 7 up are followed by a unary suffix.
+fn write_code_length(wr: &mut BitWriter, len: u8) {
+    if len < 7 {
+        wr.write_bits(3, len.into());
+    }
+    else {
+        let ones = u32::from(len) - 7;
+        wr.write_bits(3, 7);
+        wr.write_bits(ones + 1, ((1 << ones) - 1) << 1);
+    }
+}
+
+/// Writes the code lengths of the command tree, encoded with the temporary tree preceding them.
+fn write_command_tree(wr: &mut BitWriter, command_lengths: &[u8]) {
+    let num_codes = trimmed_len(command_lengths);
+    // temporary codes: 0 - a single zero, 1 - 3..=18 zeros, 2 - 20..=531 zeros, otherwise length + 2
+    let mut temp_codes: Vec<(usize, u32)> = Vec::new();
+    let mut index = 0;
+    while index < num_codes {
+        let len = command_lengths[index];
+        if len != 0 {
+            temp_codes.push((len as usize + 2, 0));
+            index += 1;
+            continue
+        }
+        let mut run = command_lengths[index..num_codes].iter().take_while(|&&len| len == 0).count();
+        index += run;
+        while run != 0 {
+            match run {
+                1|2 => {
+                    temp_codes.push((0, 0));
+                    run -= 1;
+                }
+                3..=18 => {
+                    temp_codes.push((1, run as u32 - 3));
+                    run = 0;
+                }
+                19 => {
+                    temp_codes.push((0, 0));
+                    temp_codes.push((1, 15));
+                    run = 0;
+                }
+                _ => {
+                    let skip = run.min(531);
+                    temp_codes.push((2, skip as u32 - 20));
+                    run -= skip;
+                }
+            }
+        }
+    }
+
+    let mut temp_freqs = [0u32; NUM_TEMP_CODES];
+    for &(code, _) in &temp_codes {
+        temp_freqs[code] += 1;
+    }
+    let temp_lengths = code_lengths(&temp_freqs, MAX_CODE_LENGTH);
+    match single_value(&temp_freqs, &temp_lengths) {
+        Some(code) => {
+            wr.write_bits(5, 0);
+            wr.write_bits(5, code as u32);
+        }
+        None => {
+            let num_temp_codes = trimmed_len(&temp_lengths);
+            wr.write_bits(5, num_temp_codes as u32);
+            let mut index = 0;
+            while index < num_temp_codes {
+                write_code_length(wr, temp_lengths[index]);
+                index += 1;
+                // 2-bit count of zero lengths follows the third length
+                if index == 3 {
+                    let skip = temp_lengths[index..num_temp_codes].iter().take(3)
+                               .take_while(|&&len| len == 0).count();
+                    wr.write_bits(2, skip as u32);
+                    index += skip;
+                }
+            }
+        }
+    }
+
+    let temp_tree_codes = canonical_codes(&temp_lengths);
+    wr.write_bits(9, num_codes as u32);
+    for (code, extra) in temp_codes {
+        wr.write_bits(temp_lengths[code].into(), temp_tree_codes[code].into());
+        match code {
+            1 => wr.write_bits(4, extra),
+            2 => wr.write_bits(9, extra),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use crate::decode::{Lh5Decoder, Lh7Decoder, Decoder};
+    use super::*;
+
+    fn decode<'a, D: Decoder<&'a [u8]>>(mut decoder: D, size: usize) -> Vec<u8> {
+        let mut output = vec![0u8; size];
+        decoder.fill_buffer(&mut output).unwrap();
+        output
+    }
+
+    fn sample(size: usize) -> Vec<u8> {
+        let mut seed = 12345u32;
+        let words: [&[u8]; 6] = [b"lorem ", b"ipsum ", b"dolor ", b"sit ", b"amet, ", b"\0\0\0\0"];
+        let mut data = Vec::with_capacity(size + 8);
+        while data.len() < size {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            match seed >> 28 {
+                0..=9 => data.extend_from_slice(words[(seed >> 16) as usize % words.len()]),
+                _ => data.push((seed >> 8) as u8)
+            }
+        }
+        data.truncate(size);
+        data
+    }
+
+    #[test]
+    fn lhv2_encoder_works() {
+        let mut inputs = vec![Vec::new(), b"a".to_vec(), b"aaaa".to_vec(), vec![7u8; 100_000],
+                              (0..=255).collect(), sample(1000), sample(300_000)];
+        let mut fs_data = Vec::new();
+        std::fs::File::open("tests/lha213/lh5.lzh").unwrap().read_to_end(&mut fs_data).unwrap();
+        inputs.push(fs_data);
+        for data in inputs {
+            let mut options = vec![CompressionOptions::default()];
+            if data.len() <= 100_000 {
+                options.extend([CompressionOptions::fast(), CompressionOptions::best().block_size(100)]);
+            }
+            for options in options {
+                for method in [CompressionMethod::Lh4, CompressionMethod::Lh5] {
+                    let compressed = LhaV2Encoder::with_options(method, options).unwrap().encode(&data);
+                    assert_eq!(decode(Lh5Decoder::new(&compressed[..]), data.len()), data);
+                }
+                for method in [CompressionMethod::Lh6, CompressionMethod::Lh7] {
+                    let compressed = LhaV2Encoder::with_options(method, options).unwrap().encode(&data);
+                    assert_eq!(decode(Lh7Decoder::new(&compressed[..]), data.len()), data);
+                }
+            }
+        }
+        let data = sample(300_000);
+        let lh5 = LhaV2Encoder::new(CompressionMethod::Lh5).unwrap().encode(&data);
+        let lh7 = LhaV2Encoder::new(CompressionMethod::Lh7).unwrap().encode(&data);
+        assert!(lh5.len() < data.len() / 2 && lh7.len() < data.len() / 2);
+        assert!(LhaV2Encoder::new(CompressionMethod::Lh1).is_none());
+    }
+}
@@ -0,0 +1,173 @@
+/// The minimum length of a match worth encoding.
+pub(crate) const MIN_MATCH: usize = 3;
+
+const HASH_BITS: u32 = 15;
+
+/// A command of the LZSS stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Token {
+    /// A literal byte.
+    Literal(u8),
+    /// A copy of `len` bytes from `distance` bytes back.
+    Match { len: usize, distance: usize }
+}
+
+/// Finds the longest matches of the upcoming data in the sliding window, with the chains
+/// of positions sharing the hash of their first `MIN_MATCH` bytes.
+#[derive(Debug, Clone)]
+pub(crate) struct MatchFinder {
+    window_mask: usize,
+    max_match: usize,
+    depth: usize,
+    /// The hash of the first bytes to the last position + 1, or `0`.
+    head: Box<[usize]>,
+    /// The position in the window to the previous position + 1 with the same hash, or `0`.
+    prev: Box<[usize]>
+}
+
+impl MatchFinder {
+    /// Creates a finder of matches at a distance of at most `1 << window_bits` bytes,
+    /// up to `max_match` bytes long, examining at most `depth` candidates.
+    pub fn new(window_bits: u32, max_match: usize, depth: usize) -> Self {
+        MatchFinder {
+            window_mask: (1 << window_bits) - 1,
+            max_match,
+            depth: depth.max(1),
+            head: vec![0; 1 << HASH_BITS].into_boxed_slice(),
+            prev: vec![0; 1 << window_bits].into_boxed_slice()
+        }
+    }
+
+    #[inline]
+    fn hash(bytes: &[u8]) -> usize {
+        let hash = (bytes[0] as usize) << 10 ^ (bytes[1] as usize) << 5 ^ bytes[2] as usize;
+        hash & ((1 << HASH_BITS) - 1)
+    }
+
+    /// Adds the position `pos` of `data` to the window. The positions must be added in order,
+    /// after searching for the matches at them.
+    #[inline]
+    pub fn insert(&mut self, data: &[u8], pos: usize) {
+        if let Some(bytes) = data.get(pos..pos + MIN_MATCH) {
+            let hash = Self::hash(bytes);
+            self.prev[pos & self.window_mask] = self.head[hash];
+            self.head[hash] = pos + 1;
+        }
+    }
+
+    /// Returns the length and the distance of the longest match of the data at `pos` with
+    /// the preceding data in the window. The length is `0` if there is no match of at least
+    /// `MIN_MATCH` bytes.
+    pub fn find(&self, data: &[u8], pos: usize) -> (usize, usize) {
+        let bytes = match data.get(pos..pos + MIN_MATCH) {
+            Some(bytes) => bytes,
+            None => return (0, 0)
+        };
+        let max_len = self.max_match.min(data.len() - pos);
+        let target = &data[pos..pos + max_len];
+        let (mut best_len, mut best_dist) = (MIN_MATCH - 1, 0);
+        let mut candidate = self.head[Self::hash(bytes)];
+        for _ in 0..self.depth {
+            let start = match candidate.checked_sub(1) {
+                Some(start) if pos - start <= self.window_mask + 1 => start,
+                _ => break
+            };
+            let len = data[start..].iter().zip(target).take_while(|(a, b)| a == b).count();
+            if len > best_len {
+                best_len = len;
+                best_dist = pos - start;
+                if len == max_len {
+                    break
+                }
+            }
+            candidate = self.prev[start & self.window_mask];
+        }
+        if best_dist == 0 {
+            return (0, 0)
+        }
+        (best_len, best_dist)
+    }
+
+    /// Splits `data` into the literal bytes and the matches passed to `emit` in order.
+    ///
+    /// The longest match at each position is taken greedily, unless `lazy` is `true`: then
+    /// the match is deferred in favour of a literal byte if the match at the next position is longer.
+    pub fn parse<F: FnMut(Token)>(&mut self, data: &[u8], lazy: bool, mut emit: F) {
+        let mut pos = 0;
+        // the match at `pos`, already searched for at the previous position
+        let mut pending = None;
+        while pos < data.len() {
+            let (len, distance) = pending.take().unwrap_or_else(|| self.find(data, pos));
+            if len < MIN_MATCH {
+                emit(Token::Literal(data[pos]));
+                self.insert(data, pos);
+                pos += 1;
+                continue
+            }
+            self.insert(data, pos);
+            if lazy && len < self.max_match {
+                let next = self.find(data, pos + 1);
+                if next.0 > len {
+                    emit(Token::Literal(data[pos]));
+                    pos += 1;
+                    pending = Some(next);
+                    continue
+                }
+            }
+            emit(Token::Match { len, distance });
+            for pos in pos + 1..pos + len {
+                self.insert(data, pos);
+            }
+            pos += len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_finder_works() {
+        let data = b"abcdabcabcdeabcdxxxxxxxxabcd";
+        let mut finder = MatchFinder::new(3, 8, 16);
+        let mut found = Vec::new();
+        for pos in 0..data.len() {
+            found.push(finder.find(data, pos));
+            finder.insert(data, pos);
+        }
+        assert_eq!(found[4], (3, 4));
+        assert_eq!(found[7], (4, 7));
+        assert_eq!(found[12], (4, 5));
+        assert_eq!(found[17], (7, 1));
+        // too far away
+        assert_eq!(found[24], (0, 0));
+        assert_eq!(found[26], (0, 0));
+    }
+
+    #[test]
+    fn parse_works() {
+        fn tokens(data: &[u8], lazy: bool) -> Vec<Token> {
+            let mut tokens = Vec::new();
+            MatchFinder::new(8, 16, 16).parse(data, lazy, |token| tokens.push(token));
+            let mut output = Vec::new();
+            for &token in &tokens {
+                match token {
+                    Token::Literal(byte) => output.push(byte),
+                    Token::Match { len, distance } => for _ in 0..len {
+                        output.push(output[output.len() - distance]);
+                    }
+                }
+            }
+            assert_eq!(output, data);
+            tokens
+        }
+        let data = b"abcxbcdefgabcdefg";
+        let greedy = tokens(data, false);
+        assert_eq!(greedy[10..], [Token::Match { len: 3, distance: 10 }, Token::Match { len: 4, distance: 7 }]);
+        let lazy = tokens(data, true);
+        assert_eq!(lazy[10..], [Token::Literal(b'a'), Token::Match { len: 6, distance: 7 }]);
+        assert!(tokens(b"", true).is_empty());
+        assert_eq!(tokens(&[0; 100], true).len(), 8);
+    }
+}
@@ -0,0 +1,295 @@
+//! The structured causes of parsing and decoding errors.
+use core::fmt;
+use std::error::Error;
+use std::io;
+use std::path::PathBuf;
+use crate::decode::{Cancelled, LimitError};
+use crate::header::{CompressionMethod, HeaderWarning, UnsupportedReason};
+
+/// The cause of an error returned from parsing headers or decoding archived files.
+///
+/// The methods of this library return [io::Error]s, which are being created with `LhaError` as
+/// their inner error, see [io::Error::get_ref]. Convert the [io::Error] with [From] to match on
+/// the cause of the error:
+///
+/// ```
+/// use delharc::{LhaError, LhaHeader};
+///
+/// let data = [25u8, 0, b'-', b'l', b'h', b'0', b'-'];
+/// let err = LhaHeader::read(&data[..]).unwrap_err();
+/// match LhaError::from(err) {
+///     LhaError::TruncatedHeader => {}
+///     e => panic!("unexpected error: {}", e)
+/// }
+/// ```
+///
+/// Errors of the underlying stream reader and other errors without a specific cause are being
+/// converted to [LhaError::Io].
+///
+/// The errors returned from [LhaDecodeReader][crate::LhaDecodeReader] carry the context of
+/// the failure, i.e. the offending file and its offset in the archive, which is included in their
+/// messages and can be retrieved with [ErrorContext::of].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LhaError {
+    /// The CRC-16 checksum of the decompressed content doesn't match the checksum from the header.
+    BadChecksum {
+        /// The checksum found in the header.
+        expected: u16,
+        /// The checksum of the decompressed content.
+        got: u16
+    },
+    /// The header is inconsistent and it was parsed in the [ParseMode::Strict][crate::header::ParseMode::Strict]
+    /// mode. In the lenient mode the inconsistency is reported as a [HeaderWarning] instead.
+    BadHeader(HeaderWarning),
+    /// The compression method of the file is not supported.
+    UnsupportedMethod([u8;5]),
+    /// The stream ended before the end of the header.
+    TruncatedHeader,
+    /// The size of the header or one of its parts doesn't match its content.
+    WrongHeaderSize {
+        /// The level of the header.
+        level: u8
+    },
+    /// The header level is not one of 0, 1, 2 or 3.
+    UnknownHeaderLevel(u8),
+    /// The header exceeds the maximum size, see [LhaHeader::read_with_limit][crate::LhaHeader::read_with_limit].
+    HeaderTooLarge,
+    /// The header is malformed in another way, described by the message.
+    InvalidHeader(&'static str),
+    /// The archive doesn't begin with a header.
+    MissingHeader,
+    /// The file name could not be parsed according to the selected policy, described by the message.
+    InvalidFileName(&'static str),
+    /// The archived file exceeds the configured limits.
+    Limit(LimitError),
+    /// The decoding has been cancelled.
+    Cancelled,
+    /// An error of the underlying stream reader or another error.
+    Io(io::Error)
+}
+
+/// The phase of reading an archive in which an error occurred, see [ErrorContext].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorPhase {
+    /// Reading or parsing a header.
+    Header,
+    /// Decompressing the content of a file.
+    Decode,
+    /// Verifying the checksum of the decompressed content.
+    Checksum
+}
+
+/// The context of an error returned from [LhaDecodeReader][crate::LhaDecodeReader].
+///
+/// ```no_run
+/// use delharc::error::ErrorContext;
+///
+/// let mut lha_reader = delharc::parse_file("archive.lzh")?;
+/// if let Err(err) = std::io::copy(&mut lha_reader, &mut std::io::sink()) {
+///     if let Some(context) = ErrorContext::of(&err) {
+///         eprintln!("{:?} failed at offset {}", context.phase, context.offset);
+///     }
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ErrorContext {
+    /// The phase in which the error occurred.
+    pub phase: ErrorPhase,
+    /// The path name of the file, or `None` if the error occurred while reading its header.
+    pub path: Option<PathBuf>,
+    /// The offset of the file's header in the archive, see [LhaHeader::header_offset][crate::LhaHeader::header_offset].
+    pub offset: u64
+}
+
+/// The error carrying the context of the failure.
+#[derive(Debug)]
+struct ContextError {
+    context: ErrorContext,
+    error: LhaError
+}
+
+impl ErrorContext {
+    /// Returns the context attached to the given `err`, if any.
+    pub fn of(err: &io::Error) -> Option<&ErrorContext> {
+        err.get_ref()?.downcast_ref::<ContextError>().map(|e| &e.context)
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.phase, &self.path) {
+            (ErrorPhase::Header, _) => f.write_str("header")?,
+            (ErrorPhase::Decode, Some(path)) => write!(f, "decoding \"{}\"", path.display())?,
+            (ErrorPhase::Checksum, Some(path)) => write!(f, "verifying \"{}\"", path.display())?,
+            (ErrorPhase::Decode, None) => f.write_str("decoding")?,
+            (ErrorPhase::Checksum, None) => f.write_str("verifying")?
+        }
+        write!(f, " at offset {}", self.offset)
+    }
+}
+
+impl Error for ContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.context, self.error)
+    }
+}
+
+/// Attaches the context to the error of invalid or truncated data. Other errors, the limit errors and
+/// the errors already carrying a context are returned unchanged.
+pub(crate) fn with_context<F>(err: io::Error, context: F) -> io::Error
+    where F: FnOnce() -> ErrorContext
+{
+    let kind = err.kind();
+    let attach = matches!(kind, io::ErrorKind::InvalidData|io::ErrorKind::UnexpectedEof) &&
+                 err.get_ref().map_or(true, |e| !e.is::<LimitError>() && !e.is::<ContextError>());
+    if !attach {
+        return err
+    }
+    io::Error::new(kind, ContextError { context: context(), error: LhaError::from(err) })
+}
+
+impl LhaError {
+    /// Returns the kind of [io::Error] created from this error.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            LhaError::TruncatedHeader => io::ErrorKind::UnexpectedEof,
+            LhaError::Cancelled => io::ErrorKind::Other,
+            LhaError::Io(e) => e.kind(),
+            _ => io::ErrorKind::InvalidData
+        }
+    }
+}
+
+impl Error for LhaError {}
+
+impl fmt::Display for LhaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LhaError::BadChecksum { expected, got } => {
+                write!(f, "crc16 mismatch: {:04x}, expected: {:04x}", got, expected)
+            }
+            LhaError::BadHeader(warning) => warning.fmt(f),
+            LhaError::UnsupportedMethod(method) => {
+                write!(f, "unsupported compression method: {}", method.escape_ascii())?;
+                match CompressionMethod::from(*method).check_supported() {
+                    Err(reason@UnsupportedReason::FeatureDisabled(..)) => write!(f, ", {}", reason),
+                    _ => Ok(())
+                }
+            }
+            LhaError::TruncatedHeader => f.write_str("unexpected end of header"),
+            LhaError::WrongHeaderSize { .. } => f.write_str("wrong header size"),
+            LhaError::UnknownHeaderLevel(level) => write!(f, "unknown header level: {}", level),
+            LhaError::HeaderTooLarge => f.write_str("header too large"),
+            LhaError::InvalidHeader(message) => f.write_str(message),
+            LhaError::MissingHeader => f.write_str("a header is missing"),
+            LhaError::InvalidFileName(message) => f.write_str(message),
+            LhaError::Limit(e) => e.fmt(f),
+            LhaError::Cancelled => Cancelled.fmt(f),
+            LhaError::Io(e) => e.fmt(f)
+        }
+    }
+}
+
+impl From<LhaError> for io::Error {
+    fn from(e: LhaError) -> Self {
+        match e {
+            LhaError::Io(e) => e,
+            LhaError::Limit(e) => io::Error::new(io::ErrorKind::InvalidData, e),
+            LhaError::Cancelled => Cancelled.into(),
+            e => io::Error::new(e.kind(), e)
+        }
+    }
+}
+
+impl From<io::Error> for LhaError {
+    /// Retrieves the cause of the error created by this library or wraps other errors in [LhaError::Io].
+    fn from(err: io::Error) -> Self {
+        let inner = match err.get_ref() {
+            Some(inner) => inner,
+            None => return LhaError::Io(err)
+        };
+        if let Some(&e) = inner.downcast_ref::<LimitError>() {
+            LhaError::Limit(e)
+        }
+        else if inner.is::<Cancelled>() {
+            LhaError::Cancelled
+        }
+        else if inner.is::<LhaError>() {
+            *err.into_inner().unwrap().downcast::<LhaError>().unwrap()
+        }
+        else if inner.is::<ContextError>() {
+            err.into_inner().unwrap().downcast::<ContextError>().unwrap().error
+        }
+        else {
+            LhaError::Io(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversion_works() {
+        let err = io::Error::from(LhaError::UnknownHeaderLevel(7));
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "unknown header level: 7");
+        assert!(matches!(LhaError::from(err), LhaError::UnknownHeaderLevel(7)));
+        let err = io::Error::from(LhaError::TruncatedHeader);
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(matches!(LhaError::from(err), LhaError::TruncatedHeader));
+        let limit = LimitError::EntrySize { size: 2, limit: 1 };
+        let err = io::Error::from(LhaError::Limit(limit));
+        assert_eq!(err.get_ref().unwrap().downcast_ref::<LimitError>(), Some(&limit));
+        assert!(matches!(LhaError::from(err), LhaError::Limit(e) if e == limit));
+        let err = io::Error::from(LhaError::Cancelled);
+        assert!(Cancelled::is_cancelled(&err));
+        assert!(matches!(LhaError::from(err), LhaError::Cancelled));
+        let err = LhaError::from(io::Error::new(io::ErrorKind::BrokenPipe, "broken"));
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        let err = io::Error::from(err);
+        assert_eq!(err.to_string(), "broken");
+        assert!(matches!(LhaError::from(err), LhaError::Io(..)));
+        let err = LhaError::from(io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(err, LhaError::Io(e) if e.kind() == io::ErrorKind::NotFound));
+        let err = LhaError::BadChecksum { expected: 0x1234, got: 0xabcd };
+        assert_eq!(err.to_string(), "crc16 mismatch: abcd, expected: 1234");
+        assert_eq!(LhaError::UnsupportedMethod(*b"-pm2-").to_string(), "unsupported compression method: -pm2-");
+        if !cfg!(feature = "lhx") {
+            assert_eq!(LhaError::UnsupportedMethod(*b"-lhx-").to_string(),
+                       "unsupported compression method: -lhx-, the decoder is disabled, rebuild with feature `lhx`");
+        }
+    }
+
+    #[test]
+    fn context_works() {
+        let context = || ErrorContext { phase: ErrorPhase::Checksum, path: Some("foo".into()), offset: 7 };
+        let err = with_context(LhaError::BadChecksum { expected: 0x1234, got: 0xabcd }.into(), context);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "verifying \"foo\" at offset 7: crc16 mismatch: abcd, expected: 1234");
+        assert_eq!(ErrorContext::of(&err), Some(&context()));
+        let err = with_context(err, || panic!("context attached twice"));
+        assert!(matches!(LhaError::from(err), LhaError::BadChecksum { expected: 0x1234, got: 0xabcd }));
+        let context = || ErrorContext { phase: ErrorPhase::Header, path: None, offset: 0 };
+        let err = with_context(io::Error::new(io::ErrorKind::UnexpectedEof, "eof"), context);
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(err.to_string(), "header at offset 0: eof");
+        assert!(matches!(LhaError::from(err), LhaError::Io(e) if e.to_string() == "eof"));
+        let err = with_context(io::Error::from(io::ErrorKind::BrokenPipe), context);
+        assert_eq!(ErrorContext::of(&err), None);
+        let err = with_context(LhaError::Limit(LimitError::EntrySize { size: 2, limit: 1 }).into(), context);
+        assert!(err.get_ref().unwrap().is::<LimitError>());
+        assert!(with_context(Cancelled.into(), context).get_ref().unwrap().is::<Cancelled>());
+    }
+}
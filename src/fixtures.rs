@@ -0,0 +1,49 @@
+//! # Archive fixtures shared by the unit tests.
+use crate::crc::Crc16;
+use crate::header::LhaHeaderBuilder;
+
+/// Returns the header of a stored file at `path` with the checksum `crc`, followed by its `content`.
+pub(crate) fn stored_entry(path: &str, content: &[u8], crc: u16) -> Vec<u8> {
+    let builder = LhaHeaderBuilder::new().path(path).file_crc(crc)
+                  .compressed_size(content.len() as u64).original_size(content.len() as u64);
+    let mut entry = builder.build().unwrap().to_bytes().unwrap();
+    entry.extend_from_slice(content);
+    entry
+}
+
+/// Returns an archive of stored `files`, given as path names and contents, terminated with
+/// the end of archive marker.
+pub(crate) fn stored_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for (path, content) in files {
+        let mut crc = Crc16::default();
+        crc.digest(content);
+        archive.extend(stored_entry(path, content, crc.sum16()));
+    }
+    archive.push(0);
+    archive
+}
+
+/// Returns the level 0 header of the file `a` with the `content` stored with the compression
+/// `method`, followed by the content.
+pub(crate) fn method_entry(method: &[u8;5], content: &[u8]) -> Vec<u8> {
+    packed_entry(method, content, content)
+}
+
+/// Returns the level 0 header of the file `a` with the `content` compressed with the compression
+/// `method`, followed by the `packed` data. The header is 25 bytes long.
+pub(crate) fn packed_entry(method: &[u8;5], packed: &[u8], content: &[u8]) -> Vec<u8> {
+    let mut header = Vec::new();
+    header.extend_from_slice(method);
+    header.extend_from_slice(&(packed.len() as u32).to_le_bytes());
+    header.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    header.extend_from_slice(&[0, 0, 0x21, 0x28, 0x20, 0, 1, b'a']);
+    let mut crc = Crc16::default();
+    crc.digest(content);
+    header.extend_from_slice(&crc.sum16().to_le_bytes());
+    let csum = header.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+    let mut entry = vec![header.len() as u8, csum];
+    entry.extend_from_slice(&header);
+    entry.extend_from_slice(packed);
+    entry
+}
@@ -1,26 +1,53 @@
 //! # **LHA** header and related types.
 use core::convert::TryFrom;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Component, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::borrow::Cow;
 
 use chrono::{LocalResult, prelude::*};
 
+mod amiga;
+mod builder;
+mod charset;
+mod checksum;
 mod compression;
+mod convert;
+mod extended;
+mod human68k;
+mod kind;
+mod listing;
 mod ostype;
 mod msdos;
+mod os9;
 mod parser;
+mod pathname;
+mod scan;
 mod timestamp;
+mod warning;
+mod writer;
 
-use parser::ext::*;
-
+pub use amiga::*;
+pub use builder::*;
+pub use charset::*;
+pub use checksum::*;
 pub use msdos::*;
 pub use compression::*;
+pub use extended::*;
+pub use human68k::*;
+pub use kind::*;
+pub use listing::*;
 pub use ostype::*;
+pub use os9::*;
 pub use parser::*;
+pub use pathname::*;
+pub(crate) use scan::{find_signature, is_invalid_header, is_signature};
 pub use timestamp::*;
+pub use warning::*;
 
 /// Semi-parsed LHA header.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LhaHeader {
     /// Header level: 0, 1, 2 or 3.
     pub level: u8,
@@ -54,6 +81,23 @@ pub struct LhaHeader {
     pub first_header_len: u32,
     /// The extra headers' data.
     pub extra_headers: Box<[u8]>,
+    /// The anomalies found while parsing the header with [ParseMode::Lenient].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub warnings: Vec<HeaderWarning>,
+    /// The exact bytes of the header as read from the archive, including the length and checksum
+    /// fields. Only retained by [LhaHeader::read_with_raw].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub raw_header: Option<Box<[u8]>>,
+    /// The total size of the header in bytes, including the length and checksum fields, as parsed
+    /// or as built.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub header_size: u64,
+    /// The offset of the header from the beginning of the archive stream, if known.
+    ///
+    /// The offset is being set by [LhaDecodeReader][crate::LhaDecodeReader], relative to
+    /// the position of the stream reader when the reading of the archive began.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub header_offset: Option<u64>,
 }
 
 impl Default for LhaHeader {
@@ -71,6 +115,10 @@ impl Default for LhaHeader {
             extended_area: Box::new([]),
             first_header_len: 0,
             extra_headers: Box::new([]),
+            warnings: Vec::new(),
+            raw_header: None,
+            header_size: 0,
+            header_offset: None,
         }
     }
 }
@@ -82,37 +130,73 @@ impl LhaHeader {
             .filter(CompressionMethod::is_directory)
             .is_some()
     }
+    /// Classifies the entry as a regular file, a directory, a symbolic link or a special file.
+    ///
+    /// The classification is based on, in order of precedence:
+    /// * the file type bits of the Unix file mode, see [LhaHeader::unix_mode], unless the archive
+    ///   was made on OS-9, which stores the OS-9 attributes there instead,
+    /// * the OS-9 directory attribute,
+    /// * the `-lhd-` compression method, see [LhaHeader::is_directory],
+    /// * the MS-DOS attributes.
+    pub fn file_type(&self) -> EntryKind {
+        let os = self.os();
+        let is_os9 = matches!(os, OsType::Os9|OsType::Osk);
+        if let Some(mode) = self.unix_mode() {
+            if is_os9 {
+                if Os9Perms::from_bits_retain(mode).contains(Os9Perms::DIRECTORY) {
+                    return EntryKind::Directory
+                }
+            }
+            else {
+                match mode & 0o170000 {
+                    0 => {}
+                    0o100000 => return EntryKind::File,
+                    0o040000 => return EntryKind::Directory,
+                    0o120000 => return EntryKind::Symlink,
+                    _ => return EntryKind::Special
+                }
+            }
+        }
+        if self.os9_metadata().map_or(false, |os9| os9.perms.contains(Os9Perms::DIRECTORY)) ||
+           self.is_directory() ||
+           self.msdos_attrs.contains(MsDosAttrs::SUBDIR)
+        {
+            EntryKind::Directory
+        }
+        else if self.msdos_attrs.contains(MsDosAttrs::VOLUME) {
+            EntryKind::Special
+        }
+        else {
+            EntryKind::File
+        }
+    }
     /// Attempts to parse the `os_type` field and returns the `OsType` enum on success.
     pub fn parse_os_type(&self) -> Result<OsType, UnrecognizedOsType> {
         OsType::try_from(self.os_type)
     }
+    /// Returns the `OsType` of the `os_type` field, an unrecognized value is returned as
+    /// [OsType::Unknown].
+    pub fn os(&self) -> OsType {
+        OsType::from_raw(self.os_type)
+    }
     /// Attempts to parse the extended area, extra headers and as a last resort the `last_modified` field
     /// taking into account the header level, and on success returns an instance of [`DateTime<Utc>`][DateTime]
     /// or a [NaiveDateTime] wrapped in an `TimestampResult` enum.
     pub fn parse_last_modified(&self) -> TimestampResult {
-        for header in self.iter_extra() {
+        for header in self.parse_extra() {
             match header {
-                [EXT_HEADER_UNIX_TIME, data @ ..] => {
-                    if let Some(ts) = data.get(0..4).and_then(read_u32) {
-                        return Utc.timestamp_opt(ts as i64, 0).into()
-                    }
+                ExtraHeader::UnixTime(ts) => {
+                    return Utc.timestamp_opt(ts as i64, 0).into()
                 }
-                [EXT_HEADER_MSDOS_TIME, data @ ..] if data.len() == 24 => {
-                    if let Some(mtime) = read_u64(&data[8..16]) {
-                        return parse_win_filetime(mtime).into()
-                    }
+                ExtraHeader::MsDosTimes(times) if times.last_modified != 0 => {
+                    return times.parse_last_modified()
                 }
                 _ => {}
             }
         }
         if self.level < 2 {
-            match self.parse_os_type() {
-                Ok(OsType::Unix)|Ok(OsType::Osk) => {
-                    if let Some(ts) = self.extended_area.get(1..5).and_then(read_u32) {
-                        return Utc.timestamp_opt(ts as i64, 0).into()
-                    }
-                }
-                _ => {}
+            if let Some(ts) = self.unix_extended_area().map(|area| area.last_modified) {
+                return Utc.timestamp_opt(ts as i64, 0).into()
             }
             parse_msdos_datetime(self.last_modified).into()
         }
@@ -120,7 +204,216 @@ impl LhaHeader {
             Utc.timestamp_opt(self.last_modified as i64, 0).into()
         }
     }
+    /// Returns the best available timestamps of the file, each with its source.
+    ///
+    /// Each timestamp is taken from the most precise source found, in this order:
+    ///
+    /// 1. the ["MS-DOS Time"][parser::ext::EXT_HEADER_MSDOS_TIME] extra header's Windows FILETIMEs,
+    ///    with a 100-nanosecond precision, the only source of the creation and last access timestamps,
+    /// 2. the ["Unix Time"][parser::ext::EXT_HEADER_UNIX_TIME] extra header,
+    /// 3. the extended area of level 0 and 1 headers made on Unix-like systems,
+    /// 4. the `last_modified` field: a Unix timestamp for level 2 and 3 headers or the MS-DOS date
+    ///    and time, lacking the time zone, for level 0 and 1 headers.
+    ///
+    /// Sources which can't be parsed are skipped.
+    pub fn timestamps(&self) -> Timestamps {
+        let times = self.msdos_times().unwrap_or_default();
+        let filetime = |time: TimestampResult| Timestamp::new(time, TimestampSource::WindowsFileTime);
+        let last_modified = filetime(times.parse_last_modified())
+            .or_else(|| self.parse_extra().find_map(|header| match header {
+                ExtraHeader::UnixTime(ts) => {
+                    Timestamp::new(Utc.timestamp_opt(ts as i64, 0).into(), TimestampSource::UnixTimeExtra)
+                }
+                _ => None
+            }))
+            .or_else(|| {
+                let ts = self.unix_extended_area()?.last_modified;
+                Timestamp::new(Utc.timestamp_opt(ts as i64, 0).into(), TimestampSource::ExtendedArea)
+            })
+            .or_else(|| if self.level < 2 {
+                Timestamp::new(parse_msdos_datetime(self.last_modified).into(), TimestampSource::MsDosDateTime)
+            }
+            else {
+                Timestamp::new(Utc.timestamp_opt(self.last_modified as i64, 0).into(), TimestampSource::UnixTime)
+            });
+        Timestamps {
+            last_modified,
+            created: filetime(times.parse_created()),
+            last_access: filetime(times.parse_last_access())
+        }
+    }
+    /// Returns the file's last modified time, assuming the MS-DOS timestamps, see
+    /// [LhaHeader::parse_last_modified], are in the `Local` time zone.
+    ///
+    /// The MS-DOS timestamps are in the local time of the machine the archive was made on, which is
+    /// unknown. Use [LhaHeader::last_modified_in] if the time zone of the archive is known.
+    pub fn last_modified_time(&self) -> Option<SystemTime> {
+        self.last_modified_in(&Local)
+    }
+    /// Returns the file's last modified time, assuming the MS-DOS timestamps, see
+    /// [LhaHeader::parse_last_modified], are in the `tz` time zone.
+    ///
+    /// The Unix and Windows timestamps are in UTC and are not affected by `tz`.
+    ///
+    /// ```
+    /// use chrono::FixedOffset;
+    /// # use delharc::header::LhaHeader;
+    /// # use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// // 1980-01-01 09:00:00 MS-DOS timestamp
+    /// let header = LhaHeader { level: 1, last_modified: 0x00214800, ..LhaHeader::default() };
+    /// let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+    /// assert_eq!(header.last_modified_in(&tokyo), Some(UNIX_EPOCH + Duration::from_secs(315532800)));
+    /// ```
+    pub fn last_modified_in<Tz: TimeZone>(&self, tz: &Tz) -> Option<SystemTime> {
+        self.parse_last_modified().to_timezone(tz).map(SystemTime::from)
+    }
+    /// Returns the Windows timestamps found in the ["MS-DOS Time"][parser::ext::EXT_HEADER_MSDOS_TIME]
+    /// extra header.
+    pub fn msdos_times(&self) -> Option<MsDosTimes> {
+        self.parse_extra().find_map(|header| match header {
+            ExtraHeader::MsDosTimes(times) => Some(times),
+            _ => None
+        })
+    }
+    /// Attempts to parse the file's creation timestamp from the extra headers.
+    pub fn parse_created(&self) -> TimestampResult {
+        self.msdos_times().map(|times| times.parse_created()).unwrap_or(TimestampResult::None)
+    }
+    /// Attempts to parse the file's last access timestamp from the extra headers.
+    pub fn parse_last_access(&self) -> TimestampResult {
+        self.msdos_times().map(|times| times.parse_last_access()).unwrap_or(TimestampResult::None)
+    }
+    /// Attempts to find the file's last modified Unix timestamp and returns it as `SystemTime`.
+    ///
+    /// The ["Unix Time"][parser::ext::EXT_HEADER_UNIX_TIME] extra header takes precedence, next the
+    /// extended area of level 0 and 1 headers made on Unix-like systems is checked and finally
+    /// the `last_modified` field of level 2 and 3 headers is used.
+    ///
+    /// Returns `None` if only the MS-DOS or Windows timestamps are available, in this instance use
+    /// [LhaHeader::parse_last_modified].
+    pub fn unix_last_modified(&self) -> Option<SystemTime> {
+        self.parse_extra()
+            .find_map(|header| match header {
+                ExtraHeader::UnixTime(ts) => Some(ts),
+                _ => None
+            })
+            .or_else(|| self.unix_extended_area().map(|area| area.last_modified))
+            .or(if self.level < 2 { None } else { Some(self.last_modified) })
+            .map(|ts| UNIX_EPOCH + Duration::from_secs(ts.into()))
+    }
+    /// Attempts to find the Unix file mode and returns the `st_mode` bits on success.
+    ///
+    /// The mode is read from the ["Unix Permission"][parser::ext::EXT_HEADER_UNIX_PERM] extra header
+    /// or the extended area of level 0 and 1 headers made on Unix-like systems.
+    ///
+    /// The returned value includes the file type bits (`0o170000` mask), e.g. `0o120000` for
+    /// symbolic links or `0o040000` for directories.
+    pub fn unix_mode(&self) -> Option<u16> {
+        self.parse_extra()
+            .find_map(|header| match header {
+                ExtraHeader::UnixPerm(mode) => Some(mode),
+                _ => None
+            })
+            .or_else(|| self.unix_extended_area()?.mode)
+    }
+    /// Attempts to find the numeric Unix owner and group identifiers and returns them as
+    /// a `(uid, gid)` tuple on success.
+    ///
+    /// The identifiers are read from the ["Unix UID/GID"][parser::ext::EXT_HEADER_UNIX_UIDGID] extra
+    /// header or the extended area of level 0 and 1 headers made on Unix-like systems.
+    pub fn unix_uid_gid(&self) -> Option<(u16, u16)> {
+        self.parse_extra()
+            .find_map(|header| match header {
+                ExtraHeader::UidGid { uid, gid } => Some((uid, gid)),
+                _ => None
+            })
+            .or_else(|| self.unix_extended_area()?.uid_gid)
+    }
+    /// Attempts to find the Unix group name in the ["Unix Group"][parser::ext::EXT_HEADER_UNIX_GROUP]
+    /// extra header.
+    ///
+    /// The routine converts all non-ASCII or control characters to `%xx` sequences.
+    pub fn unix_group_name(&self) -> Option<Cow<'_, str>> {
+        self.parse_extra().find_map(|header| match header {
+            ExtraHeader::UnixGroup(data) => Some(parse_str_nilterm(data, true, true)),
+            _ => None
+        })
+        .filter(|name| !name.is_empty())
+    }
+    /// Attempts to find the Unix owner name in the ["Unix Owner"][parser::ext::EXT_HEADER_UNIX_OWNER]
+    /// extra header.
+    ///
+    /// The routine converts all non-ASCII or control characters to `%xx` sequences.
+    pub fn unix_owner_name(&self) -> Option<Cow<'_, str>> {
+        self.parse_extra().find_map(|header| match header {
+            ExtraHeader::UnixOwner(data) => Some(parse_str_nilterm(data, true, true)),
+            _ => None
+        })
+        .filter(|name| !name.is_empty())
+    }
+    /// Returns the content of the ["Multi-disc"][parser::ext::EXT_HEADER_MULTI_DISC] extra header
+    /// if the file is a part of the archive split across several discs.
+    pub fn multi_disc(&self) -> Option<MultiDiscInfo<'_>> {
+        self.parse_extra().find_map(|header| match header {
+            ExtraHeader::MultiDisc(info) => Some(info),
+            _ => None
+        })
+    }
+    /// Returns the content of the ["OS-9"][parser::ext::EXT_HEADER_OS9] extra header of the files
+    /// archived on OS-9 systems.
+    ///
+    /// The same metadata embedded in the extended area of level 0 headers is returned if there
+    /// is no extra header.
+    pub fn os9_metadata(&self) -> Option<Os9Metadata<'_>> {
+        self.parse_extra()
+            .find_map(|header| match header {
+                ExtraHeader::Os9(metadata) => Some(metadata),
+                _ => None
+            })
+            .or_else(|| match self.parse_extended_area()? {
+                ExtendedArea::Os9(metadata) => Some(metadata),
+                _ => None
+            })
+    }
+    /// Returns the protection bits of the files archived on [OsType::Amiga], stored in the lower
+    /// byte of the `msdos_attrs` field.
+    ///
+    /// Returns `None` if the archive OS is not Amiga.
+    pub fn amiga_protection(&self) -> Option<AmigaProtection> {
+        if self.os() != OsType::Amiga {
+            return None
+        }
+        Some(AmigaProtection::from_bits_retain(self.msdos_attrs.bits() as u8))
+    }
+    /// Returns the file attributes of the files archived on [OsType::Human68k], stored in the lower
+    /// byte of the `msdos_attrs` field.
+    ///
+    /// Returns `None` if the archive OS is not Human68k. Level 0 headers made on X68000 computers
+    /// have no OS type, so their attributes are available only as [LhaHeader::msdos_attrs].
+    ///
+    /// # Note
+    /// The MS-DOS timestamps of the files archived on X68000 computers are usually in Japan Standard
+    /// Time (UTC+9), use [LhaHeader::last_modified_in] to convert them.
+    pub fn human68k_attrs(&self) -> Option<Human68kAttrs> {
+        if self.os() != OsType::Human68k {
+            return None
+        }
+        Some(Human68kAttrs::from_bits_retain(self.msdos_attrs.bits() as u8))
+    }
+    /// Parses the extended area of level 0 and 1 headers according to the OS type of the header.
+    ///
+    /// Returns `None` if the extended area is empty or the header level is 2 or 3.
+    pub fn parse_extended_area(&self) -> Option<ExtendedArea<'_>> {
+        if self.level >= 2 {
+            return None
+        }
+        ExtendedArea::parse(self.os(), &self.extended_area)
+    }
     /// Attempts to parse the `compression` method field and returns the `CompressionMethod` enum on success.
+    ///
+    /// Returns an error if the method is not recognized, use `CompressionMethod::from(header.compression)`
+    /// to get [CompressionMethod::Other] instead.
     pub fn compression_method(&self) -> Result<CompressionMethod, UnrecognizedCompressionMethod> {
         CompressionMethod::try_from(&self.compression)
     }
@@ -132,6 +425,13 @@ impl LhaHeader {
     ///
     /// Malicious path components, like `..`, `.` or `//` are stripped from the path names.
     ///
+    /// If the Unicode file or directory name extra headers are present, they are preferred over
+    /// the legacy names.
+    ///
+    /// On Windows a leading drive letter, e.g. `C:`, and the `\\?\` prefix are stripped as well,
+    /// UNC prefixes become ordinary directories and all other `:` characters, which could select
+    /// a drive or an NTFS alternate data stream, are converted to `_`.
+    ///
     /// # Notes
     /// * If the path name could not be found the returned `PathBuf` will be empty.
     /// * Some filesystems may still reject the file or path names if path names include some forbidden
@@ -140,20 +440,87 @@ impl LhaHeader {
     ///   so make sure the path is not absolute before creating a file or a directory.
     /// * If the archive OS is [OsType::Amiga] the file name parsing terminates before the `nul` character.
     pub fn parse_pathname(&self) -> PathBuf {
+        self.parse_pathname_with(FilenameEncoding::Ascii)
+    }
+    /// Attempts to parse the `filename` field and searches extended data for the directory and an
+    /// alternative file name and returns a `PathBuf`, decoding names from the Shift-JIS (CP932)
+    /// character set.
+    ///
+    /// This is the same as calling [LhaHeader::parse_pathname_with] with `SHIFT_JIS` encoding.
+    #[cfg(feature = "encoding")]
+    pub fn parse_pathname_sjis(&self) -> PathBuf {
+        self.parse_pathname_with(FilenameEncoding::Encoding(encoding_rs::SHIFT_JIS))
+    }
+    /// Attempts to parse the `filename` field and searches extended data for the directory and an
+    /// alternative file name and returns a `PathBuf`, decoding names with the given `encoding`.
+    ///
+    /// The routine converts all control characters to `%xx` sequences and all system specific
+    /// directory separator characters to `_` in file names. Names that are not valid in the
+    /// given encoding are converted the same way as in [LhaHeader::parse_pathname].
+    ///
+    /// See [LhaHeader::parse_pathname] for more details.
+    pub fn parse_pathname_with(&self, encoding: FilenameEncoding) -> PathBuf {
+        self.try_parse_pathname(encoding, SanitizePolicy::PercentEncode)
+            .expect("percent encoding never fails")
+    }
+    /// Attempts to parse the `filename` field and searches extended data for the directory and an
+    /// alternative file name and returns a `PathBuf`, decoding names with the given `encoding` and
+    /// handling forbidden characters according to the `policy`.
+    ///
+    /// The forbidden characters are: the control characters, system specific directory separator
+    /// characters in file names and the bytes that are not valid in the given encoding.
+    ///
+    /// Malicious path components, like `..`, `.` or `//` are stripped from the path names regardless
+    /// of the `policy`, see [LhaHeader::try_parse_pathname_traversal] to handle them differently.
+    /// See [LhaHeader::parse_pathname] for more details.
+    ///
+    /// # Errors
+    /// Returns an error only with the [SanitizePolicy::Error] policy if a forbidden character was found.
+    pub fn try_parse_pathname(&self, encoding: FilenameEncoding, policy: SanitizePolicy) -> io::Result<PathBuf> {
+        self.try_parse_pathname_traversal(encoding, policy, TraversalPolicy::SilentlySkip)
+            .map(|(path, _)| path)
+    }
+    /// Attempts to parse the `filename` field and searches extended data for the directory and an
+    /// alternative file name and returns a `PathBuf` with a list of the removed path components.
+    ///
+    /// Names are decoded with the given `encoding` and the forbidden characters are handled
+    /// according to the `policy`, see [LhaHeader::try_parse_pathname]. The malicious path
+    /// components, like `..`, `.`, the root directory or the Windows drive prefixes, are handled
+    /// according to the `traversal` policy.
+    ///
+    /// # Errors
+    /// Returns an error with the [SanitizePolicy::Error] policy if a forbidden character was found
+    /// and with the [TraversalPolicy::Error] policy if a path traversal was attempted.
+    pub fn try_parse_pathname_traversal(
+            &self,
+            encoding: FilenameEncoding,
+            policy: SanitizePolicy,
+            traversal: TraversalPolicy
+        ) -> io::Result<(PathBuf, Vec<RemovedComponent>)>
+    {
+        let encoding = encoding.resolve(self.os());
+        let (unicode_path, unicode_name) = self.unicode_names();
         let mut path = PathBuf::new();
+        let mut sink = PathSink::new(&mut path, traversal);
         let mut filename = Cow::Borrowed("");
         let nilterm = self.parse_os_type() == Ok(OsType::Amiga);
-        for header in self.iter_extra() {
+        if let Some(data) = &unicode_path {
+            parse_pathname_policy(data, &mut sink, FilenameEncoding::Utf8, policy)?;
+        }
+        for header in self.parse_extra() {
             match header {
-                [EXT_HEADER_FILENAME, data @ ..] => {
-                    filename = parse_str_nilterm(data, nilterm, false);
+                ExtraHeader::Filename(data) if unicode_name.is_none() => {
+                    filename = sanitize_name(data, nilterm, false, encoding, policy)?;
                 },
-                [EXT_HEADER_PATH, data @ ..] => {
-                    parse_pathname(data, &mut path);
+                ExtraHeader::Path(data) if unicode_path.is_none() => {
+                    parse_pathname_policy(data, &mut sink, encoding, policy)?;
                 }
                 _ => {}
             }
         }
+        if let Some(data) = &unicode_name {
+            filename = sanitize_name(data, false, false, FilenameEncoding::Utf8, policy)?;
+        }
         if filename.is_empty() {
             let data = if nilterm {
                 split_data_at_nil_or_end(&self.filename).0
@@ -161,30 +528,189 @@ impl LhaHeader {
             else {
                 &self.filename
             };
-            parse_pathname(data, &mut path);
+            parse_pathname_policy(data, &mut sink, encoding, policy)?;
         }
-        else {
-            path.push(filename.as_ref());
+        else if sink.accept(filename.as_bytes())? {
+            sink.push(&filename)?;
+        }
+        let removed = sink.into_removed();
+        Ok((path, removed))
+    }
+    /// Returns the directory and the file names found in the Unicode extra headers, converted
+    /// to UTF-8, with the directory components separated by `0xFF`.
+    ///
+    /// Empty names are ignored.
+    fn unicode_names(&self) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
+        let mut path: Option<Vec<u8>> = None;
+        let mut filename = None;
+        for header in self.parse_extra() {
+            match header {
+                ExtraHeader::UnicodeFilename(data) => {
+                    filename = Some(utf16_to_utf8(data)).filter(|name| !name.is_empty());
+                }
+                ExtraHeader::UnicodePath(data) if data.len() >= 2 => {
+                    let path = path.get_or_insert_with(Vec::new);
+                    path.extend(utf16_to_utf8(data));
+                    path.push(0xFF);
+                }
+                _ => {}
+            }
+        }
+        (path, filename)
+    }
+    /// Returns an iterator through the components of the path name, without building a `PathBuf`.
+    ///
+    /// The components are decoded and sanitized the same way as in [LhaHeader::parse_pathname],
+    /// the directory names are followed by the file name. Malicious path components, like `..`,
+    /// `.` or `//` are skipped. On Windows the drive prefixes are removed and the other colons
+    /// are converted to `_`.
+    ///
+    /// The components are borrowed from the header unless they need to be sanitized and are
+    /// decoded one at a time.
+    pub fn path_components(&self) -> PathComponents<'_> {
+        PathComponents::new(self)
+    }
+    /// Returns the raw bytes of the file name, as found in the extra headers or in the `filename` field.
+    ///
+    /// For level 0 and 1 headers the returned name may also include the directory components.
+    ///
+    /// If the archive OS is [OsType::Amiga] the returned name ends before the `nul` character.
+    pub fn raw_filename_bytes(&self) -> &[u8] {
+        let nilterm = self.parse_os_type() == Ok(OsType::Amiga);
+        let filename = self.parse_extra().fold(None, |filename, header| match header {
+            ExtraHeader::Filename(data) => Some(data),
+            _ => filename
+        })
+        .map(|data| if nilterm { split_data_at_nil_or_end(data).0 } else { data })
+        .filter(|data| !data.is_empty());
+        match filename {
+            Some(filename) => filename,
+            None if nilterm => split_data_at_nil_or_end(&self.filename).0,
+            None => &self.filename
+        }
+    }
+    /// Returns the raw bytes of the directory name found in the extra headers, with components
+    /// separated by `0xFF`.
+    pub fn raw_path_bytes(&self) -> Option<&[u8]> {
+        self.parse_extra().find_map(|header| match header {
+            ExtraHeader::Path(data) => Some(data),
+            _ => None
+        })
+    }
+    /// Attempts to parse the `filename` field and searches extended data for the directory and an
+    /// alternative file name and returns a `PathBuf`, preserving the raw bytes of the names.
+    ///
+    /// On Unix the bytes of the path components are preserved as is, except `nul` and `/` characters
+    /// found in file names which are converted to `_`. On other platforms the names are decoded
+    /// as UTF-8 and the invalid sequences are converted to `%xx`.
+    ///
+    /// The Unicode file and directory name extra headers are ignored.
+    ///
+    /// Malicious path components, like `..`, `.` or `//` are stripped from the path names.
+    /// See [LhaHeader::parse_pathname] for more details.
+    pub fn parse_pathname_raw(&self) -> PathBuf {
+        let mut path = PathBuf::new();
+        let mut sink = PathSink::new(&mut path, TraversalPolicy::SilentlySkip);
+        let mut filename: &[u8] = &[];
+        let nilterm = self.parse_os_type() == Ok(OsType::Amiga);
+        for header in self.parse_extra() {
+            match header {
+                ExtraHeader::Filename(data) => {
+                    filename = if nilterm { split_data_at_nil_or_end(data).0 } else { data };
+                },
+                ExtraHeader::Path(data) => {
+                    parse_pathname_raw(data, &mut sink).expect("skipping never fails");
+                }
+                _ => {}
+            }
+        }
+        if filename.is_empty() {
+            let data = if nilterm {
+                split_data_at_nil_or_end(&self.filename).0
+            }
+            else {
+                &self.filename
+            };
+            parse_pathname_raw(data, &mut sink).expect("skipping never fails");
+        }
+        else if sink.accept(filename).expect("skipping never fails") {
+            sink.push_raw(filename).expect("skipping never fails");
         }
         path
     }
-    /// Attempts to find and return the file comment field in extended header data.
+    /// Attempts to parse the target of a symbolic link and returns it as a `PathBuf`.
+    ///
+    /// LHa for UNIX stores symbolic links as directory entries with the link's target appended to
+    /// the path name after the `|` character. Returns `None` unless the Unix file mode, see
+    /// [LhaHeader::unix_mode], marks the entry as a symbolic link.
     ///
     /// The routine converts all non-ASCII or control characters to `%xx` sequences.
     ///
+    /// # Note
+    /// The returned target is preserved as is: it may be an absolute path or may include `..`
+    /// components pointing outside of the extraction directory, so make sure to validate it
+    /// before creating a link.
+    pub fn symlink_target(&self) -> Option<PathBuf> {
+        if self.unix_mode()? & 0o170000 != 0o120000 {
+            return None
+        }
+        let mut raw = Vec::new();
+        for header in self.parse_extra() {
+            if let ExtraHeader::Path(data) = header {
+                raw.extend_from_slice(data);
+                raw.push(0xFF);
+            }
+        }
+        raw.extend_from_slice(self.raw_filename_bytes());
+        let index = memchr::memchr(b'|', &raw)?;
+        let target = &raw[index + 1..];
+        let is_sep = |c: &u8| *c == 0xFF || *c == b'/' || *c == b'\\';
+        let mut path = PathBuf::new();
+        if target.first().map_or(false, is_sep) {
+            path.push(Component::RootDir);
+        }
+        for part in target.split(is_sep) {
+            match part {
+                b"."|[] => {}
+                b".." => path.push(Component::ParentDir),
+                name => path.push(parse_str_nilterm(name, false, false).as_ref())
+            }
+        }
+        if path.as_os_str().is_empty() {
+            return None
+        }
+        Some(path)
+    }
+    /// Attempts to find and return the file comment field in extended header data.
+    ///
+    /// The routine converts all non-ASCII or control characters to `%xx` sequences, the same as
+    /// in file names, except directory separators which are left intact. The comment ends
+    /// before the first `nul` character. Empty comments are ignored.
+    ///
     /// # Notes
-    /// Some archives made on [OsType::Amiga] can have a comment embedded in the filename field
-    /// after the `nul` character. If the comment could not be found in extended data, an attempt
+    /// Some archives made on [OsType::Amiga] can have a comment (a "filenote") embedded in the filename
+    /// field after the `nul` character. If the comment could not be found in extended data, an attempt
     /// is made to extract the comment from the filename if the archive OS supports it.
-    pub fn parse_comment(&self) -> Option<Cow<str>> {
+    pub fn parse_comment(&self) -> Option<Cow<'_, str>> {
+        self.parse_comment_with(FilenameEncoding::Ascii)
+    }
+    /// Attempts to find and return the file comment field in extended header data, decoding it with
+    /// the given `encoding`.
+    ///
+    /// The routine converts all control characters to `%xx` sequences. A comment that is not valid in
+    /// the given encoding is converted the same way as in [LhaHeader::parse_comment].
+    ///
+    /// See [LhaHeader::parse_comment] for more details.
+    pub fn parse_comment_with(&self, encoding: FilenameEncoding) -> Option<Cow<'_, str>> {
+        let encoding = encoding.resolve(self.os());
         let mut raw_filename = &self.filename[..];
-        for header in self.iter_extra() {
+        for header in self.parse_extra() {
             match header {
-                [EXT_HEADER_FILENAME, data @ ..] => {
+                ExtraHeader::Filename(data) => {
                     raw_filename = data;
                 },
-                [EXT_HEADER_COMMENT, data @ ..] => {
-                    let comment = parse_str_nilterm(data, false, true);
+                ExtraHeader::Comment(data)|ExtraHeader::AmigaFilenote(data) => {
+                    let comment = decode_str_nilterm(data, true, true, encoding);
                     if !comment.is_empty() {
                         return Some(comment)
                     }
@@ -195,12 +721,58 @@ impl LhaHeader {
         if self.parse_os_type() == Ok(OsType::Amiga) {
             split_data_at_nil_or_end(raw_filename)
             .1
-            .map(|data| parse_str_nilterm(data, false, true))
+            .map(|data| decode_str_nilterm(data, true, true, encoding))
+            .filter(|comment| !comment.is_empty())
         }
         else {
             None
         }
     }
+    /// Returns the Amiga filenote of the files archived on [OsType::Amiga], decoded as `Latin1`.
+    ///
+    /// The filenote is found in the ["Comment"][parser::ext::EXT_HEADER_COMMENT] extra header, see
+    /// [ExtraHeader::AmigaFilenote], or in the filename field after the `nul` character.
+    /// The routine converts all control characters to `%xx` sequences. Empty filenotes are ignored.
+    ///
+    /// Returns `None` if the archive OS is not Amiga.
+    pub fn amiga_filenote(&self) -> Option<Cow<'_, str>> {
+        self.raw_amiga_filenote().map(|data| decode_str_nilterm(data, true, true, FilenameEncoding::Latin1))
+    }
+    /// Returns the raw bytes of the Amiga filenote, excluding the terminating `nul` character.
+    ///
+    /// See [LhaHeader::amiga_filenote] for more details.
+    pub fn raw_amiga_filenote(&self) -> Option<&[u8]> {
+        if self.os() != OsType::Amiga {
+            return None
+        }
+        let mut raw_filename = &self.filename[..];
+        for header in self.parse_extra() {
+            match header {
+                ExtraHeader::Filename(data) => {
+                    raw_filename = data;
+                }
+                ExtraHeader::AmigaFilenote(data) => {
+                    let note = split_data_at_nil_or_end(data).0;
+                    if !note.is_empty() {
+                        return Some(note)
+                    }
+                }
+                _ => {}
+            }
+        }
+        split_data_at_nil_or_end(raw_filename).1
+            .map(|data| split_data_at_nil_or_end(data).0)
+            .filter(|note| !note.is_empty())
+    }
+
+    /// Returns the metadata found in the extended area of level 0 and 1 headers made on Unix-like
+    /// systems.
+    fn unix_extended_area(&self) -> Option<UnixExtendedArea<'_>> {
+        match self.parse_extended_area()? {
+            ExtendedArea::Unix(area)|ExtendedArea::Osk(area) => Some(area),
+            _ => None
+        }
+    }
 }
 
 /// Returns a `NaiveDateTime` on success from MS-DOS timestamp format.
@@ -229,6 +801,16 @@ pub fn parse_msdos_datetime(ts: u32) -> Option<NaiveDateTime> {
     NaiveDate::from_ymd_opt(year, mon, day).and_then(|d| d.and_hms_opt(hour, min, sec))
 }
 
+/// Converts `NaiveDateTime` to the MS-DOS timestamp format, see [parse_msdos_datetime].
+///
+/// The seconds are rounded down to an even number. Returns `None` if the year is out of
+/// the range from 1980 to 2107.
+pub fn to_msdos_datetime(dt: NaiveDateTime) -> Option<u32> {
+    let year = u32::try_from(dt.year() - 1980).ok().filter(|&year| year <= 0x7f)?;
+    Some(year << 25 | dt.month() << 21 | dt.day() << 16 |
+         dt.hour() << 11 | dt.minute() << 5 | dt.second() >> 1)
+}
+
 /// Returns a `DateTime<Utc>` on success from Windows [FILETIME] format.
 ///
 /// [FILETIME]: https://docs.microsoft.com/en-us/windows/win32/api/minwinbase/ns-minwinbase-filetime
@@ -242,3 +824,413 @@ pub fn parse_win_filetime(filetime: u64) -> LocalResult<DateTime<Utc>> {
     }
     LocalResult::None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a level 2 header with the given raw extra headers.
+    fn header_with_extra(extra: &[&[u8]]) -> LhaHeader {
+        let mut extra_headers = Vec::new();
+        let mut first_header_len = 0;
+        for (i, data) in extra.iter().enumerate() {
+            let len = data.len() as u32 + 2;
+            if i == 0 {
+                first_header_len = len;
+            }
+            else {
+                extra_headers.extend_from_slice(&(len as u16).to_le_bytes());
+            }
+            extra_headers.extend_from_slice(data);
+        }
+        extra_headers.extend_from_slice(&[0, 0]);
+        LhaHeader {
+            level: 2,
+            os_type: b'U',
+            first_header_len,
+            extra_headers: extra_headers.into_boxed_slice(),
+            ..LhaHeader::default()
+        }
+    }
+
+    #[test]
+    fn unix_names_works() {
+        let header = header_with_extra(&[b"\x50\xa4\x81", b"\x52users", b"\x53r\xf3ot\x00"]);
+        assert_eq!(header.unix_mode(), Some(0o100644));
+        assert_eq!(header.unix_group_name().unwrap(), "users");
+        assert_eq!(header.unix_owner_name().unwrap(), "r%f3ot");
+        let header = header_with_extra(&[b"\x52", b"\x01foo"]);
+        assert_eq!(header.unix_group_name(), None);
+        assert_eq!(header.unix_owner_name(), None);
+        assert_eq!(header.parse_pathname().to_str().unwrap(), "foo");
+    }
+
+    #[test]
+    fn multi_disc_works() {
+        let header = header_with_extra(&[b"\x01foo", b"\x39\x03\x01"]);
+        assert_eq!(header.multi_disc(), Some(MultiDiscInfo { disc_number: 3, flags: 1, rest: &[] }));
+        let header = header_with_extra(&[b"\x01foo"]);
+        assert_eq!(header.multi_disc(), None);
+    }
+
+    #[test]
+    fn amiga_protection_works() {
+        let mut header = LhaHeader {
+            msdos_attrs: MsDosAttrs::from_bits_retain(0x52),
+            os_type: b'A',
+            ..LhaHeader::default()
+        };
+        assert_eq!(header.amiga_protection(), Some(AmigaProtection::SCRIPT|AmigaProtection::ARCHIVE|AmigaProtection::EXECUTE));
+        header.os_type = b'M';
+        assert_eq!(header.amiga_protection(), None);
+    }
+
+    #[test]
+    fn msdos_times_works() {
+        let mut raw = vec![0x41];
+        for ft in [0u64, 129_067_776_001_234_567, 129_067_776_000_000_000] {
+            raw.extend_from_slice(&ft.to_le_bytes());
+        }
+        let header = header_with_extra(&[&raw]);
+        let times = header.msdos_times().unwrap();
+        assert_eq!(times, MsDosTimes {
+            created: 0, last_modified: 129_067_776_001_234_567, last_access: 129_067_776_000_000_000
+        });
+        assert!(header.parse_created().is_none());
+        assert_eq!(header.parse_last_modified().to_utc().unwrap().to_rfc3339(), "2010-01-01T00:00:00.123456700+00:00");
+        assert_eq!(header.parse_last_access().to_utc().unwrap().to_rfc3339(), "2010-01-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn timestamps_works() {
+        let mut header = LhaHeader { level: 1, last_modified: 0x00214800, ..LhaHeader::default() };
+        let dt = NaiveDate::from_ymd_opt(1980, 1, 1).unwrap().and_hms_opt(9, 0, 0).unwrap();
+        assert_eq!(header.timestamps(), Timestamps {
+            last_modified: Some(Timestamp { time: dt.into(), source: TimestampSource::MsDosDateTime }),
+            created: None,
+            last_access: None
+        });
+        header.os_type = b'U';
+        header.extended_area = b"\x00\x00\x3b\x3d\x4b"[..].into();
+        let utc = |ts| TimestampResult::from(Utc.timestamp_opt(ts, 0));
+        assert_eq!(header.timestamps().last_modified,
+                   Some(Timestamp { time: utc(0x4b3d3b00), source: TimestampSource::ExtendedArea }));
+        let mut raw = vec![0x41];
+        for ft in [129_067_776_000_000_000u64, 0, 129_067_776_001_234_567] {
+            raw.extend_from_slice(&ft.to_le_bytes());
+        }
+        let mut header = header_with_extra(&[b"\x54\x00\x00\x00\x01", &raw]);
+        header.last_modified = 0x4b3d3b00;
+        let times = header.timestamps();
+        assert_eq!(times.last_modified,
+                   Some(Timestamp { time: utc(0x01000000), source: TimestampSource::UnixTimeExtra }));
+        assert_eq!(times.created,
+                   Some(Timestamp { time: utc(1262304000), source: TimestampSource::WindowsFileTime }));
+        assert_eq!(times.last_access.unwrap().source, TimestampSource::WindowsFileTime);
+        let header = header_with_extra(&[&raw]);
+        assert_eq!(header.timestamps().last_modified.unwrap().source, TimestampSource::UnixTime);
+    }
+
+    #[test]
+    fn msdos_datetime_works() {
+        let dt = NaiveDate::from_ymd_opt(2023, 7, 14).unwrap().and_hms_opt(13, 45, 31).unwrap();
+        let ts = to_msdos_datetime(dt).unwrap();
+        assert_eq!(parse_msdos_datetime(ts), dt.with_second(30));
+        let dt = NaiveDate::from_ymd_opt(1980, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        assert_eq!(to_msdos_datetime(dt), Some(0x0021_0000));
+        assert_eq!(to_msdos_datetime(dt - chrono::Duration::seconds(1)), None);
+        let dt = NaiveDate::from_ymd_opt(2108, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        assert_eq!(to_msdos_datetime(dt), None);
+    }
+
+    #[test]
+    fn last_modified_in_works() {
+        let dt = NaiveDate::from_ymd_opt(2023, 7, 14).unwrap().and_hms_opt(13, 45, 30).unwrap();
+        let mut header = LhaHeader {
+            level: 1,
+            last_modified: to_msdos_datetime(dt).unwrap(),
+            ..LhaHeader::default()
+        };
+        let ts = UNIX_EPOCH + Duration::from_secs(Utc.from_utc_datetime(&dt).timestamp() as u64);
+        let east = FixedOffset::east_opt(2 * 3600).unwrap();
+        assert_eq!(header.last_modified_in(&Utc), Some(ts));
+        assert_eq!(header.last_modified_in(&east), Some(ts - Duration::from_secs(2 * 3600)));
+        assert_eq!(header.last_modified_time(), header.last_modified_in(&Local));
+        header.last_modified = 0;
+        assert_eq!(header.last_modified_in(&Utc), None);
+        header.level = 2;
+        assert_eq!(header.last_modified_in(&east), Some(UNIX_EPOCH));
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn offset_datetime_works() {
+        use time::{OffsetDateTime, UtcOffset};
+        let header = LhaHeader {
+            level: 1,
+            last_modified: 0x00214800,
+            ..LhaHeader::default()
+        };
+        let offset = UtcOffset::from_hms(9, 0, 0).unwrap();
+        let dt = header.parse_last_modified().to_offset_datetime_in(offset).unwrap();
+        assert_eq!(dt.unix_timestamp(), 315532800);
+        assert_eq!(dt.offset(), offset);
+        let dt = header.parse_last_modified().to_offset_datetime().unwrap();
+        assert_eq!(dt.unix_timestamp(), 315532800 + 9 * 3600);
+        assert_eq!(dt.offset(), UtcOffset::UTC);
+        let header = LhaHeader { level: 2, last_modified: 1234567890, ..header };
+        assert_eq!(header.parse_last_modified().to_offset_datetime_in(offset),
+                   OffsetDateTime::from_unix_timestamp(1234567890).map(|dt| dt.to_offset(offset)).ok());
+        let times = MsDosTimes { last_modified: 129_067_776_001_234_567, ..MsDosTimes::default() };
+        assert_eq!(times.parse_last_modified().to_offset_datetime().unwrap().nanosecond(), 123456700);
+        assert_eq!(times.parse_created().to_offset_datetime(), None);
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn parse_pathname_sjis_works() {
+        let sep = std::path::MAIN_SEPARATOR;
+        // "表示" contains '\\' as the second byte of the first character
+        let header = header_with_extra(&[b"\x01\x95\x5c\x8e\xa6.txt", b"\x02\x83\x66\x81\x5b\x83\x5e\xff..\xff"]);
+        assert_eq!(header.parse_pathname_sjis().to_str().unwrap(), format!("データ{}表示.txt", sep));
+        // an invalid sequence is percent encoded
+        let header = LhaHeader {
+            filename: b"\x95\x5c\\\x1b/../tmp\xff\x8e"[..].into(),
+            ..LhaHeader::default()
+        };
+        assert_eq!(header.parse_pathname_sjis().to_str().unwrap(), format!("表{}%1b{}tmp{}%8e", sep, sep, sep));
+    }
+
+    #[test]
+    fn parse_pathname_with_works() {
+        let sep = std::path::MAIN_SEPARATOR;
+        let mut header = header_with_extra(&[b"\x01Gr\xfc\xdfe\x00note", b"\x02dir\xe9\xff\x01\xff"]);
+        header.os_type = b'A';
+        assert_eq!(header.parse_pathname().to_str().unwrap(), format!("dir%e9{}%01{}Gr%fc%dfe", sep, sep));
+        assert_eq!(header.parse_pathname_with(FilenameEncoding::Auto).to_str().unwrap(),
+                   format!("diré{}%01{}Grüße", sep, sep));
+        assert_eq!(header.parse_pathname_with(FilenameEncoding::Cp437).to_str().unwrap(),
+                   format!("dirΘ{}%01{}Grⁿ▀e", sep, sep));
+        assert_eq!(header.parse_pathname_with(FilenameEncoding::Utf8).to_str().unwrap(),
+                   format!("dir%e9{}%01{}Gr%fc%dfe", sep, sep));
+        assert_eq!(header.parse_comment_with(FilenameEncoding::Auto).unwrap(), "note");
+        let header = header_with_extra(&[b"\x3fna\xefve\n"]);
+        assert_eq!(header.parse_comment().unwrap(), "na%efve%0a");
+        assert_eq!(header.parse_comment_with(FilenameEncoding::Latin1).unwrap(), "naïve%0a");
+    }
+
+    #[test]
+    fn raw_filename_works() {
+        let sep = std::path::MAIN_SEPARATOR;
+        let mut header = header_with_extra(&[b"\x02sub\xe9\xff..\xff", b"\x01na/me\xfc\x00note"]);
+        assert_eq!(header.raw_filename_bytes(), b"na/me\xfc\x00note");
+        assert_eq!(header.raw_path_bytes(), Some(&b"sub\xe9\xff..\xff"[..]));
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            assert_eq!(header.parse_pathname_raw().as_os_str().as_bytes(), b"sub\xe9/na_me\xfc_note");
+        }
+        #[cfg(not(unix))]
+        assert_eq!(header.parse_pathname_raw().to_str().unwrap(), format!("sub%e9{}na_me%fc%00note", sep));
+        header.os_type = b'A';
+        assert_eq!(header.raw_filename_bytes(), b"na/me\xfc");
+        let header = LhaHeader {
+            filename: b"dir\\..\\x\x01y"[..].into(),
+            ..LhaHeader::default()
+        };
+        assert_eq!(header.raw_filename_bytes(), b"dir\\..\\x\x01y");
+        assert_eq!(header.raw_path_bytes(), None);
+        let expected = if cfg!(unix) { format!("dir{}x\x01y", sep) } else { format!("dir{}x%01y", sep) };
+        assert_eq!(header.parse_pathname_raw().to_str().unwrap(), expected);
+    }
+
+    #[test]
+    fn try_parse_pathname_works() {
+        use SanitizePolicy::*;
+        let sep = std::path::MAIN_SEPARATOR;
+        let header = header_with_extra(&[b"\x02dir\xff..\xff\x1b[0m\xff", b"\x01na/me\xe9"]);
+        let parse = |encoding, policy| header.try_parse_pathname(encoding, policy)
+                                             .map(|path| path.to_str().unwrap().to_string());
+        assert_eq!(parse(FilenameEncoding::Ascii, PercentEncode).unwrap(), header.parse_pathname().to_str().unwrap());
+        assert_eq!(parse(FilenameEncoding::Ascii, Replace('?')).unwrap(), format!("dir{}?[0m{}na?me?", sep, sep));
+        assert_eq!(parse(FilenameEncoding::Latin1, Replace('?')).unwrap(), format!("dir{}?[0m{}na?meé", sep, sep));
+        assert_eq!(parse(FilenameEncoding::Latin1, Replace('/')).unwrap(), format!("dir{}_[0m{}na_meé", sep, sep));
+        assert_eq!(parse(FilenameEncoding::Latin1, Raw).unwrap(), format!("dir{}\x1b[0m{}na_meé", sep, sep));
+        assert_eq!(parse(FilenameEncoding::Utf8, Raw).unwrap(), format!("dir{}\x1b[0m{}na_me\u{fffd}", sep, sep));
+        assert_eq!(parse(FilenameEncoding::Latin1, Error).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let header = header_with_extra(&[b"\x02dir\xff..\xff", b"\x01name\xe9"]);
+        assert!(header.try_parse_pathname(FilenameEncoding::Ascii, Error).is_err());
+        assert_eq!(header.try_parse_pathname(FilenameEncoding::Latin1, Error).unwrap().to_str().unwrap(),
+                   format!("dir{}nameé", sep));
+    }
+
+    #[test]
+    fn try_parse_pathname_traversal_works() {
+        let sep = std::path::MAIN_SEPARATOR;
+        let header = header_with_extra(&[b"\x02\xff..\xffdir\xff.\xff", b"\x01name"]);
+        let parse = |traversal| header.try_parse_pathname_traversal(FilenameEncoding::Ascii,
+                                                                     SanitizePolicy::PercentEncode,
+                                                                     traversal);
+        let (path, removed) = parse(TraversalPolicy::SilentlySkip).unwrap();
+        assert_eq!(path, header.parse_pathname());
+        assert_eq!(path.to_str().unwrap(), format!("dir{}name", sep));
+        assert_eq!(removed, [RemovedComponent::RootDir, RemovedComponent::ParentDir, RemovedComponent::CurDir]);
+        assert_eq!(parse(TraversalPolicy::Error).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let (path, removed) = parse(TraversalPolicy::Preserve).unwrap();
+        assert!(removed.is_empty());
+        assert!(path.has_root());
+        assert!(path.components().eq(std::path::Path::new("/../dir/./name").components()));
+        let header = header_with_extra(&[b"\x02dir\xff", b"\x01.."]);
+        assert_eq!(header.parse_pathname().to_str().unwrap(), "dir");
+        assert_eq!(header.parse_pathname_raw().to_str().unwrap(), "dir");
+        assert!(header.try_parse_pathname_traversal(FilenameEncoding::Utf8, SanitizePolicy::Error,
+                                                    TraversalPolicy::Error).is_err());
+    }
+
+    #[test]
+    fn path_components_works() {
+        let header = header_with_extra(&[b"\x02\xffdir/..\xffsub\x1b\xff", b"\x01na/me"]);
+        assert!(header.path_components().eq(["dir", "sub%1b", "na_me"]));
+        assert!(header.path_components().map(|name| name.into_owned()).collect::<PathBuf>()
+                      .iter().eq(header.parse_pathname().iter()));
+        assert!(matches!(header.path_components().next(), Some(Cow::Borrowed("dir"))));
+        let header = header_with_extra(&[b"\x01..", b"\x02dir"]);
+        assert!(header.path_components().eq(["dir"]));
+        let header = header_with_extra(&[b"\x01"]);
+        assert_eq!(header.path_components().count(), 0);
+        let header = LhaHeader { filename: b"./dir\\sub/name".to_vec().into_boxed_slice(), ..LhaHeader::default() };
+        assert!(header.path_components().eq(["dir", "sub", "name"]));
+        let header = header_with_extra(&[b"\x02C:\xffdir", b"\x01a:b"]);
+        if cfg!(windows) {
+            assert!(header.path_components().eq(["dir", "a_b"]));
+        }
+        else {
+            assert!(header.path_components().eq(["C:", "dir", "a:b"]));
+        }
+        assert!(header.path_components().map(|name| name.into_owned()).collect::<PathBuf>()
+                      .iter().eq(header.parse_pathname().iter()));
+    }
+
+    #[test]
+    fn unicode_names_works() {
+        let sep = std::path::MAIN_SEPARATOR;
+        let header = header_with_extra(&[b"\x02legacy\xff", b"\x01legacy.txt",
+                                         b"\x45d\x00i\x00r\x00\xff\xff.\x00.\x00\xff\xffs\x00u\x00b\x00",
+                                         b"\x44\x7a\x01\x1b\x00.\x00t\x00x\x00t\x00"]);
+        assert_eq!(header.parse_pathname().to_str().unwrap(), format!("dir{}sub{}ź%1b.txt", sep, sep));
+        assert_eq!(header.parse_pathname_with(FilenameEncoding::Latin1), header.parse_pathname());
+        assert!(header.path_components().eq(["dir", "sub", "ź%1b.txt"]));
+        assert_eq!(header.parse_pathname_raw().to_str().unwrap(), format!("legacy{}legacy.txt", sep));
+        let header = header_with_extra(&[b"\x02legacy\xff", b"\x01legacy.txt", b"\x44\x7a\x01"]);
+        assert_eq!(header.parse_pathname().to_str().unwrap(), format!("legacy{}ź", sep));
+        assert!(header.path_components().eq(["legacy", "ź"]));
+        let header = header_with_extra(&[b"\x02legacy\xff", b"\x01legacy.txt", b"\x45d\x00", b"\x44"]);
+        assert_eq!(header.parse_pathname().to_str().unwrap(), format!("d{}legacy.txt", sep));
+        assert!(header.path_components().eq(["d", "legacy.txt"]));
+        assert_eq!(header.try_parse_pathname(FilenameEncoding::Ascii, SanitizePolicy::Error).unwrap(),
+                   header.parse_pathname());
+    }
+
+    #[test]
+    fn symlink_target_works() {
+        let sep = std::path::MAIN_SEPARATOR;
+        let header = header_with_extra(&[b"\x50\xff\xa1", b"\x02dir\xfflink|..\xff", b"\x01./x\x1b"]);
+        assert_eq!(header.symlink_target().unwrap().to_str().unwrap(), format!("..{}x%1b", sep));
+        let header = header_with_extra(&[b"\x50\xa4\x81", b"\x01link|target"]);
+        assert_eq!(header.symlink_target(), None);
+        let header = header_with_extra(&[b"\x50\xff\xa1", b"\x01link|"]);
+        assert_eq!(header.symlink_target(), None);
+        let header = header_with_extra(&[b"\x01link|target"]);
+        assert_eq!(header.symlink_target(), None);
+    }
+
+    #[test]
+    fn file_type_works() {
+        assert_eq!(header_with_extra(&[b"\x50\xa4\x81"]).file_type(), EntryKind::File);
+        assert_eq!(header_with_extra(&[b"\x50\xed\x41"]).file_type(), EntryKind::Directory);
+        assert_eq!(header_with_extra(&[b"\x50\xff\xa1"]).file_type(), EntryKind::Symlink);
+        assert_eq!(header_with_extra(&[b"\x50\xa4\x11"]).file_type(), EntryKind::Special);
+        assert_eq!(header_with_extra(&[b"\x50\xa4\x01"]).file_type(), EntryKind::File);
+        let mut header = header_with_extra(&[b"\x50\xbf\xff"]);
+        header.os_type = b'K';
+        assert_eq!(header.file_type(), EntryKind::Directory);
+        header.os_type = b'U';
+        assert_eq!(header.file_type(), EntryKind::Special);
+        let mut header = header_with_extra(&[b"\xcc\x00\x00\x00\x00\x00\x00\x00\x80\x00"]);
+        header.os_type = b'9';
+        assert_eq!(header.file_type(), EntryKind::Directory);
+        let mut header = LhaHeader { compression: *b"-lhd-", ..LhaHeader::default() };
+        assert_eq!(header.file_type(), EntryKind::Directory);
+        header.compression = *b"-lh5-";
+        assert_eq!(header.file_type(), EntryKind::File);
+        header.msdos_attrs = MsDosAttrs::SUBDIR;
+        assert_eq!(header.file_type(), EntryKind::Directory);
+        header.msdos_attrs = MsDosAttrs::VOLUME;
+        assert_eq!(header.file_type(), EntryKind::Special);
+    }
+
+    #[test]
+    fn parse_comment_works() {
+        let header = header_with_extra(&[b"\x01foo", b"\x3fHello/World\x01\x00garbage"]);
+        assert_eq!(header.parse_comment().unwrap(), "Hello/World%01");
+        let header = header_with_extra(&[b"\x3f\x00", b"\x3fsecond"]);
+        assert_eq!(header.parse_comment().unwrap(), "second");
+        let header = header_with_extra(&[b"\x3f", b"\x01foo\x00note"]);
+        assert_eq!(header.parse_comment(), None);
+        let mut header = header_with_extra(&[b"\x3f", b"\x01foo\x00note\x00"]);
+        header.os_type = b'A';
+        assert_eq!(header.parse_comment().unwrap(), "note");
+        assert_eq!(header.parse_pathname().to_str().unwrap(), "foo");
+        let mut header = header_with_extra(&[b"\x01foo\x00"]);
+        header.os_type = b'A';
+        assert_eq!(header.parse_comment(), None);
+    }
+
+    #[test]
+    fn amiga_filenote_works() {
+        let mut header = header_with_extra(&[b"\x01foo", b"\x3fGr\xfc\xdfe\x00garbage"]);
+        assert_eq!(header.amiga_filenote(), None);
+        assert_eq!(header.parse_extra().nth(1), Some(ExtraHeader::Comment(b"Gr\xfc\xdfe\x00garbage")));
+        header.os_type = b'A';
+        assert_eq!(header.parse_extra().nth(1), Some(ExtraHeader::AmigaFilenote(b"Gr\xfc\xdfe\x00garbage")));
+        assert_eq!(header.parse_extra().nth(1).unwrap().id(), parser::ext::EXT_HEADER_COMMENT);
+        assert_eq!(header.raw_amiga_filenote(), Some(&b"Gr\xfc\xdfe"[..]));
+        assert_eq!(header.amiga_filenote().unwrap(), "Grüße");
+        assert_eq!(header.parse_comment().unwrap(), "Gr%fc%dfe");
+        let mut header = header_with_extra(&[b"\x01foo\x00note\x00"]);
+        header.os_type = b'A';
+        assert_eq!(header.amiga_filenote().unwrap(), "note");
+        let header = LhaHeader {
+            level: 0,
+            filename: b"dir/foo\x00\x00"[..].into(),
+            os_type: b'A',
+            ..LhaHeader::default()
+        };
+        assert_eq!(header.amiga_filenote(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_works() {
+        let mut header = header_with_extra(&[b"\x01foo", b"\x50\xa4\x81", b"\x51\x64\x00\xe8\x03"]);
+        header.compression = *b"-lh5-";
+        header.msdos_attrs = MsDosAttrs::ARCHIVE|MsDosAttrs::READ_ONLY;
+        let json = serde_json::to_string(&header).unwrap();
+        let header2: LhaHeader = serde_json::from_str(&json).unwrap();
+        assert_eq!(header2.to_bytes().unwrap(), header.to_bytes().unwrap());
+        assert_eq!(serde_json::to_value(header.msdos_attrs).unwrap(), "READ_ONLY | ARCHIVE");
+        assert_eq!(serde_json::to_value(header.compression_method().unwrap()).unwrap(), "Lh5");
+        assert_eq!(serde_json::to_value(header.os()).unwrap(), "Unix");
+        assert_eq!(serde_json::to_value(header.file_type()).unwrap(), "File");
+        let extra: Vec<_> = header.parse_extra().collect();
+        assert_eq!(serde_json::to_string(&extra[1..]).unwrap(),
+                   r#"[{"UnixPerm":33188},{"UidGid":{"gid":100,"uid":1000}}]"#);
+        let ts = header.parse_last_modified();
+        let json = serde_json::to_string(&ts).unwrap();
+        assert_eq!(json, r#"{"Utc":"1970-01-01T00:00:00Z"}"#);
+        assert_eq!(serde_json::from_str::<TimestampResult>(&json).unwrap(), ts);
+        let times = MsDosTimes { created: 1, last_modified: 2, last_access: 3 };
+        let json = serde_json::to_string(&times).unwrap();
+        assert_eq!(serde_json::from_str::<MsDosTimes>(&json).unwrap(), times);
+    }
+}
@@ -0,0 +1,79 @@
+use bitflags::bitflags;
+
+bitflags! {
+    /// Amiga file protection bits, stored by LhA for Amiga in the MS-DOS attribute field.
+    ///
+    /// The same as in the Amiga file system, the [AmigaProtection::READ], [AmigaProtection::WRITE],
+    /// [AmigaProtection::EXECUTE] and [AmigaProtection::DELETE] bits are active low: a set bit
+    /// *denies* the access.
+    #[derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct AmigaProtection: u8 {
+        const DELETE  = 0b00000001;
+        const EXECUTE = 0b00000010;
+        const WRITE   = 0b00000100;
+        const READ    = 0b00001000;
+        const ARCHIVE = 0b00010000;
+        const PURE    = 0b00100000;
+        const SCRIPT  = 0b01000000;
+        const HOLD    = 0b10000000;
+    }
+}
+
+impl AmigaProtection {
+    /// Returns `true` if the file can be read.
+    pub fn is_readable(self) -> bool {
+        !self.contains(AmigaProtection::READ)
+    }
+    /// Returns `true` if the file can be written to.
+    pub fn is_writable(self) -> bool {
+        !self.contains(AmigaProtection::WRITE)
+    }
+    /// Returns `true` if the file can be executed, either as a program or as a script.
+    pub fn is_executable(self) -> bool {
+        !self.contains(AmigaProtection::EXECUTE) || self.contains(AmigaProtection::SCRIPT)
+    }
+    /// Converts the protection bits to the Unix permission bits, excluding the file type bits.
+    ///
+    /// The Amiga protection bits apply to the owner only. The read and execute permissions are
+    /// granted to everyone, the write permission only to the owner, the same as with
+    /// the common `umask` of `022`.
+    ///
+    /// ```
+    /// use delharc::header::AmigaProtection;
+    ///
+    /// assert_eq!(AmigaProtection::empty().to_unix_perms(), 0o755);
+    /// assert_eq!(AmigaProtection::EXECUTE.to_unix_perms(), 0o644);
+    /// assert_eq!((AmigaProtection::EXECUTE|AmigaProtection::WRITE|AmigaProtection::DELETE).to_unix_perms(), 0o444);
+    /// ```
+    pub fn to_unix_perms(self) -> u16 {
+        let mut perms = 0;
+        if self.is_readable() {
+            perms |= 0o444;
+        }
+        if self.is_writable() {
+            perms |= 0o200;
+        }
+        if self.is_executable() {
+            perms |= 0o111;
+        }
+        perms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amiga_protection_works() {
+        let prot = AmigaProtection::ARCHIVE|AmigaProtection::SCRIPT|AmigaProtection::EXECUTE;
+        assert!(prot.is_readable() && prot.is_writable() && prot.is_executable());
+        assert_eq!(prot.to_unix_perms(), 0o755);
+        let prot = AmigaProtection::READ|AmigaProtection::WRITE|AmigaProtection::EXECUTE;
+        assert!(!prot.is_readable() && !prot.is_writable() && !prot.is_executable());
+        assert_eq!(prot.to_unix_perms(), 0);
+        assert_eq!(AmigaProtection::READ.to_unix_perms(), 0o311);
+        assert_eq!(AmigaProtection::from_bits_retain(0x0F).to_unix_perms(), 0);
+    }
+}
@@ -0,0 +1,498 @@
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+use chrono::prelude::*;
+use super::*;
+use super::ext::*;
+
+/// A builder of [LhaHeader]s, for archive writers and test generators.
+///
+/// By default a level 2 header of an empty file with the `-lh0-` compression method is built.
+/// The extra headers are created from the given fields, the same way LHa for UNIX does.
+///
+/// ```
+/// use delharc::header::*;
+///
+/// let header = LhaHeaderBuilder::new()
+///     .path("dir/file.txt")
+///     .compression(CompressionMethod::Lh5)
+///     .compressed_size(100)
+///     .original_size(200)
+///     .unix_mode(0o100644)
+///     .build()
+///     .unwrap();
+/// assert_eq!(header.level, 2);
+/// assert_eq!(header.parse_pathname(), std::path::Path::new("dir").join("file.txt"));
+/// assert_eq!(header.unix_mode(), Some(0o100644));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LhaHeaderBuilder {
+    level: u8,
+    compression: [u8;5],
+    compressed_size: u64,
+    original_size: u64,
+    file_crc: u16,
+    filename: Vec<u8>,
+    directory: Vec<u8>,
+    last_modified: Option<SystemTime>,
+    msdos_attrs: MsDosAttrs,
+    os_type: u8,
+    unix_mode: Option<u16>,
+    unix_uid_gid: Option<(u16, u16)>,
+    msdos_times: Option<MsDosTimes>,
+    comment: Option<Vec<u8>>,
+    comment_text: Option<(String, FilenameEncoding)>,
+    extra: Vec<(u8, Vec<u8>)>
+}
+
+impl Default for LhaHeaderBuilder {
+    fn default() -> Self {
+        LhaHeaderBuilder::new()
+    }
+}
+
+impl LhaHeaderBuilder {
+    /// Creates a new builder with default values.
+    pub fn new() -> Self {
+        LhaHeaderBuilder {
+            level: 2,
+            compression: *b"-lh0-",
+            compressed_size: 0,
+            original_size: 0,
+            file_crc: 0,
+            filename: Vec::new(),
+            directory: Vec::new(),
+            last_modified: None,
+            msdos_attrs: MsDosAttrs::ARCHIVE,
+            os_type: OsType::Generic.into(),
+            unix_mode: None,
+            unix_uid_gid: None,
+            msdos_times: None,
+            comment: None,
+            comment_text: None,
+            extra: Vec::new()
+        }
+    }
+    /// Sets the header level: 0, 1, 2 or 3.
+    pub fn level(mut self, level: u8) -> Self {
+        self.level = level;
+        self
+    }
+    /// Sets the compression method.
+    pub fn compression(mut self, compression: CompressionMethod) -> Self {
+        self.compression = compression.into();
+        self
+    }
+    /// Sets the raw compression method identifier.
+    pub fn raw_compression(mut self, compression: [u8;5]) -> Self {
+        self.compression = compression;
+        self
+    }
+    /// Sets the compressed file size.
+    pub fn compressed_size(mut self, size: u64) -> Self {
+        self.compressed_size = size;
+        self
+    }
+    /// Sets the original file size.
+    pub fn original_size(mut self, size: u64) -> Self {
+        self.original_size = size;
+        self
+    }
+    /// Sets the uncompressed file's CRC-16.
+    pub fn file_crc(mut self, crc: u16) -> Self {
+        self.file_crc = crc;
+        self
+    }
+    /// Sets the path name, with components separated by `/`.
+    ///
+    /// The last component becomes the file name, the preceding ones the directory name.
+    pub fn path(self, path: &str) -> Self {
+        let (directory, filename) = match path.rfind('/') {
+            Some(index) => (&path[..index], &path[index + 1..]),
+            None => ("", path)
+        };
+        let directory: Vec<u8> = directory.bytes().map(|c| if c == b'/' { 0xFF } else { c }).collect();
+        self.raw_directory(&directory).raw_filename(filename.as_bytes())
+    }
+    /// Sets the raw file name.
+    pub fn raw_filename(mut self, filename: &[u8]) -> Self {
+        self.filename = filename.to_vec();
+        self
+    }
+    /// Sets the raw directory name, with components separated by `0xFF`.
+    pub fn raw_directory(mut self, directory: &[u8]) -> Self {
+        self.directory = directory.to_vec();
+        if !self.directory.is_empty() && self.directory.last() != Some(&0xFF) {
+            self.directory.push(0xFF);
+        }
+        self
+    }
+    /// Sets the file's last modified timestamp. If not set, the timestamp field is `0`.
+    ///
+    /// Level 0 and 1 headers store the timestamp in the MS-DOS format in the local time zone,
+    /// level 1 headers also get the ["Unix Time"][EXT_HEADER_UNIX_TIME] extra header.
+    pub fn last_modified(mut self, last_modified: SystemTime) -> Self {
+        self.last_modified = Some(last_modified);
+        self
+    }
+    /// Sets the fields from the file system `metadata`: the last modified timestamp, if it can be
+    /// represented, and the read-only and directory attributes. A directory also gets the `-lhd-`
+    /// compression method.
+    ///
+    /// On Unix the OS type is set to [OsType::Unix] and the file mode as well as the user and group
+    /// identifiers, if they fit in 16 bits, are stored in the extra headers.
+    ///
+    /// ```no_run
+    /// use delharc::header::LhaHeaderBuilder;
+    ///
+    /// let metadata = std::fs::metadata("file.txt")?;
+    /// let header = LhaHeaderBuilder::new().path("file.txt").metadata(&metadata).build()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn metadata(mut self, metadata: &std::fs::Metadata) -> Self {
+        if let Some(modified) = metadata.modified().ok().filter(|time| {
+            time.duration_since(UNIX_EPOCH).map_or(false, |d| d.as_secs() <= u32::MAX as u64)
+        }) {
+            self.last_modified = Some(modified);
+        }
+        if metadata.permissions().readonly() {
+            self.msdos_attrs |= MsDosAttrs::READ_ONLY;
+        }
+        if metadata.is_dir() {
+            self.msdos_attrs |= MsDosAttrs::SUBDIR;
+            self.compression = *CompressionMethod::Lhd.as_identifier();
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            self.os_type = OsType::Unix.into();
+            self.unix_mode = u16::try_from(metadata.mode()).ok();
+            if let (Ok(uid), Ok(gid)) = (u16::try_from(metadata.uid()), u16::try_from(metadata.gid())) {
+                self.unix_uid_gid = Some((uid, gid));
+            }
+        }
+        self
+    }
+    /// Sets the MS-DOS attributes.
+    pub fn msdos_attrs(mut self, attrs: MsDosAttrs) -> Self {
+        self.msdos_attrs = attrs;
+        self
+    }
+    /// Sets the OS type, [OsType::Generic] by default.
+    pub fn os_type(mut self, os_type: OsType) -> Self {
+        self.os_type = os_type.into();
+        self
+    }
+    /// Sets the Unix file mode, stored in the ["Unix Permission"][EXT_HEADER_UNIX_PERM] extra header.
+    pub fn unix_mode(mut self, mode: u16) -> Self {
+        self.unix_mode = Some(mode);
+        self
+    }
+    /// Sets the Unix user and group identifiers, stored in the ["Unix UID GID"][EXT_HEADER_UNIX_UIDGID]
+    /// extra header.
+    pub fn unix_uid_gid(mut self, uid: u16, gid: u16) -> Self {
+        self.unix_uid_gid = Some((uid, gid));
+        self
+    }
+    /// Sets the Windows timestamps, stored in the ["MS-DOS Time"][EXT_HEADER_MSDOS_TIME] extra header.
+    pub fn msdos_times(mut self, times: MsDosTimes) -> Self {
+        self.msdos_times = Some(times);
+        self
+    }
+    /// Sets the raw file comment, stored in the ["Comment"][EXT_HEADER_COMMENT] extra header.
+    pub fn comment(mut self, comment: &[u8]) -> Self {
+        self.comment = Some(comment.to_vec());
+        self.comment_text = None;
+        self
+    }
+    /// Sets the file comment, encoded with the given `encoding` when the header is built and stored
+    /// in the ["Comment"][EXT_HEADER_COMMENT] extra header. [FilenameEncoding::Auto] selects
+    /// the encoding from the OS type, see [FilenameEncoding::from_os_type].
+    ///
+    /// The comment can be read back with [LhaHeader::parse_comment_with].
+    pub fn comment_with(mut self, comment: &str, encoding: FilenameEncoding) -> Self {
+        self.comment_text = Some((comment.to_owned(), encoding));
+        self.comment = None;
+        self
+    }
+    /// Appends a custom extra header with the identifier `id`.
+    pub fn extra_header(mut self, id: u8, data: &[u8]) -> Self {
+        self.extra.push((id, data.to_vec()));
+        self
+    }
+    /// Validates the fields and builds the header.
+    ///
+    /// # Errors
+    /// Returns an error of the kind [io::ErrorKind::InvalidInput] if any of the fields is out of
+    /// range for the selected level, e.g. the timestamp can't be represented, level 0 headers
+    /// are requested with fields that need extra headers, the comment can't be encoded or the header
+    /// would be too long.
+    pub fn build(mut self) -> io::Result<LhaHeader> {
+        let level = self.level;
+        if level > 3 {
+            return Err(invalid_input("unknown header level"))
+        }
+        if self.filename.is_empty() {
+            return Err(invalid_input("file name must not be empty"))
+        }
+        let unix_time = match self.last_modified {
+            Some(last_modified) => Some(last_modified.duration_since(UNIX_EPOCH).ok()
+                                    .and_then(|d| u32::try_from(d.as_secs()).ok())
+                                    .ok_or_else(|| invalid_input("timestamp out of range"))?),
+            None => None
+        };
+        let mut extra: Vec<(u8, Vec<u8>)> = Vec::new();
+        let mut filename = Vec::new();
+        let last_modified = if level < 2 {
+            if level == 0 {
+                filename.extend(self.directory.iter().map(|&c| if c == 0xFF { b'\\' } else { c }));
+            }
+            filename.extend_from_slice(&self.filename);
+            match self.last_modified {
+                Some(last_modified) => {
+                    let local = DateTime::<Local>::from(last_modified).naive_local();
+                    to_msdos_datetime(local).ok_or_else(|| invalid_input("timestamp out of range"))?
+                }
+                None => 0
+            }
+        }
+        else {
+            extra.push((EXT_HEADER_COMMON, vec![0, 0]));
+            extra.push((EXT_HEADER_FILENAME, self.filename));
+            unix_time.unwrap_or(0)
+        };
+        if level != 0 && !self.directory.is_empty() {
+            extra.push((EXT_HEADER_PATH, self.directory));
+        }
+        if let Some((comment, encoding)) = self.comment_text.take() {
+            self.comment = Some(encode_comment(&comment, encoding.resolve(OsType::from_raw(self.os_type)))?);
+        }
+        if let Some(comment) = self.comment {
+            extra.push((EXT_HEADER_COMMENT, comment));
+        }
+        if self.msdos_attrs.bits() > 0xFF {
+            extra.push((EXT_HEADER_MSDOS_ATTRS, self.msdos_attrs.bits().to_le_bytes().to_vec()));
+        }
+        if let Some(times) = self.msdos_times {
+            let mut data = Vec::with_capacity(24);
+            for time in [times.created, times.last_modified, times.last_access] {
+                data.extend_from_slice(&time.to_le_bytes());
+            }
+            extra.push((EXT_HEADER_MSDOS_TIME, data));
+        }
+        if level >= 2 && (self.compressed_size > u32::MAX as u64 || self.original_size > u32::MAX as u64) {
+            let mut data = Vec::with_capacity(16);
+            data.extend_from_slice(&self.compressed_size.to_le_bytes());
+            data.extend_from_slice(&self.original_size.to_le_bytes());
+            extra.push((EXT_HEADER_MSDOS_SIZE, data));
+        }
+        if let Some(mode) = self.unix_mode {
+            extra.push((EXT_HEADER_UNIX_PERM, mode.to_le_bytes().to_vec()));
+        }
+        if let Some((uid, gid)) = self.unix_uid_gid {
+            let mut data = gid.to_le_bytes().to_vec();
+            data.extend_from_slice(&uid.to_le_bytes());
+            extra.push((EXT_HEADER_UNIX_UIDGID, data));
+        }
+        if let (1, Some(unix_time)) = (level, unix_time) {
+            extra.push((EXT_HEADER_UNIX_TIME, unix_time.to_le_bytes().to_vec()));
+        }
+        extra.extend(self.extra);
+        if level == 0 && !extra.is_empty() {
+            return Err(invalid_input("level 0 headers can't have extra headers"))
+        }
+
+        let (first_header_len, extra_headers) = chain_extra_headers(level, extra)?;
+        let header = LhaHeader {
+            level,
+            compression: self.compression,
+            compressed_size: self.compressed_size,
+            original_size: self.original_size,
+            filename: filename.into_boxed_slice(),
+            msdos_attrs: self.msdos_attrs,
+            last_modified,
+            os_type: self.os_type,
+            file_crc: self.file_crc,
+            extended_area: Box::new([]),
+            first_header_len,
+            extra_headers: extra_headers.into_boxed_slice(),
+            warnings: Vec::new(),
+            raw_header: None,
+            header_size: 0,
+            header_offset: None
+        };
+        // validates the lengths of the header
+        let header_size = header.to_bytes()?.len() as u64;
+        Ok(LhaHeader { header_size, ..header })
+    }
+}
+
+/// Serializes extra headers, each followed by the length of the next one.
+pub(super) fn chain_extra_headers(level: u8, extra: Vec<(u8, Vec<u8>)>) -> io::Result<(u32, Vec<u8>)> {
+    let counter_size = if level == 3 { 4 } else { 2 };
+    let max_len = if level == 3 { u32::MAX as usize } else { u16::MAX as usize };
+    let header_len = |data: &Vec<u8>| {
+        let len = 1 + data.len() + counter_size;
+        if len > max_len {
+            return Err(invalid_input("extra header too long"))
+        }
+        Ok(len as u32)
+    };
+    let first_header_len = match extra.first() {
+        Some((_, data)) => header_len(data)?,
+        None => 0
+    };
+    let mut buf = Vec::with_capacity(extra.iter().map(|(_, data)| data.len() + 5).sum());
+    for (index, (id, data)) in extra.iter().enumerate() {
+        let next_len = match extra.get(index + 1) {
+            Some((_, data)) => header_len(data)?,
+            None => 0
+        };
+        buf.push(*id);
+        buf.extend_from_slice(data);
+        if level == 3 {
+            buf.extend_from_slice(&next_len.to_le_bytes());
+        }
+        else {
+            buf.extend_from_slice(&(next_len as u16).to_le_bytes());
+        }
+    }
+    Ok((first_header_len, buf))
+}
+
+/// Encodes the comment text with a resolved `encoding`.
+pub(super) fn encode_comment(comment: &str, encoding: FilenameEncoding) -> io::Result<Vec<u8>> {
+    if comment.contains('\0') {
+        return Err(invalid_input("comment must not contain nul characters"))
+    }
+    encoding.encode(comment).map(Cow::into_owned)
+            .ok_or_else(|| invalid_input("comment can't be represented in the encoding"))
+}
+
+fn invalid_input(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::Duration;
+
+    fn reparse(header: &LhaHeader) -> LhaHeader {
+        LhaHeader::read(&header.to_bytes().unwrap()[..]).unwrap().unwrap()
+    }
+
+    #[test]
+    fn header_builder_comment_with() {
+        let header = LhaHeaderBuilder::new().path("name").os_type(OsType::Unix).comment(b"raw")
+                     .comment_with("zażółć", FilenameEncoding::Auto).build().unwrap();
+        let header = reparse(&header);
+        assert_eq!(header.get_extra(EXT_HEADER_COMMENT), Some("zażółć".as_bytes()));
+        assert_eq!(header.parse_comment_with(FilenameEncoding::Utf8).unwrap(), "zażółć");
+        let header = LhaHeaderBuilder::new().path("name").comment_with("Grüße", FilenameEncoding::Cp437)
+                     .build().unwrap();
+        assert_eq!(reparse(&header).parse_comment_with(FilenameEncoding::Cp437).unwrap(), "Grüße");
+        let header = LhaHeaderBuilder::new().path("name").comment_with("raw", FilenameEncoding::Utf8)
+                     .comment(b"raw").build().unwrap();
+        assert_eq!(header.get_extra(EXT_HEADER_COMMENT), Some(&b"raw"[..]));
+        for (comment, encoding) in [("Grüße", FilenameEncoding::Ascii), ("a\0b", FilenameEncoding::Utf8)] {
+            let err = LhaHeaderBuilder::new().path("name").comment_with(comment, encoding).build().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+        let err = LhaHeaderBuilder::new().level(0).path("name").comment_with("note", FilenameEncoding::Utf8)
+                  .build().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn header_builder_works() {
+        let ts = UNIX_EPOCH + Duration::from_secs(1_262_304_000);
+        for level in 1..=3 {
+            let header = LhaHeaderBuilder::new()
+                .level(level)
+                .path("dir/sub/name.txt")
+                .compression(CompressionMethod::Lh5)
+                .compressed_size(1234)
+                .original_size(5678)
+                .file_crc(0xBEEF)
+                .last_modified(ts)
+                .os_type(OsType::Unix)
+                .unix_mode(0o100644)
+                .unix_uid_gid(1000, 100)
+                .comment(b"hello")
+                .build()
+                .unwrap();
+            let header = reparse(&header);
+            assert_eq!(header.level, level);
+            assert_eq!(header.compression_method().unwrap(), CompressionMethod::Lh5);
+            assert_eq!(header.compressed_size, 1234);
+            assert_eq!(header.original_size, 5678);
+            assert_eq!(header.file_crc, 0xBEEF);
+            assert_eq!(header.os(), OsType::Unix);
+            assert_eq!(header.parse_pathname(), Path::new("dir").join("sub").join("name.txt"));
+            assert_eq!(header.unix_mode(), Some(0o100644));
+            assert_eq!(header.unix_uid_gid(), Some((1000, 100)));
+            assert_eq!(header.unix_last_modified(), Some(ts));
+            assert_eq!(header.parse_comment().unwrap(), "hello");
+            assert_eq!(header.msdos_attrs, MsDosAttrs::ARCHIVE);
+        }
+        let header = LhaHeaderBuilder::new().level(0).path("dir/name").last_modified(ts).build().unwrap();
+        assert_eq!(&header.filename[..], b"dir\\name");
+        let header = reparse(&header);
+        assert_eq!(header.parse_pathname(), Path::new("dir").join("name"));
+        assert_eq!(header.parse_last_modified().to_naive_local(),
+                   Some(DateTime::<Local>::from(ts).naive_local()));
+        let header = LhaHeaderBuilder::new().path("name").build().unwrap();
+        assert_eq!(header.level, 2);
+        assert_eq!(header.last_modified, 0);
+        assert_eq!(header.compression_method().unwrap(), CompressionMethod::Lh0);
+        assert_eq!(reparse(&header).parse_pathname(), Path::new("name"));
+    }
+
+    #[test]
+    fn header_builder_metadata() {
+        let metadata = std::fs::metadata("Cargo.toml").unwrap();
+        let header = LhaHeaderBuilder::new().path("Cargo.toml").metadata(&metadata).build().unwrap();
+        assert_eq!(header.last_modified_time(), metadata.modified().ok().map(|time| {
+            UNIX_EPOCH + Duration::from_secs(time.duration_since(UNIX_EPOCH).unwrap().as_secs())
+        }));
+        assert!(!header.is_directory());
+        #[cfg(unix)]
+        {
+            assert_eq!(header.os(), OsType::Unix);
+            assert_eq!(header.unix_mode().map(|mode| mode & 0o170000), Some(0o100000));
+        }
+        let metadata = std::fs::metadata("src").unwrap();
+        let header = LhaHeaderBuilder::new().path("src").metadata(&metadata).build().unwrap();
+        assert!(header.is_directory());
+        assert!(header.msdos_attrs.contains(MsDosAttrs::SUBDIR));
+    }
+
+    #[test]
+    fn header_builder_large_sizes() {
+        let header = LhaHeaderBuilder::new().path("big").compressed_size(5 << 32).original_size(6 << 32)
+                                           .build().unwrap();
+        let header = reparse(&header);
+        assert_eq!(header.compressed_size, 5 << 32);
+        assert_eq!(header.original_size, 6 << 32);
+        let builder = LhaHeaderBuilder::new().level(1).path("big").original_size(1 << 32);
+        assert_eq!(builder.build().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn header_builder_validates() {
+        let invalid = |builder: LhaHeaderBuilder| {
+            builder.build().err().map(|err| err.kind()) == Some(io::ErrorKind::InvalidInput)
+        };
+        assert!(invalid(LhaHeaderBuilder::new()));
+        assert!(invalid(LhaHeaderBuilder::new().path("dir/")));
+        assert!(invalid(LhaHeaderBuilder::new().path("name").level(4)));
+        assert!(invalid(LhaHeaderBuilder::new().path("name").level(0).unix_mode(0o644)));
+        assert!(invalid(LhaHeaderBuilder::new().path("name").level(0).msdos_attrs(MsDosAttrs::from_bits_retain(0x100))));
+        assert!(invalid(LhaHeaderBuilder::new().path("name").level(0).last_modified(UNIX_EPOCH)));
+        assert!(invalid(LhaHeaderBuilder::new().path("name").last_modified(UNIX_EPOCH - Duration::from_secs(1))));
+        assert!(invalid(LhaHeaderBuilder::new().path(&"a".repeat(300)).level(1)));
+        assert!(invalid(LhaHeaderBuilder::new().path("name").extra_header(0x7f, &[0; 65533])));
+        assert!(!invalid(LhaHeaderBuilder::new().path("name").level(3).extra_header(0x7f, &[0; 65533])));
+    }
+}
@@ -0,0 +1,232 @@
+use std::borrow::Cow;
+use super::OsType;
+
+/// The character encoding of file names and comments.
+///
+/// LHA headers don't specify the encoding of names, it depends on the system the archive was
+/// created on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FilenameEncoding {
+    /// Only printable ASCII characters are preserved, all other bytes are converted to `%xx`
+    /// sequences.
+    #[default]
+    Ascii,
+    /// UTF-8.
+    Utf8,
+    /// ISO-8859-1, used on Amiga.
+    Latin1,
+    /// IBM PC code page 437, used on MS-DOS in the US.
+    Cp437,
+    /// IBM PC code page 850, used on MS-DOS and OS/2 in Western Europe.
+    Cp850,
+    /// Any encoding from the [encoding_rs] crate, e.g. `SHIFT_JIS` or `KOI8_R`.
+    #[cfg(feature = "encoding")]
+    Encoding(&'static encoding_rs::Encoding),
+    /// The encoding is selected from the header's OS type with [FilenameEncoding::from_os_type].
+    Auto
+}
+
+impl FilenameEncoding {
+    /// Returns the most probable encoding of names in the archives created on the `os` system.
+    ///
+    /// | OS type                                           | encoding                              |
+    /// |---------------------------------------------------|---------------------------------------|
+    /// | `Generic`, `MsDos`, `Win95`, `WinNt`, `Human68k`  | `Shift_JIS` or `Ascii`                |
+    /// | `Os2`                                             | `Cp850`                               |
+    /// | `MacOs`                                           | `macintosh` or `Ascii`                |
+    /// | `Amiga`                                           | `Latin1`                              |
+    /// | `Unix`, `Osk`                                     | `Utf8`                                |
+    /// | other                                             | `Ascii`                               |
+    ///
+    /// `Shift_JIS` and `macintosh` encodings are only available with the `encoding` feature.
+    pub fn from_os_type(os: OsType) -> FilenameEncoding {
+        match os {
+            #[cfg(feature = "encoding")]
+            OsType::Generic|OsType::MsDos|OsType::Win95|OsType::WinNt|OsType::Human68k => {
+                FilenameEncoding::Encoding(encoding_rs::SHIFT_JIS)
+            }
+            #[cfg(feature = "encoding")]
+            OsType::MacOs => FilenameEncoding::Encoding(encoding_rs::MACINTOSH),
+            OsType::Os2 => FilenameEncoding::Cp850,
+            OsType::Amiga => FilenameEncoding::Latin1,
+            OsType::Unix|OsType::Osk => FilenameEncoding::Utf8,
+            _ => FilenameEncoding::Ascii
+        }
+    }
+
+    /// Resolves [FilenameEncoding::Auto] to a specific encoding.
+    pub(super) fn resolve(self, os: OsType) -> FilenameEncoding {
+        match self {
+            FilenameEncoding::Auto => FilenameEncoding::from_os_type(os),
+            encoding => encoding
+        }
+    }
+
+    /// Decodes `data` without any sanitization. Returns `None` if `data` is not valid in this
+    /// encoding.
+    pub(super) fn decode(self, data: &[u8]) -> Option<Cow<'_, str>> {
+        match self {
+            FilenameEncoding::Ascii|FilenameEncoding::Auto => {
+                if data.is_ascii() {
+                    std::str::from_utf8(data).ok().map(Cow::Borrowed)
+                }
+                else {
+                    None
+                }
+            }
+            FilenameEncoding::Utf8 => std::str::from_utf8(data).ok().map(Cow::Borrowed),
+            FilenameEncoding::Latin1 => Some(decode_table(data, |b| b as char)),
+            FilenameEncoding::Cp437 => Some(decode_table(data, |b| CP437[b as usize - 0x80])),
+            FilenameEncoding::Cp850 => Some(decode_table(data, |b| CP850[b as usize - 0x80])),
+            #[cfg(feature = "encoding")]
+            FilenameEncoding::Encoding(encoding) => {
+                encoding.decode_without_bom_handling_and_without_replacement(data)
+            }
+        }
+    }
+
+    /// Encodes `text`. Returns `None` if `text` can't be represented in this encoding.
+    pub(super) fn encode(self, text: &str) -> Option<Cow<'_, [u8]>> {
+        match self {
+            FilenameEncoding::Ascii|FilenameEncoding::Auto => {
+                text.is_ascii().then_some(Cow::Borrowed(text.as_bytes()))
+            }
+            FilenameEncoding::Utf8 => Some(Cow::Borrowed(text.as_bytes())),
+            FilenameEncoding::Latin1 => encode_table(text, |c| u8::try_from(c as u32).ok()),
+            FilenameEncoding::Cp437 => encode_table(text, |c| table_position(&CP437, c)),
+            FilenameEncoding::Cp850 => encode_table(text, |c| table_position(&CP850, c)),
+            #[cfg(feature = "encoding")]
+            FilenameEncoding::Encoding(encoding) => {
+                let (data, used, had_errors) = encoding.encode(text);
+                (!had_errors && used == encoding).then_some(data)
+            }
+        }
+    }
+
+    /// Decodes `data`, replacing invalid sequences with `U+FFFD`.
+    pub(super) fn decode_lossy(self, data: &[u8]) -> Cow<'_, str> {
+        if let Some(name) = self.decode(data) {
+            return name
+        }
+        match self {
+            #[cfg(feature = "encoding")]
+            FilenameEncoding::Encoding(encoding) => encoding.decode_without_bom_handling(data).0,
+            FilenameEncoding::Utf8 => String::from_utf8_lossy(data),
+            _ => decode_table(data, |_| char::REPLACEMENT_CHARACTER)
+        }
+    }
+}
+
+/// Determines how to handle the characters not allowed in names: control characters, directory
+/// separators found in file names and the bytes that can't be decoded.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SanitizePolicy {
+    /// Control characters and bytes that can't be decoded are converted to `%xx` sequences,
+    /// directory separators to `_`.
+    #[default]
+    PercentEncode,
+    /// Forbidden characters and bytes that can't be decoded are replaced with the given character.
+    ///
+    /// If the given character is itself a control or a directory separator character, `_` is used
+    /// instead.
+    Replace(char),
+    /// Forbidden characters and bytes that can't be decoded result in an error.
+    Error,
+    /// Control characters are preserved, bytes that can't be decoded are replaced with
+    /// `U+FFFD` and directory separators with `_`.
+    Raw
+}
+
+/// Decodes a single byte encoding, `high` maps bytes from `0x80` to `0xFF`.
+fn decode_table(data: &[u8], high: impl Fn(u8) -> char) -> Cow<'_, str> {
+    if data.is_ascii() {
+        return Cow::Borrowed(std::str::from_utf8(data).expect("ASCII is valid UTF-8"))
+    }
+    Cow::Owned(data.iter().map(|&b| if b < 0x80 { b as char } else { high(b) }).collect())
+}
+
+/// Encodes a single byte encoding, `high` maps non-ASCII characters to bytes from `0x80` to `0xFF`.
+fn encode_table(text: &str, high: impl Fn(char) -> Option<u8>) -> Option<Cow<'_, [u8]>> {
+    if text.is_ascii() {
+        return Some(Cow::Borrowed(text.as_bytes()))
+    }
+    text.chars().map(|c| if c.is_ascii() { Some(c as u8) } else { high(c) }).collect()
+}
+
+fn table_position(table: &[char;128], c: char) -> Option<u8> {
+    table.iter().position(|&ch| ch == c).map(|index| index as u8 + 0x80)
+}
+
+static CP437: [char;128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+static CP850: [char;128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', 'ø', '£', 'Ø', '×', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '®', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', 'Á', 'Â', 'À', '©', '╣', '║', '╗', '╝', '¢', '¥', '┐',
+    '└', '┴', '┬', '├', '─', '┼', 'ã', 'Ã', '╚', '╔', '╩', '╦', '╠', '═', '╬', '¤',
+    'ð', 'Ð', 'Ê', 'Ë', 'È', 'ı', 'Í', 'Î', 'Ï', '┘', '┌', '█', '▄', '¦', 'Ì', '▀',
+    'Ó', 'ß', 'Ô', 'Ò', 'õ', 'Õ', 'µ', 'þ', 'Þ', 'Ú', 'Û', 'Ù', 'ý', 'Ý', '¯', '´',
+    '\u{ad}', '±', '‗', '¾', '¶', '§', '÷', '¸', '°', '¨', '·', '¹', '³', '²', '■', '\u{a0}',
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filename_encoding_works() {
+        assert_eq!(FilenameEncoding::Ascii.decode(b"foo"), Some(Cow::Borrowed("foo")));
+        assert_eq!(FilenameEncoding::Ascii.decode(b"f\x80o"), None);
+        assert_eq!(FilenameEncoding::Utf8.decode("zażółć".as_bytes()).unwrap(), "zażółć");
+        assert_eq!(FilenameEncoding::Utf8.decode(b"\xff"), None);
+        assert_eq!(FilenameEncoding::Latin1.decode(b"Gr\xfc\xdfe").unwrap(), "Grüße");
+        assert_eq!(FilenameEncoding::Cp437.decode(b"Gr\x81\xe1e \xe3").unwrap(), "Grüße π");
+        assert_eq!(FilenameEncoding::Cp850.decode(b"\x90t\x82 \x9d\xff").unwrap(), "Été Ø\u{a0}");
+        assert_eq!(FilenameEncoding::from_os_type(OsType::Amiga), FilenameEncoding::Latin1);
+        assert_eq!(FilenameEncoding::Auto.resolve(OsType::Unix), FilenameEncoding::Utf8);
+        assert_eq!(FilenameEncoding::Cp437.resolve(OsType::Unix), FilenameEncoding::Cp437);
+        assert_eq!(FilenameEncoding::from_os_type(OsType::Atari), FilenameEncoding::Ascii);
+        assert_eq!(FilenameEncoding::Ascii.decode_lossy(b"f\x80o"), "f\u{fffd}o");
+        assert_eq!(FilenameEncoding::Utf8.decode_lossy(b"f\xc3\xb3\xc3o"), "fó\u{fffd}o");
+        assert_eq!(FilenameEncoding::Cp850.decode_lossy(b"f\xa2o"), "fóo");
+        #[cfg(feature = "encoding")]
+        {
+            assert_eq!(FilenameEncoding::Encoding(encoding_rs::SHIFT_JIS).decode_lossy(b"\x95\x5c\x8e"),
+                       "表\u{fffd}");
+            assert_eq!(FilenameEncoding::from_os_type(OsType::MsDos),
+                       FilenameEncoding::Encoding(encoding_rs::SHIFT_JIS));
+            let koi8 = FilenameEncoding::Encoding(encoding_rs::KOI8_R);
+            assert_eq!(koi8.decode(b"\xf0\xd2\xc9\xd7\xc5\xd4").unwrap(), "Привет");
+        }
+    }
+
+    #[test]
+    fn encode_works() {
+        assert_eq!(FilenameEncoding::Ascii.encode("foo"), Some(Cow::Borrowed(&b"foo"[..])));
+        assert_eq!(FilenameEncoding::Ascii.encode("fóo"), None);
+        assert_eq!(FilenameEncoding::Utf8.encode("zażółć").unwrap(), "zażółć".as_bytes());
+        assert_eq!(FilenameEncoding::Latin1.encode("Grüße").unwrap(), &b"Gr\xfc\xdfe"[..]);
+        assert_eq!(FilenameEncoding::Latin1.encode("π"), None);
+        assert_eq!(FilenameEncoding::Cp437.encode("Grüße π").unwrap(), &b"Gr\x81\xe1e \xe3"[..]);
+        assert_eq!(FilenameEncoding::Cp850.encode("Été Ø\u{a0}").unwrap(), &b"\x90t\x82 \x9d\xff"[..]);
+        assert_eq!(FilenameEncoding::Cp850.encode("π"), None);
+        #[cfg(feature = "encoding")]
+        {
+            let sjis = FilenameEncoding::Encoding(encoding_rs::SHIFT_JIS);
+            assert_eq!(sjis.encode("表").unwrap(), &b"\x95\x5c"[..]);
+            assert_eq!(sjis.encode("Привет\u{1F600}"), None);
+        }
+    }
+}
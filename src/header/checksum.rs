@@ -0,0 +1,154 @@
+use core::num::Wrapping;
+use std::io;
+use crate::crc::Crc16;
+use super::*;
+use super::ext::EXT_HEADER_COMMON;
+
+/// Computes the checksum of a raw level 0 or 1 header, stored in the second byte of the header.
+///
+/// The checksum is the wrapping sum of the base header bytes, excluding the first 2 bytes and
+/// the extra headers. The current value of the checksum field is ignored.
+///
+/// # Errors
+/// Returns an error of the kind [io::ErrorKind::InvalidData] if the header is not a level 0 or 1
+/// header or `raw` is too short.
+pub fn header_checksum(raw: &[u8]) -> io::Result<u8> {
+    match header_level(raw)? {
+        0|1 => {}
+        _ => return Err(invalid_data("no header level checksum in level 2 and 3 headers"))
+    }
+    let base = raw.get(2..2 + raw[0] as usize).ok_or_else(too_short)?;
+    Ok(base.iter().copied().map(Wrapping).sum::<Wrapping<u8>>().0)
+}
+
+/// Computes the CRC-16 of a raw header of any level, stored in the ["Common"][EXT_HEADER_COMMON]
+/// extra header. Returns `Ok(None)` if the header has no "Common" extra header.
+///
+/// The CRC-16 is computed over all of the bytes of the header with the CRC-16 field of
+/// the "Common" extra header reset to `0`. The `raw` data must contain the whole header and
+/// nothing else, see [LhaHeader::raw_header].
+///
+/// # Errors
+/// Returns an error of the kind [io::ErrorKind::InvalidData] if the extra headers are malformed
+/// or `raw` is too short.
+pub fn header_crc16(raw: &[u8]) -> io::Result<Option<u16>> {
+    let offset = match common_crc_offset(raw)? {
+        Some(offset) => offset,
+        None => return Ok(None)
+    };
+    let mut crc = Crc16::default();
+    crc.digest(&raw[..offset]);
+    crc.digest(&[0, 0]);
+    crc.digest(&raw[offset + 2..]);
+    Ok(Some(crc.sum16()))
+}
+
+/// Recomputes and stores the checksum of a raw level 0 or 1 header, see [header_checksum], and
+/// the CRC-16 of the ["Common"][EXT_HEADER_COMMON] extra header if present, see [header_crc16].
+///
+/// The checksum is updated first, as the CRC-16 covers the checksum field.
+///
+/// # Errors
+/// Returns an error of the kind [io::ErrorKind::InvalidData] if the header is malformed.
+pub fn update_header_checksums(raw: &mut [u8]) -> io::Result<()> {
+    if header_level(raw)? < 2 {
+        raw[1] = header_checksum(raw)?;
+    }
+    if let Some(crc) = header_crc16(raw)? {
+        let offset = common_crc_offset(raw)?.unwrap();
+        raw[offset..offset + 2].copy_from_slice(&crc.to_le_bytes());
+    }
+    Ok(())
+}
+
+/// Returns the level of a raw header.
+fn header_level(raw: &[u8]) -> io::Result<u8> {
+    match raw.get(20) {
+        Some(&level) if level <= 3 => Ok(level),
+        Some(..) => Err(invalid_data("unknown header level")),
+        None => Err(too_short())
+    }
+}
+
+/// Returns the offset of the CRC-16 field of the "Common" extra header in a raw header.
+fn common_crc_offset(raw: &[u8]) -> io::Result<Option<usize>> {
+    let level = header_level(raw)?;
+    // the offset of the first extra header's length field and the size of the length fields
+    let (mut offset, counter_size) = match level {
+        0 => return Ok(None),
+        1 => (raw[0] as usize, 2),
+        2 => (24, 2),
+        _ => (28, 4)
+    };
+    loop {
+        let header_len = raw.get(offset..offset + counter_size).ok_or_else(too_short)?;
+        let header_len = if counter_size == 4 {
+            read_u32(header_len).unwrap() as usize
+        }
+        else {
+            read_u16(header_len).unwrap() as usize
+        };
+        if header_len == 0 {
+            return Ok(None)
+        }
+        if header_len < counter_size + 1 {
+            return Err(invalid_data("wrong extra header size"))
+        }
+        let start = offset + counter_size;
+        let header = raw.get(start..start + header_len).ok_or_else(too_short)?;
+        if header[0] == EXT_HEADER_COMMON && header_len >= counter_size + 3 {
+            return Ok(Some(start + 1))
+        }
+        offset = start + header_len - counter_size;
+    }
+}
+
+fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn too_short() -> io::Error {
+    invalid_data("header too short")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_checksums_work() {
+        let builder = LhaHeaderBuilder::new().path("dir/foo").compressed_size(10);
+        for level in 0..=3 {
+            let header = builder.clone().level(level).build().unwrap();
+            let data = header.to_bytes().unwrap();
+            if level < 2 {
+                assert_eq!(header_checksum(&data).unwrap(), data[1]);
+            }
+            else {
+                assert_eq!(header_checksum(&data).unwrap_err().kind(), io::ErrorKind::InvalidData);
+            }
+            let crc = header_crc16(&data).unwrap();
+            assert_eq!(crc.is_some(), level >= 2);
+            let mut broken = data.clone();
+            broken[15] ^= 0x55;
+            assert!(LhaHeader::read(&broken[..]).is_err());
+            update_header_checksums(&mut broken).unwrap();
+            let header2 = LhaHeader::read(&broken[..]).unwrap().unwrap();
+            assert_eq!(header2.last_modified, header.last_modified ^ 0x55);
+            broken[15] ^= 0x55;
+            update_header_checksums(&mut broken).unwrap();
+            assert_eq!(broken, data);
+        }
+        let header = builder.clone().level(1).extra_header(EXT_HEADER_COMMON, &[0, 0]).build().unwrap();
+        let mut data = header.to_bytes().unwrap();
+        let crc = header_crc16(&data).unwrap().unwrap();
+        assert!(data.windows(2).any(|w| w == crc.to_le_bytes()));
+        data[15] ^= 1;
+        assert!(LhaHeader::read(&data[..]).is_err());
+        update_header_checksums(&mut data).unwrap();
+        assert!(LhaHeader::read(&data[..]).is_ok());
+        let data = builder.level(2).build().unwrap().to_bytes().unwrap();
+        assert_eq!(header_crc16(&data[..28]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(header_crc16(&data[..20]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
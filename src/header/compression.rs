@@ -1,10 +1,16 @@
 use core::fmt;
+use core::str::FromStr;
 use std::error::Error;
 use std::io;
 use core::convert::TryFrom;
 
+/// The compression method of an archived file.
+///
+/// The `-lz?-` methods come from LArc, the `-pm?-` methods from PMarc, the others from LHarc,
+/// LHA and their descendants. Any other identifier is preserved as [CompressionMethod::Other].
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompressionMethod {
     /// Special "meta" method marking a directory. Also used for symlinks.
     Lhd,
@@ -13,6 +19,8 @@ pub enum CompressionMethod {
     Lz5,
     Lh0,
     Lh1,
+    Lh2,
+    Lh3,
     Lh4,
     Lh5,
     Lh6,
@@ -21,11 +29,33 @@ pub enum CompressionMethod {
     Pm0,
     Pm1,
     Pm2,
+    /// An unrecognized method with its raw identifier.
+    Other([u8;5])
 }
 
+/// The error returned when parsing a compression method identifier with [str::parse].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseCompressionMethodError;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct UnrecognizedCompressionMethod([u8;5]);
 
+/// The reason why the content compressed with a method can't be decoded, returned from
+/// [CompressionMethod::check_supported].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum UnsupportedReason {
+    /// The entry is a directory or a symbolic link, without any content to decode.
+    Directory,
+    /// The decoder of the method is disabled in this build of the library and can be enabled with
+    /// the cargo feature of the given name.
+    FeatureDisabled(&'static str),
+    /// The method is known, but this library has no decoder for it.
+    NotImplemented,
+    /// The method identifier is not recognized.
+    Unknown
+}
+
 impl TryFrom<&[u8;5]> for CompressionMethod {
     type Error = UnrecognizedCompressionMethod;
     fn try_from(s: &[u8;5]) -> Result<Self, Self::Error> {
@@ -36,6 +66,8 @@ impl TryFrom<&[u8;5]> for CompressionMethod {
             b"-lz5-" => CompressionMethod::Lz5,
             b"-lh0-" => CompressionMethod::Lh0,
             b"-lh1-" => CompressionMethod::Lh1,
+            b"-lh2-" => CompressionMethod::Lh2,
+            b"-lh3-" => CompressionMethod::Lh3,
             b"-lh4-" => CompressionMethod::Lh4,
             b"-lh5-" => CompressionMethod::Lh5,
             b"-lh6-" => CompressionMethod::Lh6,
@@ -49,6 +81,38 @@ impl TryFrom<&[u8;5]> for CompressionMethod {
     }
 }
 
+impl From<[u8;5]> for CompressionMethod {
+    /// Converts a raw identifier, preserving unrecognized identifiers as [CompressionMethod::Other].
+    fn from(id: [u8;5]) -> Self {
+        CompressionMethod::try_from(&id).unwrap_or(CompressionMethod::Other(id))
+    }
+}
+
+impl From<CompressionMethod> for [u8;5] {
+    /// Converts the method into its raw identifier, including the [CompressionMethod::Other] ones.
+    fn from(method: CompressionMethod) -> Self {
+        match method {
+            CompressionMethod::Other(id) => id,
+            method => *method.as_identifier()
+        }
+    }
+}
+
+impl FromStr for CompressionMethod {
+    type Err = ParseCompressionMethodError;
+    /// Parses a compression method identifier, e.g. `-lh5-`. The dashes can be omitted, e.g. `lh5`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.as_bytes();
+        let mut id = [b'-';5];
+        match s.len() {
+            5 => id.copy_from_slice(s),
+            3 => id[1..4].copy_from_slice(s),
+            _ => return Err(ParseCompressionMethodError)
+        }
+        Ok(CompressionMethod::from(id))
+    }
+}
+
 impl CompressionMethod {
     pub fn is_directory(&self) -> bool {
         if let CompressionMethod::Lhd = self {
@@ -57,6 +121,90 @@ impl CompressionMethod {
         false
     }
 
+    /// Returns `true` if the content compressed with this method can be decoded by one of the
+    /// decoders enabled in this build of the library.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            CompressionMethod::Pm0|
+            CompressionMethod::Lz4|
+            CompressionMethod::Lh0|
+            CompressionMethod::Lh4|
+            CompressionMethod::Lh5|
+            CompressionMethod::Lh6|
+            CompressionMethod::Lh7 => true,
+            #[cfg(feature = "lz")]
+            CompressionMethod::Lzs|
+            CompressionMethod::Lz5 => true,
+            #[cfg(feature = "lh1")]
+            CompressionMethod::Lh1 => true,
+            #[cfg(feature = "lh2")]
+            CompressionMethod::Lh2 => true,
+            #[cfg(feature = "lh3")]
+            CompressionMethod::Lh3 => true,
+            #[cfg(feature = "lhx")]
+            CompressionMethod::Lhx => true,
+            _ => false
+        }
+    }
+
+    /// Returns `true` if the content can be compressed with this method by
+    /// [compress][crate::encode::compress] in this build of the library.
+    pub fn is_encoder_supported(&self) -> bool {
+        match self {
+            CompressionMethod::Pm0|
+            CompressionMethod::Lz4|
+            CompressionMethod::Lh0|
+            CompressionMethod::Lh4|
+            CompressionMethod::Lh5|
+            CompressionMethod::Lh6|
+            CompressionMethod::Lh7 => true,
+            #[cfg(feature = "lh1")]
+            CompressionMethod::Lh1 => true,
+            _ => false
+        }
+    }
+
+    /// Returns `Ok(())` if the method is supported, see [CompressionMethod::is_supported], otherwise
+    /// returns the reason why it is not supported.
+    ///
+    /// ```
+    /// use delharc::header::{CompressionMethod, UnsupportedReason};
+    ///
+    /// if let Err(UnsupportedReason::FeatureDisabled(feature)) = CompressionMethod::Lh2.check_supported() {
+    ///     println!("rebuild with feature `{}`", feature);
+    /// }
+    /// ```
+    pub fn check_supported(&self) -> Result<(), UnsupportedReason> {
+        if self.is_supported() {
+            return Ok(())
+        }
+        Err(match (self, self.required_feature()) {
+            (CompressionMethod::Lhd, _) => UnsupportedReason::Directory,
+            (CompressionMethod::Other(..), _) => UnsupportedReason::Unknown,
+            (_, Some(feature)) => UnsupportedReason::FeatureDisabled(feature),
+            (_, None) => UnsupportedReason::NotImplemented
+        })
+    }
+
+    /// Returns the name of the cargo feature enabling the decoder of the method or `None` if
+    /// the decoder is always enabled or there is no decoder of the method.
+    pub fn required_feature(&self) -> Option<&'static str> {
+        match self {
+            CompressionMethod::Lzs|
+            CompressionMethod::Lz5 => Some("lz"),
+            CompressionMethod::Lh1 => Some("lh1"),
+            CompressionMethod::Lh2 => Some("lh2"),
+            CompressionMethod::Lh3 => Some("lh3"),
+            CompressionMethod::Lhx => Some("lhx"),
+            _ => None
+        }
+    }
+
+    /// Returns the raw identifier of the method, e.g. `-lh5-`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the method is [CompressionMethod::Other], convert it into `[u8;5]` instead.
     pub fn as_identifier(self) -> &'static [u8;5] {
         match self {
             CompressionMethod::Lhd => b"-lhd-",
@@ -65,6 +213,8 @@ impl CompressionMethod {
             CompressionMethod::Lz5 => b"-lz5-",
             CompressionMethod::Lh0 => b"-lh0-",
             CompressionMethod::Lh1 => b"-lh1-",
+            CompressionMethod::Lh2 => b"-lh2-",
+            CompressionMethod::Lh3 => b"-lh3-",
             CompressionMethod::Lh4 => b"-lh4-",
             CompressionMethod::Lh5 => b"-lh5-",
             CompressionMethod::Lh6 => b"-lh6-",
@@ -73,6 +223,7 @@ impl CompressionMethod {
             CompressionMethod::Pm0 => b"-pm0-",
             CompressionMethod::Pm1 => b"-pm1-",
             CompressionMethod::Pm2 => b"-pm2-",
+            CompressionMethod::Other(..) => panic!("no static identifier of an unrecognized method")
         }
     }
 }
@@ -86,12 +237,38 @@ impl fmt::Display for UnrecognizedCompressionMethod {
 }
 
 impl fmt::Display for CompressionMethod {
+    /// Writes the identifier, e.g. `-lh5-`, escaping the bytes other than printable ASCII characters.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = <[u8;5]>::from(*self);
+        if id.iter().all(u8::is_ascii_graphic) {
+            // the identifier is ASCII
+            return std::str::from_utf8(&id).unwrap().fmt(f)
+        }
+        let escaped: String = id.iter().flat_map(|&b| core::ascii::escape_default(b)).map(char::from).collect();
+        escaped.fmt(f)
+    }
+}
+
+impl Error for ParseCompressionMethodError {}
+
+impl fmt::Display for ParseCompressionMethodError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "invalid compression method identifier".fmt(f)
+    }
+}
+
+impl Error for UnsupportedReason {}
+
+impl fmt::Display for UnsupportedReason {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let strid = self.as_identifier();
-        assert!(strid.is_ascii());
-        unsafe {
-            std::str::from_utf8_unchecked(strid)
-        }.fmt(f)
+        match self {
+            UnsupportedReason::Directory => f.write_str("a directory has no content to decode"),
+            UnsupportedReason::FeatureDisabled(feature) => {
+                write!(f, "the decoder is disabled, rebuild with feature `{}`", feature)
+            }
+            UnsupportedReason::NotImplemented => f.write_str("the compression method is not implemented"),
+            UnsupportedReason::Unknown => f.write_str("unknown compression method")
+        }
     }
 }
 
@@ -100,3 +277,45 @@ impl From<UnrecognizedCompressionMethod> for io::Error {
         io::Error::new(io::ErrorKind::InvalidData, e)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compression_method_works() {
+        assert_eq!(CompressionMethod::from(*b"-lh5-"), CompressionMethod::Lh5);
+        assert_eq!(CompressionMethod::from(*b"-lhd-").as_identifier(), b"-lhd-");
+        let other = CompressionMethod::from(*b"-pc1-");
+        assert_eq!(other, CompressionMethod::Other(*b"-pc1-"));
+        assert_eq!(<[u8;5]>::from(other), *b"-pc1-");
+        assert!(!other.is_supported());
+        assert_eq!(other.to_string(), "-pc1-");
+        assert_eq!(CompressionMethod::Other(*b"-l\x00\xff-").to_string(), "-l\\x00\\xff-");
+        assert_eq!(format!("{:>7}", CompressionMethod::Lh7), "  -lh7-");
+        assert_eq!("-lhx-".parse(), Ok(CompressionMethod::Lhx));
+        assert_eq!("pm2".parse(), Ok(CompressionMethod::Pm2));
+        assert_eq!("-zz9-".parse(), Ok(CompressionMethod::Other(*b"-zz9-")));
+        assert_eq!("lh".parse::<CompressionMethod>(), Err(ParseCompressionMethodError));
+        assert_eq!("-lh10-".parse::<CompressionMethod>(), Err(ParseCompressionMethodError));
+        assert!(CompressionMethod::try_from(b"-pc1-").is_err());
+    }
+
+    #[test]
+    fn check_supported_works() {
+        assert_eq!(CompressionMethod::Lh5.check_supported(), Ok(()));
+        assert_eq!(CompressionMethod::Lhd.check_supported(), Err(UnsupportedReason::Directory));
+        assert_eq!(CompressionMethod::Pm2.check_supported(), Err(UnsupportedReason::NotImplemented));
+        assert_eq!(CompressionMethod::Other(*b"-pc1-").check_supported(), Err(UnsupportedReason::Unknown));
+        assert_eq!(CompressionMethod::Lh2.required_feature(), Some("lh2"));
+        assert_eq!(CompressionMethod::Lh7.required_feature(), None);
+        let lh2 = CompressionMethod::Lh2.check_supported();
+        if cfg!(feature = "lh2") {
+            assert_eq!(lh2, Ok(()));
+        }
+        else {
+            assert_eq!(lh2, Err(UnsupportedReason::FeatureDisabled("lh2")));
+            assert_eq!(lh2.unwrap_err().to_string(), "the decoder is disabled, rebuild with feature `lh2`");
+        }
+    }
+}
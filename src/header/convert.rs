@@ -0,0 +1,289 @@
+use std::io;
+use super::*;
+use super::ext::*;
+use super::builder::{chain_extra_headers, encode_comment};
+
+impl LhaHeader {
+    /// Converts the header to an equivalent level 2 header, e.g. for normalizing old archives.
+    ///
+    /// The file and directory names of level 0 and 1 headers are moved to the
+    /// ["Filename"][EXT_HEADER_FILENAME] and ["Path"][EXT_HEADER_PATH] extra headers, the directory
+    /// separators being converted to `0xFF`. The bytes of the names are preserved, the `encoding`
+    /// is only used to find the directory separators, so the double byte characters of Shift-JIS
+    /// (available with the `encoding` feature) are not split.
+    ///
+    /// The MS-DOS timestamp is converted to a Unix timestamp, assuming the local time zone, unless
+    /// a Unix timestamp is found, see [LhaHeader::unix_last_modified]. A timestamp that can't be
+    /// converted is set to `0`. The Unix file mode and identifiers as well as the OS-9 metadata found
+    /// in the extended area, see [LhaHeader::parse_extended_area], are moved to the extra headers, any
+    /// other content of the extended area has no equivalent in level 2 headers and is dropped.
+    ///
+    /// The remaining extra headers are preserved, a new ["Common"][EXT_HEADER_COMMON] header is
+    /// prepended, so the header's CRC-16 is computed when the header is written. Level 2 and 3 headers
+    /// are re-chained with 16-bit lengths.
+    ///
+    /// # Errors
+    /// Returns an error of the kind [io::ErrorKind::InvalidInput] if the header can't be represented
+    /// as a level 2 header, e.g. an extra header of a level 3 header is too long.
+    pub fn to_level2(&self, encoding: FilenameEncoding) -> io::Result<LhaHeader> {
+        let os = self.os();
+        let sjis = is_sjis(encoding.resolve(os));
+        let nilterm = os == OsType::Amiga;
+        let mut filename: Option<Vec<u8>> = None;
+        let mut path = Vec::new();
+        let mut extra: Vec<(u8, Vec<u8>)> = Vec::new();
+        let has_unix_time = self.level < 2 && self.unix_last_modified().is_some();
+        for header in self.iter_extra() {
+            let (&id, data) = header.split_first().unwrap();
+            match id {
+                EXT_HEADER_COMMON => {}
+                EXT_HEADER_UNIX_TIME if has_unix_time => {}
+                EXT_HEADER_FILENAME => {
+                    filename = Some(data.to_vec()).filter(|data| !data.is_empty());
+                }
+                EXT_HEADER_PATH if !data.is_empty() => {
+                    path.extend_from_slice(data);
+                    if !path.ends_with(&[0xFF]) {
+                        path.push(0xFF);
+                    }
+                }
+                _ => extra.push((id, data.to_vec()))
+            }
+        }
+        let filename = match filename {
+            Some(filename) => filename,
+            None => {
+                let (name, rest) = if nilterm {
+                    match split_data_at_nil_or_end(&self.filename) {
+                        (name, Some(..)) => (name, &self.filename[name.len()..]),
+                        (name, None) => (name, &[][..])
+                    }
+                }
+                else {
+                    (&self.filename[..], &[][..])
+                };
+                let mut start = 0;
+                for index in separators(name, sjis) {
+                    path.extend_from_slice(&name[start..index]);
+                    path.push(0xFF);
+                    start = index + 1;
+                }
+                let mut filename = name[start..].to_vec();
+                filename.extend_from_slice(rest);
+                filename
+            }
+        };
+        if let Some(area) = self.unix_extended_area() {
+            let has_id = |id| self.get_extra(id).is_some();
+            if let (Some(mode), false) = (area.mode, has_id(EXT_HEADER_UNIX_PERM)) {
+                extra.push((EXT_HEADER_UNIX_PERM, mode.to_le_bytes().to_vec()));
+            }
+            if let (Some((uid, gid)), false) = (area.uid_gid, has_id(EXT_HEADER_UNIX_UIDGID)) {
+                let mut data = gid.to_le_bytes().to_vec();
+                data.extend_from_slice(&uid.to_le_bytes());
+                extra.push((EXT_HEADER_UNIX_UIDGID, data));
+            }
+        }
+        if let Some(ExtendedArea::Os9(metadata)) = self.parse_extended_area() {
+            if self.get_extra(EXT_HEADER_OS9).is_none() {
+                extra.push((EXT_HEADER_OS9, metadata.data.to_vec()));
+            }
+        }
+        let mut names = vec![(EXT_HEADER_COMMON, vec![0, 0]), (EXT_HEADER_FILENAME, filename)];
+        if !path.is_empty() {
+            names.push((EXT_HEADER_PATH, path));
+        }
+        extra.splice(0..0, names);
+
+        let last_modified = if self.level < 2 {
+            self.unix_last_modified()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs())
+                .or_else(|| {
+                    let local = parse_msdos_datetime(self.last_modified)?;
+                    let time = Local.from_local_datetime(&local).earliest()?;
+                    u64::try_from(time.timestamp()).ok()
+                })
+                .and_then(|secs| u32::try_from(secs).ok())
+                .unwrap_or(0)
+        }
+        else {
+            self.last_modified
+        };
+        let (first_header_len, extra_headers) = chain_extra_headers(2, extra)?;
+        let header = LhaHeader {
+            level: 2,
+            compression: self.compression,
+            compressed_size: self.compressed_size,
+            original_size: self.original_size,
+            filename: Box::new([]),
+            msdos_attrs: self.msdos_attrs,
+            last_modified,
+            os_type: self.os_type,
+            file_crc: self.file_crc,
+            extended_area: Box::new([]),
+            first_header_len,
+            extra_headers: extra_headers.into_boxed_slice(),
+            warnings: Vec::new(),
+            raw_header: None,
+            header_size: 0,
+            header_offset: None
+        };
+        // validates the lengths of the header
+        let header_size = header.to_bytes()?.len() as u64;
+        Ok(LhaHeader { header_size, ..header })
+    }
+
+    /// Sets the file comment, encoded with the given `encoding`, replacing the
+    /// ["Comment"][EXT_HEADER_COMMENT] extra headers. [FilenameEncoding::Auto] selects the encoding
+    /// from the header's OS type. An empty comment removes the extra headers.
+    ///
+    /// The comment can be read back with [LhaHeader::parse_comment_with].
+    ///
+    /// # Errors
+    /// Returns an error of the kind [io::ErrorKind::InvalidInput] if the header is a level 0 header,
+    /// which has no extra headers, if the comment can't be represented in the `encoding` or contains
+    /// a `nul` character or if the extra header would be too long.
+    pub fn set_comment_with(&mut self, comment: &str, encoding: FilenameEncoding) -> io::Result<()> {
+        if self.level == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "level 0 headers can't have extra headers"))
+        }
+        let data = encode_comment(comment, encoding.resolve(self.os()))?;
+        let mut extra: Vec<(u8, Vec<u8>)> = self.iter_extra()
+                        .map(|header| (header[0], header[1..].to_vec()))
+                        .filter(|&(id, _)| id != EXT_HEADER_COMMENT)
+                        .collect();
+        if !data.is_empty() {
+            extra.push((EXT_HEADER_COMMENT, data));
+        }
+        let (first_header_len, extra_headers) = chain_extra_headers(self.level, extra)?;
+        self.first_header_len = first_header_len;
+        self.extra_headers = extra_headers.into_boxed_slice();
+        self.raw_header = None;
+        Ok(())
+    }
+    /// Synchronizes the ["MS-DOS Size"][EXT_HEADER_MSDOS_SIZE] extra header of a level 2 or 3 header
+    /// with the size fields: the extra header is added if any of the sizes exceeds 32 bits and
+    /// updated if present. Level 0 and 1 headers are left intact.
+    pub(crate) fn sync_msdos_size(&mut self) -> io::Result<()> {
+        if self.level < 2 {
+            return Ok(())
+        }
+        let current = self.get_extra(EXT_HEADER_MSDOS_SIZE);
+        let large = self.compressed_size > u32::MAX as u64 || self.original_size > u32::MAX as u64;
+        if current.is_none() && !large {
+            return Ok(())
+        }
+        let mut data = self.compressed_size.to_le_bytes().to_vec();
+        data.extend_from_slice(&self.original_size.to_le_bytes());
+        if current == Some(&data[..]) {
+            return Ok(())
+        }
+        let mut extra: Vec<(u8, Vec<u8>)> = self.iter_extra()
+                        .map(|header| (header[0], header[1..].to_vec()))
+                        .filter(|&(id, _)| id != EXT_HEADER_MSDOS_SIZE)
+                        .collect();
+        extra.push((EXT_HEADER_MSDOS_SIZE, data));
+        let (first_header_len, extra_headers) = chain_extra_headers(self.level, extra)?;
+        self.first_header_len = first_header_len;
+        self.extra_headers = extra_headers.into_boxed_slice();
+        Ok(())
+    }
+}
+
+/// Returns `true` if `encoding` is Shift-JIS, in which the second byte of a double byte character
+/// can be `\`.
+fn is_sjis(encoding: FilenameEncoding) -> bool {
+    match encoding {
+        #[cfg(feature = "encoding")]
+        FilenameEncoding::Encoding(encoding) => encoding == encoding_rs::SHIFT_JIS,
+        _ => false
+    }
+}
+
+/// Returns the indices of the directory separators in `name`, skipping the second bytes of
+/// Shift-JIS double byte characters if `sjis` is `true`.
+fn separators(name: &[u8], sjis: bool) -> impl Iterator<Item=usize> + '_ {
+    let mut trail = false;
+    name.iter().enumerate().filter_map(move |(index, &c)| {
+        if trail {
+            trail = false;
+            return None
+        }
+        trail = sjis && matches!(c, 0x81..=0x9F|0xE0..=0xFC);
+        matches!(c, 0xFF|b'/'|b'\\').then_some(index)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sync_msdos_size_works() {
+        let mut header = LhaHeaderBuilder::new().path("foo").comment(b"bar").build().unwrap();
+        let extra_headers = header.extra_headers.clone();
+        header.sync_msdos_size().unwrap();
+        assert_eq!(header.extra_headers, extra_headers);
+        header.original_size = 5 << 32;
+        header.sync_msdos_size().unwrap();
+        let parsed = LhaHeader::read(&header.to_bytes().unwrap()[..]).unwrap().unwrap();
+        assert_eq!(parsed.original_size, 5 << 32);
+        assert_eq!(parsed.get_extra(EXT_HEADER_COMMENT), Some(&b"bar"[..]));
+        header.original_size = 5;
+        header.sync_msdos_size().unwrap();
+        let parsed = LhaHeader::read(&header.to_bytes().unwrap()[..]).unwrap().unwrap();
+        assert_eq!(parsed.original_size, 5);
+        assert_eq!(parsed.get_extra_all(EXT_HEADER_MSDOS_SIZE).count(), 1);
+    }
+
+    #[test]
+    fn set_comment_with_works() {
+        let mut header = LhaHeaderBuilder::new().level(1).path("foo").comment(b"old").os_type(OsType::Amiga)
+                         .build().unwrap();
+        header.set_comment_with("Grüße", FilenameEncoding::Auto).unwrap();
+        assert_eq!(header.get_extra_all(EXT_HEADER_COMMENT).collect::<Vec<_>>(), [&b"Gr\xfc\xdfe"[..]]);
+        let parsed = LhaHeader::read(&header.to_bytes().unwrap()[..]).unwrap().unwrap();
+        assert_eq!(parsed.parse_comment_with(FilenameEncoding::Auto).unwrap(), "Grüße");
+        assert_eq!(parsed.parse_pathname(), std::path::Path::new("foo"));
+        assert_eq!(header.set_comment_with("π", FilenameEncoding::Latin1).unwrap_err().kind(),
+                   io::ErrorKind::InvalidInput);
+        assert!(header.set_comment_with("a\0b", FilenameEncoding::Utf8).is_err());
+        header.set_comment_with("", FilenameEncoding::Utf8).unwrap();
+        assert_eq!(header.get_extra(EXT_HEADER_COMMENT), None);
+        let mut header = LhaHeaderBuilder::new().level(0).path("foo").build().unwrap();
+        assert!(header.set_comment_with("bar", FilenameEncoding::Utf8).is_err());
+    }
+
+    #[test]
+    fn to_level2_works() {
+        let header = LhaHeader {
+            level: 1,
+            filename: b"dir\\sub/\x95\x5c.txt"[..].into(),
+            os_type: b'M',
+            ..LhaHeader::default()
+        };
+        let header2 = header.to_level2(FilenameEncoding::Ascii).unwrap();
+        assert_eq!(header2.raw_path_bytes(), Some(&b"dir\xffsub\xff\x95\xff"[..]));
+        assert_eq!(header2.raw_filename_bytes(), b".txt");
+        assert_eq!(header2.parse_pathname(), header.parse_pathname());
+        #[cfg(feature = "encoding")]
+        {
+            let sjis = FilenameEncoding::Encoding(encoding_rs::SHIFT_JIS);
+            let header2 = header.to_level2(sjis).unwrap();
+            assert_eq!(header2.raw_path_bytes(), Some(&b"dir\xffsub\xff"[..]));
+            assert_eq!(header2.raw_filename_bytes(), b"\x95\x5c.txt");
+            assert_eq!(header2.parse_pathname_sjis(), header.parse_pathname_sjis());
+        }
+        let header = LhaHeader {
+            level: 0,
+            filename: b"dir/name\x00a/b"[..].into(),
+            os_type: b'A',
+            ..LhaHeader::default()
+        };
+        let header2 = header.to_level2(FilenameEncoding::Auto).unwrap();
+        assert_eq!(header2.raw_path_bytes(), Some(&b"dir\xff"[..]));
+        assert_eq!(header2.parse_comment().unwrap(), "a/b");
+        assert_eq!(header2.to_level2(FilenameEncoding::Auto).unwrap().extra_headers, header2.extra_headers);
+    }
+}
@@ -0,0 +1,109 @@
+use super::*;
+use super::ext::EXT_HEADER_OS9;
+
+/// The content of the extended area of level 0 and 1 headers, interpreted according to the OS type
+/// of the header.
+///
+/// Only the packers for Unix-like and OS-9 systems are known to store metadata in the extended area.
+/// The content of the extended area of the headers made on other systems is available as
+/// [ExtendedArea::Unknown].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum ExtendedArea<'a> {
+    /// The extended area of LHa for UNIX 1.x.
+    Unix(#[cfg_attr(feature = "serde", serde(borrow))] UnixExtendedArea<'a>),
+    /// The extended area of OS-9/68k (OSK) packers, following the Unix layout with an additional
+    /// timestamp. The [UnixExtendedArea::mode] contains [Os9Perms][super::Os9Perms] instead of a Unix
+    /// file mode.
+    Osk(#[cfg_attr(feature = "serde", serde(borrow))] UnixExtendedArea<'a>),
+    /// The extended area of OS-9 packers, embedding the same metadata as
+    /// the ["OS-9"][EXT_HEADER_OS9] extra header.
+    Os9(#[cfg_attr(feature = "serde", serde(borrow))] Os9Metadata<'a>),
+    /// The raw extended area in an unrecognized format.
+    Unknown(&'a [u8])
+}
+
+/// The metadata found in the extended area of level 0 and 1 headers made on Unix-like systems.
+///
+/// ```text
+/// Unix: | minor version: u8 | last modified: u32 |                  | mode: u16 | uid: u16 | gid: u16 |
+/// OSK:  | minor version: u8 | last modified: u32 | timestamp: u32   | mode: u16 | uid: u16 | gid: u16 |
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnixExtendedArea<'a> {
+    /// The minor version of the packer.
+    pub minor_version: u8,
+    /// The last modified Unix timestamp.
+    pub last_modified: u32,
+    /// The file mode, if present.
+    pub mode: Option<u16>,
+    /// The owner and group identifiers as a `(uid, gid)` tuple, if present.
+    pub uid_gid: Option<(u16, u16)>,
+    /// The raw extended area.
+    pub data: &'a [u8]
+}
+
+impl<'a> ExtendedArea<'a> {
+    /// Parses the extended area `data` of a header made on the `os` system.
+    ///
+    /// Returns `None` if `data` is empty.
+    pub fn parse(os: OsType, data: &'a [u8]) -> Option<Self> {
+        if data.is_empty() {
+            return None
+        }
+        Some(match os {
+            OsType::Unix => UnixExtendedArea::parse(data, 5).map_or(ExtendedArea::Unknown(data), ExtendedArea::Unix),
+            OsType::Osk => UnixExtendedArea::parse(data, 9).map_or(ExtendedArea::Unknown(data), ExtendedArea::Osk),
+            OsType::Os9 => match data.get(8..) {
+                Some([EXT_HEADER_OS9, metadata @ ..]) => {
+                    Os9Metadata::parse(metadata).map_or(ExtendedArea::Unknown(data), ExtendedArea::Os9)
+                }
+                _ => ExtendedArea::Unknown(data)
+            }
+            _ => ExtendedArea::Unknown(data)
+        })
+    }
+}
+
+impl<'a> UnixExtendedArea<'a> {
+    /// Parses the Unix-like extended area with the mode field at `ids_offset`.
+    fn parse(data: &'a [u8], ids_offset: usize) -> Option<Self> {
+        let minor_version = *data.first()?;
+        let last_modified = data.get(1..5).and_then(read_u32)?;
+        let ids = data.get(ids_offset..).unwrap_or_default();
+        let mode = ids.get(0..2).and_then(read_u16);
+        let uid_gid = ids.get(2..4).and_then(read_u16).zip(ids.get(4..6).and_then(read_u16));
+        Some(UnixExtendedArea { minor_version, last_modified, mode, uid_gid, data })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extended_area_works() {
+        let data = b"\x00\x00\x3b\x3d\x4b\x24\x81\xe8\x03\x64\x00";
+        assert_eq!(ExtendedArea::parse(OsType::Unix, data), Some(ExtendedArea::Unix(UnixExtendedArea {
+            minor_version: 0, last_modified: 0x4b3d3b00, mode: Some(0o100444), uid_gid: Some((1000, 100)), data
+        })));
+        assert_eq!(ExtendedArea::parse(OsType::Unix, &data[..7]), Some(ExtendedArea::Unix(UnixExtendedArea {
+            minor_version: 0, last_modified: 0x4b3d3b00, mode: Some(0o100444), uid_gid: None, data: &data[..7]
+        })));
+        assert_eq!(ExtendedArea::parse(OsType::Unix, &data[..4]), Some(ExtendedArea::Unknown(&data[..4])));
+        assert_eq!(ExtendedArea::parse(OsType::Unix, &[]), None);
+        assert_eq!(ExtendedArea::parse(OsType::MsDos, data), Some(ExtendedArea::Unknown(data)));
+        let data = b"\x00\x60\x8f\x3d\x4b\x60\x8f\x3d\x4b\x27\x00\x01\x00\x02\x00";
+        assert_eq!(ExtendedArea::parse(OsType::Osk, data), Some(ExtendedArea::Osk(UnixExtendedArea {
+            minor_version: 0, last_modified: 0x4b3d8f60, mode: Some(0x27), uid_gid: Some((1, 2)), data
+        })));
+        let data = b"\x13\x00\x00\xc3\x16\x00\x0f\x00\xcc\x18\x07\x09\x03\x01\x16\x00\x13\x00\x00\x00\x00";
+        assert_eq!(ExtendedArea::parse(OsType::Os9, data), Some(ExtendedArea::Os9(Os9Metadata {
+            perms: Os9Perms::READ|Os9Perms::WRITE|Os9Perms::PUBLIC_WRITE,
+            data: &data[9..]
+        })));
+        assert_eq!(ExtendedArea::parse(OsType::Os9, &data[..12]), Some(ExtendedArea::Unknown(&data[..12])));
+    }
+}
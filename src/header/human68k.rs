@@ -0,0 +1,47 @@
+use bitflags::bitflags;
+
+bitflags! {
+    /// Human68k file attributes, found in the MS-DOS attribute field of the files archived on
+    /// X68000 computers.
+    ///
+    /// The lower 6 bits have the same meaning as [MsDosAttrs][super::MsDosAttrs], bit 6 marks
+    /// a symbolic link and bit 7 an executable file.
+    #[derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Human68kAttrs: u8 {
+        const READ_ONLY = 0b00000001;
+        const HIDDEN    = 0b00000010;
+        const SYSTEM    = 0b00000100;
+        const VOLUME    = 0b00001000;
+        const DIRECTORY = 0b00010000;
+        const ARCHIVE   = 0b00100000;
+        const LINK      = 0b01000000;
+        const EXEC      = 0b10000000;
+    }
+}
+
+impl Human68kAttrs {
+    /// Converts the attributes to the Unix permission bits, excluding the file type bits.
+    ///
+    /// The read permission is granted to everyone, the write permission only to the owner unless
+    /// [Human68kAttrs::READ_ONLY] is set. The execute permission is granted to everyone for
+    /// directories and [Human68kAttrs::EXEC] files.
+    ///
+    /// ```
+    /// use delharc::header::Human68kAttrs;
+    ///
+    /// assert_eq!(Human68kAttrs::ARCHIVE.to_unix_perms(), 0o644);
+    /// assert_eq!((Human68kAttrs::ARCHIVE|Human68kAttrs::EXEC).to_unix_perms(), 0o755);
+    /// assert_eq!((Human68kAttrs::DIRECTORY|Human68kAttrs::READ_ONLY).to_unix_perms(), 0o555);
+    /// ```
+    pub fn to_unix_perms(self) -> u16 {
+        let mut perms = 0o444;
+        if !self.contains(Human68kAttrs::READ_ONLY) {
+            perms |= 0o200;
+        }
+        if self.intersects(Human68kAttrs::EXEC|Human68kAttrs::DIRECTORY) {
+            perms |= 0o111;
+        }
+        perms
+    }
+}
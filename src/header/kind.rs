@@ -0,0 +1,29 @@
+/// The kind of an archived entry, see [LhaHeader::file_type][super::LhaHeader::file_type].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum EntryKind {
+    /// A regular file.
+    File,
+    /// A directory.
+    Directory,
+    /// A symbolic link, see [LhaHeader::symlink_target][super::LhaHeader::symlink_target].
+    Symlink,
+    /// A platform specific special file, e.g. a Unix device or a named pipe, or an MS-DOS volume label.
+    Special
+}
+
+impl EntryKind {
+    /// Returns `true` if `self` is [EntryKind::File].
+    pub fn is_file(&self) -> bool {
+        *self == EntryKind::File
+    }
+    /// Returns `true` if `self` is [EntryKind::Directory].
+    pub fn is_dir(&self) -> bool {
+        *self == EntryKind::Directory
+    }
+    /// Returns `true` if `self` is [EntryKind::Symlink].
+    pub fn is_symlink(&self) -> bool {
+        *self == EntryKind::Symlink
+    }
+}
@@ -0,0 +1,305 @@
+use core::fmt::{self, Write};
+use std::time::SystemTime;
+use chrono::prelude::*;
+use super::*;
+
+const NORMAL_TITLE: &str = "\
+PERMISSION  UID  GID      SIZE  RATIO     STAMP           NAME
+---------- ----------- ------- ------ ------------ --------------------";
+const VERBOSE_TITLE: &str = "\
+PERMISSION  UID  GID    PACKED    SIZE  RATIO METHOD CRC     STAMP          NAME
+---------- ----------- ------- ------- ------ ---------- ------------ --------------------";
+
+/// Renders headers in the classic column layout of the `lha -l` and `lha -v` commands.
+///
+/// Each file is listed with its permissions, owner, sizes, the compression ratio, the last modified
+/// timestamp and the path name. The verbose layout additionally lists the compressed size,
+/// the compression method and the CRC-16 of the file. The timestamps older or newer than six
+/// months are listed with a year instead of the time of day.
+///
+/// The whole listing, including the title and the summary of all pushed headers, is rendered with
+/// the [fmt::Display] implementation. A single line can be rendered with [Listing::line].
+///
+/// ```
+/// use delharc::header::{Listing, LhaHeaderBuilder};
+///
+/// let header = LhaHeaderBuilder::new().path("dir/file.txt").unix_mode(0o100644)
+///                                     .compressed_size(300).original_size(1000).build()?;
+/// let mut listing = Listing::new();
+/// listing.push(&header);
+/// let text = listing.to_string();
+/// let line = text.lines().nth(2).unwrap();
+/// assert!(line.starts_with("-rw-r--r--"));
+/// assert!(line.contains("    1000  30.0% "));
+/// assert!(line.ends_with(" dir/file.txt"));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Listing {
+    verbose: bool,
+    now: NaiveDateTime,
+    lines: String,
+    files: u64,
+    packed: u64,
+    original: u64
+}
+
+impl Default for Listing {
+    fn default() -> Self {
+        Listing::new()
+    }
+}
+
+impl Listing {
+    /// Creates an empty listing in the `lha -l` layout.
+    pub fn new() -> Self {
+        Listing {
+            verbose: false,
+            now: Local::now().naive_local(),
+            lines: String::new(),
+            files: 0,
+            packed: 0,
+            original: 0
+        }
+    }
+    /// Selects the `lha -v` layout if `verbose` is `true`.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+    /// Sets the current time which determines whether the timestamps are listed with the time of day
+    /// or with the year. By default the time of creating the listing is used.
+    pub fn now(mut self, now: SystemTime) -> Self {
+        self.now = DateTime::<Local>::from(now).naive_local();
+        self
+    }
+    /// Appends the line of the header to the listing.
+    pub fn push(&mut self, header: &LhaHeader) {
+        let line = self.line(header);
+        self.lines.push_str(&line);
+        self.lines.push('\n');
+        self.files += 1;
+        self.packed = self.packed.saturating_add(header.compressed_size);
+        self.original = self.original.saturating_add(header.original_size);
+    }
+    /// Renders a single line of the header, without the line terminator.
+    pub fn line(&self, header: &LhaHeader) -> String {
+        let mut line = String::new();
+        self.write_line(&mut line, header).expect("writing to a string never fails");
+        line
+    }
+
+    fn write_line(&self, out: &mut String, header: &LhaHeader) -> fmt::Result {
+        let os = header.os();
+        match header.unix_mode() {
+            Some(mode) if !matches!(os, OsType::Os9|OsType::Osk) => {
+                write!(out, "{}", mode_string(mode))?;
+                match header.unix_uid_gid() {
+                    Some((uid, gid)) => write!(out, " {:>5}/{:<5}", uid, gid)?,
+                    None => write!(out, " {:>11}", "")?
+                }
+            }
+            _ => write!(out, "{:<22}", os_label(os))?
+        }
+        if self.verbose {
+            write!(out, " {:>7}", header.compressed_size)?;
+        }
+        write!(out, " {:>7} {}", header.original_size, Ratio(header.compressed_size, header.original_size))?;
+        if self.verbose {
+            let method = CompressionMethod::from(header.compression);
+            write!(out, " {:<5} {:04x}", method, header.file_crc)?;
+        }
+        match header.parse_last_modified().to_naive_local() {
+            Some(stamp) if (stamp - self.now).num_days().abs() < 183 => {
+                write!(out, " {}", stamp.format("%b %e %H:%M"))?
+            }
+            Some(stamp) => write!(out, " {}", stamp.format("%b %e  %Y"))?,
+            None => write!(out, " {:12}", "")?
+        }
+        out.push(' ');
+        let start = out.len();
+        for (index, component) in header.path_components().enumerate() {
+            if index != 0 {
+                out.push('/');
+            }
+            out.push_str(&component);
+        }
+        match header.symlink_target() {
+            Some(target) => {
+                // the target is appended to the link's name after the `|` character
+                if let Some(index) = out[start..].find('|') {
+                    out.truncate(start + index);
+                }
+                write!(out, " -> {}", target.display())?
+            }
+            None if header.file_type().is_dir() => out.push('/'),
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn title(&self) -> &'static str {
+        if self.verbose { VERBOSE_TITLE } else { NORMAL_TITLE }
+    }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let title = self.title();
+        let separator = title.lines().nth(1).unwrap();
+        writeln!(f, "{}", title)?;
+        f.write_str(&self.lines)?;
+        writeln!(f, "{}", separator)?;
+        let plural = if self.files == 1 { ' ' } else { 's' };
+        write!(f, " Total {:>9} file{}", self.files, plural)?;
+        if self.verbose {
+            write!(f, " {:>7}", self.packed)?;
+        }
+        writeln!(f, " {:>7} {}", self.original, Ratio(self.packed, self.original))
+    }
+}
+
+/// Formats the compression ratio in a 6 characters wide column.
+struct Ratio(u64, u64);
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Ratio(packed, original) = *self;
+        if original == 0 {
+            return f.write_str("******")
+        }
+        let permille = (packed as u128 * 1000 / original as u128).min(9999) as u64;
+        write!(f, "{:>3}.{}%", permille / 10, permille % 10)
+    }
+}
+
+/// Returns the `ls -l` style representation of a Unix file mode.
+fn mode_string(mode: u16) -> String {
+    let kind = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-'
+    };
+    let mut res = String::with_capacity(10);
+    res.push(kind);
+    for (shift, special, exec) in [(6, 0o4000, ['s', 'S']), (3, 0o2000, ['s', 'S']), (0, 0o1000, ['t', 'T'])] {
+        let bits = mode >> shift;
+        res.push(if bits & 4 != 0 { 'r' } else { '-' });
+        res.push(if bits & 2 != 0 { 'w' } else { '-' });
+        res.push(match (mode & special != 0, bits & 1 != 0) {
+            (true, true) => exec[0],
+            (true, false) => exec[1],
+            (false, true) => 'x',
+            (false, false) => '-'
+        });
+    }
+    res
+}
+
+/// Returns the label listed in place of the permissions of files made on systems other than Unix.
+fn os_label(os: OsType) -> &'static str {
+    match os {
+        OsType::Generic => "[generic]",
+        OsType::MsDos => "[MS-DOS]",
+        OsType::Win95 => "[Win9x]",
+        OsType::WinNt => "[WinNT]",
+        OsType::Unix => "[Unix]",
+        OsType::Os2 => "[OS/2]",
+        OsType::MacOs => "[Mac OS]",
+        OsType::Amiga => "[Amiga]",
+        OsType::Atari => "[Atari ST]",
+        OsType::Java => "[Java VM]",
+        OsType::Cpm => "[CP/M]",
+        OsType::FlexOs => "[FLEX]",
+        OsType::Runser => "[Runser]",
+        OsType::TownsOs => "[TownsOS]",
+        OsType::Os9 => "[OS-9]",
+        OsType::Osk => "[OS-9/68K]",
+        OsType::Os386 => "[OS-386]",
+        OsType::Human68k => "[Human68K]",
+        OsType::Xosk => "[XOSK]",
+        _ => "[unknown]"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+
+    #[test]
+    fn mode_string_works() {
+        assert_eq!(mode_string(0o100644), "-rw-r--r--");
+        assert_eq!(mode_string(0o040755), "drwxr-xr-x");
+        assert_eq!(mode_string(0o120777), "lrwxrwxrwx");
+        assert_eq!(mode_string(0o106744), "-rwsr-Sr--");
+        assert_eq!(mode_string(0o041777), "drwxrwxrwt");
+        assert_eq!(mode_string(0o041776), "drwxrwxrwT");
+    }
+
+    #[test]
+    fn listing_works() {
+        let msdos_time = to_msdos_datetime(NaiveDate::from_ymd_opt(2020, 3, 4).unwrap()
+                                            .and_hms_opt(5, 6, 8).unwrap()).unwrap();
+        // 2020-06-01 12:00:00 UTC
+        let now = UNIX_EPOCH + Duration::from_secs(1591012800);
+        let mut listing = Listing::new().now(now);
+        let header = LhaHeaderBuilder::new().level(1).path("dir/file.txt").os_type(OsType::Unix)
+                                            .unix_mode(0o100644).unix_uid_gid(1000, 100)
+                                            .compressed_size(300).original_size(1000).build().unwrap();
+        let header = LhaHeader { last_modified: msdos_time, ..header };
+        assert_eq!(listing.line(&header),
+            "-rw-r--r--  1000/100      1000  30.0% Mar  4 05:06 dir/file.txt");
+        listing.push(&header);
+        let header = LhaHeaderBuilder::new().level(1).path("link|../target").os_type(OsType::Unix)
+                                            .unix_mode(0o120777).build().unwrap();
+        let header = LhaHeader { last_modified: msdos_time - (2 << 25), ..header };
+        assert_eq!(listing.line(&header),
+            "lrwxrwxrwx                   0 ****** Mar  4  2018 link -> ../target");
+        listing.push(&header);
+        let header = LhaHeader {
+            level: 0,
+            filename: b"SUBDIR\\"[..].into(),
+            compression: *b"-lhd-",
+            os_type: b'M',
+            ..LhaHeader::default()
+        };
+        assert_eq!(listing.line(&header),
+            "[MS-DOS]                     0 ******              SUBDIR/");
+        listing.push(&header);
+        assert_eq!(listing.to_string(), "\
+PERMISSION  UID  GID      SIZE  RATIO     STAMP           NAME
+---------- ----------- ------- ------ ------------ --------------------
+-rw-r--r--  1000/100      1000  30.0% Mar  4 05:06 dir/file.txt
+lrwxrwxrwx                   0 ****** Mar  4  2018 link -> ../target
+[MS-DOS]                     0 ******              SUBDIR/
+---------- ----------- ------- ------ ------------ --------------------
+ Total         3 files    1000  30.0%
+");
+
+        let mut listing = Listing::new().now(now).verbose(true);
+        let header = LhaHeader {
+            level: 1,
+            compression: *b"-lh5-",
+            compressed_size: 12345,
+            original_size: 1234,
+            filename: b"A.BIN"[..].into(),
+            last_modified: msdos_time,
+            os_type: b'M',
+            file_crc: 0xbeef,
+            ..LhaHeader::default()
+        };
+        listing.push(&header);
+        assert_eq!(listing.to_string(), "\
+PERMISSION  UID  GID    PACKED    SIZE  RATIO METHOD CRC     STAMP          NAME
+---------- ----------- ------- ------- ------ ---------- ------------ --------------------
+[MS-DOS]                 12345    1234 999.9% -lh5- beef Mar  4 05:06 A.BIN
+---------- ----------- ------- ------- ------ ---------- ------------ --------------------
+ Total         1 file    12345    1234 999.9%
+");
+    }
+}
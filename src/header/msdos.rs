@@ -2,6 +2,7 @@ use bitflags::bitflags;
 
 bitflags! {
     #[derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct MsDosAttrs: u16 {
         const READ_ONLY = 0b00000000_00000001;
         const HIDDEN    = 0b00000000_00000010;
@@ -13,3 +14,84 @@ bitflags! {
         const RESERVED  = 0b11111111_10000000;
     }
 }
+
+impl MsDosAttrs {
+    /// Converts the attributes to a Unix file mode (`st_mode`).
+    ///
+    /// The permission bits are taken from `default_mode`, e.g. `0o644` or the permissions of newly
+    /// created files after applying the `umask`. The write permissions are removed if
+    /// [MsDosAttrs::READ_ONLY] is set. Directories, see [MsDosAttrs::SUBDIR], are also searchable
+    /// by everyone who can read them.
+    ///
+    /// The file type bits are set to a directory, a symbolic link or a regular file.
+    /// [MsDosAttrs::HIDDEN] and [MsDosAttrs::SYSTEM] have no Unix equivalent and are ignored.
+    ///
+    /// ```
+    /// use delharc::header::MsDosAttrs;
+    ///
+    /// assert_eq!(MsDosAttrs::ARCHIVE.to_unix_mode(0o644), 0o100644);
+    /// assert_eq!((MsDosAttrs::ARCHIVE|MsDosAttrs::READ_ONLY).to_unix_mode(0o644), 0o100444);
+    /// assert_eq!(MsDosAttrs::SUBDIR.to_unix_mode(0o640), 0o040750);
+    /// ```
+    pub fn to_unix_mode(self, default_mode: u16) -> u16 {
+        let mut perms = default_mode & 0o7777;
+        if self.contains(MsDosAttrs::READ_ONLY) {
+            perms &= !0o222;
+        }
+        let kind = if self.contains(MsDosAttrs::SYMLINK) {
+            0o120000
+        }
+        else if self.contains(MsDosAttrs::SUBDIR) {
+            perms |= (perms & 0o444) >> 2;
+            0o040000
+        }
+        else {
+            0o100000
+        };
+        kind | perms
+    }
+    /// Converts a Unix file mode (`st_mode`) to the attributes.
+    ///
+    /// Directories are marked with [MsDosAttrs::SUBDIR], symbolic links with [MsDosAttrs::SYMLINK]
+    /// and other files with [MsDosAttrs::ARCHIVE]. [MsDosAttrs::READ_ONLY] is set if the owner has
+    /// no write permission.
+    ///
+    /// ```
+    /// use delharc::header::MsDosAttrs;
+    ///
+    /// assert_eq!(MsDosAttrs::from_unix_mode(0o100644), MsDosAttrs::ARCHIVE);
+    /// assert_eq!(MsDosAttrs::from_unix_mode(0o040555), MsDosAttrs::SUBDIR|MsDosAttrs::READ_ONLY);
+    /// ```
+    pub fn from_unix_mode(mode: u16) -> MsDosAttrs {
+        let mut attrs = match mode & 0o170000 {
+            0o040000 => MsDosAttrs::SUBDIR,
+            0o120000 => MsDosAttrs::SYMLINK,
+            _ => MsDosAttrs::ARCHIVE
+        };
+        if mode & 0o200 == 0 {
+            attrs |= MsDosAttrs::READ_ONLY;
+        }
+        attrs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unix_mode_works() {
+        assert_eq!(MsDosAttrs::empty().to_unix_mode(0o100755), 0o100755);
+        assert_eq!((MsDosAttrs::HIDDEN|MsDosAttrs::SYSTEM).to_unix_mode(0o644), 0o100644);
+        assert_eq!((MsDosAttrs::SUBDIR|MsDosAttrs::READ_ONLY).to_unix_mode(0o604), 0o040505);
+        assert_eq!(MsDosAttrs::SYMLINK.to_unix_mode(0o777), 0o120777);
+        assert_eq!(MsDosAttrs::ARCHIVE.to_unix_mode(0o4644), 0o104644);
+        assert_eq!(MsDosAttrs::from_unix_mode(0o120777), MsDosAttrs::SYMLINK);
+        assert_eq!(MsDosAttrs::from_unix_mode(0o100444), MsDosAttrs::ARCHIVE|MsDosAttrs::READ_ONLY);
+        assert_eq!(MsDosAttrs::from_unix_mode(0o040755), MsDosAttrs::SUBDIR);
+        for attrs in [MsDosAttrs::ARCHIVE, MsDosAttrs::ARCHIVE|MsDosAttrs::READ_ONLY,
+                      MsDosAttrs::SUBDIR, MsDosAttrs::SUBDIR|MsDosAttrs::READ_ONLY] {
+            assert_eq!(MsDosAttrs::from_unix_mode(attrs.to_unix_mode(0o644)), attrs);
+        }
+    }
+}
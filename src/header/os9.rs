@@ -0,0 +1,46 @@
+use bitflags::bitflags;
+
+bitflags! {
+    /// OS-9 file attributes.
+    #[derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Os9Perms: u16 {
+        const READ         = 0b00000000_00000001;
+        const WRITE        = 0b00000000_00000010;
+        const EXEC         = 0b00000000_00000100;
+        const PUBLIC_READ  = 0b00000000_00001000;
+        const PUBLIC_WRITE = 0b00000000_00010000;
+        const PUBLIC_EXEC  = 0b00000000_00100000;
+        const SHARED       = 0b00000000_01000000;
+        const DIRECTORY    = 0b00000000_10000000;
+    }
+}
+
+/// The content of the ["OS-9"][super::ext::EXT_HEADER_OS9] extra header, found in archives made
+/// on OS-9 systems.
+///
+/// Only the file attributes are being interpreted, the timestamps of the file are stored in
+/// the base header as usual.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Os9Metadata<'a> {
+    /// File attributes.
+    pub perms: Os9Perms,
+    /// The raw header data.
+    pub data: &'a [u8]
+}
+
+impl<'a> Os9Metadata<'a> {
+    /// Parses the "OS-9" extra header data, excluding the header identifier.
+    ///
+    /// Returns `None` if the data is too short.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        match data.get(7..9)? {
+            &[lo, hi] => Some(Os9Metadata {
+                perms: Os9Perms::from_bits_retain(u16::from_le_bytes([lo, hi])),
+                data
+            }),
+            _ => None
+        }
+    }
+}
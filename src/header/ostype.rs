@@ -6,68 +6,91 @@ use core::convert::TryFrom;
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct UnrecognizedOsType(pub u8);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The operating system on which the archive was created.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
-#[repr(u8)]
 pub enum OsType {
-    Generic =            0x00,
-    MsDos =              b'M',
-    Win95 =              b'w',
-    WinNt =              b'W',
-    Unix =               b'U',
-    Os2 =                b'2',
-    MacOs =              b'm',
-    Amiga =              b'A',
-    Atari =              b'a',
-    Java =               b'J',
-    Cpm =                b'C',
-    FlexOs =             b'F',
-    Runser =             b'R',
-    TownsOs =            b'T',
-    Os9 =                b'9',
-    Osk =                b'K',
-    Os386 =              b'3',
-    Human68k =           b'H',
-    Xosk =               b'X',
+    #[default]
+    Generic,
+    MsDos,
+    Win95,
+    WinNt,
+    Unix,
+    Os2,
+    MacOs,
+    Amiga,
+    Atari,
+    Java,
+    Cpm,
+    FlexOs,
+    Runser,
+    TownsOs,
+    Os9,
+    Osk,
+    Os386,
+    Human68k,
+    Xosk,
+    /// An unrecognized raw OS-TYPE.
+    Unknown(u8),
 }
 
-impl Default for OsType {
-    fn default() -> OsType {
-        OsType::Generic
+const OS_TYPES: &[(u8, OsType)] = &[
+    (0x00, OsType::Generic),
+    (b'M', OsType::MsDos),
+    (b'w', OsType::Win95),
+    (b'W', OsType::WinNt),
+    (b'U', OsType::Unix),
+    (b'2', OsType::Os2),
+    (b'm', OsType::MacOs),
+    (b'A', OsType::Amiga),
+    (b'a', OsType::Atari),
+    (b'J', OsType::Java),
+    (b'C', OsType::Cpm),
+    (b'F', OsType::FlexOs),
+    (b'R', OsType::Runser),
+    (b'T', OsType::TownsOs),
+    (b'9', OsType::Os9),
+    (b'K', OsType::Osk),
+    (b'3', OsType::Os386),
+    (b'H', OsType::Human68k),
+    (b'X', OsType::Xosk),
+];
+
+impl OsType {
+    /// Returns the `OsType` from a raw OS-TYPE, unrecognized values are returned as [OsType::Unknown].
+    pub fn from_raw(ostype: u8) -> OsType {
+        OsType::try_from(ostype).unwrap_or(OsType::Unknown(ostype))
+    }
+    /// Returns `true` unless `self` is [OsType::Unknown].
+    pub fn is_known(&self) -> bool {
+        !matches!(self, OsType::Unknown(..))
+    }
+    /// Returns `true` for Unix-like systems storing Unix file metadata in headers.
+    pub fn is_unix_like(&self) -> bool {
+        matches!(self, OsType::Unix|OsType::Osk)
+    }
+    /// Returns `true` for MS-DOS and its descendants: Windows and OS/2.
+    pub fn is_msdos_like(&self) -> bool {
+        matches!(self, OsType::MsDos|OsType::Win95|OsType::WinNt|OsType::Os2)
     }
 }
 
 impl From<OsType> for u8 {
     fn from(ostype: OsType) -> u8 {
-        ostype as u8
+        match ostype {
+            OsType::Unknown(ostype) => ostype,
+            ostype => OS_TYPES.iter().find(|(_, os)| *os == ostype).unwrap().0
+        }
     }
 }
 
 impl TryFrom<u8> for OsType {
     type Error = UnrecognizedOsType;
     fn try_from(ostype: u8) -> Result<Self, Self::Error> {
-        Ok(match ostype {
-            0x00 => OsType::Generic,
-            b'M' => OsType::MsDos,
-            b'w' => OsType::Win95,
-            b'W' => OsType::WinNt,
-            b'U' => OsType::Unix,
-            b'2' => OsType::Os2,
-            b'm' => OsType::MacOs,
-            b'A' => OsType::Amiga,
-            b'a' => OsType::Atari,
-            b'J' => OsType::Java,
-            b'C' => OsType::Cpm,
-            b'F' => OsType::FlexOs,
-            b'R' => OsType::Runser,
-            b'T' => OsType::TownsOs,
-            b'9' => OsType::Os9,
-            b'K' => OsType::Osk,
-            b'3' => OsType::Os386,
-            b'H' => OsType::Human68k,
-            b'X' => OsType::Xosk,
-            _ => return Err(UnrecognizedOsType(ostype))
-        })
+        OS_TYPES.iter().find(|(raw, _)| *raw == ostype)
+                       .map(|&(_, os)| os)
+                       .ok_or(UnrecognizedOsType(ostype))
     }
 }
 
@@ -84,3 +107,25 @@ impl From<UnrecognizedOsType> for io::Error {
         io::Error::new(io::ErrorKind::InvalidData, e)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn os_type_works() {
+        for raw in 0..=255u8 {
+            let os = OsType::from_raw(raw);
+            assert_eq!(u8::from(os), raw);
+            assert_eq!(OsType::try_from(raw).is_ok(), os.is_known());
+        }
+        assert_eq!(OsType::from_raw(b'U'), OsType::Unix);
+        assert_eq!(OsType::from_raw(b'Z'), OsType::Unknown(b'Z'));
+        assert_eq!(OsType::try_from(b'Z'), Err(UnrecognizedOsType(b'Z')));
+        assert_eq!(u8::from(OsType::default()), 0);
+        assert!(OsType::Osk.is_unix_like());
+        assert!(!OsType::Os9.is_unix_like());
+        assert!(OsType::WinNt.is_msdos_like());
+        assert!(!OsType::Generic.is_msdos_like());
+    }
+}
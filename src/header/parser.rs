@@ -1,10 +1,12 @@
 use core::num::Wrapping;
+use core::ops::Range;
 use core::slice;
 use std::fmt::Write;
 use std::io::{self, Read};
-use std::path::PathBuf;
+use std::path::{Component, PathBuf};
 use std::borrow::Cow;
 use crate::crc::Crc16;
+use crate::error::LhaError;
 use super::*;
 
 /// Raw identifiers of extra headers.
@@ -19,6 +21,8 @@ pub mod ext {
     pub const EXT_HEADER_MSDOS_ATTRS: u8 = 0x40;
     pub const EXT_HEADER_MSDOS_TIME:  u8 = 0x41;
     pub const EXT_HEADER_MSDOS_SIZE:  u8 = 0x42;
+    pub const EXT_HEADER_UNICODE_FILENAME: u8 = 0x44;
+    pub const EXT_HEADER_UNICODE_PATH:     u8 = 0x45;
     pub const EXT_HEADER_UNIX_PERM:   u8 = 0x50;
     pub const EXT_HEADER_UNIX_UIDGID: u8 = 0x51;
     pub const EXT_HEADER_UNIX_GROUP:  u8 = 0x52;
@@ -60,6 +64,166 @@ impl<'a> Iterator for ExtraHeaderIter<'a> {
     }
 }
 
+/// A parsed extra header.
+///
+/// Headers with a recognized identifier, but with data too short for the expected content,
+/// are represented as [ExtraHeader::Unknown].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum ExtraHeader<'a> {
+    /// The "Common" header data. The header's CRC-16 field is always reset to 0.
+    Common(&'a [u8]),
+    /// A raw file name.
+    Filename(&'a [u8]),
+    /// A raw directory name, components are separated with `0xFF`.
+    Path(&'a [u8]),
+    /// Multi-disc archive data.
+    MultiDisc(#[cfg_attr(feature = "serde", serde(borrow))] MultiDiscInfo<'a>),
+    /// A raw file comment.
+    Comment(&'a [u8]),
+    /// A raw Amiga filenote: the file comment of a file archived on [OsType::Amiga], yielded by
+    /// [LhaHeader::parse_extra] instead of [ExtraHeader::Comment]. See [LhaHeader::amiga_filenote].
+    AmigaFilenote(&'a [u8]),
+    /// MS-DOS attributes.
+    MsDosAttrs(MsDosAttrs),
+    /// Windows [FILETIME] timestamps.
+    ///
+    /// [FILETIME]: https://docs.microsoft.com/en-us/windows/win32/api/minwinbase/ns-minwinbase-filetime
+    MsDosTimes(MsDosTimes),
+    /// 64-bit file sizes.
+    MsDosSize {
+        compressed_size: u64,
+        original_size: u64
+    },
+    /// A raw UTF-16LE file name.
+    UnicodeFilename(&'a [u8]),
+    /// A raw UTF-16LE directory name, components are separated with `U+FFFF`.
+    UnicodePath(&'a [u8]),
+    /// Unix file mode.
+    UnixPerm(u16),
+    /// Unix group and user identifiers.
+    UidGid {
+        gid: u16,
+        uid: u16
+    },
+    /// A raw Unix group name.
+    UnixGroup(&'a [u8]),
+    /// A raw Unix owner name.
+    UnixOwner(&'a [u8]),
+    /// Unix last modified timestamp.
+    UnixTime(u32),
+    /// OS-9 file metadata.
+    Os9(#[cfg_attr(feature = "serde", serde(borrow))] Os9Metadata<'a>),
+    /// Any other header, the `data` excludes the header identifier.
+    Unknown {
+        id: u8,
+        data: &'a [u8]
+    }
+}
+
+impl<'a> ExtraHeader<'a> {
+    /// Parses the raw header data as yielded by [ExtraHeaderIter].
+    ///
+    /// # Panics
+    /// Panics if `raw` is empty.
+    pub fn parse(raw: &'a [u8]) -> Self {
+        let (id, data) = raw.split_first().expect("extra header can't be empty");
+        match (*id, data) {
+            (EXT_HEADER_COMMON, data) => ExtraHeader::Common(data),
+            (EXT_HEADER_FILENAME, data) => ExtraHeader::Filename(data),
+            (EXT_HEADER_PATH, data) => ExtraHeader::Path(data),
+            (EXT_HEADER_MULTI_DISC, &[disc_number, flags, ref rest @ ..]) => {
+                ExtraHeader::MultiDisc(MultiDiscInfo { disc_number, flags, rest })
+            }
+            (EXT_HEADER_COMMENT, data) => ExtraHeader::Comment(data),
+            (EXT_HEADER_MSDOS_ATTRS, &[lo, hi, ..]) => {
+                ExtraHeader::MsDosAttrs(MsDosAttrs::from_bits_retain(u16::from_le_bytes([lo, hi])))
+            }
+            (EXT_HEADER_MSDOS_TIME, data) if data.len() >= 24 => ExtraHeader::MsDosTimes(MsDosTimes {
+                created: read_u64(&data[0..8]).unwrap(),
+                last_modified: read_u64(&data[8..16]).unwrap(),
+                last_access: read_u64(&data[16..24]).unwrap()
+            }),
+            (EXT_HEADER_MSDOS_SIZE, data) if data.len() >= 16 => ExtraHeader::MsDosSize {
+                compressed_size: read_u64(&data[0..8]).unwrap(),
+                original_size: read_u64(&data[8..16]).unwrap()
+            },
+            (EXT_HEADER_UNICODE_FILENAME, data) => ExtraHeader::UnicodeFilename(data),
+            (EXT_HEADER_UNICODE_PATH, data) => ExtraHeader::UnicodePath(data),
+            (EXT_HEADER_UNIX_PERM, &[lo, hi, ..]) => ExtraHeader::UnixPerm(u16::from_le_bytes([lo, hi])),
+            (EXT_HEADER_UNIX_UIDGID, &[g0, g1, u0, u1, ..]) => ExtraHeader::UidGid {
+                gid: u16::from_le_bytes([g0, g1]),
+                uid: u16::from_le_bytes([u0, u1])
+            },
+            (EXT_HEADER_UNIX_GROUP, data) => ExtraHeader::UnixGroup(data),
+            (EXT_HEADER_UNIX_OWNER, data) => ExtraHeader::UnixOwner(data),
+            (EXT_HEADER_UNIX_TIME, data) if data.len() >= 4 => {
+                ExtraHeader::UnixTime(read_u32(&data[0..4]).unwrap())
+            }
+            (EXT_HEADER_OS9, data) if data.len() >= 9 => {
+                ExtraHeader::Os9(Os9Metadata::parse(data).unwrap())
+            }
+            (id, data) => ExtraHeader::Unknown { id, data }
+        }
+    }
+    /// Returns the raw header identifier.
+    pub fn id(&self) -> u8 {
+        match self {
+            ExtraHeader::Common(..) => EXT_HEADER_COMMON,
+            ExtraHeader::Filename(..) => EXT_HEADER_FILENAME,
+            ExtraHeader::Path(..) => EXT_HEADER_PATH,
+            ExtraHeader::MultiDisc(..) => EXT_HEADER_MULTI_DISC,
+            ExtraHeader::Comment(..)|ExtraHeader::AmigaFilenote(..) => EXT_HEADER_COMMENT,
+            ExtraHeader::MsDosAttrs(..) => EXT_HEADER_MSDOS_ATTRS,
+            ExtraHeader::MsDosTimes(..) => EXT_HEADER_MSDOS_TIME,
+            ExtraHeader::MsDosSize {..} => EXT_HEADER_MSDOS_SIZE,
+            ExtraHeader::UnicodeFilename(..) => EXT_HEADER_UNICODE_FILENAME,
+            ExtraHeader::UnicodePath(..) => EXT_HEADER_UNICODE_PATH,
+            ExtraHeader::UnixPerm(..) => EXT_HEADER_UNIX_PERM,
+            ExtraHeader::UidGid {..} => EXT_HEADER_UNIX_UIDGID,
+            ExtraHeader::UnixGroup(..) => EXT_HEADER_UNIX_GROUP,
+            ExtraHeader::UnixOwner(..) => EXT_HEADER_UNIX_OWNER,
+            ExtraHeader::UnixTime(..) => EXT_HEADER_UNIX_TIME,
+            ExtraHeader::Os9(..) => EXT_HEADER_OS9,
+            ExtraHeader::Unknown { id, .. } => *id
+        }
+    }
+}
+
+/// The content of the ["Multi-disc"][EXT_HEADER_MULTI_DISC] extra header, present in the archives
+/// split across several discs.
+///
+/// The layout of this header is not well documented: the first byte is interpreted as the disc
+/// number and the second one as raw flags. Any remaining data is available as raw bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiDiscInfo<'a> {
+    /// The number of the disc.
+    pub disc_number: u8,
+    /// Raw flags.
+    pub flags: u8,
+    /// The remaining header data.
+    pub rest: &'a [u8]
+}
+
+/// An iterator through extra headers, yielding parsed [ExtraHeader]s.
+pub struct ParseExtraIter<'a> {
+    inner: ExtraHeaderIter<'a>,
+    amiga: bool
+}
+
+impl<'a> Iterator for ParseExtraIter<'a> {
+    type Item = ExtraHeader<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|raw| match ExtraHeader::parse(raw) {
+            ExtraHeader::Comment(data) if self.amiga => ExtraHeader::AmigaFilenote(data),
+            header => header
+        })
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 #[repr(C)]
 #[repr(packed)]
@@ -72,22 +236,51 @@ struct LhaRawBaseHeader {
     lha_level: u8
 }
 
-struct Parser<R> {
+/// A reader retaining all of the bytes read.
+struct RecordingReader<R> {
+    rd: R,
+    raw: Vec<u8>
+}
+
+impl<R: Read> Read for RecordingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.rd.read(buf)?;
+        self.raw.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+}
+
+struct Parser<'p, R> {
     rd: R,
     crc: Crc16,
     csum: Wrapping<u8>,
-    len: usize
+    len: usize,
+    progress: &'p mut HeaderProgress
 }
 
-impl<R: Read> Parser<R> {
+/// The progress of parsing a truncated header, see [LhaHeader::read_with_progress].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct HeaderProgress {
+    /// The number of bytes spanned by the header at least.
+    pub min_len: usize,
+    /// The size of the length field of the extra header ending at `min_len`, if it was being read.
+    pub extra_header: Option<usize>
+}
+
+impl<R: Read> Parser<'_, R> {
     // NOTE: does not update wrapping sum
     fn read_u8_or_none(&mut self) -> io::Result<Option<u8>> {
-        self.rd.by_ref().bytes().next().transpose().map(|mb|
-            mb.map(|byte| {
-                self.update_checksums_no_wrapping_sum(slice::from_ref(&byte));
-                byte
-            })
-        )
+        let mut byte = 0u8;
+        loop {
+            match self.rd.read(slice::from_mut(&mut byte)) {
+                Ok(0) => return Ok(None),
+                Ok(..) => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e)
+            }
+        }
+        self.update_checksums_no_wrapping_sum(slice::from_ref(&byte));
+        Ok(Some(byte))
     }
 
     fn read_u8(&mut self) -> io::Result<u8> {
@@ -109,7 +302,10 @@ impl<R: Read> Parser<R> {
     }
 
     fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
-        self.rd.read_exact(buf)?;
+        self.rd.read_exact(buf).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => self.truncated(buf.len()),
+            _ => e
+        })?;
         self.update_checksums(buf);
         Ok(())
     }
@@ -117,7 +313,7 @@ impl<R: Read> Parser<R> {
     fn read_limit(&mut self, limit: usize) -> io::Result<Box<[u8]>> {
         let mut buf = Vec::with_capacity(limit);
         self.read_limit_no_checksums(limit, &mut buf)?;
-        self.update_checksums(&mut buf);
+        self.update_checksums(&buf);
         Ok(buf.into_boxed_slice())
     }
 
@@ -133,12 +329,21 @@ impl<R: Read> Parser<R> {
 
     fn read_limit_no_checksums(&mut self, limit: usize, buf: &mut Vec<u8>) -> io::Result<()> {
         if self.rd.by_ref().take(limit as u64).read_to_end(buf)? != limit {
-            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "file is too short"))
+            return Err(self.truncated(limit))
         }
         Ok(())
     }
+
+    /// Records the progress of parsing the header ending before a field of `len` bytes.
+    fn truncated(&mut self, len: usize) -> io::Error {
+        self.progress.min_len = self.len.saturating_add(len);
+        LhaError::TruncatedHeader.into()
+    }
 }
 
+/// The maximum size of a header in bytes accepted by [LhaHeader::read] and [LhaHeader::read_with_mode].
+pub const DEFAULT_MAX_HEADER_SIZE: usize = 4 << 20;
+
 impl LhaHeader {
     /// Attempts to parse the LHA header. Returns `Ok(Some(LhaHeader))` on success. Returns `Ok(None)`
     /// if the end of archive marker (a `0` byte) was encountered.
@@ -150,6 +355,8 @@ impl LhaHeader {
     /// * The ["MS-DOS Size"][EXT_HEADER_MSDOS_SIZE] header for reading 64-bit file size.
     ///
     /// All extra data is available as raw bytes and extra headers can be iterated with [LhaHeader::iter_extra].
+    /// The file name, the directory name and the file size extra headers must not be repeated.
+    /// Headers larger than [DEFAULT_MAX_HEADER_SIZE] bytes are refused, see [LhaHeader::read_with_limit].
     ///
     /// Instance methods can be further called on the parsed `LhaHeader` struct to attempt to parse the
     /// name and path of the file or other file's meta-data.
@@ -157,11 +364,60 @@ impl LhaHeader {
     /// # Errors
     /// Returns an error from the underlying reading operations or because a malformed header was encountered.
     pub fn read<R: Read>(rd: R) -> io::Result<Option<LhaHeader>> {
+        LhaHeader::read_with_mode(rd, ParseMode::Strict)
+    }
+
+    /// Attempts to parse the LHA header the same way as [LhaHeader::read], handling inconsistent
+    /// length and checksum fields according to the given `mode`.
+    ///
+    /// With [ParseMode::Lenient] the anomalies which don't prevent reading the header are recorded
+    /// in [LhaHeader::warnings]:
+    /// * a wrong checksum of level 0 and 1 headers or a wrong CRC-16 of the "Common" extra header,
+    /// * a header length field not matching the actual size of the header, the extended area of level
+    ///   0 and 1 headers is assumed empty if the stored length is too short and the excessive bytes
+    ///   of level 2 headers are skipped,
+    /// * the level 1 compressed size not including the size of the extra headers,
+    /// * a repeated file name, directory name or file size extra header.
+    ///
+    /// # Errors
+    /// Returns an error from the underlying reading operations or because a malformed header was encountered.
+    pub fn read_with_mode<R: Read>(rd: R, mode: ParseMode) -> io::Result<Option<LhaHeader>> {
+        LhaHeader::read_with_limit(rd, mode, Some(DEFAULT_MAX_HEADER_SIZE))
+    }
+
+    /// Attempts to parse the LHA header the same way as [LhaHeader::read_with_mode], refusing
+    /// headers larger than `max_header_size` bytes, if given, before reading their extra headers.
+    ///
+    /// The extra headers of level 1 and 3 headers can be chained without bound, limiting the size of
+    /// the headers protects from excessive allocations when reading untrusted archives.
+    /// [LhaHeader::read] and [LhaHeader::read_with_mode] limit the size of the headers to
+    /// [DEFAULT_MAX_HEADER_SIZE] bytes, pass `None` to read headers of any size.
+    ///
+    /// # Errors
+    /// Returns an error from the underlying reading operations or because a malformed header was encountered.
+    /// An error of the kind [io::ErrorKind::InvalidData] is returned if the header is too large.
+    pub fn read_with_limit<R: Read>(rd: R, mode: ParseMode, max_header_size: Option<usize>) -> io::Result<Option<LhaHeader>> {
+        LhaHeader::read_with_progress(rd, mode, max_header_size, &mut HeaderProgress::default())
+    }
+
+    /// Attempts to parse the LHA header the same way as [LhaHeader::read_with_limit]. If the header
+    /// is truncated, records the number of bytes it spans at least in `progress`.
+    pub(crate) fn read_with_progress<R: Read>(
+            rd: R,
+            mode: ParseMode,
+            max_header_size: Option<usize>,
+            progress: &mut HeaderProgress
+        ) -> io::Result<Option<LhaHeader>>
+    {
+        let max_header_size = max_header_size.unwrap_or(usize::MAX);
+        let lenient = mode == ParseMode::Lenient;
+        let mut warnings = Vec::new();
         let mut parser = Parser {
             rd, 
             crc: Crc16::default(),
             csum: Wrapping(0),
-            len: 0
+            len: 0,
+            progress
         };
         let header_len = match parser.read_u8_or_none()? {
             Some(0)|None => return Ok(None),
@@ -177,14 +433,14 @@ impl LhaHeader {
             struct_slice_mut(&mut raw_header)
         })?;
         if raw_header.lha_level > 3 {
-            return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown header level"))
+            return Err(LhaError::UnknownHeaderLevel(raw_header.lha_level).into())
         }
 
         // read filename if level 0 or 1
         let filename = if raw_header.lha_level < 2 {
             let filename_len = parser.read_u8()? as usize;
-            if (header_len as usize) < parser.len + filename_len {
-                return Err(io::Error::new(io::ErrorKind::InvalidData, "wrong header size"))
+            if !lenient && (header_len as usize) < parser.len + filename_len {
+                return Err(LhaError::WrongHeaderSize { level: raw_header.lha_level }.into())
             }
             parser.read_limit(filename_len)?
         }
@@ -208,10 +464,17 @@ impl LhaHeader {
             if raw_header.lha_level == 0 {
                 min_len -= 2; // no extra headers
             }
-            if (header_len as usize) < min_len {
-                return Err(io::Error::new(io::ErrorKind::InvalidData, "wrong header size"))
-            }
-            let mut extended_len = (header_len as usize) - min_len;
+            let mut extended_len = match (header_len as usize).checked_sub(min_len) {
+                Some(len) => len,
+                None if lenient => {
+                    warnings.push(HeaderWarning::HeaderLength {
+                        stored: header_len as u32,
+                        actual: min_len as u32
+                    });
+                    0
+                }
+                None => return Err(LhaError::WrongHeaderSize { level: raw_header.lha_level }.into())
+            };
             if extended_len != 0 && raw_header.lha_level == 0  {
                 // get os_type from level 0 extended area
                 extended_len -= 1;
@@ -239,7 +502,7 @@ impl LhaHeader {
                 long_header_len = parser.read_u32()?;
                 first_header_len = parser.read_u32()?;
                 if header_len != 4 || csum != 0 {
-                    return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid header"))
+                    return Err(LhaError::InvalidHeader("invalid header").into())
                 }
             }
             _ => {}
@@ -248,42 +511,77 @@ impl LhaHeader {
         // validate level 0 and 1 header checksum
         if raw_header.lha_level < 2 {
             if csum != parser.csum.0 {
-                return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid header level checksum"))
+                let warning = HeaderWarning::Checksum { stored: csum, computed: parser.csum.0 };
+                if !lenient {
+                    return Err(LhaError::BadHeader(warning).into())
+                }
+                warnings.push(warning);
             }
         }
-        else if long_header_len < parser.len as u32 + first_header_len {
-            return Err(io::Error::new(io::ErrorKind::InvalidData, "wrong header size"))
+        else if !lenient && long_header_len < parser.len as u32 + first_header_len {
+            return Err(LhaError::WrongHeaderSize { level: raw_header.lha_level }.into())
         }
 
         let mut msdos_attrs = MsDosAttrs::from_bits_retain(raw_header.msdos_attrs as u16);
         let mut original_size = u32::from_le_bytes(raw_header.original_size) as u64;
         let mut compressed_size = u32::from_le_bytes(raw_header.compressed_size) as u64;
         let mut header_crc: Option<u16> = None;
+        // the identifiers and data ranges of the headers which should not be repeated
+        let mut unique_headers: Vec<(u8, Range<usize>)> = Vec::new();
         // read extra headers
         let min_header_len = if raw_header.lha_level == 3 { 5 } else { 3 };
         let mut extra_header_len = first_header_len as usize;
         while extra_header_len != 0 {
             if extra_header_len < min_header_len {
-                return Err(io::Error::new(io::ErrorKind::InvalidData, "wrong extra header size"))
+                return Err(LhaError::InvalidHeader("wrong extra header size").into())
             }
             // check long header length (level 2, 3)
-            if long_header_len != 0 {
+            if lenient {
+                // the lengths are verified after all extra headers are read
+            }
+            else if long_header_len != 0 {
                 if (long_header_len as usize) < parser.len + extra_header_len - 2 {
-                    return Err(io::Error::new(io::ErrorKind::InvalidData, "wrong header size"))
+                    return Err(LhaError::WrongHeaderSize { level: raw_header.lha_level }.into())
                 }
             }
             else if compressed_size < (extra_headers.len() + extra_header_len) as u64 {
                 // otherwise check skip size (level 1)
-                return Err(io::Error::new(io::ErrorKind::InvalidData, "wrong header size"))
+                return Err(LhaError::WrongHeaderSize { level: raw_header.lha_level }.into())
             }
+            if parser.len.saturating_add(extra_header_len) > max_header_size {
+                return Err(header_too_large())
+            }
+            parser.progress.extra_header = Some(min_header_len - 1);
             parser.read_limit_no_checksums(extra_header_len, &mut extra_headers)?;
+            parser.progress.extra_header = None;
             let start = extra_headers.len() - extra_header_len;
+            let id = extra_headers[start];
+            if matches!(id, EXT_HEADER_FILENAME|EXT_HEADER_PATH|EXT_HEADER_MSDOS_SIZE|
+                            EXT_HEADER_UNICODE_FILENAME|EXT_HEADER_UNICODE_PATH)
+            {
+                let data = start + 1..extra_headers.len() - min_header_len + 1;
+                if let Some((_, other)) = unique_headers.iter().find(|(other_id, _)| *other_id == id) {
+                    let warning = if extra_headers[other.clone()] == extra_headers[data.clone()] {
+                        HeaderWarning::DuplicateExtraHeader { id }
+                    }
+                    else {
+                        HeaderWarning::ConflictingExtraHeader { id }
+                    };
+                    if !lenient {
+                        return Err(LhaError::BadHeader(warning).into())
+                    }
+                    warnings.push(warning);
+                }
+                else {
+                    unique_headers.push((id, data));
+                }
+            }
             let header = &mut extra_headers[start..];
             match header {
                 // we need to extract the CRC-16 from header and clear it in order to calculate checksum
                 [EXT_HEADER_COMMON, data @ ..] => {
                     if header_crc.is_some() {
-                        return Err(io::Error::new(io::ErrorKind::InvalidData, "double common CRC-16 header"))
+                        return Err(LhaError::InvalidHeader("double common CRC-16 header").into())
                     }
                     if let Some(crc) = data.get_mut(0..2) {
                         header_crc = read_u16(crc);
@@ -299,12 +597,9 @@ impl LhaHeader {
                     }
                 }
                 [EXT_HEADER_MSDOS_SIZE, data @ ..] if raw_header.lha_level >= 2 && data.len() >= 16 => {
-                    match (read_u64(&data[0..8]), read_u64(&data[8..16])) {
-                        (Some(compr), Some(orig)) => {
-                            compressed_size = compr;
-                            original_size = orig;
-                        }
-                        _ => {}
+                    if let (Some(compr), Some(orig)) = (read_u64(&data[0..8]), read_u64(&data[8..16])) {
+                        compressed_size = compr;
+                        original_size = orig;
                     }
                 }
                 _ => {}
@@ -319,33 +614,58 @@ impl LhaHeader {
         }
 
         // validate long header length
-        if long_header_len != 0 {
-            if long_header_len != parser.len as u32 {
-                if raw_header.lha_level == 2 && long_header_len == parser.len as u32 + 1
-                {
-                    // read padding byte
-                    parser.read_u8()?;
+        if long_header_len != 0 && long_header_len != parser.len as u32 {
+            if raw_header.lha_level == 2 && long_header_len == parser.len as u32 + 1
+            {
+                // read padding byte
+                parser.read_u8()?;
+            }
+            else if raw_header.lha_level == 2 && long_header_len + 2 != parser.len as u32 {
+                // some packers (Osk) don't include self in the header length
+                let warning = HeaderWarning::HeaderLength {
+                    stored: long_header_len,
+                    actual: parser.len as u32
+                };
+                if !lenient {
+                    return Err(LhaError::BadHeader(warning).into())
                 }
-                else if raw_header.lha_level == 2 && long_header_len + 2 != parser.len as u32 {
-                    // some packers (Osk) don't include self in the header length
-                    return Err(io::Error::new(io::ErrorKind::InvalidData, "wrong length of headers"))
+                warnings.push(warning);
+                if let Some(excess) = (long_header_len as usize).checked_sub(parser.len) {
+                    if long_header_len as usize > max_header_size {
+                        return Err(header_too_large())
+                    }
+                    parser.read_limit(excess)?;
                 }
             }
         }
 
         // validate headers CRC
         if let Some(crc) = header_crc {
-            if crc != parser.crc.sum16() {
-                return Err(io::Error::new(io::ErrorKind::InvalidData, "wrong header CRC-16 checksum"))
+            let computed = parser.crc.sum16();
+            if crc != computed {
+                let warning = HeaderWarning::HeaderCrc { stored: crc, computed };
+                if !lenient {
+                    return Err(LhaError::BadHeader(warning).into())
+                }
+                warnings.push(warning);
             }
         }
 
         // adjust compressed size for level 1
         if raw_header.lha_level == 1 {
-            if extra_headers.len() as u64 > compressed_size {
-                return Err(io::Error::new(io::ErrorKind::InvalidData, "wrong length of skip size"))
+            let extra_len = extra_headers.len() as u64;
+            if extra_len > compressed_size {
+                let warning = HeaderWarning::SkipSize { stored: compressed_size, extra_headers: extra_len };
+                if !lenient {
+                    return Err(LhaError::BadHeader(warning).into())
+                }
+                warnings.push(warning);
             }
-            compressed_size -= extra_headers.len() as u64;
+            compressed_size = compressed_size.saturating_sub(extra_len);
+        }
+
+        if parser.len > max_header_size {
+            return Err(header_too_large())
         }
 
         let compression = raw_header.compression;
@@ -364,7 +684,29 @@ impl LhaHeader {
             file_crc,
             extended_area,
             first_header_len,
-            extra_headers
+            extra_headers,
+            warnings,
+            raw_header: None,
+            header_size: parser.len as u64,
+            header_offset: None
+        }))
+    }
+
+    /// Attempts to parse the LHA header the same way as [LhaHeader::read_with_mode], retaining
+    /// the exact bytes of the header in [LhaHeader::raw_header].
+    ///
+    /// The retained bytes include the length and checksum fields as well as the padding byte of
+    /// level 2 headers, so the original header can be preserved or written back unchanged, even if it
+    /// was produced by a packer with its own quirks.
+    ///
+    /// # Errors
+    /// Returns an error from the underlying reading operations or because a malformed header was encountered.
+    pub fn read_with_raw<R: Read>(rd: R, mode: ParseMode) -> io::Result<Option<LhaHeader>> {
+        let mut rd = RecordingReader { rd, raw: Vec::new() };
+        let header = LhaHeader::read_with_mode(&mut rd, mode)?;
+        Ok(header.map(|header| LhaHeader {
+            raw_header: Some(rd.raw.into_boxed_slice()),
+            ..header
         }))
     }
 
@@ -380,9 +722,35 @@ impl LhaHeader {
             header_len32: self.level == 3
         }
     }
+
+    /// Returns an iterator that will iterate through extra headers, yielding the parsed [ExtraHeader]s.
+    ///
+    /// The comments of the files archived on [OsType::Amiga] are yielded as
+    /// [ExtraHeader::AmigaFilenote].
+    pub fn parse_extra(&self) -> ParseExtraIter<'_> {
+        ParseExtraIter {
+            inner: self.iter_extra(),
+            amiga: self.os() == OsType::Amiga
+        }
+    }
+
+    /// Returns the data of the first extra header with the identifier `id`, excluding the identifier
+    /// and the next header length field. See [ext] for the identifiers of the known headers.
+    pub fn get_extra(&self, id: u8) -> Option<&[u8]> {
+        self.get_extra_all(id).next()
+    }
+
+    /// Returns an iterator through the data of all extra headers with the identifier `id`, excluding
+    /// the identifier and the next header length field.
+    pub fn get_extra_all(&self, id: u8) -> impl Iterator<Item=&[u8]> + '_ {
+        self.iter_extra().filter_map(move |header| match header.split_first() {
+            Some((&header_id, data)) if header_id == id => Some(data),
+            _ => None
+        })
+    }
 }
 
-fn read_u16(slice: &[u8]) -> Option<u16> {
+pub(super) fn read_u16(slice: &[u8]) -> Option<u16> {
     match slice {
         &[lo, hi] => Some(u16::from_le_bytes([lo, hi])),
         _ => None
@@ -403,6 +771,10 @@ pub(super) fn read_u64(slice: &[u8]) -> Option<u64> {
     }
 }
 
+fn header_too_large() -> io::Error {
+    LhaError::HeaderTooLarge.into()
+}
+
 fn wrapping_csum(init: Wrapping<u8>, data: &[u8]) -> Wrapping<u8> {
     let sum: Wrapping<u8> = data.iter().copied().map(Wrapping).sum();
     sum + init
@@ -415,23 +787,325 @@ pub(super) fn split_data_at_nil_or_end(data: &[u8]) -> (&[u8], Option<&[u8]>) {
     }
 }
 
-pub(super) fn parse_pathname(data: &[u8], path: &mut PathBuf) {
-    path.reserve(data.len());
+/// Collects the components of a parsed path name, handling the malicious ones according to
+/// the traversal policy.
+pub(super) struct PathSink<'a> {
+    path: &'a mut PathBuf,
+    traversal: TraversalPolicy,
+    removed: Vec<RemovedComponent>,
+    started: bool
+}
+
+impl<'a> PathSink<'a> {
+    pub(super) fn new(path: &'a mut PathBuf, traversal: TraversalPolicy) -> Self {
+        PathSink { path, traversal, removed: Vec::new(), started: false }
+    }
+
+    /// Returns the components removed from the path name.
+    pub(super) fn into_removed(self) -> Vec<RemovedComponent> {
+        self.removed
+    }
+
+    /// Checks a part of the path name split at directory separators and returns `true`
+    /// if it's a name that should be pushed to the path.
+    ///
+    /// Empty parts are ignored, unless found at the beginning of the path name, where they
+    /// denote the root directory.
+    pub(super) fn accept(&mut self, part: &[u8]) -> io::Result<bool> {
+        let is_leading = !core::mem::replace(&mut self.started, true);
+        let component = match part {
+            b"." => RemovedComponent::CurDir,
+            b".." => RemovedComponent::ParentDir,
+            [] if is_leading => RemovedComponent::RootDir,
+            [] => return Ok(false),
+            _ => return Ok(true)
+        };
+        self.traverse(component)?;
+        Ok(false)
+    }
+
+    /// Appends a name to the path.
+    ///
+    /// On Windows the parts of the name that could be interpreted as a path prefix are being
+    /// neutralized, see [neutralize_windows_prefix].
+    pub(super) fn push(&mut self, name: &str) -> io::Result<()> {
+        let (prefix, name) = neutralize_windows_prefix(name, self.path.as_os_str().is_empty());
+        if !prefix.is_empty() {
+            self.traverse(RemovedComponent::Prefix(prefix.to_string()))?;
+        }
+        if !name.is_empty() {
+            self.path.push(name.as_ref());
+        }
+        Ok(())
+    }
+
+    /// Appends a name to the path, preserving raw bytes of the name where the platform
+    /// allows it.
+    ///
+    /// On Unix the bytes are preserved as is, except directory separators and the `nul` characters,
+    /// which are converted to `_`. On other platforms the name is converted from UTF-8 and invalid
+    /// sequences are converted to `%xx`.
+    pub(super) fn push_raw(&mut self, name: &[u8]) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            self.path.push(raw_name(name));
+            Ok(())
+        }
+        #[cfg(not(unix))]
+        {
+            self.push(&decode_str_nilterm(name, false, false, FilenameEncoding::Utf8))
+        }
+    }
+
+    fn traverse(&mut self, component: RemovedComponent) -> io::Result<()> {
+        match (self.traversal, component) {
+            (TraversalPolicy::Preserve, RemovedComponent::RootDir) => self.path.push(Component::RootDir),
+            (TraversalPolicy::Preserve, RemovedComponent::CurDir) => self.path.push(Component::CurDir),
+            (TraversalPolicy::Preserve, RemovedComponent::ParentDir) => self.path.push(Component::ParentDir),
+            (TraversalPolicy::Error, component) if component != RemovedComponent::CurDir => {
+                return Err(LhaError::InvalidFileName("path traversal in a file name").into())
+            }
+            (_, component) => self.removed.push(component)
+        }
+        Ok(())
+    }
+}
+
+pub(super) fn parse_pathname(data: &[u8], sink: &mut PathSink<'_>) -> io::Result<()> {
+    if data.is_empty() {
+        return Ok(())
+    }
+    sink.path.reserve(data.len());
     // split by all possible path separators
     for part in data.split(|&c| c == 0xFF || c == b'/' || c == b'\\') {
-        match part {
-            b"."|b".."|[] => {} // ignore malicious and empty paths
-            name => path.push(parse_str_nilterm(name, false, false).as_ref())
+        // handle malicious and empty paths
+        if sink.accept(part)? {
+            sink.push(&parse_str_nilterm(part, false, false))?;
+        }
+    }
+    Ok(())
+}
+
+/// Parses the path name decoding its components with `encoding` and sanitizing them
+/// according to `policy`.
+pub(super) fn parse_pathname_policy(
+        data: &[u8], sink: &mut PathSink<'_>, encoding: FilenameEncoding, policy: SanitizePolicy
+    ) -> io::Result<()>
+{
+    if let SanitizePolicy::PercentEncode = policy {
+        return parse_pathname_encoding(data, sink, encoding)
+    }
+    if data.is_empty() {
+        return Ok(())
+    }
+    sink.path.reserve(data.len());
+    for segment in data.split(|&c| c == 0xFF) {
+        let segment = sanitize_name(segment, false, true, encoding, policy)?;
+        for part in segment.split(['/', '\\']) {
+            if sink.accept(part.as_bytes())? {
+                sink.push(part)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decodes `data` with `encoding` and sanitizes it according to `policy`. Unless `ignore_sep` is
+/// `true`, system specific directory separator characters are being treated as forbidden.
+pub(super) fn sanitize_name(
+        data: &[u8], nilterm: bool, ignore_sep: bool, encoding: FilenameEncoding, policy: SanitizePolicy
+    ) -> io::Result<Cow<'_, str>>
+{
+    let replacement = match policy {
+        SanitizePolicy::PercentEncode => return Ok(decode_str_nilterm(data, nilterm, ignore_sep, encoding)),
+        SanitizePolicy::Replace(c) if c.is_control() || std::path::is_separator(c) => Some('_'),
+        SanitizePolicy::Replace(c) => Some(c),
+        SanitizePolicy::Error => None,
+        SanitizePolicy::Raw => Some('_')
+    };
+    let data = if nilterm { split_data_at_nil_or_end(data).0 } else { data };
+    let name = match (encoding.decode(data), policy) {
+        (Some(name), _) => name,
+        (None, SanitizePolicy::Error) => {
+            return Err(LhaError::InvalidFileName("invalid characters in a file name").into())
+        }
+        (None, SanitizePolicy::Replace(..)) => {
+            let name = encoding.decode_lossy(data);
+            if let Some(c) = replacement {
+                Cow::Owned(name.replace(char::REPLACEMENT_CHARACTER, c.encode_utf8(&mut [0;4])))
+            }
+            else {
+                name
+            }
+        }
+        (None, _) => encoding.decode_lossy(data)
+    };
+    let is_forbidden = |c: char| {
+        (c.is_control() && policy != SanitizePolicy::Raw) ||
+        (!ignore_sep && std::path::is_separator(c))
+    };
+    if !name.contains(is_forbidden) {
+        return Ok(name)
+    }
+    match replacement {
+        Some(replacement) => Ok(Cow::Owned(name.replace(is_forbidden, replacement.encode_utf8(&mut [0;4])))),
+        None => Err(LhaError::InvalidFileName("forbidden characters in a file name").into())
+    }
+}
+
+/// Parses the path name the same way as [parse_pathname], but preserves raw bytes of
+/// the path components where the platform allows it.
+pub(super) fn parse_pathname_raw(data: &[u8], sink: &mut PathSink<'_>) -> io::Result<()> {
+    if data.is_empty() {
+        return Ok(())
+    }
+    sink.path.reserve(data.len());
+    for part in data.split(|&c| c == 0xFF || c == b'/' || c == b'\\') {
+        if sink.accept(part)? {
+            sink.push_raw(part)?;
+        }
+    }
+    Ok(())
+}
+
+/// Converts a UTF-16LE name to UTF-8, replacing unpaired surrogates with `U+FFFD`.
+///
+/// The `U+FFFF` directory separators are converted to `0xFF` bytes, the same as in the legacy
+/// path headers, as `0xFF` never appears in UTF-8.
+pub(super) fn utf16_to_utf8(data: &[u8]) -> Vec<u8> {
+    let units = data.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]]));
+    let mut out = Vec::with_capacity(data.len());
+    for c in char::decode_utf16(units) {
+        match c {
+            Ok('\u{ffff}') => out.push(0xFF),
+            c => {
+                let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+                out.extend_from_slice(c.encode_utf8(&mut [0;4]).as_bytes());
+            }
+        }
+    }
+    out
+}
+
+/// Neutralizes the parts of a path component that Windows would interpret as a path prefix.
+/// Returns the prefix split off, see [split_windows_prefix], and the rest of the name with
+/// the colons converted, see [neutralize_colons]. On other platforms the name is returned as is.
+pub(super) fn neutralize_windows_prefix(name: &str, is_first: bool) -> (&str, Cow<'_, str>) {
+    if !cfg!(windows) {
+        return ("", Cow::Borrowed(name))
+    }
+    let (prefix, name) = split_windows_prefix(name, is_first);
+    (prefix, neutralize_colons(name))
+}
+
+/// Splits off the part of a path component that Windows would interpret as a path prefix.
+///
+/// Directory separators are already split, so UNC prefixes, e.g. `\\server\share`, become
+/// ordinary components. If `is_first` is `true`, the `?` component of `\\?\` prefixes is
+/// split off and so is a leading drive letter, e.g. `C:`.
+fn split_windows_prefix(name: &str, is_first: bool) -> (&str, &str) {
+    match name.as_bytes() {
+        b"?" if is_first => (name, ""),
+        [drive, b':', ..] if is_first && drive.is_ascii_alphabetic() => name.split_at(2),
+        _ => ("", name)
+    }
+}
+
+/// Converts `:` characters, which could select a drive or an NTFS alternate data stream
+/// on Windows, to `_`.
+fn neutralize_colons(name: &str) -> Cow<'_, str> {
+    if name.contains(':') {
+        Cow::Owned(name.replace(':', "_"))
+    }
+    else {
+        Cow::Borrowed(name)
+    }
+}
+
+/// Converts raw bytes of a Unix file name to `OsStr`, preserving them as is, except
+/// directory separators and the `nul` characters, which are converted to `_`.
+#[cfg(unix)]
+fn raw_name(name: &[u8]) -> Cow<'_, std::ffi::OsStr> {
+    use std::ffi::{OsStr, OsString};
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+    if name.iter().any(|&c| c == 0 || c == b'/') {
+        let name: Vec<u8> = name.iter().map(|&c| if c == 0 || c == b'/' { b'_' } else { c }).collect();
+        Cow::Owned(OsString::from_vec(name))
+    }
+    else {
+        Cow::Borrowed(OsStr::from_bytes(name))
+    }
+}
+
+/// Parses the path name decoding its components with `encoding`, see [parse_pathname].
+pub(super) fn parse_pathname_encoding(
+        data: &[u8], sink: &mut PathSink<'_>, encoding: FilenameEncoding
+    ) -> io::Result<()>
+{
+    if let FilenameEncoding::Ascii = encoding {
+        return parse_pathname(data, sink)
+    }
+    if data.is_empty() {
+        return Ok(())
+    }
+    sink.path.reserve(data.len());
+    // 0xFF is not a valid byte in multi-byte encodings, but '/' or '\\' may appear as
+    // the second byte of a character, so other separators are being split after decoding
+    for segment in data.split(|&c| c == 0xFF) {
+        let segment = decode_str_nilterm(segment, false, true, encoding);
+        for part in segment.split(['/', '\\']) {
+            if sink.accept(part.as_bytes())? {
+                sink.push(&sanitize_str(Cow::Borrowed(part), false))?;
+            }
         }
     }
+    Ok(())
+}
+
+/// Decodes `data` with `encoding`, converting control characters to `%xx` sequences and unless
+/// `ignore_sep` is `true`, system specific directory separator characters to `_`.
+///
+/// Data that can't be decoded is being parsed with [parse_str_nilterm] instead.
+pub(super) fn decode_str_nilterm(
+        data: &[u8], nilterm: bool, ignore_sep: bool, encoding: FilenameEncoding
+    ) -> Cow<'_, str>
+{
+    if let FilenameEncoding::Ascii = encoding {
+        return parse_str_nilterm(data, nilterm, ignore_sep)
+    }
+    let data = if nilterm { split_data_at_nil_or_end(data).0 } else { data };
+    match encoding.decode(data) {
+        Some(name) => sanitize_str(name, ignore_sep),
+        None => parse_str_nilterm(data, false, ignore_sep)
+    }
+}
+
+fn sanitize_str(name: Cow<'_, str>, ignore_sep: bool) -> Cow<'_, str> {
+    let is_forbidden = |c: char| c.is_control() || (!ignore_sep && std::path::is_separator(c));
+    if !name.contains(is_forbidden) {
+        return name
+    }
+    let mut out = String::with_capacity(name.len()*3);
+    for c in name.chars() {
+        if c.is_control() {
+            write!(out, "%{:02x}", c as u32).unwrap();
+        }
+        else if is_forbidden(c) {
+            out.push('_');
+        }
+        else {
+            out.push(c);
+        }
+    }
+    Cow::Owned(out)
 }
 
 pub(super) fn parse_str_nilterm(
         data: &[u8], nilterm: bool, ignore_sep: bool
-    ) -> Cow<str>
+    ) -> Cow<'_, str>
 {
     if let Some(index) = data.iter().position(|&c|
-            c < 0x20 || c >= 0x7f ||
+            !(0x20..0x7f).contains(&c) ||
             (!ignore_sep && std::path::is_separator(c as char))
         )
     {
@@ -479,12 +1153,117 @@ unsafe fn struct_slice_mut<T: Copy>(obj: &mut T) -> &mut [u8] {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::{MAIN_SEPARATOR, PathBuf};
+    use std::path::{MAIN_SEPARATOR, Path, PathBuf};
 
-    fn parse_filename(data: &[u8]) -> Cow<str> {
+    fn parse_filename(data: &[u8]) -> Cow<'_, str> {
         parse_str_nilterm(data, false, false)
     }
 
+    fn parse_pathname(data: &[u8], path: &mut PathBuf) {
+        super::parse_pathname(data, &mut PathSink::new(path, TraversalPolicy::SilentlySkip)).unwrap()
+    }
+
+    fn parse_traversal(data: &[u8], traversal: TraversalPolicy) -> io::Result<(String, Vec<RemovedComponent>)> {
+        let mut path = PathBuf::new();
+        let mut sink = PathSink::new(&mut path, traversal);
+        parse_pathname_encoding(data, &mut sink, FilenameEncoding::Utf8)?;
+        let removed = sink.into_removed();
+        Ok((path.to_str().unwrap().to_string(), removed))
+    }
+
+    #[test]
+    fn lenient_mode_works() {
+        fn read(data: &[u8], mode: ParseMode) -> io::Result<LhaHeader> {
+            LhaHeader::read_with_mode(data, mode).map(Option::unwrap)
+        }
+        fn check(data: &[u8], warnings: &[HeaderWarning]) -> LhaHeader {
+            assert_eq!(read(data, ParseMode::Strict).unwrap_err().kind(), io::ErrorKind::InvalidData);
+            let header = read(data, ParseMode::Lenient).unwrap();
+            assert_eq!(header.warnings, warnings);
+            header
+        }
+        fn update_crc(data: &mut [u8]) {
+            data[27..29].copy_from_slice(&[0, 0]);
+            let mut crc = Crc16::default();
+            crc.digest(data);
+            data[27..29].copy_from_slice(&crc.sum16().to_le_bytes());
+        }
+        let builder = LhaHeaderBuilder::new().path("foo").compressed_size(10);
+        for level in 0..=3 {
+            let data = builder.clone().level(level).build().unwrap().to_bytes().unwrap();
+            assert!(read(&data, ParseMode::Lenient).unwrap().warnings.is_empty());
+        }
+        // level 0 and 1 checksum
+        let mut data = builder.clone().level(1).build().unwrap().to_bytes().unwrap();
+        data[1] ^= 0x55;
+        let header = check(&data, &[HeaderWarning::Checksum { stored: data[1], computed: data[1] ^ 0x55 }]);
+        assert_eq!(header.parse_pathname(), Path::new("foo"));
+        // level 0 header length
+        let mut data = builder.clone().level(0).build().unwrap().to_bytes().unwrap();
+        data[0] -= 1;
+        data[1] = data[2..].iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+        check(&data, &[HeaderWarning::HeaderLength { stored: data[0] as u32, actual: data[0] as u32 + 1 }]);
+        // level 1 skip size
+        let header = builder.clone().level(1).path("dir/foo").compressed_size(0).build().unwrap();
+        let extra_headers = header.extra_headers.len() as u64;
+        let mut data = header.to_bytes().unwrap();
+        data[7..11].copy_from_slice(&[1, 0, 0, 0]);
+        data[1] = data[2..2 + data[0] as usize].iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+        let header = check(&data, &[HeaderWarning::SkipSize { stored: 1, extra_headers }]);
+        assert_eq!(header.compressed_size, 0);
+        // level 2 header CRC-16
+        let mut data = builder.clone().build().unwrap().to_bytes().unwrap();
+        let crc = u16::from_le_bytes([data[27], data[28]]);
+        data[27] ^= 1;
+        check(&data, &[HeaderWarning::HeaderCrc { stored: crc ^ 1, computed: crc }]);
+        // level 2 header length
+        let mut data = builder.clone().build().unwrap().to_bytes().unwrap();
+        let len = data.len();
+        data[0] += 3;
+        data.extend_from_slice(&[0, 0, 0]);
+        update_crc(&mut data);
+        let header = check(&data, &[HeaderWarning::HeaderLength { stored: len as u32 + 3, actual: len as u32 }]);
+        assert_eq!(header.compressed_size, 10);
+        data[0] -= 6;
+        data.truncate(len);
+        update_crc(&mut data);
+        assert_eq!(read(&data, ParseMode::Lenient).unwrap().warnings,
+                   [HeaderWarning::HeaderLength { stored: len as u32 - 3, actual: len as u32 }]);
+        assert_eq!(HeaderWarning::SkipSize { stored: 1, extra_headers: 8 }.to_string(),
+                   "wrong length of skip size: 1, extra headers: 8");
+    }
+
+    #[test]
+    fn duplicate_extra_headers_works() {
+        fn check(builder: LhaHeaderBuilder, warnings: &[HeaderWarning]) {
+            let data = builder.build().unwrap().to_bytes().unwrap();
+            let err = LhaHeader::read(&data[..]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(err.to_string(), warnings[0].to_string());
+            let header = LhaHeader::read_with_mode(&data[..], ParseMode::Lenient).unwrap().unwrap();
+            assert_eq!(header.warnings, warnings);
+        }
+        let builder = LhaHeaderBuilder::new().path("dir/foo");
+        check(builder.clone().level(1).extra_header(EXT_HEADER_PATH, b"dir\xff"),
+              &[HeaderWarning::DuplicateExtraHeader { id: EXT_HEADER_PATH }]);
+        for level in 2..=3 {
+            let builder = builder.clone().level(level);
+            check(builder.clone().extra_header(EXT_HEADER_FILENAME, b"foo"),
+                  &[HeaderWarning::DuplicateExtraHeader { id: EXT_HEADER_FILENAME }]);
+            check(builder.clone().extra_header(EXT_HEADER_PATH, b"bar\xff"),
+                  &[HeaderWarning::ConflictingExtraHeader { id: EXT_HEADER_PATH }]);
+            check(builder.clone().extra_header(EXT_HEADER_MSDOS_SIZE, &[0;16])
+                             .extra_header(EXT_HEADER_MSDOS_SIZE, &[1;16])
+                             .extra_header(EXT_HEADER_MSDOS_SIZE, &[0;16]),
+                  &[HeaderWarning::ConflictingExtraHeader { id: EXT_HEADER_MSDOS_SIZE },
+                    HeaderWarning::DuplicateExtraHeader { id: EXT_HEADER_MSDOS_SIZE }]);
+            let data = builder.clone().extra_header(EXT_HEADER_COMMENT, b"a")
+                              .extra_header(EXT_HEADER_COMMENT, b"b").build().unwrap().to_bytes().unwrap();
+            assert!(LhaHeader::read(&data[..]).unwrap().unwrap().warnings.is_empty());
+        }
+        assert_eq!(HeaderWarning::ConflictingExtraHeader { id: 2 }.to_string(), "conflicting extra headers: 0x02");
+    }
+
    #[test]
     fn split_data_at_nil_or_end_works() {
         assert_eq!((&b"Foo"[..], None), split_data_at_nil_or_end(b"Foo"));
@@ -492,6 +1271,116 @@ mod tests {
         assert_eq!((&[][..], Some(&b"Bar"[..])), split_data_at_nil_or_end(b"\x00Bar"));
     }
 
+    #[test]
+    fn extra_header_parse_works() {
+        assert_eq!(ExtraHeader::parse(b"\x01foo"), ExtraHeader::Filename(b"foo"));
+        assert_eq!(ExtraHeader::parse(b"\x02foo\xffbar"), ExtraHeader::Path(b"foo\xffbar"));
+        assert_eq!(ExtraHeader::parse(b"\x3fHello"), ExtraHeader::Comment(b"Hello"));
+        assert_eq!(ExtraHeader::parse(b"\x40\x21\x00"),
+                   ExtraHeader::MsDosAttrs(MsDosAttrs::ARCHIVE|MsDosAttrs::READ_ONLY));
+        assert_eq!(ExtraHeader::parse(b"\x50\xa4\x81"), ExtraHeader::UnixPerm(0o100644));
+        assert_eq!(ExtraHeader::parse(b"\x51\xe8\x03\xe9\x03"), ExtraHeader::UidGid { gid: 1000, uid: 1001 });
+        assert_eq!(ExtraHeader::parse(b"\x52users"), ExtraHeader::UnixGroup(b"users"));
+        assert_eq!(ExtraHeader::parse(b"\x53root"), ExtraHeader::UnixOwner(b"root"));
+        assert_eq!(ExtraHeader::parse(b"\x54\x00\x3b\x3d\x4b"), ExtraHeader::UnixTime(1262304000));
+        let mut times = vec![EXT_HEADER_MSDOS_TIME];
+        for ft in [1u64, 2, 3] {
+            times.extend_from_slice(&ft.to_le_bytes());
+        }
+        assert_eq!(ExtraHeader::parse(&times),
+                   ExtraHeader::MsDosTimes(MsDosTimes { created: 1, last_modified: 2, last_access: 3 }));
+        // too short data
+        assert_eq!(ExtraHeader::parse(&times[..24]), ExtraHeader::Unknown { id: 0x41, data: &times[1..24] });
+        assert_eq!(ExtraHeader::parse(b"\x54\x00\xe1\x3c"), ExtraHeader::Unknown { id: 0x54, data: b"\x00\xe1\x3c" });
+        assert_eq!(ExtraHeader::parse(b"\x50"), ExtraHeader::Unknown { id: 0x50, data: b"" });
+        assert_eq!(ExtraHeader::parse(b"\x39\x02\x01\xff"),
+                   ExtraHeader::MultiDisc(MultiDiscInfo { disc_number: 2, flags: 1, rest: b"\xff" }));
+        assert_eq!(ExtraHeader::parse(b"\x39\x02"), ExtraHeader::Unknown { id: 0x39, data: b"\x02" });
+        let os9 = b"\xcc\x18\x07\x09\x03\x01\x16\x00\x2b\x00\x00\x00\x00";
+        assert_eq!(ExtraHeader::parse(os9), ExtraHeader::Os9(Os9Metadata {
+            perms: Os9Perms::READ|Os9Perms::WRITE|Os9Perms::PUBLIC_READ|Os9Perms::PUBLIC_EXEC,
+            data: &os9[1..]
+        }));
+        assert_eq!(ExtraHeader::parse(b"\xccos9"), ExtraHeader::Unknown { id: 0xcc, data: b"os9" });
+        assert_eq!(ExtraHeader::parse(b"\x7f\x01"), ExtraHeader::Unknown { id: 0x7f, data: b"\x01" });
+        for raw in [&b"\x00\x00\x00"[..], b"\x01foo", b"\x41", b"\x51\x00\x00\x00\x00", b"\xee"] {
+            assert_eq!(ExtraHeader::parse(raw).id(), raw[0]);
+        }
+
+        let data = include_bytes!("../../tests/lha_unix114i/h2_lh5.lzh");
+        let header = LhaHeader::read(&data[..]).unwrap().unwrap();
+        let extra: Vec<_> = header.parse_extra().collect();
+        assert_eq!(extra, [
+            ExtraHeader::Common(&[0, 0]),
+            ExtraHeader::UnixPerm(0o100444),
+            ExtraHeader::UidGid { gid: 1000, uid: 1000 },
+            ExtraHeader::Filename(b"gpl-2")
+        ]);
+        assert!(header.iter_extra().map(ExtraHeader::parse).eq(header.parse_extra()));
+        assert_eq!(header.get_extra(EXT_HEADER_FILENAME), Some(&b"gpl-2"[..]));
+        assert_eq!(header.get_extra(EXT_HEADER_COMMON), Some(&[0, 0][..]));
+        assert_eq!(header.get_extra(EXT_HEADER_PATH), None);
+        assert!(header.get_extra_all(EXT_HEADER_UNIX_PERM).eq([&[0x24, 0x81][..]]));
+        let header = LhaHeaderBuilder::new().path("foo").extra_header(0xEE, b"1").extra_header(0xEE, b"")
+                                            .build().unwrap();
+        assert!(header.get_extra_all(0xEE).eq([&b"1"[..], &[]]));
+        assert_eq!(header.get_extra(0xEE), Some(&b"1"[..]));
+    }
+
+    #[test]
+    fn utf16_to_utf8_works() {
+        assert_eq!(utf16_to_utf8(b""), b"");
+        assert_eq!(utf16_to_utf8(b"a\x00\x7a\x01\xff\xff\x3d\xd8\x00\xde/\x00\x00"),
+                   b"a\xc5\xba\xff\xf0\x9f\x98\x80/");
+        assert_eq!(utf16_to_utf8(b"\x00\xd8a\x00"), "\u{fffd}a".as_bytes());
+    }
+
+    #[test]
+    fn split_windows_prefix_works() {
+        assert_eq!(split_windows_prefix("C:", true), ("C:", ""));
+        assert_eq!(split_windows_prefix("?", true), ("?", ""));
+        assert_eq!(split_windows_prefix("c:foo", true), ("c:", "foo"));
+        assert_eq!(split_windows_prefix("C:", false), ("", "C:"));
+        assert_eq!(split_windows_prefix("?", false), ("", "?"));
+        assert_eq!(split_windows_prefix("1:foo", true), ("", "1:foo"));
+        assert_eq!(neutralize_colons("C:"), "C_");
+        assert_eq!(neutralize_colons("file:stream:$DATA"), "file_stream_$DATA");
+        assert!(matches!(neutralize_colons("foo"), Cow::Borrowed("foo")));
+        if cfg!(windows) {
+            let mut path = PathBuf::new();
+            parse_pathname(b"C:\\dir\\file:ads", &mut path);
+            assert_eq!("dir\\file_ads", path.to_str().unwrap());
+            let mut path = PathBuf::new();
+            parse_pathname(b"\\\\?\\D:\\x\xffC:", &mut path);
+            assert_eq!("x\\C_", path.to_str().unwrap());
+            let mut path = PathBuf::new();
+            parse_pathname(b"\\\\server\\share\\file", &mut path);
+            assert_eq!("server\\share\\file", path.to_str().unwrap());
+        }
+    }
+
+    #[test]
+    fn path_traversal_works() {
+        use RemovedComponent::*;
+        let sep = MAIN_SEPARATOR;
+        assert_eq!(parse_traversal(b"/foo/./../bar//", TraversalPolicy::SilentlySkip).unwrap(),
+                   (format!("foo{}bar", sep), vec![RootDir, CurDir, ParentDir]));
+        assert_eq!(parse_traversal(b"foo\xff.\xffbar", TraversalPolicy::Error).unwrap(),
+                   (format!("foo{}bar", sep), vec![CurDir]));
+        assert!(parse_traversal(b"foo/../bar", TraversalPolicy::Error).is_err());
+        assert!(parse_traversal(b"\xfffoo", TraversalPolicy::Error).is_err());
+        assert_eq!(parse_traversal(b"", TraversalPolicy::Error).unwrap(), (String::new(), vec![]));
+        let (path, removed) = parse_traversal(b"/foo/./../bar", TraversalPolicy::Preserve).unwrap();
+        assert!(removed.is_empty());
+        assert!(Path::new(&path).has_root());
+        assert!(Path::new(&path).components().eq(Path::new("/foo/../bar").components()));
+        if cfg!(windows) {
+            assert_eq!(parse_traversal(b"C:\\foo", TraversalPolicy::SilentlySkip).unwrap(),
+                       ("foo".to_string(), vec![Prefix("C:".to_string())]));
+            assert!(parse_traversal(b"C:\\foo", TraversalPolicy::Error).is_err());
+        }
+    }
+
    #[test]
     fn path_parser_works() {
         assert_eq!("", parse_filename(b""));
@@ -565,4 +1454,26 @@ mod tests {
         assert_eq!(expect, path.to_str().unwrap());
         path.clear();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn read_with_limit_works() {
+        for level in 0..=3 {
+            let mut builder = LhaHeaderBuilder::new().level(level).path("foo").compressed_size(2000);
+            if level != 0 {
+                builder = builder.extra_header(0x7f, &[0x55; 1000]);
+            }
+            let data = builder.build().unwrap().to_bytes().unwrap();
+            let size = data.len();
+            let header = LhaHeader::read_with_limit(&data[..], ParseMode::Strict, Some(size)).unwrap().unwrap();
+            assert_eq!(header.header_size, size as u64);
+            let err = LhaHeader::read_with_limit(&data[..], ParseMode::Strict, Some(size - 1)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(err.to_string(), "header too large");
+            assert!(LhaHeader::read_with_limit(&data[..], ParseMode::Lenient, None).is_ok());
+        }
+        let data = LhaHeaderBuilder::new().level(3).path("foo").extra_header(0x7f, &vec![0; DEFAULT_MAX_HEADER_SIZE])
+                                          .build().unwrap().to_bytes().unwrap();
+        assert_eq!(LhaHeader::read(&data[..]).unwrap_err().to_string(), "header too large");
+        assert!(LhaHeader::read_with_limit(&data[..], ParseMode::Strict, None).unwrap().is_some());
+    }
+}
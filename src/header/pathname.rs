@@ -0,0 +1,187 @@
+use std::borrow::Cow;
+use super::*;
+
+/// Determines how to handle the path components that could lead outside of the extraction
+/// directory: `..`, `.`, the root directory and, on Windows, drive prefixes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TraversalPolicy {
+    /// The components are removed from the path name.
+    #[default]
+    SilentlySkip,
+    /// The `..` components, the root directory and the drive prefixes result in an error,
+    /// the `.` components are removed.
+    Error,
+    /// The `..` and `.` components and the root directory are preserved.
+    ///
+    /// Windows drive prefixes are still removed, as they can't be preserved in a path name
+    /// without changing its meaning.
+    ///
+    /// # Note
+    /// The returned path may be absolute or may point outside of the extraction directory.
+    Preserve
+}
+
+/// A path component removed from the path name, see [LhaHeader::try_parse_pathname_traversal].
+///
+/// [LhaHeader::try_parse_pathname_traversal]: super::LhaHeader::try_parse_pathname_traversal
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RemovedComponent {
+    /// A leading directory separator.
+    RootDir,
+    /// A `.` component.
+    CurDir,
+    /// A `..` component.
+    ParentDir,
+    /// A Windows drive prefix, e.g. `C:`.
+    Prefix(String)
+}
+
+/// An iterator through the decoded path name components, see [LhaHeader::path_components].
+///
+/// [LhaHeader::path_components]: super::LhaHeader::path_components
+pub struct PathComponents<'a> {
+    extra: ParseExtraIter<'a>,
+    parts: Option<&'a [u8]>,
+    filename: Option<&'a [u8]>,
+    fallback: Option<&'a [u8]>,
+    skip_path: bool,
+    unicode_path: Option<(Vec<u8>, usize)>,
+    unicode_name: Option<Vec<u8>>,
+    started: bool
+}
+
+impl<'a> PathComponents<'a> {
+    pub(super) fn new(header: &'a LhaHeader) -> Self {
+        let (unicode_path, unicode_name) = header.unicode_names();
+        let mut components = PathComponents::new_legacy(header, unicode_path.is_some(),
+                                                              unicode_name.is_some());
+        // the converted path is being iterated from the beginning
+        components.unicode_path = unicode_path.map(|path| (path, 0));
+        components.unicode_name = unicode_name;
+        components
+    }
+
+    fn new_legacy(header: &'a LhaHeader, skip_path: bool, skip_name: bool) -> Self {
+        let nilterm = header.parse_os_type() == Ok(OsType::Amiga);
+        let cut = |data: &'a [u8]| if nilterm { split_data_at_nil_or_end(data).0 } else { data };
+        let filename = header.parse_extra().fold(None, |filename, header| match header {
+            ExtraHeader::Filename(data) => Some(cut(data)),
+            _ => filename
+        })
+        .filter(|data| !data.is_empty() && !skip_name);
+        let fallback = match filename {
+            Some(..) => None,
+            None if skip_name => None,
+            None => Some(cut(&header.filename))
+        };
+        PathComponents {
+            extra: header.parse_extra(),
+            parts: None,
+            filename,
+            fallback,
+            skip_path,
+            unicode_path: None,
+            unicode_name: None,
+            started: false
+        }
+    }
+
+    /// Returns the next part of the path name split at directory separators.
+    fn next_part(&mut self) -> Option<&'a [u8]> {
+        let parts = self.parts?;
+        match parts.iter().position(|&c| c == 0xFF || c == b'/' || c == b'\\') {
+            Some(index) => {
+                self.parts = Some(&parts[index + 1..]);
+                Some(&parts[..index])
+            }
+            None => {
+                self.parts = None;
+                Some(parts)
+            }
+        }
+    }
+
+    /// Returns the next part of the Unicode path name, skipping the malicious and empty ones.
+    fn next_unicode_part(&mut self) -> Option<Cow<'a, str>> {
+        let (path, pos) = self.unicode_path.as_mut()?;
+        while *pos < path.len() {
+            let rest = &path[*pos..];
+            let len = rest.iter().position(|&c| c == 0xFF || c == b'/' || c == b'\\').unwrap_or(rest.len());
+            *pos += len + 1;
+            match &rest[..len] {
+                b"."|b".."|[] => {}
+                part => return Some(decode_unicode(part))
+            }
+        }
+        None
+    }
+
+    /// Returns the next decoded component, before the Windows path prefixes are neutralized.
+    fn next_name(&mut self) -> Option<Cow<'a, str>> {
+        if let Some(part) = self.next_unicode_part() {
+            return Some(part)
+        }
+        loop {
+            if let Some(part) = self.next_part() {
+                match part {
+                    b"."|b".."|[] => {} // ignore malicious and empty paths
+                    name => return Some(parse_str_nilterm(name, false, false))
+                }
+            }
+            else if let Some(header) = self.extra.next() {
+                match header {
+                    ExtraHeader::Path(data) if !self.skip_path => self.parts = Some(data),
+                    _ => {}
+                }
+            }
+            else if let Some(name) = self.filename.take() {
+                match name {
+                    b"."|b".." => {}
+                    name => return Some(parse_str_nilterm(name, false, false))
+                }
+            }
+            else if let Some(parts) = self.fallback.take() {
+                self.parts = Some(parts);
+            }
+            else {
+                let name = self.unicode_name.take().filter(|name| !matches!(name.as_slice(), b"."|b".."))?;
+                return Some(decode_unicode(&name))
+            }
+        }
+    }
+}
+
+/// Decodes a part of the converted Unicode path name.
+fn decode_unicode<'a>(part: &[u8]) -> Cow<'a, str> {
+    Cow::Owned(decode_str_nilterm(part, false, false, FilenameEncoding::Utf8).into_owned())
+}
+
+impl<'a> Iterator for PathComponents<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let name = self.next_name()?;
+            let is_first = !self.started;
+            // the prefixes are neutralized the same way as by the path name parsers
+            let name = match name {
+                Cow::Borrowed(name) => neutralize_windows_prefix(name, is_first).1,
+                Cow::Owned(name) => {
+                    let rest = neutralize_windows_prefix(&name, is_first).1;
+                    if matches!(rest, Cow::Borrowed(rest) if rest.len() == name.len()) {
+                        Cow::Owned(name)
+                    }
+                    else {
+                        Cow::Owned(rest.into_owned())
+                    }
+                }
+            };
+            if !name.is_empty() {
+                self.started = true;
+                return Some(name)
+            }
+        }
+    }
+}
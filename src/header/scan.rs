@@ -0,0 +1,140 @@
+use std::io::{self, Read, Seek, SeekFrom};
+use super::{LhaHeader, ParseMode, DEFAULT_MAX_HEADER_SIZE};
+
+/// The size of the buffer used when searching for the compression method signature.
+const SCAN_BUFFER_SIZE: usize = 8192;
+
+impl LhaHeader {
+    /// Searches the stream for the first valid header, starting at the current position, and
+    /// returns it with [LhaHeader::header_offset] set to its offset from the initial position.
+    /// Returns `Ok(None)` if no header could be found until the end of the stream.
+    ///
+    /// Candidate headers are recognized by the `-lh?-`, `-lz?-` or `-pm?-` compression method
+    /// identifier and are validated by parsing them in the [ParseMode::Strict] mode. This allows
+    /// reading archives with leading junk or embedded inside other files.
+    ///
+    /// On success the stream is positioned at the end of the header, i.e. at the beginning of
+    /// the file's compressed content.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::{fs::File, io::{self, Seek, SeekFrom}};
+    /// use delharc::{LhaDecodeReader, LhaHeader};
+    ///
+    /// let mut file = File::open("image.bin")?;
+    /// if let Some(header) = LhaHeader::scan(&mut file)? {
+    ///     println!("archive found at: {}", header.header_offset.unwrap());
+    ///     // go back to the beginning of the header
+    ///     file.seek(SeekFrom::Current(-(header.header_size as i64)))?;
+    ///     let lha_reader = LhaDecodeReader::new(file)?;
+    /// }
+    /// # Ok::<(), io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if reading from or seeking the stream fails.
+    pub fn scan<R: Read + Seek>(rd: R) -> io::Result<Option<LhaHeader>> {
+        LhaHeader::scan_with_mode(rd, ParseMode::Strict)
+    }
+    /// Searches the stream for the first header which can be parsed in the given `mode`,
+    /// see [LhaHeader::scan].
+    ///
+    /// # Errors
+    /// Returns an error if reading from or seeking the stream fails.
+    pub fn scan_with_mode<R: Read + Seek>(mut rd: R, mode: ParseMode) -> io::Result<Option<LhaHeader>> {
+        let start = rd.stream_position()?;
+        let mut pos = start;
+        loop {
+            match find_signature(&mut rd)? {
+                Some(skipped) => pos += skipped,
+                None => return Ok(None)
+            }
+            match LhaHeader::read_with_limit(&mut rd, mode, Some(DEFAULT_MAX_HEADER_SIZE)) {
+                Ok(Some(header)) => {
+                    return Ok(Some(LhaHeader { header_offset: Some(pos - start), ..header }))
+                }
+                Ok(None) => {}
+                Err(e) if is_invalid_header(&e) => {}
+                Err(e) => return Err(e)
+            }
+            pos += 1;
+            rd.seek(SeekFrom::Start(pos))?;
+        }
+    }
+}
+
+/// Returns `true` if the error of reading a header is caused by the invalid content of the stream.
+pub(crate) fn is_invalid_header(err: &io::Error) -> bool {
+    matches!(err.kind(), io::ErrorKind::InvalidData|io::ErrorKind::UnexpectedEof)
+}
+
+/// Searches for the next compression method signature, positioning the stream at the beginning
+/// of the header containing it, and returns the number of skipped bytes. Returns `None` if there
+/// is no signature until the end of the stream.
+pub(crate) fn find_signature<R: Read + Seek>(rd: &mut R) -> io::Result<Option<u64>> {
+    let mut buf = vec![0u8; SCAN_BUFFER_SIZE];
+    let mut len = 0;
+    let mut skipped = 0u64;
+    loop {
+        let read = match rd.read(&mut buf[len..]) {
+            Ok(0) => return Ok(None),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e)
+        };
+        len += read;
+        if let Some(pos) = buf[..len].windows(7).position(is_signature) {
+            rd.seek(SeekFrom::Current(pos as i64 - len as i64))?;
+            return Ok(Some(skipped + pos as u64))
+        }
+        let keep = len.min(6);
+        buf.copy_within(len - keep..len, 0);
+        skipped += (len - keep) as u64;
+        len = keep;
+    }
+}
+
+/// Checks the header length or size, followed by the checksum or size, and the compression
+/// method identifier.
+pub(crate) fn is_signature(data: &[u8]) -> bool {
+    match data {
+        [len, _, b'-', a, b, c, b'-'] => {
+            *len != 0 && matches!([a, b], [b'l', b'h']|[b'l', b'z']|[b'p', b'm']) && c.is_ascii_alphanumeric()
+        }
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use crate::header::LhaHeaderBuilder;
+    use super::*;
+
+    #[test]
+    fn scan_works() {
+        let header = LhaHeaderBuilder::new().path("foo").compressed_size(5).original_size(5)
+                     .build().unwrap().to_bytes().unwrap();
+        let mut data = b"MZ junk -lh5- junk".to_vec();
+        data.extend_from_slice(&header);
+        data.extend_from_slice(b"Hello");
+        let mut rd = Cursor::new(&data[..]);
+        rd.set_position(2);
+        let found = LhaHeader::scan(&mut rd).unwrap().unwrap();
+        assert_eq!(found.parse_pathname().to_str(), Some("foo"));
+        assert_eq!(found.header_offset, Some(16));
+        assert_eq!(found.header_size, header.len() as u64);
+        assert_eq!(rd.position(), 18 + header.len() as u64);
+        assert_eq!(LhaHeader::scan(&mut rd).unwrap().map(|h| h.header_offset), None);
+
+        let mut data = vec![0u8; SCAN_BUFFER_SIZE * 2 - 3];
+        data.extend_from_slice(&header);
+        let found = LhaHeader::scan(Cursor::new(&data)).unwrap().unwrap();
+        assert_eq!(found.header_offset, Some(SCAN_BUFFER_SIZE as u64 * 2 - 3));
+
+        assert!(LhaHeader::scan(Cursor::new(&header[..header.len() - 1])).unwrap().is_none());
+        assert!(!is_signature(b"\0\0-lh5-"));
+        assert!(!is_signature(b"\x01\0-ab5-"));
+        assert!(is_signature(b"\x01\0-pm0-"));
+    }
+}
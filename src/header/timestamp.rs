@@ -1,8 +1,10 @@
 use core::fmt;
 use chrono::{LocalResult, prelude::*};
+use super::parse_win_filetime;
 
 /// The type returned when parsing last modified timestamp.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TimestampResult {
     /// The timestamp could not be parsed.
     None,
@@ -57,7 +59,7 @@ impl TimestampResult {
     /// In this instance the `Naive` date and time variant is assumed to be in the UTC time zone.
     pub fn to_utc(&self) -> Option<DateTime<Utc>> {
         match self {
-            TimestampResult::Naive(dt) => Some(DateTime::from_utc(*dt, Utc)),
+            TimestampResult::Naive(dt) => Some(Utc.from_utc_datetime(dt)),
             TimestampResult::Utc(dt) => Some(*dt),
             _ => None
         }
@@ -73,6 +75,127 @@ impl TimestampResult {
             _ => None
         }
     }
+
+    /// Returns a date time in the given time zone, e.g. a [FixedOffset].
+    ///
+    /// In this instance the `Naive` date and time variant is assumed to be in the `tz` time zone.
+    /// A date and time which is ambiguous in the `tz` time zone, e.g. when the clocks are turned back,
+    /// resolves to the earliest instant.
+    pub fn to_timezone<Tz: TimeZone>(&self, tz: &Tz) -> Option<DateTime<Tz>> {
+        match self {
+            TimestampResult::Naive(dt) => tz.from_local_datetime(dt).earliest(),
+            TimestampResult::Utc(dt) => Some(dt.with_timezone(tz)),
+            _ => None
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+impl TimestampResult {
+    /// Returns a date time as [time::OffsetDateTime] in the UTC time zone.
+    ///
+    /// In this instance the `Naive` date and time variant is assumed to be in the UTC time zone.
+    pub fn to_offset_datetime(&self) -> Option<time::OffsetDateTime> {
+        self.to_offset_datetime_in(time::UtcOffset::UTC)
+    }
+
+    /// Returns a date time as [time::OffsetDateTime] with the given `offset`.
+    ///
+    /// In this instance the `Naive` date and time variant is assumed to be at the `offset`
+    /// from UTC.
+    pub fn to_offset_datetime_in(&self, offset: time::UtcOffset) -> Option<time::OffsetDateTime> {
+        let dt = self.to_timezone(&FixedOffset::east_opt(offset.whole_seconds())?)?;
+        let nanos = i128::from(dt.timestamp()) * 1_000_000_000 + i128::from(dt.timestamp_subsec_nanos());
+        time::OffsetDateTime::from_unix_timestamp_nanos(nanos).ok().map(|dt| dt.to_offset(offset))
+    }
+}
+
+/// The origin of a [Timestamp].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TimestampSource {
+    /// The MS-DOS date and time in the `last_modified` field of level 0 and 1 headers, in the local
+    /// time of the machine the archive was made on.
+    MsDosDateTime,
+    /// The Unix timestamp in the `last_modified` field of level 2 and 3 headers.
+    UnixTime,
+    /// The ["Unix Time"][super::ext::EXT_HEADER_UNIX_TIME] extra header.
+    UnixTimeExtra,
+    /// The Unix timestamp in the extended area of level 0 and 1 headers made on Unix-like systems.
+    ExtendedArea,
+    /// The Windows FILETIME in the ["MS-DOS Time"][super::ext::EXT_HEADER_MSDOS_TIME] extra header.
+    WindowsFileTime
+}
+
+/// A parsed timestamp together with its origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timestamp {
+    /// The parsed timestamp, never [TimestampResult::None].
+    pub time: TimestampResult,
+    /// Where the timestamp was found.
+    pub source: TimestampSource
+}
+
+/// The best available timestamps of a file, as returned by
+/// [LhaHeader::timestamps][super::LhaHeader::timestamps].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timestamps {
+    /// The last modified timestamp.
+    pub last_modified: Option<Timestamp>,
+    /// The creation timestamp.
+    pub created: Option<Timestamp>,
+    /// The last access timestamp.
+    pub last_access: Option<Timestamp>
+}
+
+impl Timestamp {
+    /// Returns a `Timestamp` unless `time` is [TimestampResult::None].
+    pub(super) fn new(time: TimestampResult, source: TimestampSource) -> Option<Self> {
+        if time.is_none() {
+            return None
+        }
+        Some(Timestamp { time, source })
+    }
+}
+
+/// Windows [FILETIME] timestamps found in the ["MS-DOS Time"][super::ext::EXT_HEADER_MSDOS_TIME]
+/// extra header.
+///
+/// Each timestamp is a number of 100-nanosecond intervals since January 1, 1601 (UTC).
+/// The value `0` means the timestamp is not set.
+///
+/// [FILETIME]: https://docs.microsoft.com/en-us/windows/win32/api/minwinbase/ns-minwinbase-filetime
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MsDosTimes {
+    pub created: u64,
+    pub last_modified: u64,
+    pub last_access: u64
+}
+
+impl MsDosTimes {
+    /// Parses the file's creation timestamp.
+    pub fn parse_created(&self) -> TimestampResult {
+        parse_filetime_or_none(self.created)
+    }
+    /// Parses the file's last modified timestamp.
+    pub fn parse_last_modified(&self) -> TimestampResult {
+        parse_filetime_or_none(self.last_modified)
+    }
+    /// Parses the file's last access timestamp.
+    pub fn parse_last_access(&self) -> TimestampResult {
+        parse_filetime_or_none(self.last_access)
+    }
+}
+
+fn parse_filetime_or_none(filetime: u64) -> TimestampResult {
+    if filetime == 0 {
+        return TimestampResult::None
+    }
+    parse_win_filetime(filetime).into()
 }
 
 impl fmt::Display for TimestampResult {
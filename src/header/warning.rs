@@ -0,0 +1,84 @@
+use core::fmt;
+
+/// Determines how [LhaHeader::read_with_mode][super::LhaHeader::read_with_mode] handles
+/// inconsistent length and checksum fields of a header.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ParseMode {
+    /// Any inconsistency results in an error of the kind [std::io::ErrorKind::InvalidData].
+    #[default]
+    Strict,
+    /// Wrong checksums and header lengths which don't prevent reading the header are recorded
+    /// in [LhaHeader::warnings][super::LhaHeader::warnings] instead.
+    ///
+    /// Some packers write slightly wrong length or checksum fields, archives made by them
+    /// can still be read in this mode.
+    Lenient
+}
+
+/// An anomaly found in a header parsed with [ParseMode::Lenient].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum HeaderWarning {
+    /// The level 0 or 1 header checksum doesn't match the header's content.
+    Checksum {
+        stored: u8,
+        computed: u8
+    },
+    /// The CRC-16 of the ["Common"][super::ext::EXT_HEADER_COMMON] extra header doesn't match
+    /// the header's content.
+    HeaderCrc {
+        stored: u16,
+        computed: u16
+    },
+    /// The header length field doesn't match the actual length of the header.
+    ///
+    /// The actual length is the number of bytes read, excluding the extended area of level 0 and 1
+    /// headers which was assumed empty if the stored length was too short.
+    HeaderLength {
+        stored: u32,
+        actual: u32
+    },
+    /// The level 1 compressed size doesn't include the size of the extra headers. The compressed
+    /// size was assumed to be `0`.
+    SkipSize {
+        stored: u64,
+        extra_headers: u64
+    },
+    /// An extra header which should appear only once, e.g. the file name, the directory name
+    /// or the file size, is repeated with the same content.
+    DuplicateExtraHeader {
+        id: u8
+    },
+    /// An extra header which should appear only once is repeated with a different content, so
+    /// the file's metadata is ambiguous.
+    ConflictingExtraHeader {
+        id: u8
+    }
+}
+
+impl fmt::Display for HeaderWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderWarning::Checksum { stored, computed } => {
+                write!(f, "invalid header level checksum: {:02x}, expected: {:02x}", stored, computed)
+            }
+            HeaderWarning::HeaderCrc { stored, computed } => {
+                write!(f, "wrong header CRC-16 checksum: {:04x}, expected: {:04x}", stored, computed)
+            }
+            HeaderWarning::HeaderLength { stored, actual } => {
+                write!(f, "wrong length of headers: {}, actual: {}", stored, actual)
+            }
+            HeaderWarning::SkipSize { stored, extra_headers } => {
+                write!(f, "wrong length of skip size: {}, extra headers: {}", stored, extra_headers)
+            }
+            HeaderWarning::DuplicateExtraHeader { id } => {
+                write!(f, "duplicate extra header: 0x{:02x}", id)
+            }
+            HeaderWarning::ConflictingExtraHeader { id } => {
+                write!(f, "conflicting extra headers: 0x{:02x}", id)
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::io::{self, Write};
+use super::*;
+
+impl LhaHeader {
+    /// Serializes the header and writes it to `wr`. This is the inverse of [LhaHeader::read].
+    ///
+    /// The header level is determined by the `level` field. The method calculates the header's
+    /// length and checksum fields and, if the ["Common"][ext::EXT_HEADER_COMMON] extra header is
+    /// present, its CRC-16 field. Extra headers are written as raw bytes from the `extra_headers`
+    /// field, starting with the header of size `first_header_len`, chained by their next header
+    /// length fields.
+    ///
+    /// A level 2 header that would begin with a `0` byte is padded with an additional byte, the same
+    /// as LHa for UNIX does.
+    ///
+    /// # Errors
+    /// Returns an error from the underlying writing operations or an error of the kind
+    /// [io::ErrorKind::InvalidInput] if the header can't be represented with the given level,
+    /// e.g. a file name is too long, sizes exceed 32 bits without the
+    /// ["MS-DOS Size"][ext::EXT_HEADER_MSDOS_SIZE] extra header or the extra headers are malformed.
+    pub fn write<W: Write>(&self, mut wr: W) -> io::Result<()> {
+        let buf = self.to_bytes()?;
+        wr.write_all(&buf)
+    }
+
+    /// Serializes the header to a vector of bytes, see [LhaHeader::write].
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let level = self.level;
+        if level > 3 {
+            return Err(invalid_input("unknown header level"))
+        }
+        let extra_len = self.validate_extra_headers()?;
+        let has_msdos_size = level >= 2 && self.parse_extra().any(|header|
+                                matches!(header, ExtraHeader::MsDosSize {..}));
+        let compressed_size = match level {
+            1 => self.compressed_size.saturating_add(extra_len as u64),
+            _ => self.compressed_size
+        };
+        let size32 = |size: u64| match u32::try_from(size) {
+            Ok(size) => Ok(size),
+            Err(..) if has_msdos_size => Ok(u32::MAX),
+            Err(..) => Err(invalid_input("file size too large for the header"))
+        };
+        let compressed_size = size32(compressed_size)?;
+        let original_size = size32(self.original_size)?;
+
+        let mut buf = Vec::with_capacity(32 + self.filename.len() + self.extended_area.len() + extra_len);
+        buf.extend_from_slice(&[0, 0]); // header length and checksum, filled later
+        buf.extend_from_slice(&self.compression);
+        buf.extend_from_slice(&compressed_size.to_le_bytes());
+        buf.extend_from_slice(&original_size.to_le_bytes());
+        buf.extend_from_slice(&self.last_modified.to_le_bytes());
+        buf.push(self.msdos_attrs.bits() as u8);
+        buf.push(level);
+        if level < 2 {
+            let filename_len = u8::try_from(self.filename.len())
+                               .map_err(|_| invalid_input("file name too long"))?;
+            buf.push(filename_len);
+            buf.extend_from_slice(&self.filename);
+        }
+        else if !self.filename.is_empty() || !self.extended_area.is_empty() {
+            return Err(invalid_input("file name and extended area must be empty in level 2 and 3 headers"))
+        }
+        buf.extend_from_slice(&self.file_crc.to_le_bytes());
+        match level {
+            0 => {
+                if self.os_type != 0 || !self.extended_area.is_empty() {
+                    buf.push(self.os_type);
+                    buf.extend_from_slice(&self.extended_area);
+                }
+            }
+            1 => {
+                buf.push(self.os_type);
+                buf.extend_from_slice(&self.extended_area);
+                buf.extend_from_slice(&(self.first_header_len as u16).to_le_bytes());
+            }
+            2 => {
+                buf.push(self.os_type);
+                buf.extend_from_slice(&(self.first_header_len as u16).to_le_bytes());
+            }
+            _ => {
+                buf[0] = 4;
+                buf.push(self.os_type);
+                buf.extend_from_slice(&[0;4]); // header length, filled later
+                buf.extend_from_slice(&self.first_header_len.to_le_bytes());
+            }
+        }
+        let base_len = buf.len();
+        buf.extend_from_slice(&self.extra_headers);
+
+        match level {
+            0|1 => {
+                let header_len = u8::try_from(base_len - 2)
+                                 .map_err(|_| invalid_input("header too long"))?;
+                buf[0] = header_len;
+            }
+            2 => {
+                if buf.len() & 0xFF == 0 {
+                    buf.push(0);
+                }
+                let header_len = u16::try_from(buf.len())
+                                 .map_err(|_| invalid_input("header too long"))?;
+                buf[0..2].copy_from_slice(&header_len.to_le_bytes());
+            }
+            _ => {
+                let header_len = u32::try_from(buf.len())
+                                 .map_err(|_| invalid_input("header too long"))?;
+                buf[24..28].copy_from_slice(&header_len.to_le_bytes());
+            }
+        }
+
+        update_header_checksums(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Verifies that the extra headers chain is consistent with the header level and returns
+    /// the total size of the extra headers.
+    fn validate_extra_headers(&self) -> io::Result<usize> {
+        if self.level == 0 {
+            if self.first_header_len != 0 || !self.extra_headers.is_empty() {
+                return Err(invalid_input("level 0 headers can't have extra headers"))
+            }
+            return Ok(0)
+        }
+        let (counter_size, min_header_len) = if self.level == 3 { (4, 5) } else { (2, 3) };
+        if self.level < 3 && self.first_header_len > u16::MAX as u32 {
+            return Err(invalid_input("wrong extra header size"))
+        }
+        let data = &self.extra_headers[..];
+        let mut offset = 0;
+        let mut header_len = self.first_header_len as usize;
+        while header_len != 0 {
+            if header_len < min_header_len || data.len() - offset < header_len {
+                return Err(invalid_input("wrong extra header size"))
+            }
+            offset += header_len;
+            let len = &data[offset - counter_size..offset];
+            header_len = if counter_size == 4 {
+                read_u32(len).unwrap() as usize
+            }
+            else {
+                read_u16(len).unwrap() as usize
+            };
+        }
+        if offset != data.len() {
+            return Err(invalid_input("wrong extra header size"))
+        }
+        Ok(data.len())
+    }
+}
+
+fn invalid_input(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
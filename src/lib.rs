@@ -5,6 +5,9 @@ methods used by the archive format.
 
 This library does not provide high level methods for creating files or directories from the extracted archives.
 
+Archives can be created with [LhaWriter], compressing the files with the `-lh1-` and `-lh4-` to `-lh7-`
+methods or storing them without compression.
+
 There are many extensions to the base LHA headers, used by many different archive programs, in many different
 operating systems. This library only allows for parsing some basic properties of the archived files, such as
 file path names and last modification timestamps.
@@ -14,6 +17,23 @@ extended header data and may be explored by the user program in case extra archi
 
 LHA header levels: 0, 1, 2 and 3 are recognized.
 
+Archives can be also decoded from the byte chunks provided by the caller, without a blocking
+stream reader, using [LhaPushDecoder].
+
+The archived files can be listed without decompressing their content with
+[archive::LhaArchive::list] and single files can be extracted from seekable sources with
+[archive::LhaArchive::by_name]. The content of a single file can be decompressed in one call with
+[decompress_first] or [decompress_entry].
+
+When reading archives from slow media, wrap the stream reader in [ReadAhead] to prefetch the archive
+data on a background thread while the content is being decompressed.
+
+Archives split across several volumes, e.g. `archive.lzh`, `archive.l01` and so on, can be read
+by joining the volumes with [volume::MultiVolume].
+
+The errors are returned as [std::io::Error]s, which can be converted to [LhaError] to match on
+the cause of the failure, e.g. a checksum mismatch or an unsupported compression method.
+
 ## Compression methods
 
 You may include or opt out of some of the decoders:
@@ -27,10 +47,29 @@ features = ["lh1"] # select desired features
 
 `lh1` and `lz` features are enabled by **default**.
 
+The optional `rayon` feature enables `parallel::par_extract`, decompressing archived files
+from seekable sources concurrently.
+
+The optional `encoding` feature enables `LhaHeader::parse_pathname_sjis`, decoding Shift-JIS
+(CP932) file names, common in archives made in Japan, and other encodings from the `encoding_rs`
+crate with `header::FilenameEncoding::Encoding`.
+
+The optional `serde` feature implements `Serialize` and `Deserialize` for [LhaHeader] and
+the header metadata types, such as `header::MsDosAttrs`, `header::CompressionMethod` or
+`header::ExtraHeader`, so the archive listings can be emitted e.g. as JSON.
+
+The optional `tracing` feature emits [tracing](https://docs.rs/tracing) events of parsing the headers,
+decompressing the archived files, verifying their checksums and recovering from malformed headers.
+
+The optional `time` feature enables `header::TimestampResult::to_offset_datetime`, converting
+the MS-DOS, Unix and Windows timestamps to `time::OffsetDateTime`.
+
 | identifier | decoder            | feature | description
 |------------|--------------------|---------|------------
 | `-lh0-`    | PassthroughDecoder |         | no compression
 | `-lh1-`    | Lh1Decoder         | lh1     | LHarc version 1, 4kB sliding window, dynamic huffman
+| `-lh2-`    | Lh2Decoder         | lh2     | LHA 2.x beta, 8kB sliding window, dynamic huffman
+| `-lh3-`    | Lh3Decoder         | lh3     | LHA 2.x beta, 8kB sliding window, static huffman
 | `-lh4-`    | Lh5Decoder         |         | LHarc version 2, 4kB sliding window, static huffman
 | `-lh5-`    | Lh5Decoder         |         | LHarc version 2, 8kB sliding window, static huffman
 | `-lh6-`    | Lh7Decoder         |         | LHarc version 2, 32kB sliding window, static huffman
@@ -44,6 +83,10 @@ features = ["lh1"] # select desired features
 | `-pm1-`    | unsupported        | N/A     | PMarc, 8 Kb sliding window, static huffman
 | `-pm2-`    | unsupported        | N/A     | PMarc, 4 Kb sliding window, static huffman
 
+The `fast-unsafe` feature omits the bounds checks of copying the repeated sequences within the
+sliding window of the decoders. The copied ranges are clamped to the window size right before
+copying.
+
 ## Example
 
 ```no_run
@@ -87,14 +130,38 @@ fn extract_to_stdout<P: AsRef<Path>>(
 ```
 */
 // http://archive.gamedev.net/archive/reference/articles/article295.html
+
+/// Emits a `tracing` event, e.g. `trace!(debug!(offset, "message"))`, if the `tracing` feature
+/// is enabled, otherwise does nothing.
+macro_rules! trace {
+    ($level:ident!($($arg:tt)*)) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)*);
+    };
+}
+
+pub mod archive;
 pub mod crc;
 pub mod decode;
+pub mod encode;
+pub mod error;
 pub mod header;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod push;
+pub mod readahead;
+pub mod volume;
 pub(crate) mod ringbuf;
 pub(crate) mod bitstream;
 pub(crate) mod statictree;
+#[cfg(test)]
+pub(crate) mod fixtures;
 
 pub use decode::LhaDecodeReader;
+pub use encode::LhaWriter;
+pub use error::LhaError;
+pub use push::LhaPushDecoder;
+pub use readahead::ReadAhead;
 pub use header::{
     LhaHeader, CompressionMethod, OsType, TimestampResult, MsDosAttrs
 };
@@ -107,10 +174,75 @@ use std::fs::File;
 /// [LhaDecodeReader] with the first parsed LHA file header, ready to decode the content of the first
 /// archived file.
 ///
+/// The executable stub of a self-extracting archive is being skipped, see
+/// [LhaDecodeReader::new_seekable_with_options].
+///
 /// # Errors
 /// This function will return an error if an opened file is not an LHA/LZH file or the header couldn't
 /// be recognized. Other errors may also be returned from [File::open] and from attempts to read the file.
 pub fn parse_file<P: AsRef<Path>>(path: P) -> io::Result<LhaDecodeReader<File>> {
   let file = File::open(path)?;
-  Ok(LhaDecodeReader::new(file)?)
+  Ok(LhaDecodeReader::new_seekable(file)?)
+}
+
+/// Attempts to open a file from a filesystem in read-only mode and on success returns an instance of
+/// [LhaDecodeReader] configured with `options`, with the first parsed LHA file header.
+///
+/// # Errors
+/// This function will return an error if an opened file is not an LHA/LZH file or the header couldn't
+/// be recognized. Other errors may also be returned from [File::open] and from attempts to read the file.
+pub fn parse_file_with_options<P: AsRef<Path>>(path: P, options: decode::LhaOptions) -> io::Result<LhaDecodeReader<File>> {
+  let file = File::open(path)?;
+  Ok(LhaDecodeReader::new_seekable_with_options(file, options)?)
+}
+
+/// Opens the archive at `path` and returns the decompressed content of its first file, skipping
+/// the directory entries.
+///
+/// # Example
+/// ```no_run
+/// let content = delharc::decompress_first("archive.lzh")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// # Errors
+/// Returns an error of the kind [io::ErrorKind::NotFound] if the archive contains only directories,
+/// [LhaError::UnsupportedMethod] if the file's compression method is not supported,
+/// [LhaError::BadChecksum] if the checksum of its content doesn't match and other errors returned
+/// from [parse_file] and from attempts to read the file.
+pub fn decompress_first<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+  let mut lha_reader = parse_file(path)?;
+  decompress_where(&mut lha_reader, |header| !header.is_directory())
+}
+
+/// Reads the archive from `rd` and returns the decompressed content of the first file with the path
+/// name equal to `name`, see [LhaHeader::parse_pathname].
+///
+/// The content of the preceding files is being skipped without decompressing it.
+///
+/// # Example
+/// ```no_run
+/// let file = std::fs::File::open("archive.lzh")?;
+/// let content = delharc::decompress_entry(file, "dir/file.txt")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// # Errors
+/// Returns an error of the kind [io::ErrorKind::NotFound] if there is no such file in the archive,
+/// [LhaError::UnsupportedMethod] if the file's compression method is not supported,
+/// [LhaError::BadChecksum] if the checksum of its content doesn't match and other errors returned
+/// from attempts to read or parse the archive.
+pub fn decompress_entry<R: io::Read, P: AsRef<Path>>(rd: R, name: P) -> io::Result<Vec<u8>> {
+  let name = name.as_ref();
+  let mut lha_reader = LhaDecodeReader::new(rd)?;
+  decompress_where(&mut lha_reader, |header| header.parse_pathname() == name)
+}
+
+/// Decompresses the first file accepted by the `predicate`, verifying its checksum.
+fn decompress_where<R, F>(lha_reader: &mut LhaDecodeReader<R>, predicate: F) -> io::Result<Vec<u8>>
+  where R: io::Read, F: FnMut(&LhaHeader) -> bool
+{
+  let entry = lha_reader.entries_where(predicate).next()
+                        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found in archive"))??;
+  entry.content.ok_or_else(|| LhaError::UnsupportedMethod(entry.header.compression).into())
 }
@@ -0,0 +1,106 @@
+//! # Parallel decompression.
+//!
+//! Archived files are compressed independently, so their content can be decompressed concurrently.
+//! [par_extract] distributes decompression of the archived files across the threads of the current
+//! [rayon] thread pool.
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+
+use crate::crc::Crc16;
+use crate::decode::{Decoder, DecoderAny};
+use crate::error::LhaError;
+use crate::header::LhaHeader;
+
+/// Decompresses all files from the archive concurrently and returns the values produced by `f`
+/// in the order of the files in the archive.
+///
+/// First, all headers are being read from `rd`, seeking over the compressed content. Next, for each
+/// archived file the compressed content is being read (one file at a time) and decompressed in
+/// parallel. The decompressed content and its header are then passed to `f`, which is called from
+/// the thread pool's worker threads.
+///
+/// `f` receives `None` instead of the content if the file's compression method is not supported,
+/// e.g. for directory entries, see [LhaHeader::is_directory].
+///
+/// To run the decompression in a custom thread pool, call this function from the closure passed
+/// to [rayon::ThreadPool::install].
+///
+/// # Example
+/// ```no_run
+/// use std::{fs, io, path::PathBuf};
+///
+/// let file = fs::File::open("archive.lzh")?;
+/// let names: Vec<PathBuf> = delharc::parallel::par_extract(file, |header, content| {
+///     let path = header.parse_pathname();
+///     if let Some(content) = content {
+///         fs::write(path.file_name().unwrap(), content)?;
+///     }
+///     Ok(path)
+/// })?;
+/// # Ok::<(), io::Error>(())
+/// ```
+///
+/// # Errors
+/// Returns an error if a header could not be read or parsed, if the content of any file could not
+/// be decompressed or its checksum doesn't match, or the first error returned from `f`.
+pub fn par_extract<R, F, T>(mut rd: R, f: F) -> io::Result<Vec<T>>
+    where R: Read + Seek + Send,
+          F: Fn(&LhaHeader, Option<&[u8]>) -> io::Result<T> + Sync,
+          T: Send
+{
+    let mut entries = Vec::new();
+    while let Some(header) = LhaHeader::read(rd.by_ref())? {
+        let position = rd.stream_position()?;
+        let offset = i64::try_from(header.compressed_size).map_err(|_|
+            io::Error::new(io::ErrorKind::InvalidData, "compressed size too large"))?;
+        rd.seek(SeekFrom::Current(offset))?;
+        entries.push((header, position));
+    }
+    let rd = Mutex::new(rd);
+    entries.into_par_iter().map(|(header, position)| {
+        let supported = header.compression_method().map(|c| c.is_supported()).unwrap_or(false);
+        if !supported {
+            return f(&header, None)
+        }
+        let packed = read_packed(&rd, &header, position)?;
+        let content = decompress(&header, packed)?;
+        f(&header, Some(&content))
+    }).collect()
+}
+
+fn read_packed<R: Read + Seek>(rd: &Mutex<R>, header: &LhaHeader, position: u64) -> io::Result<Vec<u8>> {
+    let mut packed = Vec::new();
+    let mut rd = rd.lock().unwrap_or_else(|e| e.into_inner());
+    rd.seek(SeekFrom::Start(position))?;
+    rd.by_ref().take(header.compressed_size).read_to_end(&mut packed)?;
+    if (packed.len() as u64) < header.compressed_size {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected end of archive"))
+    }
+    Ok(packed)
+}
+
+/// The size of the chunks by which the decompressed content is grown, so the untrusted original
+/// size is never allocated up front.
+const CHUNK_SIZE: u64 = 1 << 16;
+
+fn decompress(header: &LhaHeader, packed: Vec<u8>) -> io::Result<Vec<u8>> {
+    let mut content = Vec::new();
+    let mut decoder = DecoderAny::new_from_header(header, io::Cursor::new(packed));
+    let mut remaining = header.original_size;
+    while remaining != 0 {
+        let len = remaining.min(CHUNK_SIZE) as usize;
+        let start = content.len();
+        content.try_reserve(len).map_err(|_| io::Error::new(io::ErrorKind::Other, "file too large"))?;
+        content.resize(start + len, 0);
+        decoder.fill_buffer(&mut content[start..])?;
+        remaining -= len as u64;
+    }
+    let mut crc = Crc16::default();
+    crc.digest(&content);
+    if crc.sum16() != header.file_crc {
+        return Err(LhaError::BadChecksum { expected: header.file_crc, got: crc.sum16() }.into())
+    }
+    Ok(content)
+}
@@ -0,0 +1,309 @@
+//! # Push-based decoding.
+//!
+//! [LhaPushDecoder] is a state machine decoding LHA/LZH archives from the arbitrary byte chunks
+//! provided by the caller. It doesn't perform any I/O by itself and thus can be used where the
+//! blocking [io::Read] trait is not available, e.g. with asynchronous runtimes or network protocols.
+use core::mem;
+use std::io;
+
+use crate::crc::Crc16;
+use crate::decode::{Decoder, DecoderAny, LhaOptions, LimitError, SpareDecoder};
+use crate::error::LhaError;
+use crate::header::{HeaderProgress, LhaHeader, ParseMode};
+
+const OUTPUT_CHUNK_SIZE: usize = 8192;
+
+type ContentDecoder = DecoderAny<io::Take<io::Cursor<Vec<u8>>>>;
+
+/// A push-based (sans-IO) decoder of LHA/LZH archives.
+///
+/// Feed the archive data with [LhaPushDecoder::push] and act on the returned [PushStatus].
+///
+/// The decoders of this library pull the compressed data from readers, so the compressed content
+/// of each file is being buffered in memory before it's decompressed. Files with unsupported
+/// compression methods are skipped without buffering. The memory used is bounded by the largest
+/// header and the largest compressed size of the supported files, which can be limited with
+/// [LhaPushDecoder::with_options].
+///
+/// The bytes of a partially received header are being buffered as well, and the header is parsed
+/// again only once the bytes it is known to span are available.
+///
+/// # Example
+/// ```no_run
+/// use delharc::push::{LhaPushDecoder, PushStatus};
+///
+/// fn decode_chunks<'a, I: Iterator<Item=&'a [u8]>>(chunks: I) -> std::io::Result<()> {
+///     let mut decoder = LhaPushDecoder::new();
+///     for mut chunk in chunks {
+///         loop {
+///             let (consumed, status) = decoder.push(chunk)?;
+///             chunk = &chunk[consumed..];
+///             match status {
+///                 PushStatus::NeedMore => break,
+///                 PushStatus::Header(header) => println!("{:?}", header.parse_pathname()),
+///                 PushStatus::Output(data) => println!("{} bytes", data.len()),
+///                 PushStatus::EndOfFile => println!("crc ok: {}", decoder.crc_is_ok()),
+///                 PushStatus::EndOfArchive => return Ok(())
+///             }
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LhaPushDecoder {
+    state: State,
+    pending: Vec<u8>,
+    header: Option<LhaHeader>,
+    crc: Crc16,
+    output: Box<[u8]>,
+    spare: Option<SpareDecoder>,
+    progress: HeaderProgress,
+    parse_mode: ParseMode,
+    max_header_size: usize,
+    max_entry_size: Option<u64>,
+    max_compression_ratio: Option<u64>
+}
+
+/// The status returned from [LhaPushDecoder::push].
+#[derive(Debug)]
+pub enum PushStatus<'a> {
+    /// The whole input has been consumed and more data is needed to proceed.
+    NeedMore,
+    /// The header of the next file has been parsed.
+    Header(&'a LhaHeader),
+    /// A chunk of the current file's decompressed content.
+    Output(&'a [u8]),
+    /// The whole content of the current file has been processed.
+    ///
+    /// The checksum of the decompressed content can now be verified.
+    EndOfFile,
+    /// The end of archive marker has been encountered. No more data will be consumed.
+    EndOfArchive,
+}
+
+#[derive(Debug)]
+enum State {
+    Header,
+    Content,
+    Skip(u64),
+    Decode(Box<ContentDecoder>, u64),
+    Done
+}
+
+enum Event {
+    NeedMore,
+    Header,
+    Output(usize),
+    EndOfFile,
+    EndOfArchive
+}
+
+impl Default for LhaPushDecoder {
+    fn default() -> Self {
+        LhaPushDecoder::with_options(LhaOptions::default())
+    }
+}
+
+impl LhaPushDecoder {
+    /// Creates a new instance of `LhaPushDecoder` expecting the first file header.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Creates a new instance of `LhaPushDecoder` expecting the first file header, configured
+    /// with the `options`.
+    ///
+    /// The headers are parsed in the [ParseMode] given with [LhaOptions::parse_mode] and their size
+    /// is limited with [LhaOptions::max_header_size]. A file exceeding [LhaOptions::max_entry_size] or
+    /// [LhaOptions::max_compression_ratio] results in [LimitError] once its header is parsed.
+    /// As the compressed content is being buffered, [LhaOptions::max_entry_size] limits the compressed
+    /// size of the decompressed files too. The other options are ignored.
+    pub fn with_options(options: LhaOptions) -> Self {
+        LhaPushDecoder {
+            state: State::Header,
+            pending: Vec::new(),
+            header: None,
+            crc: Crc16::default(),
+            output: vec![0u8; OUTPUT_CHUNK_SIZE].into_boxed_slice(),
+            spare: None,
+            progress: HeaderProgress::default(),
+            parse_mode: options.parse_mode,
+            max_header_size: options.max_header_size,
+            max_entry_size: options.max_entry_size,
+            max_compression_ratio: options.max_compression_ratio
+        }
+    }
+    /// Feeds the decoder with the next chunk of the archive data.
+    ///
+    /// On success returns the number of bytes consumed from `input` and the status. Bytes that
+    /// were not consumed should be provided again with the next call.
+    ///
+    /// The method should be called repeatedly, until [PushStatus::NeedMore] is returned, before
+    /// providing the next chunk of data. The decompressed output is provided in chunks with
+    /// [PushStatus::Output], possibly without consuming any input.
+    ///
+    /// # Errors
+    /// Returns an error if a header is malformed or the content could not be decompressed.
+    pub fn push(&mut self, input: &[u8]) -> io::Result<(usize, PushStatus<'_>)> {
+        let (consumed, event) = self.advance(input)?;
+        let status = match event {
+            Event::NeedMore => PushStatus::NeedMore,
+            Event::Header => PushStatus::Header(self.header.as_ref().unwrap()),
+            Event::Output(len) => PushStatus::Output(&self.output[..len]),
+            Event::EndOfFile => PushStatus::EndOfFile,
+            Event::EndOfArchive => PushStatus::EndOfArchive
+        };
+        Ok((consumed, status))
+    }
+    /// Returns a reference to the last parsed file's [LhaHeader] if any.
+    pub fn header(&self) -> Option<&LhaHeader> {
+        self.header.as_ref()
+    }
+    /// Returns `true` if the current file's compression method is supported.
+    ///
+    /// The content of files with unsupported compression methods is being skipped.
+    pub fn is_decoder_supported(&self) -> bool {
+        self.header.as_ref().and_then(|h| h.compression_method().ok())
+                   .map(|c| c.is_supported()).unwrap_or(false)
+    }
+    /// Returns `true` if the computed CRC-16 matches the checksum in the header.
+    ///
+    /// This should be called after [PushStatus::EndOfFile] has been returned.
+    pub fn crc_is_ok(&self) -> bool {
+        self.header.as_ref().map(|h| h.file_crc == self.crc.sum16()).unwrap_or(false)
+    }
+    /// Returns CRC-16 checksum if the computed checksum matches the one in the header.
+    /// Otherwise returns an error.
+    ///
+    /// This should be called after [PushStatus::EndOfFile] has been returned.
+    pub fn crc_check(&self) -> io::Result<u16> {
+        let got = self.crc.sum16();
+        match &self.header {
+            Some(header) if header.file_crc == got => Ok(got),
+            Some(header) => Err(LhaError::BadChecksum { expected: header.file_crc, got }.into()),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, "crc16 mismatch"))
+        }
+    }
+
+    fn advance(&mut self, input: &[u8]) -> io::Result<(usize, Event)> {
+        let mut consumed = 0;
+        loop {
+            let input = &input[consumed..];
+            match &mut self.state {
+                State::Header => {
+                    if self.pending.is_empty() && input.is_empty() {
+                        return Ok((consumed, Event::NeedMore))
+                    }
+                    self.pending.extend_from_slice(input);
+                    if !self.is_header_available() {
+                        return Ok((consumed + input.len(), Event::NeedMore))
+                    }
+                    let mut rd = &self.pending[..];
+                    let mut progress = HeaderProgress::default();
+                    let res = LhaHeader::read_with_progress(&mut rd, self.parse_mode, Some(self.max_header_size),
+                                                            &mut progress);
+                    // a failed attempt consumes the whole input, so unused bytes may come only from input
+                    let unused = rd.len().min(input.len());
+                    self.progress = HeaderProgress::default();
+                    let event = match res {
+                        Ok(Some(header)) => {
+                            self.state = match header.compression_method() {
+                                Ok(compression) if compression.is_supported() => {
+                                    self.check_limits(&header)?;
+                                    State::Content
+                                }
+                                _ => State::Skip(header.compressed_size)
+                            };
+                            self.header = Some(header);
+                            self.crc.reset();
+                            Event::Header
+                        }
+                        Ok(None) => {
+                            self.state = State::Done;
+                            Event::EndOfArchive
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                            self.progress = progress;
+                            return Ok((consumed + input.len(), Event::NeedMore))
+                        }
+                        Err(e) => return Err(e)
+                    };
+                    self.pending.clear();
+                    return Ok((consumed + input.len() - unused, event))
+                }
+                State::Content => {
+                    let header = self.header.as_ref().unwrap();
+                    let missing = header.compressed_size - self.pending.len() as u64;
+                    let len = input.len().min(usize::try_from(missing).unwrap_or(usize::MAX));
+                    self.pending.extend_from_slice(&input[..len]);
+                    consumed += len;
+                    if (len as u64) < missing {
+                        return Ok((consumed, Event::NeedMore))
+                    }
+                    let content = io::Cursor::new(mem::take(&mut self.pending));
+                    let decoder = DecoderAny::new_from_header_reusing(header, content, &mut self.spare);
+                    self.state = State::Decode(Box::new(decoder), header.original_size);
+                }
+                State::Skip(remaining) => {
+                    let len = input.len().min(usize::try_from(*remaining).unwrap_or(usize::MAX));
+                    *remaining -= len as u64;
+                    consumed += len;
+                    if *remaining != 0 {
+                        return Ok((consumed, Event::NeedMore))
+                    }
+                    self.state = State::Header;
+                    return Ok((consumed, Event::EndOfFile))
+                }
+                State::Decode(decoder, remaining) => {
+                    if *remaining == 0 {
+                        if let State::Decode(decoder, _) = mem::replace(&mut self.state, State::Header) {
+                            // keep the buffers for the next file
+                            let mut pending = decoder.into_inner_reusing(&mut self.spare).into_inner().into_inner();
+                            pending.clear();
+                            self.pending = pending;
+                        }
+                        return Ok((consumed, Event::EndOfFile))
+                    }
+                    let len = self.output.len().min(usize::try_from(*remaining).unwrap_or(usize::MAX));
+                    let target = &mut self.output[..len];
+                    decoder.fill_buffer(target)?;
+                    self.crc.digest(target);
+                    *remaining -= len as u64;
+                    return Ok((consumed, Event::Output(len)))
+                }
+                State::Done => return Ok((consumed, Event::EndOfArchive))
+            }
+        }
+    }
+    /// Returns `true` if the pending bytes include all of the bytes the header is known to span,
+    /// following the chain of its extra headers as far as the pending bytes allow.
+    fn is_header_available(&mut self) -> bool {
+        let progress = &mut self.progress;
+        while progress.min_len <= self.pending.len() {
+            let size = match progress.extra_header {
+                Some(size) => size,
+                None => return true
+            };
+            // the length of the next extra header ends the current one
+            let end = progress.min_len;
+            let len = self.pending[end - size..end].iter().rev().fold(0, |len, &b| len << 8 | usize::from(b));
+            if len <= size || end > self.max_header_size {
+                // the last or a malformed extra header, the parser takes over
+                return true
+            }
+            progress.min_len = end.saturating_add(len);
+        }
+        false
+    }
+    /// Returns an error if the file of the `header` exceeds the limits of decompressed files.
+    fn check_limits(&self, header: &LhaHeader) -> io::Result<()> {
+        let (size, compressed_size) = (header.original_size, header.compressed_size);
+        let err = LimitError::check(size, compressed_size, self.max_entry_size, self.max_compression_ratio)
+            .or_else(|| self.max_entry_size.filter(|&limit| compressed_size > limit)
+                                           .map(|limit| LimitError::EntrySize { size: compressed_size, limit }));
+        match err {
+            Some(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+            None => Ok(())
+        }
+    }
+}
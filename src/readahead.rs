@@ -0,0 +1,224 @@
+//! # Background read-ahead.
+//!
+//! [ReadAhead] wraps a stream reader and prefetches its data on a background thread, so reading
+//! from slow media overlaps with decompression performed on the caller's thread.
+use std::io::{self, BufRead, Read};
+use std::panic;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+
+/// The default number of buffers in flight.
+pub const DEFAULT_BUFFER_COUNT: usize = 4;
+/// The default size of each buffer in bytes.
+pub const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A stream reader prefetching data from the inner reader on a background thread.
+///
+/// The data is being read into a ring of buffers: the background thread fills the empty buffers,
+/// while the filled ones are being consumed by reading from this instance and then handed back.
+///
+/// Errors encountered by the background thread are returned from reads in order with the data.
+/// After an error has been returned no more data is available.
+///
+/// # Example
+/// ```no_run
+/// use std::fs::File;
+/// use delharc::{LhaDecodeReader, ReadAhead};
+///
+/// let file = File::open("archive.lzh")?;
+/// let mut lha_reader = LhaDecodeReader::new(ReadAhead::new(file))?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct ReadAhead<R> {
+    filled: Receiver<io::Result<Vec<u8>>>,
+    empty: SyncSender<Vec<u8>>,
+    current: Vec<u8>,
+    pos: usize,
+    state: State,
+    handle: JoinHandle<R>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Reading,
+    Eof,
+    Failed
+}
+
+impl<R: Read + Send + 'static> ReadAhead<R> {
+    /// Creates a new instance of `ReadAhead<R>` with [DEFAULT_BUFFER_COUNT] buffers of [DEFAULT_BUFFER_SIZE]
+    /// bytes each and starts prefetching data from `rd`.
+    pub fn new(rd: R) -> Self {
+        Self::with_buffers(rd, DEFAULT_BUFFER_COUNT, DEFAULT_BUFFER_SIZE)
+    }
+    /// Creates a new instance of `ReadAhead<R>` with `count` buffers of `size` bytes each and starts
+    /// prefetching data from `rd`.
+    ///
+    /// # Panics
+    /// Panics if `count` or `size` is 0 or if the background thread could not be spawned.
+    pub fn with_buffers(mut rd: R, count: usize, size: usize) -> Self {
+        assert!(count != 0 && size != 0, "the number and the size of buffers must be non-zero");
+        let (filled_tx, filled) = mpsc::sync_channel(count);
+        let (empty, empty_rx) = mpsc::sync_channel::<Vec<u8>>(count);
+        for _ in 0..count {
+            empty.send(Vec::with_capacity(size)).unwrap();
+        }
+        let handle = thread::Builder::new()
+            .name("delharc-read-ahead".into())
+            .spawn(move || {
+                while let Ok(mut buf) = empty_rx.recv() {
+                    buf.resize(size, 0);
+                    let (len, res) = read_chunk(&mut rd, &mut buf);
+                    buf.truncate(len);
+                    let err = res.err();
+                    // the data read before an error is sent first, an empty buffer marks the end of stream
+                    if (len != 0 || err.is_none()) && filled_tx.send(Ok(buf)).is_err() {
+                        break
+                    }
+                    if let Some(e) = err {
+                        let _ = filled_tx.send(Err(e));
+                        break
+                    }
+                    if len == 0 {
+                        break
+                    }
+                }
+                rd
+            })
+            .expect("failed to spawn the read-ahead thread");
+        ReadAhead {
+            filled,
+            empty,
+            current: Vec::new(),
+            pos: 0,
+            state: State::Reading,
+            handle
+        }
+    }
+    /// Stops the background thread and returns the inner reader.
+    ///
+    /// Note that the data already prefetched is lost, so the position of the returned reader
+    /// may be ahead of the data read from this instance.
+    ///
+    /// This call blocks until the pending read of the background thread completes.
+    pub fn into_inner(self) -> R {
+        let ReadAhead { filled, empty, handle, .. } = self;
+        drop(filled);
+        drop(empty);
+        handle.join().unwrap_or_else(|e| panic::resume_unwind(e))
+    }
+}
+
+impl<R> BufRead for ReadAhead<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos == self.current.len() && self.state == State::Reading {
+            let buf = match self.filled.recv() {
+                Ok(Ok(buf)) => buf,
+                Ok(Err(e)) => {
+                    self.state = State::Failed;
+                    return Err(e)
+                }
+                Err(..) => {
+                    self.state = State::Failed;
+                    return Err(io::Error::new(io::ErrorKind::Other, "the read-ahead thread has stopped"))
+                }
+            };
+            if buf.is_empty() {
+                self.state = State::Eof;
+            }
+            let used = core::mem::replace(&mut self.current, buf);
+            self.pos = 0;
+            // the thread may have already finished
+            let _ = self.empty.send(used);
+        }
+        match self.state {
+            State::Failed => Err(io::Error::new(io::ErrorKind::Other, "the read-ahead thread has stopped")),
+            _ => Ok(&self.current[self.pos..])
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.current.len());
+    }
+}
+
+impl<R> Read for ReadAhead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = {
+            let data = self.fill_buf()?;
+            let len = data.len().min(buf.len());
+            buf[..len].copy_from_slice(&data[..len]);
+            len
+        };
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+/// Reads from `rd` until `buf` is full, EOF is reached or an error occurs.
+///
+/// Returns the number of bytes read and the error if any.
+fn read_chunk<R: Read>(rd: &mut R, buf: &mut [u8]) -> (usize, io::Result<()>) {
+    let mut len = 0;
+    while len < buf.len() {
+        match rd.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return (len, Err(e))
+        }
+    }
+    (len, Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Yields data in small pieces, then fails if `fail` is set.
+    struct Trickle {
+        data: Vec<u8>,
+        pos: usize,
+        fail: bool
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.pos == self.data.len() && self.fail {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "broken"))
+            }
+            let len = buf.len().min(self.data.len() - self.pos).min(7);
+            buf[..len].copy_from_slice(&self.data[self.pos..self.pos + len]);
+            self.pos += len;
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn read_ahead_works() {
+        let data: Vec<u8> = (0..10000u32).map(|n| (n.wrapping_mul(0x9E3779B9) >> 13) as u8).collect();
+        for (count, size) in [(1, 5), (2, 10), (3, 1000), (4, 100000)] {
+            let rd = Trickle { data: data.clone(), pos: 0, fail: false };
+            let mut read_ahead = ReadAhead::with_buffers(rd, count, size);
+            let mut output = Vec::new();
+            read_ahead.read_to_end(&mut output).unwrap();
+            assert_eq!(output, data);
+            assert_eq!(read_ahead.read(&mut [0u8;1]).unwrap(), 0);
+            assert_eq!(read_ahead.into_inner().pos, data.len());
+        }
+        let rd = Trickle { data: data.clone(), pos: 0, fail: true };
+        let mut read_ahead = ReadAhead::with_buffers(rd, 2, 1024);
+        let mut output = Vec::new();
+        let err = read_ahead.read_to_end(&mut output).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(output, data);
+        assert!(read_ahead.read(&mut [0u8;1]).is_err());
+        let rd = Trickle { data: data.clone(), pos: 0, fail: false };
+        let mut read_ahead = ReadAhead::with_buffers(rd, 2, 16);
+        let mut buf = [0u8;100];
+        read_ahead.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[..100]);
+        assert!(read_ahead.into_inner().pos >= 100);
+    }
+}
@@ -23,6 +23,21 @@ pub trait RingBuffer: Default + Index<usize, Output=u8> {
     ///
     /// At each iteration the yielded value is also being pushed to the ring buffer.
     fn iter_from_pos<'a>(&'a mut self, pos: usize) -> HistoryIter<'a, Self>;
+    /// Fills the whole `target` with consecutive bytes from the buffer starting at `-offset`
+    /// from the last element.
+    ///
+    /// `offset` = 0 indicates the last element written to the buffer.
+    ///
+    /// The copied bytes are also being pushed to the ring buffer, so the result is the same as if
+    /// the bytes were taken from the iterator returned by [RingBuffer::iter_from_offset].
+    fn copy_from_offset(&mut self, offset: usize, target: &mut [u8]);
+    /// Fills the whole `target` with consecutive bytes from the buffer starting at `pos`.
+    ///
+    /// The copied bytes are also being pushed to the ring buffer, so the result is the same as if
+    /// the bytes were taken from the iterator returned by [RingBuffer::iter_from_pos].
+    fn copy_from_pos(&mut self, pos: usize, target: &mut [u8]);
+    /// Restores the initial state of the buffer, as created by [Default::default].
+    fn reset(&mut self);
 }
 
 /// A generic ring buffer implementation using arrays of the size of the power of two as internal buffers.
@@ -111,6 +126,53 @@ impl<const N: usize> RingBuffer for RingArrayBuf<N> {
         let index = pos & index_mask!(N);
         HistoryIter { index, ringbuf: self }
     }
+
+    #[inline]
+    fn copy_from_offset(&mut self, offset: usize, target: &mut [u8]) {
+        let offset = (offset & index_mask!(N)) + 1;
+        self.copy_from_pos(self.cursor + N - offset, target)
+    }
+
+    fn copy_from_pos(&mut self, mut pos: usize, mut target: &mut [u8]) {
+        while !target.is_empty() {
+            let src = pos & index_mask!(N);
+            let dst = self.cursor;
+            // bytes pushed at the distance from the source are being copied again
+            let distance = match dst.wrapping_sub(src) & index_mask!(N) {
+                0 => N,
+                distance => distance
+            };
+            let len = target.len().min(distance).min(N - src).min(N - dst);
+            #[cfg(feature = "fast-unsafe")]
+            unsafe {
+                // safe because len is clamped above, so both ranges fit in the buffer
+                // and len <= target.len()
+                debug_assert!(src + len <= N && dst + len <= N && len <= target.len());
+                let buffer = self.buffer.as_mut_ptr();
+                core::ptr::copy(buffer.add(src), buffer.add(dst), len);
+                core::ptr::copy_nonoverlapping(buffer.add(dst), target.as_mut_ptr(), len);
+                target = target.get_unchecked_mut(len..);
+            }
+            #[cfg(not(feature = "fast-unsafe"))]
+            {
+                self.buffer.copy_within(src..src + len, dst);
+                let (chunk, rest) = target.split_at_mut(len);
+                chunk.copy_from_slice(&self.buffer[dst..dst + len]);
+                target = rest;
+            }
+            self.cursor = (dst + len) & index_mask!(N);
+            // if the whole repeated sequence has been copied, the source can stay in place,
+            // doubling the length of the next chunk, as long as it fits in the buffer
+            if len != distance || 2 * distance > N {
+                pos = src + len;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(b' ');
+        self.cursor = 0;
+    }
 }
 
 #[cfg(test)]
@@ -157,6 +219,47 @@ mod tests {
             let mut hist = buffer.iter_from_offset(15).take(11);
             vec.extend(hist.by_ref());
             assert_eq!(vec, (16..32).collect::<Vec<u8>>());
+            buffer.reset();
+            assert_eq!(buffer.cursor(), 0);
+            assert_eq!(buffer.buffer, TestRingBuffer::default().buffer);
+        }
+    }
+
+    #[test]
+    fn ringbuf_copy_works() {
+        let mut seed = 1u32;
+        let mut random = move |max: usize| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as usize % max
+        };
+        let mut buffer = TestRingBuffer::default();
+        let mut expected = TestRingBuffer::default();
+        let mut target = [0u8;80];
+        let mut target_exp = [0u8;80];
+        for _ in 0..5000 {
+            for _ in 0..random(5) {
+                let byte = random(256) as u8;
+                buffer.push(byte);
+                expected.push(byte);
+            }
+            let len = random(80);
+            if random(2) == 0 {
+                let offset = random(40);
+                buffer.copy_from_offset(offset, &mut target[..len]);
+                for (t, s) in target_exp[..len].iter_mut().zip(expected.iter_from_offset(offset)) {
+                    *t = s;
+                }
+            }
+            else {
+                let pos = random(100);
+                buffer.copy_from_pos(pos, &mut target[..len]);
+                for (t, s) in target_exp[..len].iter_mut().zip(expected.iter_from_pos(pos)) {
+                    *t = s;
+                }
+            }
+            assert_eq!(target[..len], target_exp[..len]);
+            assert_eq!(buffer.cursor(), expected.cursor());
+            assert_eq!(buffer.buffer, expected.buffer);
         }
     }
 }
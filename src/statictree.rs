@@ -17,6 +17,11 @@ Branch numbers indicate their positions in a vector in which the tree is being s
           f   g h   i
 ```
 
+To speed up reading, a lookup table is being built along with the tree, indexed by the next
+`lookup_bits` bits of the path. Each table entry contains the length of the code prefix and
+either the leaf found at the end of the path or the branch at the depth of `lookup_bits`, from
+which the rest of the path is being followed bit by bit.
+
 The above tree can be built from the following `lengths`:
 
 ```text
@@ -52,25 +57,39 @@ use crate::bitstream::BitRead;
 pub mod entry;
 use entry::*;
 
+/// The maximum number of path bits resolved with a single table lookup.
+const LOOKUP_BITS: u32 = 10;
+
 /// A static Huffman tree.
 #[derive(Debug, Clone)]
 pub struct HuffTree {
-    tree: Vec<TreeEntry>
+    tree: Vec<TreeEntry>,
+    lookup: Vec<LookupEntry>,
+    lookup_bits: u32
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LookupEntry {
+    node: TreeEntry,
+    len: u8
 }
 
 impl HuffTree {
     /// Creates a new and empty `HuffTree` with the reserved node capacity.
     ///
-    /// Any attempt to read from a new tree will result in panic.
+    /// Reading from a new tree will always result in `0`.
     pub fn with_capacity(capacity: usize) -> Self {
         let tree = Vec::with_capacity(capacity);
-        HuffTree { tree }
+        let mut huff_tree = HuffTree { tree, lookup: Vec::with_capacity(1 << LOOKUP_BITS), lookup_bits: 0 };
+        huff_tree.clear_lookup();
+        huff_tree
     }
     /// Initializes a `HuffTree` in such a way that any attept to read from it will always
     /// result in the given value, without even reading any position bits.
     pub fn set_single(&mut self, value: u16) {
         self.tree.clear();
         self.tree.push(TreeEntry::leaf(value));
+        self.build_lookup(0);
     }
     /// Builds the tree from the given array of lengths.
     ///
@@ -86,6 +105,8 @@ impl HuffTree {
     /// * An error is returned if a built tree is incomplete.
     pub fn build_tree(&mut self, value_lengths: &[u8]) -> Result<(), &'static str> {
         // println!("({}) {:?}", value_lengths.len(), value_lengths);
+        // the tree is being modified, so the lookup table can't point to any of its nodes
+        self.clear_lookup();
         if value_lengths.len() > TreeEntry::MAX_INDEX / 2 {
             return Err("too many code lengths");
         }
@@ -95,9 +116,12 @@ impl HuffTree {
         // the number of allocated tree indices
         // the tree size should be equal to the value of this variable
         let mut max_allocated: usize = 1; // start with a single (root) node
+        let mut max_len = 0;
         for current_len in 1u8.. {
+            max_len = current_len;
             // add missing branches
-            for _ in  tree.len()..max_allocated {
+            let missing_branches = max_allocated - tree.len();
+            for _ in 0..missing_branches {
                 match TreeEntry::branch(max_allocated) {
                     Ok(branch) => tree.push(branch),
                     Err(e) => {
@@ -136,6 +160,7 @@ impl HuffTree {
         // tree.extend(
         //     (tree.len()..max_allocated).map(|_| TreeEntry::leaf(0) )
         // );
+        self.build_lookup(max_len.into());
         Ok(())
     }
     /// Returns the `value` of the leaf by following the bit `path` read from the given bit reader.
@@ -149,11 +174,13 @@ impl HuffTree {
     /// If a tree has been initialized with [HuffTree::set_single] this method will always return the
     /// single `value`, without reading any bits from the stream.
     ///
-    /// # Panics
-    /// Panics if a tree has not been built or otherwise initialized as a single value tree.
+    /// If a tree has not been built successfully this method will always return `0`.
     pub fn read_entry<R: BitRead>(&self, mut path: R) -> io::Result<u16> {
+        let bits = path.peek_bits(self.lookup_bits)?;
+        let LookupEntry { node, len } = self.lookup[bits];
+        path.skip_bits(len.into())?;
         let tree = &self.tree;
-        let mut node = &tree[0]; // panics if tree uninitialized
+        let mut node = &node;
         loop {
             match node.as_type() {
                 NodeType::Leaf(code) => return Ok(code),
@@ -171,6 +198,41 @@ impl HuffTree {
     }
 }
 
+impl HuffTree {
+    /// Makes the lookup table resolve every path to the leaf of `0` without reading any bits.
+    fn clear_lookup(&mut self) {
+        self.lookup_bits = 0;
+        self.lookup.clear();
+        self.lookup.push(LookupEntry { node: TreeEntry::leaf(0), len: 0 });
+    }
+
+    fn build_lookup(&mut self, max_len: u32) {
+        fn fill_step(tree: &[TreeEntry], lookup: &mut [LookupEntry], index: usize, depth: u32, prefix: usize, bits: u32) {
+            let node = tree[index];
+            match node.as_type() {
+                NodeType::Leaf(..) => {
+                    let start = prefix << (bits - depth);
+                    let end = (prefix + 1) << (bits - depth);
+                    lookup[start..end].fill(LookupEntry { node, len: depth as u8 });
+                }
+                NodeType::Branch(index) if depth < bits => {
+                    fill_step(tree, lookup, index as usize, depth + 1, prefix << 1, bits);
+                    fill_step(tree, lookup, index as usize + 1, depth + 1, prefix << 1 | 1, bits);
+                }
+                NodeType::Branch(..) => {
+                    lookup[prefix] = LookupEntry { node, len: depth as u8 };
+                }
+            }
+        }
+
+        let bits = max_len.min(LOOKUP_BITS);
+        self.lookup_bits = bits;
+        self.lookup.clear();
+        self.lookup.resize(1 << bits, LookupEntry { node: TreeEntry::leaf(0), len: 0 });
+        fill_step(&self.tree, &mut self.lookup, 0, 0, 0, bits);
+    }
+}
+
 impl fmt::Display for HuffTree {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 
@@ -269,6 +331,31 @@ mod tests {
         }
         assert_eq!(res, [3, 5, 6, 8, 9, 10, 13, 14, 15]);
 
+        // codes longer than the lookup table bits
+        let lengths: Vec<u8> = (1..=14).chain([14]).collect();
+        tree.build_tree(&lengths).unwrap();
+        validate_tree(&tree, 15);
+        // 0, 10, 110, ..., 11111111111110, 11111111111111
+        let values = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 14, 13, 0];
+        let mut bits = Vec::new();
+        let mut acc = 0u32;
+        let mut nbits = 0;
+        for value in values {
+            let (code, len) = if value == 14 { ((1 << 14) - 1, 14) } else { (((1 << value) - 1) << 1, value + 1) };
+            acc = acc << len | code;
+            nbits += len;
+            while nbits >= 8 {
+                nbits -= 8;
+                bits.push((acc >> nbits) as u8);
+            }
+        }
+        bits.push((acc << (8 - nbits)) as u8 | ((1 << (8 - nbits)) - 1) as u8);
+        let mut path = BitStream::new(bits.as_slice());
+        for value in values {
+            assert_eq!(tree.read_entry(path.by_ref()).unwrap(), value);
+        }
+        assert!(tree.read_entry(path.by_ref()).is_err());
+
         assert!(tree.build_tree(&[0, 1, 0, 1, 1]).is_err());
         assert!(tree.build_tree(&[0, 1, 0, 1, 10]).is_err());
     }
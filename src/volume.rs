@@ -0,0 +1,180 @@
+//! # Multi-volume archives.
+//!
+//! [MultiVolume] joins the volumes of an archive split across several files, e.g. `archive.lzh`,
+//! `archive.l01`, `archive.l02` and so on, into a single stream. The next volume is being requested
+//! from the provider when the previous one ends, so the content of a file spanning the boundary of
+//! volumes is being decompressed seamlessly.
+//!
+//! The volume of a file can be established with [LhaHeader::multi_disc][crate::LhaHeader::multi_disc],
+//! if its header contains the ["Multi-disc"][crate::header::ext::EXT_HEADER_MULTI_DISC] extra header,
+//! or with [MultiVolume::volume] after the header has been read.
+use core::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// A stream reader joining the volumes of a split archive.
+///
+/// The `provider` receives the index of the requested volume, counting from `1` for the volume
+/// following the first one, and returns `Ok(None)` if there are no more volumes.
+///
+/// # Example
+/// ```no_run
+/// use std::fs::File;
+/// use delharc::{LhaDecodeReader, volume::MultiVolume};
+///
+/// let volumes = MultiVolume::new(File::open("archive.lzh")?, |index| {
+///     match File::open(format!("archive.l{:02}", index)) {
+///         Ok(file) => Ok(Some(file)),
+///         Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+///         Err(e) => Err(e)
+///     }
+/// });
+/// let mut lha_reader = LhaDecodeReader::new(volumes)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct MultiVolume<R, F> {
+    current: R,
+    volume: u32,
+    provider: F,
+    done: bool
+}
+
+impl<R: Read, F: FnMut(u32) -> io::Result<Option<R>>> MultiVolume<R, F> {
+    /// Creates a new instance of `MultiVolume<R, F>` reading from the `first` volume and then from
+    /// the volumes returned from `provider`.
+    pub fn new(first: R, provider: F) -> Self {
+        MultiVolume { current: first, volume: 0, provider, done: false }
+    }
+    /// Returns the index of the volume being read, `0` for the first one.
+    pub fn volume(&self) -> u32 {
+        self.volume
+    }
+    /// Returns a reference to the reader of the current volume.
+    pub fn get_ref(&self) -> &R {
+        &self.current
+    }
+    /// Unwraps this `MultiVolume<R, F>`, returning the reader of the current volume.
+    pub fn into_inner(self) -> R {
+        self.current
+    }
+}
+
+impl<R: Read, F: FnMut(u32) -> io::Result<Option<R>>> Read for MultiVolume<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let len = self.current.read(buf)?;
+            if len != 0 || buf.is_empty() || self.done {
+                return Ok(len)
+            }
+            match (self.provider)(self.volume + 1)? {
+                Some(next) => {
+                    self.current = next;
+                    self.volume += 1;
+                }
+                None => self.done = true
+            }
+        }
+    }
+}
+
+impl<R: fmt::Debug, F> fmt::Debug for MultiVolume<R, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultiVolume")
+         .field("current", &self.current)
+         .field("volume", &self.volume)
+         .field("done", &self.done)
+         .finish()
+    }
+}
+
+/// Opens the first volume at `path` and returns [MultiVolume] opening the following volumes from
+/// the same directory, named according to [volume_path], until the next volume file doesn't exist.
+///
+/// # Example
+/// ```no_run
+/// let mut lha_reader = delharc::LhaDecodeReader::new(delharc::volume::open_volumes("archive.lzh")?)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// # Errors
+/// Returns an error if the first volume could not be opened.
+pub fn open_volumes<P: AsRef<Path>>(path: P)
+    -> io::Result<MultiVolume<File, impl FnMut(u32) -> io::Result<Option<File>>>>
+{
+    let path = path.as_ref().to_path_buf();
+    let first = File::open(&path)?;
+    Ok(MultiVolume::new(first, move |index| {
+        match File::open(volume_path(&path, index)) {
+            Ok(file) => Ok(Some(file)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e)
+        }
+    }))
+}
+
+/// Returns the path of the volume with the given `index` following the first volume at `first`.
+///
+/// The extension of the first volume is being replaced with its first character followed by
+/// the two digit index, e.g. `archive.lzh` is followed by `archive.l01`, `archive.l02` and so on.
+/// The first character is `l` if the first volume has no extension.
+pub fn volume_path(first: &Path, index: u32) -> PathBuf {
+    let prefix = first.extension()
+                      .and_then(|ext| ext.to_str())
+                      .and_then(|ext| ext.chars().next())
+                      .unwrap_or('l');
+    first.with_extension(format!("{}{:02}", prefix, index))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::*;
+
+    #[test]
+    fn volume_path_works() {
+        assert_eq!(volume_path(Path::new("dir/archive.lzh"), 1), Path::new("dir/archive.l01"));
+        assert_eq!(volume_path(Path::new("ARCHIVE.LZH"), 12), Path::new("ARCHIVE.L12"));
+        assert_eq!(volume_path(Path::new("archive"), 100), Path::new("archive.l100"));
+    }
+
+    #[test]
+    fn multi_volume_works() {
+        let volumes = [&b"Hello, "[..], b"", b"World", b"!"];
+        let mut requested = Vec::new();
+        let mut rd = MultiVolume::new(Cursor::new(volumes[0]), |index| {
+            requested.push(index);
+            Ok(volumes.get(index as usize).map(|data| Cursor::new(*data)))
+        });
+        let mut buf = [0u8; 4];
+        assert_eq!(rd.read(&mut buf).unwrap(), 4);
+        assert_eq!(rd.read(&mut []).unwrap(), 0);
+        assert_eq!(rd.volume(), 0);
+        let mut rest = String::new();
+        rd.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "o, World!");
+        assert_eq!(rd.volume(), 3);
+        assert_eq!(rd.read(&mut buf).unwrap(), 0);
+        assert_eq!(requested, [1, 2, 3, 4]);
+
+        let mut rd = MultiVolume::new(Cursor::new(&b"a"[..]), |_| Err(io::ErrorKind::NotFound.into()));
+        assert_eq!(rd.read(&mut buf).unwrap(), 1);
+        assert_eq!(rd.read(&mut buf).unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(rd.into_inner().position(), 1);
+    }
+
+    #[test]
+    fn split_archive_works() {
+        let content = b"Hello, World!";
+        let archive = crate::fixtures::stored_archive(&[("foo", content)]);
+        let (first, rest) = archive.split_at(archive.len() - 8);
+        let volumes = MultiVolume::new(first, |index| Ok((index == 1).then_some(rest)));
+        let mut lha_reader = crate::LhaDecodeReader::new(volumes).unwrap();
+        let mut output = Vec::new();
+        lha_reader.read_to_end(&mut output).unwrap();
+        assert_eq!(output, content);
+        lha_reader.crc_check().unwrap();
+        assert!(!lha_reader.next_file().unwrap());
+        assert_eq!(lha_reader.into_inner().volume(), 1);
+    }
+}
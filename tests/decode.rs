@@ -24,25 +24,77 @@ fn test_decode() {
     let file = fs::File::open("tests/decode/lh0.bin").unwrap();
     let size = file.metadata().unwrap().len();
     let mut decoder = PassthroughDecoder::new(file);
-    test_decoder(&mut decoder, size as usize, CRC32, usize::max_value());
+    test_decoder(&mut decoder, size as usize, CRC32, usize::MAX);
 
     for (name, compression, offset) in TESTS_CASES {
         println!("-------------\n{:?}", name);
         let mut file = fs::File::open(format!("tests/decode/{}", name)).unwrap();
         let compressed_size = file.metadata().unwrap().len();
-        for limit in [usize::max_value(), 128, 31, 3, 2, 1].iter().copied() {
+        for limit in [usize::MAX, 128, 31, 3, 2, 1].iter().copied() {
             let mut decoder = DecoderAny::new_from_compression(*compression,
                                           file.take(compressed_size - offset));
             assert!(decoder.is_supported());
             test_decoder(&mut decoder, size as usize, CRC32, limit);
             // println!("{:?}", decoder);
             file = decoder.into_inner().into_inner();
-            assert_eq!(compressed_size - offset, file.seek(SeekFrom::Current(0)).unwrap());
+            assert_eq!(compressed_size - offset, file.stream_position().unwrap());
             file.seek(SeekFrom::Start(0)).unwrap();
         }
     }
 }
 
+const ARCHIVES: &[&str] = &[
+    #[cfg(feature = "lz")]
+    "tests/lzs/long.lzs",
+    #[cfg(feature = "lz")]
+    "tests/lengths/lz5-64k.lzs",
+    #[cfg(feature = "lh1")]
+    "tests/lengths/lh1-2m.lzh",
+    #[cfg(feature = "lh2")]
+    "tests/lh2/ramp.lzh",
+    #[cfg(feature = "lh3")]
+    "tests/lh3/ramp.lzh",
+    "tests/lha_unix114i/h2_lh5.lzh",
+    "tests/lha_unix114i/lh6_long.lzh",
+    "tests/lha_unix114i/lh7_long.lzh",
+];
+
+/// A reader returning at most a single byte on every read.
+struct OneByteReader<R>(R);
+
+impl<R: Read> Read for OneByteReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(1);
+        self.0.read(&mut buf[..len])
+    }
+}
+
+#[test]
+fn test_decode_short_reads() {
+    const CRC32: u32 = 0x4e46f4a1;
+    let size = fs::metadata("tests/decode/lh0.bin").unwrap().len();
+    for (name, compression, offset) in TESTS_CASES {
+        println!("-------------\n{:?}", name);
+        let data = fs::read(format!("tests/decode/{}", name)).unwrap();
+        let data = &data[..data.len() - *offset as usize];
+        let mut decoder = DecoderAny::new_from_compression(*compression, OneByteReader(data));
+        test_decoder(&mut decoder, size as usize, CRC32, usize::MAX);
+    }
+    for name in ARCHIVES {
+        println!("-------------\n{:?}", name);
+        let file = fs::File::open(name).unwrap();
+        let mut lha_reader = LhaDecodeReader::new(OneByteReader(file)).unwrap();
+        loop {
+            assert!(lha_reader.is_decoder_supported());
+            io::copy(&mut lha_reader, &mut io::sink()).unwrap();
+            lha_reader.crc_check().unwrap();
+            if !lha_reader.next_file().unwrap() {
+                break;
+            }
+        }
+    }
+}
+
 fn test_decoder<R: io::Read, D: Decoder<R>>(
     decoder: &mut D,
     mut target_size: usize,
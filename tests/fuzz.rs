@@ -0,0 +1,90 @@
+//! Randomized tests of decoding malformed data.
+//!
+//! The decoders must never panic or access memory out of bounds, regardless of the input.
+//! This is especially important with the `fast-unsafe` feature, where some of the bounds checks
+//! are only verified by the debug assertions.
+use std::{io, fs};
+use delharc::decode::*;
+use delharc::header::*;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+const TESTS_CASES: &[(&str, CompressionMethod)] = &[
+    #[cfg(feature = "lz")]
+    ("lzs.bin", CompressionMethod::Lzs),
+    #[cfg(feature = "lz")]
+    ("lz5.bin", CompressionMethod::Lz5),
+    #[cfg(feature = "lh1")]
+    ("lh1.bin", CompressionMethod::Lh1),
+    #[cfg(feature = "lh2")]
+    ("lh5.bin", CompressionMethod::Lh2),
+    #[cfg(feature = "lh3")]
+    ("lh5.bin", CompressionMethod::Lh3),
+    ("lh5.bin", CompressionMethod::Lh5),
+    ("lh7.bin", CompressionMethod::Lh7),
+    #[cfg(feature = "lhx")]
+    ("lh7.bin", CompressionMethod::Lhx),
+];
+
+const ORIGINAL_SIZE: usize = 18092;
+
+fn decode(compression: CompressionMethod, data: &[u8], chunk_size: usize) -> io::Result<()> {
+    let mut decoder = DecoderAny::new_from_compression(compression, data);
+    let mut buf = vec![0u8; chunk_size];
+    let mut remaining = ORIGINAL_SIZE;
+    while remaining != 0 {
+        let len = remaining.min(chunk_size);
+        decoder.fill_buffer(&mut buf[..len])?;
+        remaining -= len;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_fuzz_decoders() {
+    let mut rng = StdRng::seed_from_u64(0x4c48415f);
+    for (name, compression) in TESTS_CASES {
+        println!("-------------\n{:?} {:?}", name, compression);
+        let valid = fs::read(format!("tests/decode/{}", name)).unwrap();
+        for _ in 0..100 {
+            let mut data = valid.clone();
+            // mutate some bytes, more often near the beginning, where the trees are
+            for _ in 0..rng.gen_range(1..8) {
+                let index = rng.gen_range(0..data.len()) >> rng.gen_range(0..8);
+                data[index] = rng.gen();
+            }
+            data.truncate(rng.gen_range(data.len() / 2..=data.len()));
+            let _ = decode(*compression, &data, rng.gen_range(1..5000));
+        }
+        for _ in 0..100 {
+            let len = rng.gen_range(0..2000);
+            let data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let _ = decode(*compression, &data, ORIGINAL_SIZE);
+        }
+    }
+}
+
+#[test]
+fn test_fuzz_archive() {
+    let mut rng = StdRng::seed_from_u64(0x4c5a48);
+    let valid = fs::read("tests/lha_unix114i/lh7_long.lzh").unwrap();
+    for _ in 0..20 {
+        let mut data = valid.clone();
+        for _ in 0..rng.gen_range(1..8) {
+            let index = rng.gen_range(0..data.len()) >> rng.gen_range(0..16);
+            data[index] = rng.gen();
+        }
+        let mut lha_reader = match delharc::LhaDecodeReader::new(&data[..]) {
+            Ok(lha_reader) => lha_reader,
+            Err(..) => continue
+        };
+        loop {
+            if lha_reader.is_decoder_supported() {
+                let _ = io::copy(&mut lha_reader, &mut io::sink());
+            }
+            match lha_reader.next_file() {
+                Ok(true) => {}
+                _ => break
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::{io, fs};
+use std::path::PathBuf;
+use delharc::header::{*, ext::*};
+
+/// Archives made by packers with quirks that are not reproduced by `LhaHeader::write`.
+const QUIRKS: &[&str] = &[
+    "lha_os9_211c", // padded level 2 headers
+    "lha_osk_201", // the length of level 2 headers doesn't include the length field
+];
+
+/// Reads all headers of an archive, returning each header with its raw bytes.
+fn read_headers(data: &[u8]) -> io::Result<Vec<(LhaHeader, &[u8])>> {
+    let mut headers = Vec::new();
+    let mut rd = data;
+    loop {
+        let start = data.len() - rd.len();
+        let header = match LhaHeader::read_with_raw(&mut rd, ParseMode::Strict)? {
+            Some(header) => header,
+            None => break
+        };
+        let raw = &data[start..data.len() - rd.len()];
+        assert_eq!(header.raw_header.as_deref(), Some(raw));
+        assert_eq!(header.header_size, raw.len() as u64);
+        let skip = (header.compressed_size as usize).min(rd.len());
+        rd = &rd[skip..];
+        headers.push((header, raw));
+    }
+    Ok(headers)
+}
+
+fn archives() -> Vec<PathBuf> {
+    let mut archives = Vec::new();
+    for dir in fs::read_dir("tests").unwrap() {
+        let dir = dir.unwrap().path();
+        if !dir.is_dir() {
+            continue
+        }
+        for file in fs::read_dir(&dir).unwrap() {
+            let file = file.unwrap().path();
+            if file.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("lzh")) {
+                archives.push(file);
+            }
+        }
+    }
+    archives.sort();
+    archives
+}
+
+fn assert_same_header(a: &LhaHeader, b: &LhaHeader) {
+    assert_eq!(a.level, b.level);
+    assert_eq!(a.compression, b.compression);
+    assert_eq!(a.compressed_size, b.compressed_size);
+    assert_eq!(a.original_size, b.original_size);
+    assert_eq!(a.filename, b.filename);
+    assert_eq!(a.msdos_attrs, b.msdos_attrs);
+    assert_eq!(a.last_modified, b.last_modified);
+    assert_eq!(a.os_type, b.os_type);
+    assert_eq!(a.file_crc, b.file_crc);
+    assert_eq!(a.extended_area, b.extended_area);
+    assert_eq!(a.first_header_len, b.first_header_len);
+    assert_eq!(a.extra_headers, b.extra_headers);
+}
+
+#[test]
+fn test_header_write() {
+    let mut count = 0;
+    for path in archives() {
+        println!("{:?}", path);
+        let data = fs::read(&path).unwrap();
+        let quirks = QUIRKS.iter().any(|dir| path.parent().unwrap().ends_with(dir));
+        for (header, raw) in read_headers(&data).unwrap() {
+            let mut bytes = Vec::new();
+            header.write(&mut bytes).unwrap();
+            let header2 = LhaHeader::read(&bytes[..]).unwrap().unwrap();
+            assert_same_header(&header, &header2);
+            assert_eq!(header2.raw_header, None);
+            assert_eq!(header2.header_size, bytes.len() as u64);
+            assert_eq!(header2.to_bytes().unwrap(), bytes);
+            // the base attributes can't be restored if they were overridden by an extra header
+            let attrs_in_extra = header.iter_extra().any(|data| data[0] == EXT_HEADER_MSDOS_ATTRS ||
+                                                                data[0] == EXT_HEADER_EXT_ATTRS);
+            if !quirks && !attrs_in_extra {
+                assert_eq!(bytes, raw);
+                count += 1;
+            }
+        }
+    }
+    assert!(count > 100);
+}
+
+#[test]
+fn test_header_write_errors() {
+    let header = LhaHeader { level: 4, ..LhaHeader::default() };
+    assert_eq!(header.to_bytes().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    let header = LhaHeader { filename: vec![b'a'; 256].into_boxed_slice(), ..LhaHeader::default() };
+    assert_eq!(header.to_bytes().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    let header = LhaHeader { filename: vec![b'a'; 240].into_boxed_slice(), ..LhaHeader::default() };
+    assert_eq!(header.to_bytes().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    let header = LhaHeader { filename: vec![b'a'; 200].into_boxed_slice(), ..LhaHeader::default() };
+    assert_eq!(header.to_bytes().unwrap().len(), 224);
+    let header = LhaHeader { level: 1, original_size: 1 << 32, ..LhaHeader::default() };
+    assert_eq!(header.to_bytes().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    let header = LhaHeader { level: 2, filename: Box::new(*b"foo"), ..LhaHeader::default() };
+    assert_eq!(header.to_bytes().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    let header = LhaHeader { level: 2, first_header_len: 3, extra_headers: Box::new([1, 0]), ..LhaHeader::default() };
+    assert_eq!(header.to_bytes().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    let header = LhaHeader { level: 2, first_header_len: 3, extra_headers: Box::new([1, 1, 0]), ..LhaHeader::default() };
+    assert_eq!(header.to_bytes().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    // 64-bit sizes
+    let mut extra = vec![EXT_HEADER_MSDOS_SIZE];
+    extra.extend_from_slice(&(5u64 << 32).to_le_bytes());
+    extra.extend_from_slice(&(6u64 << 32).to_le_bytes());
+    extra.extend_from_slice(&[0, 0]);
+    let header = LhaHeader {
+        level: 2, compressed_size: 5 << 32, original_size: 6 << 32,
+        first_header_len: extra.len() as u32, extra_headers: extra.into_boxed_slice(),
+        ..LhaHeader::default()
+    };
+    let header2 = LhaHeader::read(&header.to_bytes().unwrap()[..]).unwrap().unwrap();
+    assert_same_header(&header, &header2);
+}
+
+#[test]
+fn test_header_write_padding() {
+    // a level 2 header with the length's low byte equal to 0 is padded
+    let mut extra = vec![EXT_HEADER_COMMON, 0, 0, 225, 0];
+    extra.push(EXT_HEADER_FILENAME);
+    extra.extend(std::iter::repeat(b'a').take(222));
+    extra.extend_from_slice(&[0, 0]);
+    let header = LhaHeader {
+        level: 2, compression: *b"-lh0-", first_header_len: 5,
+        extra_headers: extra.into_boxed_slice(),
+        ..LhaHeader::default()
+    };
+    let bytes = header.to_bytes().unwrap();
+    assert_eq!(bytes.len(), 257);
+    assert_eq!(&bytes[0..2], &[1, 1]);
+    let header2 = LhaHeader::read(&bytes[..]).unwrap().unwrap();
+    assert_same_header(&header, &header2);
+    assert!(header2.parse_pathname().to_str().unwrap().starts_with("aaa"));
+}
+
+#[test]
+fn test_header_to_level2() {
+    let mut count = 0;
+    for path in archives() {
+        let data = fs::read(&path).unwrap();
+        for (header, _) in read_headers(&data).unwrap() {
+            let header2 = header.to_level2(FilenameEncoding::Auto).unwrap();
+            let header2 = LhaHeader::read(&header2.to_bytes().unwrap()[..]).unwrap().unwrap();
+            assert_eq!(header2.level, 2);
+            assert_eq!(header2.compression, header.compression);
+            assert_eq!(header2.compressed_size, header.compressed_size);
+            assert_eq!(header2.original_size, header.original_size);
+            assert_eq!(header2.msdos_attrs, header.msdos_attrs);
+            assert_eq!(header2.file_crc, header.file_crc);
+            assert_eq!(header2.parse_pathname(), header.parse_pathname());
+            assert_eq!(header2.parse_pathname_with(FilenameEncoding::Auto),
+                       header.parse_pathname_with(FilenameEncoding::Auto));
+            assert_eq!(header2.parse_comment(), header.parse_comment());
+            assert_eq!(header2.unix_mode(), header.unix_mode());
+            assert_eq!(header2.unix_uid_gid(), header.unix_uid_gid());
+            assert_eq!(header2.file_type(), header.file_type());
+            assert_eq!(header2.os9_metadata(), header.os9_metadata());
+            assert_eq!(header2.parse_last_modified().to_local(), header.parse_last_modified().to_local());
+            if header.level < 2 {
+                count += 1;
+            }
+        }
+    }
+    assert!(count > 50);
+}
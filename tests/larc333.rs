@@ -1,4 +1,5 @@
 #![cfg(feature = "lz")]
+#![allow(clippy::type_complexity)]
 use std::{io::{self, Seek, SeekFrom}, fs};
 use delharc::header::*;
 
@@ -36,7 +37,7 @@ fn test_larc333() -> io::Result<()> {
             assert_eq!(&last_modified, modif);
             assert_eq!(header.file_crc, *crc16);
             io::copy(&mut lha_reader, &mut sink)?;
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
@@ -1,3 +1,4 @@
+#![allow(clippy::type_complexity, clippy::len_zero)]
 use std::{io, fs};
 use delharc::{*, decode::DecoderAny};
 
@@ -64,7 +65,7 @@ fn test_lengths() -> io::Result<()> {
             assert_eq!(&last_modified, modif);
             assert_eq!(header.file_crc, *crc16);
             io::copy(&mut lha_reader, &mut sink)?;
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
@@ -0,0 +1,46 @@
+#![cfg(feature = "lh2")]
+use std::io;
+use delharc::header::*;
+
+mod sink;
+use sink::SinkSum;
+
+const TESTS_CASES: &[(&str, &str, u64, u64, u16, u32, &str)] = &[
+    ("text.lzh",      "TEXT.TXT",      45,    64, 0x6283, 0xB4B4C41D, "1992-03-14 12:30:08"),
+    ("runs.lzh",      "RUNS.BIN",      56,  5000, 0x42C9, 0x1B6E7DEA, "1992-03-14 12:31:08"),
+    ("ramp.lzh",      "RAMP.BIN",     556, 30000, 0x80B9, 0x0CF687D6, "1992-03-14 12:32:08"),
+    ("longmatch.lzh", "LONGMTCH.BIN", 274,   900, 0x58F9, 0xB841EE4F, "1992-03-14 12:33:08"),
+    ("repeat.lzh",    "REPEAT.TXT",   304, 29700, 0x816C, 0xC5DC5768, "1992-03-14 12:34:08"),
+];
+
+#[test]
+fn test_lh2() -> io::Result<()> {
+    for (name, path, size_c, size_o, crc16, crc32, modif) in TESTS_CASES {
+        println!("-------------\n{:?}", name);
+        let mut lha_reader = delharc::parse_file(format!("tests/lh2/{}", name))?;
+        loop {
+            let mut sink = SinkSum::new();
+            let header = lha_reader.header();
+            assert_eq!(header.level, 0);
+            assert_eq!(header.msdos_attrs, MsDosAttrs::ARCHIVE);
+            assert_eq!(&header.parse_pathname().to_str().unwrap(), path);
+            assert_eq!(OsType::Generic, header.parse_os_type()?);
+            assert_eq!(CompressionMethod::Lh2, header.compression_method().unwrap());
+            assert_eq!(header.compressed_size, *size_c);
+            assert_eq!(header.original_size, *size_o);
+            let last_modified = format!("{}", header.parse_last_modified());
+            assert_eq!(&last_modified, modif);
+            assert_eq!(header.file_crc, *crc16);
+            assert!(lha_reader.is_decoder_supported());
+            io::copy(&mut lha_reader, &mut sink)?;
+            assert_eq!(sink.length, *size_o);
+            assert_eq!(sink.crc32.get_crc(), *crc32);
+            assert_eq!(sink.crc16.get_crc(), *crc16);
+            assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
+            if !lha_reader.next_file().unwrap() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
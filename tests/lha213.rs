@@ -1,3 +1,4 @@
+#![allow(clippy::type_complexity)]
 use std::{io::{self, Seek, SeekFrom}, fs};
 use delharc::header::*;
 
@@ -38,9 +39,12 @@ fn test_lha213() -> io::Result<()> {
             assert_eq!(header.original_size, *size_o);
             let last_modified = format!("{}", header.parse_last_modified());
             assert_eq!(&last_modified, modif);
+            assert_eq!(header.unix_last_modified(), None);
+            assert_eq!(header.unix_mode(), None);
+            assert_eq!(header.unix_uid_gid(), None);
             assert_eq!(header.file_crc, *crc16);
             io::copy(&mut lha_reader, &mut sink)?;
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
@@ -51,3 +55,19 @@ fn test_lha213() -> io::Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn test_lha213_decompress() -> io::Result<()> {
+    let content = delharc::decompress_first("tests/lha213/lh5.lzh")?;
+    assert_eq!(content.len(), 18092);
+    assert_eq!(delharc::decompress_first("tests/lha213/sfx.exe")?, content);
+    let file = fs::File::open("tests/lha213/sfx.exe")?;
+    assert_eq!(delharc::decompress_entry(file, "GPL-2")?, content);
+    let file = fs::File::open("tests/lha213/subdir.lzh")?;
+    assert_eq!(delharc::decompress_entry(file, "SUBDIR/SUBDIR2/HELLO.TXT")?.len(), 12);
+    assert_eq!(delharc::decompress_first("tests/lha213/subdir.lzh")?.len(), 12);
+    let file = fs::File::open("tests/lha213/subdir.lzh")?;
+    let err = delharc::decompress_entry(file, "HELLO.TXT").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    Ok(())
+}
@@ -1,3 +1,4 @@
+#![allow(clippy::type_complexity)]
 use std::{io::{self, Seek, SeekFrom}, fs};
 use delharc::header::*;
 
@@ -61,7 +62,7 @@ fn test_lha_amiga_122() -> io::Result<()> {
             else {
                 io::copy(&mut lha_reader, &mut sink)?;
             }
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
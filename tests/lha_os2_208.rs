@@ -1,3 +1,4 @@
+#![allow(clippy::type_complexity)]
 use std::io;
 use delharc::header::*;
 
@@ -60,7 +61,7 @@ fn test_lha_os2_208() -> io::Result<()> {
             else {
                 io::copy(&mut lha_reader, &mut sink)?;
             }
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
@@ -100,7 +101,7 @@ fn test_lha_os2_208() -> io::Result<()> {
             else {
                 io::copy(&mut lha_reader, &mut sink)?;
             }
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
@@ -1,3 +1,4 @@
+#![allow(clippy::type_complexity)]
 use std::io;
 use delharc::header::*;
 
@@ -42,13 +43,28 @@ fn test_lha_os9_211c() -> io::Result<()> {
             assert_eq!(&last_modified, modif);
             assert_eq!(header.file_crc, *crc16);
             assert_eq!(header.parse_os_type()?, OsType::Os9);
+            if header.level == 0 {
+                assert!(matches!(header.parse_extended_area(), Some(ExtendedArea::Os9(..))));
+            }
+            else {
+                assert_eq!(header.parse_extended_area(), None);
+            }
+            let perms = header.os9_metadata().unwrap().perms;
+            let expected = match path.rsplit(std::path::MAIN_SEPARATOR).next().unwrap() {
+                // ---w--wr
+                "gpl2.gz" => Os9Perms::PUBLIC_WRITE|Os9Perms::WRITE|Os9Perms::READ,
+                // --e-r-wr
+                "gpl2" => Os9Perms::PUBLIC_EXEC|Os9Perms::PUBLIC_READ|Os9Perms::WRITE|Os9Perms::READ,
+                _ => Os9Perms::PUBLIC_READ|Os9Perms::WRITE|Os9Perms::READ
+            };
+            assert_eq!(perms, expected);
             if *compr == CompressionMethod::Lhd {
                 assert!(io::copy(&mut lha_reader, &mut sink).is_err());
             }
             else {
                 io::copy(&mut lha_reader, &mut sink)?;
             }
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
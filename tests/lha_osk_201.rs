@@ -1,3 +1,4 @@
+#![allow(clippy::type_complexity)]
 use std::io;
 use delharc::header::*;
 
@@ -59,13 +60,17 @@ fn test_lha_osk_201() -> io::Result<()> {
             assert_eq!(&last_modified, modif);
             assert_eq!(header.file_crc, *crc16);
             assert_eq!(header.parse_os_type()?, OsType::Osk);
+            let mode = if *compr == CompressionMethod::Lhd { 0xFFBF } else { 0o47 };
+            assert_eq!(header.unix_mode(), Some(mode));
+            assert_eq!(header.unix_uid_gid(), Some((0, 0)));
+            assert_eq!(header.file_type().is_dir(), *compr == CompressionMethod::Lhd);
             if *compr == CompressionMethod::Lhd {
                 assert!(io::copy(&mut lha_reader, &mut sink).is_err());
             }
             else {
                 io::copy(&mut lha_reader, &mut sink)?;
             }
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
@@ -99,13 +104,17 @@ fn test_lha_osk_201() -> io::Result<()> {
             assert_eq!(&last_modified, modif);
             assert_eq!(header.file_crc, *crc16);
             assert_eq!(header.parse_os_type()?, OsType::Osk);
+            let mode = if *compr == CompressionMethod::Lhd { 0xFFBF } else { 0o47 };
+            assert_eq!(header.unix_mode(), Some(mode));
+            assert_eq!(header.unix_uid_gid(), Some((0, 0)));
+            assert_eq!(header.file_type().is_dir(), *compr == CompressionMethod::Lhd);
             if *compr == CompressionMethod::Lhd {
                 assert!(io::copy(&mut lha_reader, &mut sink).is_err());
             }
             else {
                 io::copy(&mut lha_reader, &mut sink)?;
             }
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
@@ -1,4 +1,6 @@
+#![allow(clippy::type_complexity)]
 use std::io;
+use chrono::{DateTime, Utc};
 use delharc::header::*;
 
 mod sink;
@@ -75,8 +77,27 @@ fn test_lha_unix114i() -> io::Result<()> {
             assert_eq!(header.compressed_size, *size_c);
             assert_eq!(header.original_size, *size_o);
             assert_eq!(&header.parse_pathname().to_str().unwrap(), &path);
+            assert!(header.path_components().eq(path.split(std::path::MAIN_SEPARATOR).filter(|s| !s.is_empty())));
             let last_modified = format!("{}", header.parse_last_modified());
             assert_eq!(&last_modified, modif);
+            assert_eq!(header.unix_last_modified().map(DateTime::<Utc>::from),
+                       header.parse_last_modified().to_utc());
+            assert_eq!(header.unix_uid_gid(), Some((1000, 1000)));
+            let target = header.symlink_target();
+            assert_eq!(target.as_ref().map(|t| t.to_str().unwrap()), path.split_once('|').map(|(_, t)| t));
+            let file_type = header.unix_mode().unwrap() & 0o170000;
+            if *compr != CompressionMethod::Lhd {
+                assert_eq!(file_type, 0o100000);
+                assert_eq!(header.file_type(), EntryKind::File);
+            }
+            else if path.contains('|') {
+                assert_eq!(file_type, 0o120000);
+                assert_eq!(header.file_type(), EntryKind::Symlink);
+            }
+            else {
+                assert_eq!(file_type, 0o040000);
+                assert_eq!(header.file_type(), EntryKind::Directory);
+            }
             assert_eq!(header.file_crc, *crc16);
             assert_eq!(header.parse_os_type()?, OsType::Unix);
             if *compr == CompressionMethod::Lhd {
@@ -85,7 +106,7 @@ fn test_lha_unix114i() -> io::Result<()> {
             else {
                 io::copy(&mut lha_reader, &mut sink)?;
             }
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
@@ -122,8 +143,27 @@ fn test_lha_unix114i() -> io::Result<()> {
             assert_eq!(header.compressed_size, *size_c);
             assert_eq!(header.original_size, *size_o);
             assert_eq!(&header.parse_pathname().to_str().unwrap(), &path);
+            assert!(header.path_components().eq(path.split(std::path::MAIN_SEPARATOR).filter(|s| !s.is_empty())));
             let last_modified = format!("{}", header.parse_last_modified());
             assert_eq!(&last_modified, modif);
+            assert_eq!(header.unix_last_modified().map(DateTime::<Utc>::from),
+                       header.parse_last_modified().to_utc());
+            assert_eq!(header.unix_uid_gid(), Some((1000, 1000)));
+            let target = header.symlink_target();
+            assert_eq!(target.as_ref().map(|t| t.to_str().unwrap()), path.split_once('|').map(|(_, t)| t));
+            let file_type = header.unix_mode().unwrap() & 0o170000;
+            if *compr != CompressionMethod::Lhd {
+                assert_eq!(file_type, 0o100000);
+                assert_eq!(header.file_type(), EntryKind::File);
+            }
+            else if path.contains('|') {
+                assert_eq!(file_type, 0o120000);
+                assert_eq!(header.file_type(), EntryKind::Symlink);
+            }
+            else {
+                assert_eq!(file_type, 0o040000);
+                assert_eq!(header.file_type(), EntryKind::Directory);
+            }
             assert_eq!(header.file_crc, *crc16);
             assert_eq!(header.parse_os_type()?, OsType::Unix);
             if *compr == CompressionMethod::Lhd {
@@ -132,7 +172,7 @@ fn test_lha_unix114i() -> io::Result<()> {
             else {
                 io::copy(&mut lha_reader, &mut sink)?;
             }
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
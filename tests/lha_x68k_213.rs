@@ -1,4 +1,7 @@
+#![allow(clippy::type_complexity)]
 use std::{io::{self, Seek, SeekFrom}, fs};
+use std::time::SystemTime;
+use chrono::{FixedOffset, NaiveDateTime, TimeZone};
 use delharc::header::*;
 
 mod sink;
@@ -27,6 +30,16 @@ const SUBDIR_CASES: &[(&str, &[(&str, u64, u64, u16, u32, &str, u8, CompressionM
         ("subdir*subdir2*HELLO.TXT", 12, 12, 0x9778, 0xAF083B2D, "2012-04-04 12:44:30 UTC", 2, CompressionMethod::Lh0)]),
 ];
 
+/// Returns the instant of the timestamp listed in the test cases, the MS-DOS timestamps are in JST.
+fn x68k_instant(modif: &str) -> SystemTime {
+    let (naive, offset) = match modif.strip_suffix(" UTC") {
+        Some(naive) => (naive, 0),
+        None => (modif, 9 * 3600)
+    };
+    let naive = NaiveDateTime::parse_from_str(naive, "%Y-%m-%d %H:%M:%S").unwrap();
+    FixedOffset::east_opt(offset).unwrap().from_local_datetime(&naive).unwrap().into()
+}
+
 #[test]
 fn test_lha_x68k_213() -> io::Result<()> {
     for (offset, name, path, size_c, size_o, crc16, crc32, modif, level, compr) in TESTS_CASES {
@@ -55,17 +68,21 @@ fn test_lha_x68k_213() -> io::Result<()> {
             assert_eq!(header.file_crc, *crc16);
             if header.level == 0 {
                 assert_eq!(header.parse_os_type()?, OsType::Generic);
+                assert_eq!(header.human68k_attrs(), None);
             }
             else {
                 assert_eq!(header.parse_os_type()?, OsType::Human68k);
+                assert_eq!(header.human68k_attrs(), Some(Human68kAttrs::ARCHIVE));
             }
+            let jst = FixedOffset::east_opt(9 * 3600).unwrap();
+            assert_eq!(header.last_modified_in(&jst), Some(x68k_instant(modif)));
             if *compr == CompressionMethod::Lhd {
                 assert!(io::copy(&mut lha_reader, &mut sink).is_err());
             }
             else {
                 io::copy(&mut lha_reader, &mut sink)?;
             }
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
@@ -99,13 +116,24 @@ fn test_lha_x68k_213() -> io::Result<()> {
             assert_eq!(&last_modified, modif);
             assert_eq!(header.file_crc, *crc16);
             assert_eq!(header.parse_os_type()?, OsType::Human68k);
+            let attrs = header.human68k_attrs().unwrap();
+            if *compr == CompressionMethod::Lhd {
+                assert_eq!(attrs, Human68kAttrs::DIRECTORY);
+                assert_eq!(attrs.to_unix_perms(), 0o755);
+            }
+            else {
+                assert_eq!(attrs, Human68kAttrs::ARCHIVE);
+                assert_eq!(attrs.to_unix_perms(), 0o644);
+            }
+            let jst = FixedOffset::east_opt(9 * 3600).unwrap();
+            assert_eq!(header.last_modified_in(&jst), Some(x68k_instant(modif)));
             if *compr == CompressionMethod::Lhd {
                 assert!(io::copy(&mut lha_reader, &mut sink).is_err());
             }
             else {
                 io::copy(&mut lha_reader, &mut sink)?;
             }
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
@@ -1,3 +1,4 @@
+#![allow(clippy::type_complexity)]
 use std::path::PathBuf;
 use std::{io::{self, Seek, SeekFrom}, fs};
 use delharc::header::*;
@@ -71,6 +72,17 @@ fn test_lhmelt_16536() -> io::Result<()> {
                 assert_eq!(&header.parse_pathname().to_str().unwrap(), &path);
                 let last_modified = format!("{}", header.parse_last_modified());
                 assert_eq!(&last_modified, modif);
+                if header.level == 2 {
+                    let times = header.msdos_times().unwrap();
+                    assert_eq!(times.created, times.last_modified);
+                    assert_eq!(format!("{}", header.parse_created()), *modif);
+                    assert!(header.parse_last_access().to_utc().unwrap() > header.parse_created().to_utc().unwrap());
+                }
+                else {
+                    assert_eq!(header.msdos_times(), None);
+                    assert!(header.parse_created().is_none());
+                    assert!(header.parse_last_access().is_none());
+                }
                 assert_eq!(header.file_crc, *crc16);
             }
             if *level == 0 {
@@ -88,7 +100,7 @@ fn test_lhmelt_16536() -> io::Result<()> {
             }
             else {
                 io::copy(&mut lha_reader, &mut sink)?;
-                assert_eq!(sink.length, *size_o as u64);
+                assert_eq!(sink.length, *size_o);
                 assert_eq!(sink.crc32.get_crc(), *crc32);
                 assert_eq!(sink.crc16.get_crc(), *crc16);
                 assert_eq!(lha_reader.crc_check().unwrap(), *crc16);
@@ -100,3 +112,13 @@ fn test_lhmelt_16536() -> io::Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn test_lhmelt_16536_sfx_stream() -> io::Result<()> {
+    let content = delharc::decompress_first("tests/lhmelt_16536/h0_lh5.lzh")?;
+    for name in ["sfx_winsfx32_213.exe", "sfx_winsfx32m_250.exe"] {
+        let file = fs::File::open(format!("tests/lhmelt_16536/{}", name))?;
+        assert_eq!(delharc::decompress_entry(file, "gpl-2")?, content);
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "rayon")]
+use std::{io, fs};
+use delharc::parallel::par_extract;
+
+const TESTS_CASES: &[&str] = &[
+    "lha213/lh5.lzh",
+    "lha213/lh5_long.lzh",
+    "lha213/subdir.lzh",
+    "lha_amiga_212/lh1.lzh",
+    "lha_unix114i/h2_symlink.lzh",
+    "lha_unix114i/lh7_long.lzh",
+    "unlha32/h2_lhx.lzh",
+];
+
+fn decode_all(archive: &[u8]) -> io::Result<Vec<(String, Option<Vec<u8>>)>> {
+    let mut files = Vec::new();
+    let mut lha_reader = delharc::LhaDecodeReader::new(archive)?;
+    loop {
+        let name = lha_reader.header().parse_pathname().to_str().unwrap().to_string();
+        let mut content = None;
+        if lha_reader.is_decoder_supported() {
+            let mut buf = Vec::new();
+            io::copy(&mut lha_reader, &mut buf)?;
+            lha_reader.crc_check()?;
+            content = Some(buf);
+        }
+        files.push((name, content));
+        if !lha_reader.next_file()? {
+            break;
+        }
+    }
+    Ok(files)
+}
+
+#[test]
+fn test_par_extract() {
+    for name in TESTS_CASES {
+        println!("-------------\n{:?}", name);
+        let path = format!("tests/{}", name);
+        let expected = decode_all(&fs::read(&path).unwrap()[..]).unwrap();
+        let file = fs::File::open(&path).unwrap();
+        let files = par_extract(file, |header, content| {
+            let name = header.parse_pathname().to_str().unwrap().to_string();
+            Ok((name, content.map(|c| c.to_vec())))
+        }).unwrap();
+        assert_eq!(files, expected);
+    }
+}
+
+#[test]
+fn test_par_extract_errors() {
+    let mut archive = fs::read("tests/lha213/lh5.lzh").unwrap();
+    let file = io::Cursor::new(&archive);
+    let err = par_extract(file, |_, _| -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Other, "stop"))
+    }).unwrap_err();
+    assert_eq!(err.to_string(), "stop");
+    let len = archive.len();
+    archive[len - 10] ^= 0xff;
+    let file = io::Cursor::new(&archive);
+    assert!(par_extract(file, |_, _| Ok(())).is_err());
+    archive.truncate(len - 20);
+    let file = io::Cursor::new(&archive);
+    assert!(par_extract(file, |_, _| Ok(())).is_err());
+}
+
+#[test]
+fn test_par_extract_lying_size() {
+    let mut archive = fs::read("tests/lha213/lh5.lzh").unwrap();
+    // claim the original size of 4GB and fix the header checksum
+    let sum = |bytes: &[u8]| bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+    let old_sum = sum(&archive[11..15]);
+    archive[11..15].copy_from_slice(&u32::MAX.to_le_bytes());
+    archive[1] = archive[1].wrapping_sub(old_sum).wrapping_add(sum(&archive[11..15]));
+    let file = io::Cursor::new(&archive);
+    let err = par_extract(file, |_, _| Ok(())).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,115 @@
+use std::{io, fs};
+use delharc::push::*;
+
+const TESTS_CASES: &[&str] = &[
+    "lha213/lh0.lzh",
+    "lha213/lh5.lzh",
+    "lha213/lh5_long.lzh",
+    "lha213/subdir.lzh",
+    "lha_amiga_212/level0.lzh",
+    "lha_amiga_212/lh1.lzh",
+    "lha_unix114i/h1_subdir.lzh",
+    "lha_unix114i/h2_lh5.lzh",
+    "lha_unix114i/h2_symlink.lzh",
+    "lha_unix114i/lh7_long.lzh",
+];
+
+fn decode_all(archive: &[u8]) -> io::Result<Vec<(String, bool, Vec<u8>)>> {
+    let mut files = Vec::new();
+    let mut lha_reader = delharc::LhaDecodeReader::new(archive)?;
+    loop {
+        let name = lha_reader.header().parse_pathname().to_str().unwrap().to_string();
+        let mut content = Vec::new();
+        let supported = lha_reader.is_decoder_supported();
+        if supported {
+            io::copy(&mut lha_reader, &mut content)?;
+            lha_reader.crc_check()?;
+        }
+        files.push((name, supported, content));
+        if !lha_reader.next_file()? {
+            break;
+        }
+    }
+    Ok(files)
+}
+
+fn push_all(archive: &[u8], chunk_size: usize) -> io::Result<Vec<(String, bool, Vec<u8>)>> {
+    let mut files = Vec::new();
+    let mut decoder = LhaPushDecoder::new();
+    let mut finished = false;
+    for mut chunk in archive.chunks(chunk_size) {
+        loop {
+            let (consumed, status) = decoder.push(chunk)?;
+            chunk = &chunk[consumed..];
+            match status {
+                PushStatus::NeedMore => {
+                    assert!(chunk.is_empty());
+                    break
+                }
+                PushStatus::Header(header) => {
+                    assert!(!finished);
+                    let name = header.parse_pathname().to_str().unwrap().to_string();
+                    files.push((name, decoder.is_decoder_supported(), Vec::new()));
+                }
+                PushStatus::Output(data) => {
+                    files.last_mut().unwrap().2.extend_from_slice(data);
+                }
+                PushStatus::EndOfFile => {
+                    if decoder.is_decoder_supported() {
+                        decoder.crc_check()?;
+                    }
+                }
+                PushStatus::EndOfArchive => {
+                    finished = true;
+                    break
+                }
+            }
+        }
+    }
+    assert!(finished);
+    Ok(files)
+}
+
+#[test]
+fn test_push() -> io::Result<()> {
+    for name in TESTS_CASES {
+        println!("-------------\n{:?}", name);
+        let archive = fs::read(format!("tests/{}", name))?;
+        let expected = decode_all(&archive)?;
+        for chunk_size in [usize::MAX, 4096, 97, 7, 1].iter().copied() {
+            assert_eq!(push_all(&archive, chunk_size)?, expected);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_push_errors() {
+    let mut decoder = LhaPushDecoder::new();
+    let (consumed, status) = decoder.push(&[22, 0, b'-', b'l', b'h']).unwrap();
+    assert_eq!(consumed, 5);
+    assert!(matches!(status, PushStatus::NeedMore));
+    // unknown header level
+    assert!(decoder.push(&[b'0', b'-', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4]).is_err());
+}
+
+#[test]
+fn test_push_limits() -> io::Result<()> {
+    use delharc::decode::{LhaOptions, LimitError};
+    let archive = fs::read("tests/lha213/lh5.lzh")?;
+    let limit_error = |options: LhaOptions| {
+        let mut decoder = LhaPushDecoder::with_options(options);
+        let err = decoder.push(&archive).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        err.get_ref().and_then(|e| e.downcast_ref::<LimitError>()).copied()
+    };
+    assert_eq!(limit_error(LhaOptions::new().max_entry_size(18091)),
+               Some(LimitError::EntrySize { size: 18092, limit: 18091 }));
+    // the original size is more than twice the compressed size
+    assert!(matches!(limit_error(LhaOptions::new().max_entry_size(18092).max_compression_ratio(1)),
+                     Some(LimitError::CompressionRatio { limit: 1, .. })));
+    assert_eq!(limit_error(LhaOptions::new().max_header_size(10)), None);
+    let mut decoder = LhaPushDecoder::with_options(LhaOptions::new().max_entry_size(18092));
+    assert!(matches!(decoder.push(&archive)?, (_, PushStatus::Header(..))));
+    Ok(())
+}
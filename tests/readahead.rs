@@ -0,0 +1,43 @@
+use std::{io, fs};
+use delharc::{LhaDecodeReader, ReadAhead};
+
+const TESTS_CASES: &[&str] = &[
+    "lha213/lh5.lzh",
+    "lha213/lh5_long.lzh",
+    "lha213/subdir.lzh",
+    "lha_amiga_212/lh1.lzh",
+    "lha_unix114i/h2_symlink.lzh",
+    "lha_unix114i/lh7_long.lzh",
+];
+
+fn decode_all<R: io::Read>(rd: R) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    let mut lha_reader = LhaDecodeReader::new(rd)?;
+    loop {
+        let name = lha_reader.header().parse_pathname().to_str().unwrap().to_string();
+        let mut content = Vec::new();
+        if lha_reader.is_decoder_supported() {
+            io::copy(&mut lha_reader, &mut content)?;
+            lha_reader.crc_check()?;
+        }
+        files.push((name, content));
+        if !lha_reader.next_file()? {
+            break;
+        }
+    }
+    Ok(files)
+}
+
+#[test]
+fn test_read_ahead() {
+    for name in TESTS_CASES {
+        println!("-------------\n{:?}", name);
+        let path = format!("tests/{}", name);
+        let expected = decode_all(&fs::read(&path).unwrap()[..]).unwrap();
+        for (count, size) in [(1, 1000), (4, 4096), (2, 1 << 20)] {
+            let file = fs::File::open(&path).unwrap();
+            let files = decode_all(ReadAhead::with_buffers(file, count, size)).unwrap();
+            assert_eq!(files, expected);
+        }
+    }
+}
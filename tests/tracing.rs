@@ -0,0 +1,49 @@
+#![cfg(feature = "tracing")]
+use std::{fmt, io, fs};
+use std::sync::{Arc, Mutex};
+use tracing::{field, span, Event, Metadata, Subscriber};
+
+/// Collects the messages of the events.
+#[derive(Clone, Default)]
+struct Collector(Arc<Mutex<Vec<String>>>);
+
+struct MessageVisitor<'a>(&'a mut String);
+
+impl field::Visit for MessageVisitor<'_> {
+    fn record_debug(&mut self, field: &field::Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            *self.0 = format!("{:?}", value);
+        }
+    }
+}
+
+impl Subscriber for Collector {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool { true }
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id { span::Id::from_u64(1) }
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+    fn event(&self, event: &Event<'_>) {
+        let mut message = String::new();
+        event.record(&mut MessageVisitor(&mut message));
+        self.0.lock().unwrap().push(message);
+    }
+    fn enter(&self, _span: &span::Id) {}
+    fn exit(&self, _span: &span::Id) {}
+}
+
+#[test]
+fn test_tracing() -> io::Result<()> {
+    let collector = Collector::default();
+    let events = Arc::clone(&collector.0);
+    tracing::subscriber::with_default(collector, || {
+        let mut lha_reader = delharc::LhaDecodeReader::new(fs::File::open("tests/lha213/lh5.lzh")?)?;
+        io::copy(&mut lha_reader, &mut io::sink())?;
+        lha_reader.crc_check()?;
+        assert!(!lha_reader.next_file()?);
+        Ok::<_, io::Error>(())
+    })?;
+    let events = events.lock().unwrap();
+    let expected = ["header parsed", "decoding started", "decoding finished", "checksum verified", "end of archive"];
+    assert_eq!(*events, expected);
+    Ok(())
+}
@@ -1,4 +1,5 @@
 #![cfg(feature = "lhx")]
+#![allow(clippy::type_complexity)]
 use std::io;
 use delharc::header::*;
 
@@ -36,7 +37,7 @@ fn test_unlha32() -> io::Result<()> {
             else {
                 io::copy(&mut lha_reader, &mut sink)?;
             }
-            assert_eq!(sink.length, *size_o as u64);
+            assert_eq!(sink.length, *size_o);
             assert_eq!(sink.crc32.get_crc(), *crc32);
             assert_eq!(sink.crc16.get_crc(), *crc16);
             assert_eq!(lha_reader.crc_check().unwrap(), *crc16);